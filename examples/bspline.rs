@@ -27,7 +27,7 @@ fn load_teapot(
                 .faces
                 .reserve_exact(usize::from_str(items[0]).unwrap());
         } else if items.len() == 2 {
-            if points.len() > 0 {
+            if !points.is_empty() {
                 let surface = SurfacePatch {
                     surface: BSplineSurface::uniform_clamped(
                         Grid::from_vec(points, current_cols).transpose(),
@@ -8,9 +8,9 @@ pub fn bernstein(n: usize, u: Float) -> Vec<Float> {
 
     for j in 1..n {
         let mut saved = 0.0;
-        for k in 0..j {
-            let temp = values[k];
-            values[k] = saved + u1 * temp;
+        for value in values.iter_mut().take(j) {
+            let temp = *value;
+            *value = saved + u1 * temp;
             saved = u * temp;
         }
         values[j] = saved;
@@ -1,4 +1,4 @@
-use crate::{utils, Float, Point3};
+use crate::{utils, utils::Tolerance, Float, Point3, Point4, Vec3};
 use downcast_rs::{impl_downcast, Downcast};
 
 /// Parametric curve
@@ -9,10 +9,63 @@ pub trait Curve: std::fmt::Debug + Downcast {
     /// Get parameter of nearest point on the curve to the given point
     fn project(&self, point: Point3) -> Float;
 
+    /// Get the `order`-th derivative by the parameter `u`, the 0-th is the point itself,
+    /// approximated by finite differences unless the curve knows it exactly
+    fn get_derivative(&self, u: Float, order: usize) -> Vec3 {
+        approximate_derivative(self, u, order)
+    }
+
+    /// Unit tangent vector, zero where the curve stops
+    fn tangent(&self, u: Float) -> Vec3 {
+        self.get_derivative(u, 1).normalize_or_zero()
+    }
+
+    /// Curvature `|C' × C''| / |C'|³`, zero where the curve stops
+    fn curvature(&self, u: Float) -> Float {
+        let first = self.get_derivative(u, 1);
+        let speed = first.length();
+        if speed.near(0.0) {
+            return 0.0;
+        }
+        first.cross(self.get_derivative(u, 2)).length() / (speed * speed * speed)
+    }
+
+    /// Torsion `(C' × C'') · C''' / |C' × C''|²`, zero where the curvature vanishes
+    fn torsion(&self, u: Float) -> Float {
+        let binormal = self.get_derivative(u, 1).cross(self.get_derivative(u, 2));
+        let length_squared = binormal.length_squared();
+        if length_squared.near2(0.0) {
+            return 0.0;
+        }
+        binormal.dot(self.get_derivative(u, 3)) / length_squared
+    }
+
+    /// Frenet frame, `None` where the curvature vanishes
+    fn frenet_frame(&self, u: Float) -> Option<Frame> {
+        let first = self.get_derivative(u, 1);
+        let binormal = first.cross(self.get_derivative(u, 2));
+        if first.length_squared().near2(0.0) || binormal.length_squared().near2(0.0) {
+            return None;
+        }
+        let tangent = first.normalize();
+        let binormal = binormal.normalize();
+        Some(Frame {
+            origin: self.get_point(u),
+            tangent,
+            normal: binormal.cross(tangent),
+            binormal,
+        })
+    }
+
     /// Refine parameter range according to whether same sense or not
     fn refine_parameter_range(&self, range: (Float, Float), _same_sense: bool) -> (Float, Float) {
         range
     }
+
+    /// Period of the parameter when the curve is closed
+    fn period(&self) -> Option<Float> {
+        None
+    }
 }
 
 impl_downcast!(Curve);
@@ -24,9 +77,20 @@ impl Curve for Box<dyn Curve> {
     fn project(&self, point: Point3) -> Float {
         self.as_ref().project(point)
     }
+    fn get_derivative(&self, u: Float, order: usize) -> Vec3 {
+        self.as_ref().get_derivative(u, order)
+    }
     fn refine_parameter_range(&self, range: (Float, Float), same_sense: bool) -> (Float, Float) {
         self.as_ref().refine_parameter_range(range, same_sense)
     }
+    fn period(&self) -> Option<Float> {
+        self.as_ref().period()
+    }
+}
+
+/// Curves with an exact rational B-spline form, the parameterization agrees at the knots
+pub trait ToNurbs {
+    fn to_nurbs(&self) -> BSplineCurve<Point4>;
 }
 
 #[derive(Debug)]
@@ -38,25 +102,129 @@ pub struct CurveSegment<C: Curve> {
 }
 
 impl<C: Curve> CurveSegment<C> {
-    /// Get sample points on the curve segment
+    /// Get sample points on the curve segment within chord height `self.tolerance`
     pub fn get_points(&self) -> Vec<Point3> {
-        // let parameters =
-        //     utils::uniform_divide(self.parameter_range, self.parameter_division, 0.01, 1.5);
-        let parameters =
-            utils::parameter_division(&self.curve, self.parameter_range, self.tolerance);
-        // println!(
-        //     "{}",
-        //     parameters
-        //         .iter()
-        //         .map(f64::to_string)
-        //         .collect::<Vec<_>>()
-        //         .join(",")
-        // );
-        parameters
+        self.discretize(&utils::CurveTolerance::with_chord_height(self.tolerance))
+    }
+
+    /// Get sample points on the curve segment satisfying the tolerance
+    pub fn discretize(&self, tolerance: &utils::CurveTolerance) -> Vec<Point3> {
+        utils::parameter_division(&self.curve, self.parameter_range, tolerance)
             .into_iter()
             .map(|u| self.curve.get_point(u))
             .collect()
     }
+
+    /// Get parameters of the sample points given by `get_points`
+    pub fn get_parameters(&self) -> Vec<Float> {
+        utils::parameter_division(
+            &self.curve,
+            self.parameter_range,
+            &utils::CurveTolerance::with_chord_height(self.tolerance),
+        )
+    }
+}
+
+/// Parameter range of a periodic curve running in the direction given by `same_sense`
+pub(crate) fn refine_periodic_range(
+    (u0, u1): (Float, Float),
+    same_sense: bool,
+    period: Float,
+) -> (Float, Float) {
+    if same_sense && u0 >= u1 {
+        return (u0, u1 + period);
+    }
+    if !same_sense && u0 <= u1 {
+        return (u0 + period, u1);
+    }
+    (u0, u1)
+}
+
+/// Length of the parameter range when the curve ends where it starts
+pub(crate) fn closed_period<C: Curve + ?Sized>(
+    curve: &C,
+    (u0, u1): (Float, Float),
+) -> Option<Float> {
+    let distance = curve.get_point(u0).distance_squared(curve.get_point(u1));
+    Some(u1 - u0).filter(|_| distance.near2(0.0))
+}
+
+/// The `order`-th derivative approximated by central differences
+pub(crate) fn approximate_derivative<C: Curve + ?Sized>(curve: &C, u: Float, order: usize) -> Vec3 {
+    if order == 0 {
+        return curve.get_point(u);
+    }
+    // the step balancing truncation and rounding errors
+    let h = Float::EPSILON.powf(1.0 / (order as Float + 2.0)) * u.abs().max(1.0);
+    let mut sum = Vec3::ZERO;
+    let mut binomial = 1.0;
+    for i in 0..=order {
+        let offset = (order as Float / 2.0 - i as Float) * h;
+        let sign = if i % 2 == 0 { 1.0 } else { -1.0 };
+        sum += curve.get_point(u + offset) * (sign * binomial);
+        binomial = binomial * (order - i) as Float / (i + 1) as Float;
+    }
+    sum / h.powi(order as i32)
+}
+
+/// Derivatives of a rational curve from derivatives `[A, A', A'', ...]` of its
+/// weighted points `(wx,wy,wz,w)`, returns the last one.
+pub(crate) fn rational_derivative(weighted: &[Point4]) -> Vec3 {
+    let mut derivatives = Vec::<Vec3>::with_capacity(weighted.len());
+    for k in 0..weighted.len() {
+        let mut derivative = weighted[k].truncate();
+        let mut binomial = 1.0;
+        for i in 1..=k {
+            binomial = binomial * (k - i + 1) as Float / i as Float;
+            derivative -= derivatives[k - i] * (binomial * weighted[i].w);
+        }
+        derivatives.push(derivative / weighted[0].w);
+    }
+    derivatives[weighted.len() - 1]
+}
+
+/// Refine parameter of the nearest curve point by Newton iteration on central differences
+pub(crate) fn refine_nearest_parameter<C: Curve + ?Sized>(
+    curve: &C,
+    point: Point3,
+    mut u: Float,
+    range: (Float, Float),
+    trials: usize,
+) -> Float {
+    let h = 1e-5 * (range.1 - range.0).abs().clamp(1e-3, 1.0);
+    for _ in 0..trials {
+        let delta = curve.get_point(u) - point;
+        if delta.length_squared().near2(0.0) {
+            break;
+        }
+        let (before, after) = (curve.get_point(u - h), curve.get_point(u + h));
+        let tangent = (after - before) / (2.0 * h);
+        let second = (after + before - curve.get_point(u) * 2.0) / (h * h);
+        let f = tangent.dot(delta);
+        let fprime = second.dot(delta) + tangent.length_squared();
+        if f.near(0.0) || fprime.near(0.0) {
+            break;
+        }
+        u = utils::clamp_in_range(u - f / fprime, range);
+    }
+    u
+}
+
+/// Find parameter of the nearest curve point by sampling the parameter range and Newton iteration
+pub(crate) fn nearest_parameter<C: Curve + ?Sized>(
+    curve: &C,
+    point: Point3,
+    range: (Float, Float),
+    division: usize,
+    trials: usize,
+) -> Float {
+    let parameters = utils::uniform_divide(range, division);
+    let points = parameters
+        .iter()
+        .map(|&u| curve.get_point(u))
+        .collect::<Vec<_>>();
+    let u = parameters[utils::find_nearest_point(&points, point)];
+    refine_nearest_parameter(curve, point, u, range, trials)
 }
 
 /// A continuous curve made up of a series of segments.
@@ -64,13 +232,35 @@ pub struct Polycurve {
     pub segments: Vec<CurveSegment<Box<dyn Curve>>>,
 }
 
+mod arc_length;
 mod bezier;
 mod bspline;
 mod circle;
+mod ellipse;
+mod fitting;
+mod frame;
+mod hyperbola;
 mod line;
+mod offset;
+mod parabola;
 mod polyline;
+mod refinement;
+mod surface_curve;
+mod transformed;
+mod trimmed;
+pub use arc_length::*;
 pub use bezier::*;
 pub use bspline::*;
 pub use circle::*;
+pub use ellipse::*;
+pub use fitting::*;
+pub use frame::*;
+pub use hyperbola::*;
 pub use line::*;
+pub use offset::*;
+pub use parabola::*;
 pub use polyline::*;
+pub(crate) use refinement::*;
+pub use surface_curve::*;
+pub use transformed::*;
+pub use trimmed::*;
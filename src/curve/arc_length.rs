@@ -0,0 +1,223 @@
+use super::Curve;
+use crate::{utils, Float, Point3, Vec3};
+
+// 5-point Gauss-Legendre quadrature on [-1, 1]
+const GAUSS_NODES: [Float; 5] = [
+    0.0,
+    -0.538_469_310_105_683_1,
+    0.538_469_310_105_683_1,
+    -0.906_179_845_938_664,
+    0.906_179_845_938_664,
+];
+const GAUSS_WEIGHTS: [Float; 5] = [
+    0.568_888_888_888_888_9,
+    0.478_628_670_499_366_47,
+    0.478_628_670_499_366_47,
+    0.236_926_885_056_189_08,
+    0.236_926_885_056_189_08,
+];
+
+fn gauss_length<C: Curve + ?Sized>(curve: &C, (a, b): (Float, Float)) -> Float {
+    let (center, half) = ((a + b) / 2.0, (b - a) / 2.0);
+    GAUSS_NODES
+        .iter()
+        .zip(&GAUSS_WEIGHTS)
+        .map(|(&x, &w)| w * curve.get_derivative(center + half * x, 1).length())
+        .sum::<Float>()
+        * half.abs()
+}
+
+fn adaptive_length<C: Curve + ?Sized>(
+    curve: &C,
+    (a, b): (Float, Float),
+    whole: Float,
+    depth: usize,
+) -> Float {
+    let middle = (a + b) / 2.0;
+    let left = gauss_length(curve, (a, middle));
+    let right = gauss_length(curve, (middle, b));
+    let sum = left + right;
+    if depth == 0 || (sum - whole).abs() <= 1e-12 * sum.max(1.0) {
+        sum
+    } else {
+        adaptive_length(curve, (a, middle), left, depth - 1)
+            + adaptive_length(curve, (middle, b), right, depth - 1)
+    }
+}
+
+/// Length of the curve over the parameter range by adaptive Gauss-Legendre quadrature
+pub fn arc_length<C: Curve + ?Sized>(curve: &C, range: (Float, Float)) -> Float {
+    // start from several pieces to catch the kinks of piecewise curves
+    utils::uniform_divide(range, 16)
+        .windows(2)
+        .map(|pair| {
+            let piece = (pair[0], pair[1]);
+            adaptive_length(curve, piece, gauss_length(curve, piece), 24)
+        })
+        .sum()
+}
+
+/// Parameter in the range at which the arc length from the start reaches `length`,
+/// found by Newton iteration safeguarded with bisection.
+pub fn parameter_at_length<C: Curve + ?Sized>(
+    curve: &C,
+    range: (Float, Float),
+    length: Float,
+) -> Float {
+    solve_parameter(curve, range, arc_length(curve, range), length)
+}
+
+fn solve_parameter<C: Curve + ?Sized>(
+    curve: &C,
+    (u0, u1): (Float, Float),
+    total: Float,
+    length: Float,
+) -> Float {
+    if total <= 0.0 || length <= 0.0 {
+        return u0;
+    }
+    if length >= total {
+        return u1;
+    }
+    // search the ratio t of the range, u = u0 + t (u1 - u0)
+    let span = u1 - u0;
+    let (mut low, mut high) = (0.0, 1.0);
+    let mut t = length / total;
+    for _ in 0..64 {
+        let u = u0 + t * span;
+        let error = arc_length(curve, (u0, u)) - length;
+        if error.abs() <= 1e-12 * total.max(1.0) {
+            break;
+        }
+        if error > 0.0 {
+            high = t;
+        } else {
+            low = t;
+        }
+        let speed = curve.get_derivative(u, 1).length() * span.abs();
+        let next = t - error / speed;
+        t = if speed > 0.0 && next > low && next < high {
+            next
+        } else {
+            (low + high) / 2.0
+        };
+        if high - low <= Float::EPSILON {
+            break;
+        }
+    }
+    u0 + t * span
+}
+
+/// A curve parameterized by its arc length `s` in `[0, length]`
+#[derive(Debug)]
+pub struct ArcLengthCurve<C: Curve> {
+    pub curve: C,
+    /// Parameter range of the original curve
+    pub parameter_range: (Float, Float),
+    pub length: Float,
+    /// (arc length, parameter) of sample points of the original curve
+    samples: Vec<(Float, Float)>,
+}
+
+impl<C: Curve> ArcLengthCurve<C> {
+    pub fn new(curve: C, parameter_range: (Float, Float)) -> Self {
+        let parameters = utils::uniform_divide(parameter_range, 32);
+        let mut samples = vec![(0.0, parameters[0])];
+        for pair in parameters.windows(2) {
+            let length = samples[samples.len() - 1].0 + arc_length(&curve, (pair[0], pair[1]));
+            samples.push((length, pair[1]));
+        }
+        ArcLengthCurve {
+            curve,
+            parameter_range,
+            length: samples[samples.len() - 1].0,
+            samples,
+        }
+    }
+
+    /// Parameter of the original curve at the arc length
+    pub fn parameter(&self, length: Float) -> Float {
+        let index = self
+            .samples
+            .partition_point(|&(s, _)| s <= length)
+            .clamp(1, self.samples.len() - 1);
+        let (s0, u0) = self.samples[index - 1];
+        let (s1, u1) = self.samples[index];
+        solve_parameter(&self.curve, (u0, u1), s1 - s0, length - s0)
+    }
+
+    /// Arc length at the parameter of the original curve
+    pub fn length_at(&self, u: Float) -> Float {
+        let (u0, u1) = self.parameter_range;
+        let ratio = if u1 == u0 { 0.0 } else { (u - u0) / (u1 - u0) };
+        let index = ((ratio * 32.0).floor().max(0.0) as usize).min(31);
+        let (s0, start) = self.samples[index];
+        let length = arc_length(&self.curve, (start, u));
+        if (u - start) * (u1 - u0) >= 0.0 {
+            s0 + length
+        } else {
+            s0 - length
+        }
+    }
+}
+
+impl<C: Curve> Curve for ArcLengthCurve<C> {
+    fn get_point(&self, s: Float) -> Point3 {
+        self.curve.get_point(self.parameter(s))
+    }
+
+    fn project(&self, point: Point3) -> Float {
+        self.length_at(self.curve.project(point))
+    }
+
+    fn get_derivative(&self, s: Float, order: usize) -> Vec3 {
+        match order {
+            0 => self.get_point(s),
+            1 => {
+                let u = self.parameter(s);
+                let tangent = self.curve.tangent(u);
+                if self.parameter_range.1 < self.parameter_range.0 {
+                    -tangent
+                } else {
+                    tangent
+                }
+            }
+            _ => super::approximate_derivative(self, s, order),
+        }
+    }
+}
+
+#[test]
+fn test_arc_length() {
+    use crate::curve::{BezierCurve, Circle};
+
+    let circle = Circle {
+        center: Point3::new(1.0, 2.0, 3.0),
+        axis: Vec3::Z,
+        ref_dir: Vec3::X,
+        radius: 2.0,
+    };
+    let length = arc_length(&circle, (0.0, crate::consts::PI));
+    assert!((length - 2.0 * crate::consts::PI).abs() < 1e-10);
+    let u = parameter_at_length(&circle, (0.0, crate::consts::PI), 1.0);
+    assert!((u - 0.5).abs() < 1e-10);
+
+    let bezier = BezierCurve {
+        control_points: vec![
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 3.0, 0.0),
+            Point3::new(4.0, 3.0, 1.0),
+            Point3::new(5.0, 0.0, 2.0),
+        ],
+    };
+    let curve = ArcLengthCurve::new(bezier, (0.0, 1.0));
+    let steps = 20;
+    let step = curve.length / steps as Float;
+    for i in 0..steps {
+        let s = i as Float * step;
+        let u = curve.parameter(s);
+        assert!((arc_length(&curve.curve, (0.0, u)) - s).abs() < 1e-9);
+        assert!((curve.length_at(u) - s).abs() < 1e-9);
+        assert!((curve.get_derivative(s, 1).length() - 1.0).abs() < 1e-9);
+    }
+}
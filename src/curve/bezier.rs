@@ -1,6 +1,6 @@
-use super::Curve;
+use super::{BSplineCurve, Curve, ToNurbs};
 use crate::basis::bernstein;
-use crate::{utils, Float, Point3, Point4};
+use crate::{utils, Float, KnotVector, Point3, Point4, Vec3};
 
 #[derive(Debug)]
 pub struct BezierCurve<P> {
@@ -24,6 +24,52 @@ impl<P: std::ops::Sub<Output = P> + std::ops::Mul<Float, Output = P> + Copy> Bez
             .collect::<Vec<P>>();
         BezierCurve { control_points }
     }
+
+    /// The `order`-th derivative curve, `None` when the order exceeds the degree
+    pub fn nth_derivative(&self, order: usize) -> Option<BezierCurve<P>> {
+        if order > self.degree() {
+            return None;
+        }
+        let mut curve = BezierCurve {
+            control_points: self.control_points.clone(),
+        };
+        for _ in 0..order {
+            curve = curve.derivative();
+        }
+        Some(curve)
+    }
+}
+
+impl BezierCurve<Point4> {
+    /// Get the weighted point `(wx,wy,wz,w)` with parameter `u`
+    pub fn get_weighted_point(&self, u: Float) -> Point4 {
+        let mut point = Point4::ZERO;
+        let basis = bernstein(self.control_points.len(), u);
+        for (b, &p) in basis.into_iter().zip(self.control_points.iter()) {
+            point += b * p;
+        }
+        point
+    }
+}
+
+impl ToNurbs for BezierCurve<Point3> {
+    fn to_nurbs(&self) -> BSplineCurve<Point4> {
+        BSplineCurve {
+            control_points: self.control_points.iter().map(|p| p.extend(1.0)).collect(),
+            knots: KnotVector::bezier_knot(self.degree()),
+            degree: self.degree() as u8,
+        }
+    }
+}
+
+impl ToNurbs for BezierCurve<Point4> {
+    fn to_nurbs(&self) -> BSplineCurve<Point4> {
+        BSplineCurve {
+            control_points: self.control_points.clone(),
+            knots: KnotVector::bezier_knot(self.degree()),
+            degree: self.degree() as u8,
+        }
+    }
 }
 
 /// bezier curve in 3D space
@@ -45,20 +91,31 @@ impl Curve for BezierCurve<Point3> {
         let der2 = der1.derivative();
         utils::find_nearest_parameter(self, &der1, &der2, point, (0.0, 1.0), self.degree() * 4, 10)
     }
+
+    fn get_derivative(&self, u: Float, order: usize) -> Vec3 {
+        self.nth_derivative(order)
+            .map_or(Vec3::ZERO, |curve| curve.get_point(u))
+    }
 }
 
 /// Rational bezier curve, point (x,y,z) with weight w is (wx,wy,wz,w)
 impl Curve for BezierCurve<Point4> {
     fn get_point(&self, u: Float) -> Point3 {
-        let mut point = Point4::ZERO;
-        let basis = bernstein(self.control_points.len(), u);
-        for (b, &p) in basis.into_iter().zip(self.control_points.iter()) {
-            point += b * p;
-        }
+        let point = self.get_weighted_point(u);
         (1.0 / point.w) * point.truncate()
     }
 
-    fn project(&self, _point: Point3) -> Float {
-        unimplemented!()
+    fn get_derivative(&self, u: Float, order: usize) -> Vec3 {
+        let weighted = (0..=order)
+            .map(|k| {
+                self.nth_derivative(k)
+                    .map_or(Point4::ZERO, |curve| curve.get_weighted_point(u))
+            })
+            .collect::<Vec<_>>();
+        super::rational_derivative(&weighted)
+    }
+
+    fn project(&self, point: Point3) -> Float {
+        super::nearest_parameter(self, point, (0.0, 1.0), self.degree() * 4, 10)
     }
 }
@@ -1,5 +1,5 @@
-use super::Curve;
-use crate::{utils, utils::Tolerance, Float, KnotVector, Point3, Point4};
+use super::{Curve, ToNurbs};
+use crate::{utils, utils::Tolerance, Float, KnotVector, Point3, Point4, Vec3};
 
 #[derive(Debug, Clone)]
 pub struct BSplineCurve<P> {
@@ -8,6 +8,48 @@ pub struct BSplineCurve<P> {
     pub degree: u8,
 }
 
+/// Control points of B-splines, points or weighted points `(wx,wy,wz,w)` of rational ones
+pub trait ControlPoint:
+    std::ops::Add<Output = Self>
+    + std::ops::Sub<Output = Self>
+    + std::ops::Mul<Float, Output = Self>
+    + Copy
+    + Default
+    + std::fmt::Debug
+{
+    fn distance(self, other: Self) -> Float;
+
+    /// Bound on control point deviations keeping the curve within `tolerance`
+    fn removal_tolerance(_points: &[Self], tolerance: Float) -> Float {
+        tolerance
+    }
+}
+
+impl ControlPoint for Point3 {
+    fn distance(self, other: Self) -> Float {
+        Point3::distance(self, other)
+    }
+}
+
+impl ControlPoint for Point4 {
+    fn distance(self, other: Self) -> Float {
+        Point4::distance(self, other)
+    }
+
+    /// Weighted points deviate by `tolerance * w_min / (1 + max |P|)` (Piegl & Tiller eq. 5.30)
+    fn removal_tolerance(points: &[Self], tolerance: Float) -> Float {
+        let (weight, distance) = points
+            .iter()
+            .fold((Float::MAX, 0.0), |(weight, distance), p| {
+                (
+                    weight.min(p.w),
+                    Float::max(distance, (p.truncate() / p.w).length()),
+                )
+            });
+        tolerance * weight / (1.0 + distance)
+    }
+}
+
 impl<P> BSplineCurve<P> {
     #[inline]
     pub fn degree(&self) -> usize {
@@ -23,7 +65,7 @@ impl<P: std::ops::Sub<Output = P> + std::ops::Mul<Float, Output = P> + Copy> BSp
         let control_points = self
             .control_points
             .windows(2)
-            .zip(knots.spans(self.degree()).into_iter())
+            .zip(knots.spans(self.degree()))
             .map(|(pair, span)| (pair[1] - pair[0]) * p * utils::inv_or_zero(span))
             .collect::<Vec<P>>();
         BSplineCurve {
@@ -32,24 +74,44 @@ impl<P: std::ops::Sub<Output = P> + std::ops::Mul<Float, Output = P> + Copy> BSp
             degree: self.degree - 1,
         }
     }
+
+    /// The `order`-th derivative curve, `None` when the order exceeds the degree
+    pub fn nth_derivative(&self, order: usize) -> Option<BSplineCurve<P>> {
+        if order > self.degree() {
+            return None;
+        }
+        let mut curve = BSplineCurve {
+            control_points: self.control_points.clone(),
+            knots: self.knots.clone(),
+            degree: self.degree,
+        };
+        for _ in 0..order {
+            curve = curve.derivative();
+        }
+        Some(curve)
+    }
 }
 
-impl BSplineCurve<Point3> {
+impl<P> BSplineCurve<P>
+where
+    P: std::ops::Add<Output = P> + std::ops::Mul<Float, Output = P> + Copy + Default,
+{
+    /// Insert a knot keeping the shape, works on weighted points of rational curves as well
     pub fn add_knot(&mut self, knot: f64) {
         let p = self.degree();
         let n = self.control_points.len();
 
         let index = self.knots.add_knot(knot);
         if index == 0 {
-            self.control_points.insert(0, Point3::ZERO);
+            self.control_points.insert(0, P::default());
         } else {
-            let start = if index > p { index - p } else { 0 };
+            let start = index.saturating_sub(p);
             let end = if index > n {
-                self.control_points.push(Point3::ZERO);
+                self.control_points.push(P::default());
                 n + 1
             } else {
                 self.control_points
-                    .insert(index - 1, self.control_points[index - 1].clone());
+                    .insert(index - 1, self.control_points[index - 1]);
                 index
             };
             for i in (start..end).rev() {
@@ -61,7 +123,8 @@ impl BSplineCurve<Point3> {
         }
     }
 
-    pub fn split(&mut self, mut t: f64) -> BSplineCurve<Point3> {
+    /// Split the curve at the parameter, keeps the first part and returns the second one
+    pub fn split(&mut self, mut t: f64) -> BSplineCurve<P> {
         let p = self.degree();
         let index = self.knots.span_index(t);
         let s = if t.near(self.knots[index]) {
@@ -109,7 +172,21 @@ impl BSplineCurve<Point3> {
         }
     }
 
-    pub fn to_piecewise_bezier(&self) -> Vec<super::BezierCurve<Point3>> {
+    /// The part of the curve between two parameters
+    pub fn trim(&self, (u0, u1): (Float, Float)) -> BSplineCurve<P> {
+        let (start, end) = self.knots.range();
+        let (u0, u1) = (u0.min(u1).max(start), u0.max(u1).min(end));
+        let mut curve = self.clone();
+        if u0 > start && !u0.near(start) {
+            curve = curve.split(u0);
+        }
+        if u1 < end && !u1.near(end) {
+            curve.split(u1);
+        }
+        curve
+    }
+
+    pub fn to_piecewise_bezier(&self) -> Vec<super::BezierCurve<P>> {
         let mut bspline = self.clone();
         bspline.clamp();
 
@@ -132,6 +209,22 @@ impl BSplineCurve<Point3> {
     }
 }
 
+impl ToNurbs for BSplineCurve<Point3> {
+    fn to_nurbs(&self) -> BSplineCurve<Point4> {
+        BSplineCurve {
+            control_points: self.control_points.iter().map(|p| p.extend(1.0)).collect(),
+            knots: self.knots.clone(),
+            degree: self.degree,
+        }
+    }
+}
+
+impl ToNurbs for BSplineCurve<Point4> {
+    fn to_nurbs(&self) -> BSplineCurve<Point4> {
+        self.clone()
+    }
+}
+
 /// 3D BSpline curve
 impl Curve for BSplineCurve<Point3> {
     fn get_point(&self, u: Float) -> Point3 {
@@ -160,21 +253,58 @@ impl Curve for BSplineCurve<Point3> {
             10,
         )
     }
+
+    fn get_derivative(&self, u: Float, order: usize) -> Vec3 {
+        self.nth_derivative(order)
+            .map_or(Vec3::ZERO, |curve| curve.get_point(u))
+    }
+
+    fn period(&self) -> Option<Float> {
+        super::closed_period(self, self.knots.range())
+    }
 }
 
-/// Rational BSpline curve, point (x,y,z) with weight w is (wx,wy,wz,w)
-impl Curve for BSplineCurve<Point4> {
-    fn get_point(&self, u: Float) -> Point3 {
+impl BSplineCurve<Point4> {
+    /// Get the weighted point `(wx,wy,wz,w)` with parameter `u`
+    pub fn get_weighted_point(&self, u: Float) -> Point4 {
         let mut point = Point4::ZERO;
         let basis = self.knots.bspline_basis(self.degree as usize, u);
         for (b, &p) in basis.into_iter().zip(self.control_points.iter()) {
             point += b * p;
         }
+        point
+    }
+}
+
+/// Rational BSpline curve, point (x,y,z) with weight w is (wx,wy,wz,w)
+impl Curve for BSplineCurve<Point4> {
+    fn get_point(&self, u: Float) -> Point3 {
+        let point = self.get_weighted_point(u);
         (1.0 / point.w) * point.truncate()
     }
 
-    fn project(&self, _point: Point3) -> Float {
-        unimplemented!()
+    fn get_derivative(&self, u: Float, order: usize) -> Vec3 {
+        let weighted = (0..=order)
+            .map(|k| {
+                self.nth_derivative(k)
+                    .map_or(Point4::ZERO, |curve| curve.get_weighted_point(u))
+            })
+            .collect::<Vec<_>>();
+        super::rational_derivative(&weighted)
+    }
+
+    fn project(&self, point: Point3) -> Float {
+        super::nearest_parameter(
+            self,
+            point,
+            self.knots.range(),
+            self.control_points.len() * 4,
+            10,
+        )
+    }
+
+    fn period(&self) -> Option<Float> {
+        super::closed_period(self, self.knots.range())
     }
 }
 
@@ -202,3 +332,71 @@ fn test_bspline_curve() {
     };
     dbg!(segment.get_points());
 }
+
+#[test]
+fn test_rational_curve_derivatives() {
+    // quarter circle with radius 2 in xy plane
+    let weight = 0.5_f64.sqrt();
+    let arc = BSplineCurve {
+        control_points: vec![
+            Point4::new(2.0, 0.0, 0.0, 1.0),
+            Point4::new(2.0 * weight, 2.0 * weight, 0.0, weight),
+            Point4::new(0.0, 2.0, 0.0, 1.0),
+        ],
+        knots: KnotVector::uniform_knot(2, 1),
+        degree: 2,
+    };
+    for &u in &[0.3, 0.5, 0.7] {
+        for order in 1..=3 {
+            let exact = arc.get_derivative(u, order);
+            let approximate = super::approximate_derivative(&arc, u, order);
+            assert!((exact - approximate).length() < 1e-4 * exact.length());
+        }
+        assert!((arc.curvature(u) - 0.5).abs() < 1e-9);
+        assert!(arc.torsion(u).abs() < 1e-9);
+        let tangent = arc.tangent(u);
+        assert!(tangent.dot(arc.get_point(u)).abs() < 1e-9);
+    }
+    assert!(arc.get_derivative(0.5, 3).is_finite());
+    assert_eq!(arc.nth_derivative(3).map(|c| c.degree), None);
+}
+
+#[test]
+fn test_rational_curve_operations() {
+    // half circle with radius 2 in xy plane from two rational quadratic arcs
+    let weight = 0.5_f64.sqrt();
+    let arc = BSplineCurve {
+        control_points: vec![
+            Point4::new(2.0, 0.0, 0.0, 1.0),
+            Point4::new(2.0 * weight, 2.0 * weight, 0.0, weight),
+            Point4::new(0.0, 2.0, 0.0, 1.0),
+            Point4::new(-2.0 * weight, 2.0 * weight, 0.0, weight),
+            Point4::new(-2.0, 0.0, 0.0, 1.0),
+        ],
+        knots: KnotVector::from_values_and_multiplicities(vec![0.0, 0.5, 1.0], vec![3, 2, 3]),
+        degree: 2,
+    };
+    let on_circle = |point: Point3| (point.length() - 2.0).abs() < 1e-12;
+
+    let mut refined = arc.clone();
+    refined.add_knot(0.3);
+    assert_eq!(refined.control_points.len(), 6);
+    for &u in &[0.1, 0.3, 0.45, 0.8] {
+        assert!(refined.get_point(u).distance(arc.get_point(u)) < 1e-12);
+    }
+
+    let part = arc.trim((0.2, 0.7));
+    assert_eq!(part.knots.range(), (0.2, 0.7));
+    for &u in &[0.2, 0.4, 0.7] {
+        assert!(part.get_point(u).distance(arc.get_point(u)) < 1e-12);
+    }
+
+    let pieces = arc.to_piecewise_bezier();
+    assert_eq!(pieces.len(), 2);
+    assert!(pieces[1].get_point(0.5).distance(arc.get_point(0.75)) < 1e-12);
+    assert!(on_circle(pieces[0].get_point(0.3)));
+
+    let point = arc.get_point(0.65);
+    assert!((arc.project(point * 1.5) - 0.65).abs() < 1e-9);
+    assert!((pieces[1].project(point) - 0.3).abs() < 1e-9);
+}
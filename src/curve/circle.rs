@@ -1,5 +1,5 @@
-use super::Curve;
-use crate::{Float, Point3, Quat, Vec3};
+use super::{BSplineCurve, Curve, ToNurbs};
+use crate::{Float, KnotVector, Point3, Point4, Quat, Vec3};
 
 #[derive(Debug)]
 pub struct Circle {
@@ -9,6 +9,53 @@ pub struct Circle {
     pub ref_dir: Vec3,
 }
 
+/// Control points `(x, y, weight)` and knots of the unit circular arc over the angle range,
+/// split into pieces of at most a quarter turn so the knots are the angles of the joints.
+pub(crate) fn unit_arc((a0, a1): (Float, Float)) -> (Vec<Vec3>, KnotVector) {
+    let sweep = a1 - a0;
+    let pieces = ((sweep.abs() / crate::consts::FRAC_PI_2 - 1e-9).ceil() as usize).max(1);
+    let step = sweep / pieces as Float;
+    let weight = (step / 2.0).cos();
+    let mut points = vec![Vec3::new(a0.cos(), a0.sin(), 1.0)];
+    let mut knots = vec![a0; 3];
+    for i in 1..=pieces {
+        let angle = a0 + step * i as Float;
+        let middle = angle - step / 2.0;
+        points.push(Vec3::new(
+            middle.cos() / weight,
+            middle.sin() / weight,
+            weight,
+        ));
+        points.push(Vec3::new(angle.cos(), angle.sin(), 1.0));
+        let count = if i == pieces { 3 } else { 2 };
+        knots.extend(std::iter::repeat_n(angle, count));
+    }
+    (points, KnotVector(knots))
+}
+
+impl Circle {
+    /// Rational B-spline of the arc over the angle range, exact at the knots
+    pub fn arc_to_nurbs(&self, range: (Float, Float)) -> BSplineCurve<Point4> {
+        let x_axis = self.ref_dir * self.radius;
+        let y_axis = self.axis.cross(self.ref_dir) * self.radius;
+        let (points, knots) = unit_arc(range);
+        BSplineCurve {
+            control_points: points
+                .into_iter()
+                .map(|p| (self.center + x_axis * p.x + y_axis * p.y).extend(1.0) * p.z)
+                .collect(),
+            knots,
+            degree: 2,
+        }
+    }
+}
+
+impl ToNurbs for Circle {
+    fn to_nurbs(&self) -> BSplineCurve<Point4> {
+        self.arc_to_nurbs((0.0, crate::consts::TAU))
+    }
+}
+
 impl Curve for Circle {
     fn get_point(&self, angle: Float) -> Point3 {
         let rotation = Quat::from_axis_angle(self.axis, angle);
@@ -28,16 +75,22 @@ impl Curve for Circle {
         }
     }
 
+    // Each derivative turns the radius vector a quarter around the axis
+    fn get_derivative(&self, angle: Float, order: usize) -> Vec3 {
+        if order == 0 {
+            return self.get_point(angle);
+        }
+        let angle = angle + order as Float * crate::consts::PI / 2.0;
+        Quat::from_axis_angle(self.axis, angle) * self.ref_dir * self.radius
+    }
+
     // The sense of a curve is in the direction of increasing parameter
     fn refine_parameter_range(&self, range: (Float, Float), same_sense: bool) -> (Float, Float) {
-        let (a0, a1) = range;
-        if same_sense && a0 > a1 {
-            return (a0, a1 + crate::consts::TAU);
-        }
-        if !same_sense && a0 < a1 {
-            return (a0 + crate::consts::TAU, a1);
-        }
-        (a0, a1)
+        super::refine_periodic_range(range, same_sense, crate::consts::TAU)
+    }
+
+    fn period(&self) -> Option<Float> {
+        Some(crate::consts::TAU)
     }
 }
 
@@ -59,3 +112,30 @@ fn test_circle() {
     };
     dbg!(segment.get_points());
 }
+
+#[test]
+fn test_circle_to_nurbs() {
+    let circle = Circle {
+        center: Point3::new(1.0, 2.0, 3.0),
+        radius: 2.0,
+        axis: Vec3::new(0.0, 0.6, 0.8),
+        ref_dir: Vec3::X,
+    };
+    let nurbs = circle.to_nurbs();
+    assert_eq!(nurbs.control_points.len(), 9);
+    for i in 0..=40 {
+        let point = nurbs.get_point(i as Float / 40.0 * crate::consts::TAU);
+        assert!(((point - circle.center).length() - 2.0).abs() < 1e-12);
+        assert!((point - circle.center).dot(circle.axis).abs() < 1e-12);
+    }
+    // the knots are at the joints of the quarter arcs
+    for i in 0..=4 {
+        let angle = i as Float * crate::consts::FRAC_PI_2;
+        assert!(nurbs.get_point(angle).distance(circle.get_point(angle)) < 1e-12);
+    }
+
+    let arc = circle.arc_to_nurbs((0.5, 2.5));
+    assert_eq!(arc.knots.range(), (0.5, 2.5));
+    assert!(arc.get_point(2.5).distance(circle.get_point(2.5)) < 1e-12);
+    assert!(((arc.get_point(1.2) - circle.center).length() - 2.0).abs() < 1e-12);
+}
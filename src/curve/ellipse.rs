@@ -0,0 +1,50 @@
+use super::Curve;
+use crate::consts::{PI, TAU};
+use crate::{Float, Point3, Vec3};
+
+#[derive(Debug)]
+pub struct Ellipse {
+    pub center: Point3,
+    /// Semi axis along `ref_dir`
+    pub semi_axis_1: Float,
+    /// Semi axis along `axis × ref_dir`
+    pub semi_axis_2: Float,
+    pub axis: Vec3,
+    pub ref_dir: Vec3,
+}
+
+impl Curve for Ellipse {
+    fn get_point(&self, angle: Float) -> Point3 {
+        let y_axis = self.axis.cross(self.ref_dir);
+        let (sin, cos) = angle.sin_cos();
+        self.center + self.ref_dir * (self.semi_axis_1 * cos) + y_axis * (self.semi_axis_2 * sin)
+    }
+
+    fn project(&self, point: Point3) -> Float {
+        let y_axis = self.axis.cross(self.ref_dir);
+        let direction = point - self.center;
+        // angle of the point scaled to a circle as initial value
+        let x = direction.dot(self.ref_dir) / self.semi_axis_1;
+        let y = direction.dot(y_axis) / self.semi_axis_2;
+        let angle = y.atan2(x);
+        let angle = super::refine_nearest_parameter(self, point, angle, (-TAU, 2.0 * TAU), 8);
+        angle.rem_euclid(TAU)
+    }
+
+    fn get_derivative(&self, angle: Float, order: usize) -> Vec3 {
+        if order == 0 {
+            return self.get_point(angle);
+        }
+        let y_axis = self.axis.cross(self.ref_dir);
+        let (sin, cos) = (angle + order as Float * PI / 2.0).sin_cos();
+        self.ref_dir * (self.semi_axis_1 * cos) + y_axis * (self.semi_axis_2 * sin)
+    }
+
+    fn refine_parameter_range(&self, range: (Float, Float), same_sense: bool) -> (Float, Float) {
+        super::refine_periodic_range(range, same_sense, TAU)
+    }
+
+    fn period(&self) -> Option<Float> {
+        Some(TAU)
+    }
+}
@@ -0,0 +1,335 @@
+use super::{BSplineCurve, ControlPoint, Curve};
+use crate::utils::{self, Tolerance};
+use crate::{Float, Grid, KnotVector, Point3, Vec3};
+
+/// How parameters in `[0, 1]` are assigned to the data points
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Parameterization {
+    /// Equally spaced
+    Uniform,
+    /// Proportional to the distances between points
+    ChordLength,
+    /// Proportional to the square roots of the distances, better for sharp turns
+    Centripetal,
+}
+
+impl Parameterization {
+    /// Parameters of the points, uniform when all the points coincide
+    pub fn parameters<P: ControlPoint>(self, points: &[P]) -> Vec<Float> {
+        let steps = points
+            .windows(2)
+            .map(|pair| match self {
+                Parameterization::Uniform => 1.0,
+                Parameterization::ChordLength => pair[0].distance(pair[1]),
+                Parameterization::Centripetal => pair[0].distance(pair[1]).sqrt(),
+            })
+            .collect::<Vec<_>>();
+        let total = steps.iter().sum::<Float>();
+        if total.near(0.0) {
+            return utils::uniform_divide((0.0, 1.0), points.len() - 1);
+        }
+        let mut parameters = Vec::with_capacity(points.len());
+        let mut sum = 0.0;
+        parameters.push(0.0);
+        for step in &steps[..steps.len() - 1] {
+            sum += step;
+            parameters.push(sum / total);
+        }
+        parameters.push(1.0);
+        parameters
+    }
+}
+
+/// Clamped knots whose interior ones average `degree` consecutive parameters,
+/// starting from the `first` parameter
+fn averaged_knots(parameters: &[Float], degree: usize, first: usize, count: usize) -> KnotVector {
+    let mut knots = vec![0.0; degree + 1];
+    for j in first..first + count {
+        knots.push(parameters[j..j + degree].iter().sum::<Float>() / degree as Float);
+    }
+    knots.extend(std::iter::repeat_n(1.0, degree + 1));
+    KnotVector(knots)
+}
+
+fn basis_rows(knots: &KnotVector, degree: usize, parameters: &[Float]) -> Grid<Float> {
+    let rows = parameters
+        .iter()
+        .flat_map(|&u| knots.bspline_basis(degree, u))
+        .collect();
+    Grid::from_vec(rows, knots.len() - degree - 1)
+}
+
+/// Knots for interpolating points at the parameters
+pub(crate) fn interpolation_knots(parameters: &[Float], degree: usize) -> KnotVector {
+    averaged_knots(parameters, degree, 1, parameters.len() - 1 - degree)
+}
+
+/// Control points of the B-spline interpolating the points at the parameters,
+/// `None` when the parameters do not fit the knots
+pub(crate) fn interpolate_control_points<P: ControlPoint>(
+    points: &[P],
+    parameters: &[Float],
+    knots: &KnotVector,
+    degree: usize,
+) -> Option<Vec<P>> {
+    let matrix = basis_rows(knots, degree, parameters);
+    utils::solve_linear_system(matrix, points.to_vec())
+}
+
+impl BSplineCurve<Point3> {
+    /// Interpolate the points globally, `None` when points coincide
+    pub fn interpolate(
+        points: &[Point3],
+        degree: usize,
+        parameterization: Parameterization,
+    ) -> Option<Self> {
+        assert!(degree >= 1 && points.len() > degree);
+        let parameters = parameterization.parameters(points);
+        let knots = interpolation_knots(&parameters, degree);
+        let control_points = interpolate_control_points(points, &parameters, &knots, degree)?;
+        Some(BSplineCurve {
+            control_points,
+            knots,
+            degree: degree as u8,
+        })
+    }
+
+    /// Interpolate the points globally with tangent directions at both ends,
+    /// the lengths of the end derivatives are set to the total chord length.
+    pub fn interpolate_with_tangents(
+        points: &[Point3],
+        degree: usize,
+        parameterization: Parameterization,
+        (start, end): (Vec3, Vec3),
+    ) -> Option<Self> {
+        assert!(degree >= 2 && points.len() >= 2 && points.len() + 1 >= degree);
+        let parameters = parameterization.parameters(points);
+        let n = points.len() - 1;
+        let knots = averaged_knots(&parameters, degree, 0, n + 2 - degree);
+        let chord = points
+            .windows(2)
+            .map(|pair| pair[0].distance(pair[1]))
+            .sum::<Float>();
+        let p = degree as Float;
+        let size = n + 3;
+        // rows: Q_0, start derivative, Q_1 .. Q_(n-1), end derivative, Q_n
+        let mut matrix = Grid::init(size, size, 0.0);
+        let mut rhs = Vec::with_capacity(size);
+        for (k, &u) in parameters.iter().enumerate() {
+            let row = match k {
+                0 => 0,
+                k if k == n => n + 2,
+                k => k + 1,
+            };
+            for (j, value) in knots.bspline_basis(degree, u).into_iter().enumerate() {
+                matrix[row][j] = value;
+            }
+        }
+        matrix[1][0] = -1.0;
+        matrix[1][1] = 1.0;
+        matrix[n + 1][n + 1] = -1.0;
+        matrix[n + 1][n + 2] = 1.0;
+        rhs.push(points[0]);
+        rhs.push(start.normalize_or_zero() * (chord * knots[degree + 1] / p));
+        rhs.extend(&points[1..n]);
+        rhs.push(end.normalize_or_zero() * (chord * (1.0 - knots[n + 2]) / p));
+        rhs.push(points[n]);
+        let control_points = utils::solve_linear_system(matrix, rhs)?;
+        Some(BSplineCurve {
+            control_points,
+            knots,
+            degree: degree as u8,
+        })
+    }
+
+    /// Interpolate the points by cubic pieces joined with tangent continuity, each piece
+    /// depends only on nearby points. Coincident consecutive points are merged.
+    pub fn interpolate_local_cubic(points: &[Point3]) -> Self {
+        let mut points = points.to_vec();
+        points.dedup_by(|a, b| a.distance(*b).near(0.0));
+        assert!(points.len() >= 2);
+        let n = points.len() - 1;
+        let tangents = estimate_tangents(&points);
+        let mut control_points = vec![points[0]];
+        let mut parameters = vec![0.0];
+        for k in 0..n {
+            let (p0, p3) = (points[k], points[k + 1]);
+            let (t0, t3) = (tangents[k], tangents[k + 1]);
+            // speeds at both ends are equal to make the joints smooth
+            let a = 16.0 - (t0 + t3).length_squared();
+            let b = 12.0 * (p3 - p0).dot(t0 + t3);
+            let c = -36.0 * (p3 - p0).length_squared();
+            let alpha = (-b + (b * b - 4.0 * a * c).sqrt()) / (2.0 * a);
+            control_points.push(p0 + t0 * (alpha / 3.0));
+            control_points.push(p3 - t3 * (alpha / 3.0));
+            parameters.push(parameters[k] + alpha);
+        }
+        control_points.push(points[n]);
+        let total = parameters[n];
+        let mut knots = vec![0.0; 4];
+        for &u in &parameters[1..n] {
+            knots.extend([u / total; 2]);
+        }
+        knots.extend([1.0; 4]);
+        BSplineCurve {
+            control_points,
+            knots: KnotVector(knots),
+            degree: 3,
+        }
+    }
+
+    /// Approximate the points by least squares within the tolerance at their parameters,
+    /// adding knots until it is met. The end points are interpolated.
+    pub fn approximate(
+        points: &[Point3],
+        degree: usize,
+        parameterization: Parameterization,
+        tolerance: Float,
+    ) -> Option<Self> {
+        assert!(degree >= 1 && points.len() > degree);
+        let parameters = parameterization.parameters(points);
+        let mut count = degree + 1;
+        loop {
+            if count >= points.len() {
+                return Self::interpolate(points, degree, parameterization);
+            }
+            let curve = least_squares_curve(points, &parameters, degree, count)?;
+            let deviation = points
+                .iter()
+                .zip(&parameters)
+                .map(|(point, &u)| point.distance(curve.get_point(u)))
+                .fold(0.0, Float::max);
+            if deviation <= tolerance {
+                return Some(curve);
+            }
+            count += (count / 2).max(1);
+        }
+    }
+}
+
+/// Unit tangents estimated from five neighboring points, keeping corners sharp
+fn estimate_tangents(points: &[Point3]) -> Vec<Vec3> {
+    let n = points.len() - 1;
+    if n == 1 {
+        let tangent = (points[1] - points[0]).normalize();
+        return vec![tangent; 2];
+    }
+    // chords q[k + 2] = Q_k+1 - Q_k, extended by two at both ends
+    let mut chords = vec![Vec3::ZERO; 2];
+    chords.extend(points.windows(2).map(|pair| pair[1] - pair[0]));
+    chords[1] = chords[2] * 2.0 - chords[3];
+    chords[0] = chords[1] * 2.0 - chords[2];
+    for _ in 0..2 {
+        let last = chords.len() - 1;
+        chords.push(chords[last] * 2.0 - chords[last - 1]);
+    }
+    (0..=n)
+        .map(|k| {
+            let before = chords[k].cross(chords[k + 1]).length();
+            let after = chords[k + 2].cross(chords[k + 3]).length();
+            let alpha = if (before + after).near(0.0) {
+                0.5
+            } else {
+                before / (before + after)
+            };
+            (chords[k + 1] * (1.0 - alpha) + chords[k + 2] * alpha).normalize_or_zero()
+        })
+        .collect()
+}
+
+/// Least squares curve with `count` control points interpolating the end points,
+/// knots are placed so that every span contains parameters.
+fn least_squares_curve(
+    points: &[Point3],
+    parameters: &[Float],
+    degree: usize,
+    count: usize,
+) -> Option<BSplineCurve<Point3>> {
+    let m = points.len() - 1;
+    let n = count - 1;
+    let d = (m + 1) as Float / (n - degree + 1) as Float;
+    let mut knots = vec![0.0; degree + 1];
+    for j in 1..=n - degree {
+        let i = (j as Float * d).floor() as usize;
+        let alpha = j as Float * d - i as Float;
+        knots.push((1.0 - alpha) * parameters[i - 1] + alpha * parameters[i]);
+    }
+    knots.extend(std::iter::repeat_n(1.0, degree + 1));
+    let knots = KnotVector(knots);
+    let mut control_points = vec![points[0]; count];
+    control_points[n] = points[m];
+    if n >= 2 {
+        let rows = basis_rows(&knots, degree, &parameters[1..m]);
+        let inner = (0..m - 1)
+            .flat_map(|k| rows.iter_row(k).skip(1).take(n - 1).copied())
+            .collect();
+        let rhs = (1..m)
+            .map(|k| points[k] - points[0] * rows[k - 1][0] - points[m] * rows[k - 1][n])
+            .collect::<Vec<_>>();
+        let solution = utils::solve_least_squares(&Grid::from_vec(inner, n - 1), &rhs)?;
+        control_points[1..n].copy_from_slice(&solution);
+    }
+    Some(BSplineCurve {
+        control_points,
+        knots,
+        degree: degree as u8,
+    })
+}
+
+#[test]
+fn test_curve_interpolation() {
+    use crate::curve::Circle;
+
+    let circle = Circle {
+        center: Point3::new(1.0, 2.0, 0.0),
+        radius: 3.0,
+        axis: Vec3::Z,
+        ref_dir: Vec3::X,
+    };
+    let angles = [0.0, 0.3, 0.5, 1.2, 1.4, 2.5, 3.0];
+    let points = angles.map(|a| circle.get_point(a));
+    for parameterization in [
+        Parameterization::Uniform,
+        Parameterization::ChordLength,
+        Parameterization::Centripetal,
+    ] {
+        let curve = BSplineCurve::interpolate(&points, 3, parameterization).unwrap();
+        for (point, u) in points.iter().zip(parameterization.parameters(&points)) {
+            assert!(point.distance(curve.get_point(u)) < 1e-12);
+        }
+    }
+
+    let (start, end) = (circle.tangent(0.0), circle.tangent(3.0));
+    let curve = BSplineCurve::interpolate_with_tangents(
+        &points,
+        3,
+        Parameterization::ChordLength,
+        (start, end),
+    )
+    .unwrap();
+    assert!(curve.get_point(1.0).distance(points[6]) < 1e-12);
+    assert!(curve.tangent(0.0).distance(start) < 1e-12);
+    assert!(curve.tangent(1.0).distance(end) < 1e-12);
+
+    let curve = BSplineCurve::interpolate_local_cubic(&points);
+    let interior = curve
+        .knots
+        .windows(2)
+        .filter(|pair| pair[0] == pair[1] && pair[0] > 0.0 && pair[0] < 1.0);
+    for pair in interior {
+        let u = pair[0];
+        assert!(points
+            .iter()
+            .any(|point| point.distance(curve.get_point(u)) < 1e-12));
+        let (before, after) = (curve.tangent(u - 1e-9), curve.tangent(u + 1e-9));
+        assert!(before.distance(after) < 1e-6);
+    }
+
+    let points = utils::uniform_divide((0.0, 3.0), 200).into_iter();
+    let points = points.map(|a| circle.get_point(a)).collect::<Vec<_>>();
+    let curve = BSplineCurve::approximate(&points, 3, Parameterization::ChordLength, 1e-4).unwrap();
+    assert!(curve.control_points.len() < 20);
+    for point in points {
+        assert!(point.distance(curve.get_point(curve.project(point))) < 1e-4);
+    }
+}
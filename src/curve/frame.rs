@@ -0,0 +1,113 @@
+use super::Curve;
+use crate::{Float, Mat4, Point3, Vec3};
+
+/// Orthonormal frame moving along a curve
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frame {
+    pub origin: Point3,
+    pub tangent: Vec3,
+    pub normal: Vec3,
+    pub binormal: Vec3,
+}
+
+impl Frame {
+    /// Matrix mapping x, y, z axes to normal, binormal, tangent placed at the origin
+    pub fn to_matrix(&self) -> Mat4 {
+        Mat4::from_cols(
+            self.normal.extend(0.0),
+            self.binormal.extend(0.0),
+            self.tangent.extend(0.0),
+            self.origin.extend(1.0),
+        )
+    }
+}
+
+/// Rotation minimizing frames at the parameters computed by the double reflection method,
+/// `normal` is projected to be perpendicular to the tangent at the first parameter.
+pub fn rotation_minimizing_frames<C: Curve + ?Sized>(
+    curve: &C,
+    parameters: &[Float],
+    normal: Vec3,
+) -> Vec<Frame> {
+    let mut frames = Vec::<Frame>::with_capacity(parameters.len());
+    for &u in parameters {
+        let origin = curve.get_point(u);
+        let mut tangent = curve.tangent(u);
+        let frame = match frames.last() {
+            None => {
+                let mut normal = normal - tangent * normal.dot(tangent);
+                if normal.length_squared() == 0.0 {
+                    normal = tangent.any_orthonormal_vector();
+                }
+                let normal = normal.normalize();
+                Frame {
+                    origin,
+                    tangent,
+                    normal,
+                    binormal: tangent.cross(normal),
+                }
+            }
+            Some(previous) => {
+                if tangent == Vec3::ZERO {
+                    tangent = previous.tangent;
+                }
+                // reflect the previous frame by the bisector plane of the two origins
+                let v1 = origin - previous.origin;
+                let c1 = v1.length_squared();
+                if c1 == 0.0 {
+                    Frame {
+                        origin,
+                        ..*previous
+                    }
+                } else {
+                    let normal = previous.normal - v1 * (2.0 / c1 * v1.dot(previous.normal));
+                    let reflected = previous.tangent - v1 * (2.0 / c1 * v1.dot(previous.tangent));
+                    // then by the plane making the reflected tangent the new tangent
+                    let v2 = tangent - reflected;
+                    let c2 = v2.length_squared();
+                    let normal = if c2 == 0.0 {
+                        normal
+                    } else {
+                        normal - v2 * (2.0 / c2 * v2.dot(normal))
+                    };
+                    let normal = (normal - tangent * normal.dot(tangent)).normalize();
+                    Frame {
+                        origin,
+                        tangent,
+                        normal,
+                        binormal: tangent.cross(normal),
+                    }
+                }
+            }
+        };
+        frames.push(frame);
+    }
+    frames
+}
+
+#[test]
+fn test_rotation_minimizing_frames() {
+    use crate::curve::Circle;
+    use crate::utils;
+
+    let circle = Circle {
+        center: Point3::new(1.0, 2.0, 3.0),
+        axis: Vec3::Z,
+        ref_dir: Vec3::X,
+        radius: 2.0,
+    };
+    let parameters = utils::uniform_divide((0.0, crate::consts::TAU), 64);
+    // a frame starting along the axis of a planar curve stays along it
+    let frames = rotation_minimizing_frames(&circle, &parameters, Vec3::new(0.0, 0.3, 1.0));
+    for frame in &frames {
+        assert!((frame.normal - Vec3::Z).length() < 1e-9);
+        assert!(frame.tangent.dot(frame.normal).abs() < 1e-12);
+        assert!((frame.binormal.length() - 1.0).abs() < 1e-12);
+    }
+
+    let frenet = circle.frenet_frame(1.0).unwrap();
+    assert!((frenet.normal + (frenet.origin - circle.center) / 2.0).length() < 1e-9);
+    assert!((frenet.binormal - Vec3::Z).length() < 1e-9);
+    let matrix = frenet.to_matrix();
+    assert!((matrix.transform_point3(Point3::ZERO) - frenet.origin).length() < 1e-12);
+}
@@ -0,0 +1,28 @@
+use super::Curve;
+use crate::{Float, Point3, Vec3};
+
+/// Branch of hyperbola `center + semi_axis * cosh(u) * ref_dir + semi_imag_axis * sinh(u) * (axis × ref_dir)`
+#[derive(Debug)]
+pub struct Hyperbola {
+    pub center: Point3,
+    pub semi_axis: Float,
+    pub semi_imag_axis: Float,
+    pub axis: Vec3,
+    pub ref_dir: Vec3,
+}
+
+impl Curve for Hyperbola {
+    fn get_point(&self, u: Float) -> Point3 {
+        let y_axis = self.axis.cross(self.ref_dir);
+        self.center
+            + self.ref_dir * (self.semi_axis * u.cosh())
+            + y_axis * (self.semi_imag_axis * u.sinh())
+    }
+
+    fn project(&self, point: Point3) -> Float {
+        let y_axis = self.axis.cross(self.ref_dir);
+        let u = ((point - self.center).dot(y_axis) / self.semi_imag_axis).asinh();
+        let range = (u - u.abs() - 1.0, u + u.abs() + 1.0);
+        super::refine_nearest_parameter(self, point, u, range, 8)
+    }
+}
@@ -1,5 +1,5 @@
-use super::Curve;
-use crate::{Float, Point3, Vec3};
+use super::{BSplineCurve, Curve};
+use crate::{Float, KnotVector, Point3, Point4, Vec3};
 
 #[derive(Debug)]
 pub struct Line {
@@ -7,12 +7,34 @@ pub struct Line {
     pub direction: Vec3,
 }
 
+impl Line {
+    /// Rational B-spline of the segment over the parameter range
+    pub fn to_nurbs(&self, (u0, u1): (Float, Float)) -> BSplineCurve<Point4> {
+        BSplineCurve {
+            control_points: vec![
+                self.get_point(u0).extend(1.0),
+                self.get_point(u1).extend(1.0),
+            ],
+            knots: KnotVector(vec![u0, u0, u1, u1]),
+            degree: 1,
+        }
+    }
+}
+
 impl Curve for Line {
     fn get_point(&self, u: Float) -> Point3 {
         self.origin + self.direction * u
     }
 
     fn project(&self, point: Point3) -> Float {
-        (point - self.origin).dot(self.direction)
+        (point - self.origin).dot(self.direction) / self.direction.length_squared()
+    }
+
+    fn get_derivative(&self, u: Float, order: usize) -> Vec3 {
+        match order {
+            0 => self.get_point(u),
+            1 => self.direction,
+            _ => Vec3::ZERO,
+        }
     }
 }
@@ -0,0 +1,37 @@
+use super::Curve;
+use crate::{Float, Point3, Vec3};
+
+/// Curve at a constant distance from a basis curve in the direction `tangent × ref_direction`
+#[derive(Debug)]
+pub struct OffsetCurve<C: Curve> {
+    pub basis: C,
+    pub distance: Float,
+    pub ref_direction: Vec3,
+}
+
+impl<C: Curve> Curve for OffsetCurve<C> {
+    fn get_point(&self, u: Float) -> Point3 {
+        let tangent = self.basis.get_derivative(u, 1);
+        let offset = tangent.cross(self.ref_direction);
+        let offset = if offset.length_squared() > 0.0 {
+            offset.normalize()
+        } else {
+            offset
+        };
+        self.basis.get_point(u) + offset * self.distance
+    }
+
+    fn project(&self, point: Point3) -> Float {
+        let u = self.basis.project(point);
+        let reach = self.distance.abs().max(1.0);
+        super::refine_nearest_parameter(self, point, u, (u - reach, u + reach), 8)
+    }
+
+    fn refine_parameter_range(&self, range: (Float, Float), same_sense: bool) -> (Float, Float) {
+        self.basis.refine_parameter_range(range, same_sense)
+    }
+
+    fn period(&self) -> Option<Float> {
+        self.basis.period()
+    }
+}
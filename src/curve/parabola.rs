@@ -0,0 +1,28 @@
+use super::Curve;
+use crate::{Float, Point3, Vec3};
+
+/// Parabola `center + focal_dist * (u² * ref_dir + 2u * (axis × ref_dir))`
+#[derive(Debug)]
+pub struct Parabola {
+    /// Apex of the parabola
+    pub center: Point3,
+    /// Distance from the apex to the focus
+    pub focal_dist: Float,
+    pub axis: Vec3,
+    /// Axis of symmetry pointing to the focus
+    pub ref_dir: Vec3,
+}
+
+impl Curve for Parabola {
+    fn get_point(&self, u: Float) -> Point3 {
+        let y_axis = self.axis.cross(self.ref_dir);
+        self.center + (self.ref_dir * (u * u) + y_axis * (2.0 * u)) * self.focal_dist
+    }
+
+    fn project(&self, point: Point3) -> Float {
+        let y_axis = self.axis.cross(self.ref_dir);
+        let u = (point - self.center).dot(y_axis) / (2.0 * self.focal_dist);
+        let range = (u - u.abs() - 1.0, u + u.abs() + 1.0);
+        super::refine_nearest_parameter(self, point, u, range, 8)
+    }
+}
@@ -1,5 +1,5 @@
-use super::Curve;
-use crate::{utils, Float, Point3};
+use super::{BSplineCurve, Curve, ToNurbs};
+use crate::{utils, Float, KnotVector, Point3, Point4, Vec3};
 
 #[derive(Debug)]
 pub struct Polyline {
@@ -37,6 +37,24 @@ impl Polyline {
     }
 }
 
+impl ToNurbs for Polyline {
+    /// Knots are at the length ratios of the vertices
+    fn to_nurbs(&self) -> BSplineCurve<Point4> {
+        let mut knots = vec![0.0; 2];
+        let mut length = 0.0;
+        for &segment_length in &self.segment_lengths[..self.segment_lengths.len() - 1] {
+            length += segment_length;
+            knots.push(length / self.length);
+        }
+        knots.extend([1.0; 2]);
+        BSplineCurve {
+            control_points: self.vertices.iter().map(|v| v.extend(1.0)).collect(),
+            knots: KnotVector(knots),
+            degree: 1,
+        }
+    }
+}
+
 impl Curve for Polyline {
     fn get_point(&self, t: Float) -> Point3 {
         if t <= 0.0 {
@@ -74,4 +92,22 @@ impl Curve for Polyline {
                 .dot((self.vertices[min_index + 1] - self.vertices[min_index]).normalize());
         (length / self.length).clamp(0.0, 1.0)
     }
+
+    fn get_derivative(&self, t: Float, order: usize) -> Vec3 {
+        match order {
+            0 => self.get_point(t),
+            1 => {
+                // segment containing the parameter, the speed is the total length
+                let mut length = t.clamp(0.0, 1.0) * self.length;
+                let mut index = 0;
+                while index + 1 < self.segment_lengths.len() && length > self.segment_lengths[index]
+                {
+                    length -= self.segment_lengths[index];
+                    index += 1;
+                }
+                (self.vertices[index + 1] - self.vertices[index]).normalize_or_zero() * self.length
+            }
+            _ => Vec3::ZERO,
+        }
+    }
 }
@@ -0,0 +1,437 @@
+use super::{BSplineCurve, BezierCurve, ControlPoint};
+use crate::{utils, Float, KnotVector};
+
+fn binomial(n: usize, k: usize) -> Float {
+    (0..k).fold(1.0, |value, i| value * (n - i) as Float / (i + 1) as Float)
+}
+
+/// Control points of the Bezier curve raised by `times` degrees
+fn elevate_bezier<P: ControlPoint>(points: &[P], times: usize) -> Vec<P> {
+    let p = points.len() - 1;
+    (0..=p + times)
+        .map(|i| {
+            let start = i.saturating_sub(times);
+            (start..=p.min(i)).fold(P::default(), |sum, j| {
+                let factor = binomial(p, j) * binomial(times, i - j) / binomial(p + times, i);
+                sum + points[j] * factor
+            })
+        })
+        .collect()
+}
+
+/// Control points of the Bezier curve lowered by one degree keeping the end points,
+/// and the largest deviation of control points after raising them back.
+fn reduce_bezier<P: ControlPoint>(points: &[P]) -> (Vec<P>, Float) {
+    let p = points.len() - 1;
+    let alpha = |i: usize| i as Float / p as Float;
+    let r = (p - 1) / 2;
+    let mut reduced = vec![P::default(); p];
+    reduced[0] = points[0];
+    reduced[p - 1] = points[p];
+    let left = |reduced: &[P], i: usize| {
+        (points[i] - reduced[i - 1] * alpha(i)) * (1.0 / (1.0 - alpha(i)))
+    };
+    let right = |reduced: &[P], i: usize| {
+        (points[i + 1] - reduced[i + 1] * (1.0 - alpha(i + 1))) * (1.0 / alpha(i + 1))
+    };
+    let odd = p % 2 == 1;
+    let left_end = if odd { r.saturating_sub(1) } else { r };
+    for i in 1..=left_end {
+        reduced[i] = left(&reduced, i);
+    }
+    for i in (r + 1..=p.saturating_sub(2)).rev() {
+        reduced[i] = right(&reduced, i);
+    }
+    if odd && r > 0 {
+        reduced[r] = (left(&reduced, r) + right(&reduced, r)) * 0.5;
+    }
+    let error = elevate_bezier(&reduced, 1)
+        .into_iter()
+        .zip(points)
+        .map(|(a, &b)| a.distance(b))
+        .fold(0.0, Float::max);
+    (reduced, error)
+}
+
+/// Join Bezier segments meeting at the breakpoints into a B-spline with interior knots
+/// of multiplicity `degree`.
+fn join_bezier_segments<P: ControlPoint>(
+    segments: Vec<Vec<P>>,
+    breakpoints: &[Float],
+    degree: usize,
+) -> BSplineCurve<P> {
+    let mut control_points = Vec::with_capacity(segments.len() * degree + 1);
+    for (index, segment) in segments.into_iter().enumerate() {
+        let skip = if index == 0 { 0 } else { 1 };
+        control_points.extend(segment.into_iter().skip(skip));
+    }
+    let last = breakpoints.len() - 1;
+    let multiplicities = (0..breakpoints.len())
+        .map(|i| {
+            if i == 0 || i == last {
+                degree + 1
+            } else {
+                degree
+            }
+        })
+        .collect();
+    BSplineCurve {
+        control_points,
+        knots: KnotVector::from_values_and_multiplicities(breakpoints.to_vec(), multiplicities),
+        degree: degree as u8,
+    }
+}
+
+/// Remove the knot `times` times from all curves sharing their knots or from none of them,
+/// tries fewer removals when some curve fails, returns the number of removals.
+pub(crate) fn remove_knot_from_all<P: ControlPoint>(
+    curves: &mut [BSplineCurve<P>],
+    knot: Float,
+    times: usize,
+    tolerance: Float,
+) -> usize {
+    for count in (1..=times).rev() {
+        let mut trial = curves.to_vec();
+        if trial
+            .iter_mut()
+            .all(|curve| curve.remove_knot(knot, count, tolerance) == count)
+        {
+            curves.clone_from_slice(&trial);
+            return count;
+        }
+    }
+    0
+}
+
+/// Remove interior knots of curves sharing their knots as often as the tolerance allows
+pub(crate) fn remove_knots_from_all<P: ControlPoint>(
+    curves: &mut [BSplineCurve<P>],
+    tolerance: Float,
+) -> usize {
+    let (values, multiplicities) = curves[0].knots.to_values_and_multiplicities();
+    let last = values.len() - 1;
+    (1..last)
+        .map(|i| remove_knot_from_all(curves, values[i], multiplicities[i], tolerance))
+        .sum()
+}
+
+/// Raise degrees of curves sharing their knots, they keep sharing knots
+pub(crate) fn elevate_degree_of_all<P: ControlPoint>(
+    curves: &[BSplineCurve<P>],
+    times: usize,
+) -> Vec<BSplineCurve<P>> {
+    if times == 0 {
+        return curves.to_vec();
+    }
+    let p = curves[0].degree();
+    let (values, multiplicities) = curves[0].knots.to_values_and_multiplicities();
+    let mut elevated = curves
+        .iter()
+        .map(|curve| {
+            let segments = curve
+                .to_piecewise_bezier()
+                .into_iter()
+                .map(|bezier| elevate_bezier(&bezier.control_points, times))
+                .collect();
+            join_bezier_segments(segments, &values, p + times)
+        })
+        .collect::<Vec<_>>();
+    // the elevated curves keep the continuity, the extra knots are removable
+    let last = values.len() - 1;
+    for i in 1..last {
+        let extra = p.saturating_sub(multiplicities[i]);
+        remove_knot_from_all(&mut elevated, values[i], extra, utils::TOLERANCE);
+    }
+    elevated
+}
+
+/// Lower degrees of curves sharing their knots by one, `None` when they deviate more than
+/// the tolerance, interior knots are removed again as far as the tolerance allows.
+pub(crate) fn reduce_degree_of_all<P: ControlPoint>(
+    curves: &[BSplineCurve<P>],
+    tolerance: Float,
+) -> Option<Vec<BSplineCurve<P>>> {
+    let p = curves[0].degree();
+    if p < 2 {
+        return None;
+    }
+    let (values, multiplicities) = curves[0].knots.to_values_and_multiplicities();
+    let mut error: Float = 0.0;
+    let mut reduced = curves
+        .iter()
+        .map(|curve| {
+            let segments = curve
+                .to_piecewise_bezier()
+                .into_iter()
+                .map(|bezier| {
+                    let (points, deviation) = reduce_bezier(&bezier.control_points);
+                    error = error.max(deviation);
+                    points
+                })
+                .collect();
+            join_bezier_segments(segments, &values, p - 1)
+        })
+        .collect::<Vec<_>>();
+    if error > tolerance {
+        return None;
+    }
+    let last = values.len() - 1;
+    for i in 1..last {
+        // knots keep their continuity, i.e. lose one multiplicity with the degree
+        let extra = p.saturating_sub(multiplicities[i]);
+        remove_knot_from_all(&mut reduced, values[i], extra, tolerance - error);
+    }
+    Some(reduced)
+}
+
+impl<P: ControlPoint> BSplineCurve<P> {
+    /// Insert the interior knots keeping the shape, all in one pass (Piegl & Tiller A5.4)
+    pub fn refine_knots(&mut self, knots: &[Float]) {
+        let (start, end) = self.knots.range();
+        let mut inserted = knots
+            .iter()
+            .copied()
+            .filter(|&knot| knot > start && knot < end)
+            .collect::<Vec<_>>();
+        if inserted.is_empty() {
+            return;
+        }
+        inserted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let p = self.degree();
+        let n = self.control_points.len() - 1;
+        let r = inserted.len() - 1;
+        let old_knots = &self.knots.0;
+        let m = old_knots.len() - 1;
+        let a = self.knots.span_index(inserted[0]).clamp(p, n);
+        let b = self.knots.span_index(inserted[r]).clamp(p, n) + 1;
+        let points = &self.control_points;
+        let mut new_points = vec![P::default(); n + r + 2];
+        let mut new_knots = vec![0.0; m + r + 2];
+        new_points[..=a - p].copy_from_slice(&points[..=a - p]);
+        new_points[b + r..].copy_from_slice(&points[b - 1..]);
+        new_knots[..=a].copy_from_slice(&old_knots[..=a]);
+        new_knots[b + p + r + 1..].copy_from_slice(&old_knots[b + p..]);
+        let (mut i, mut k) = (b + p - 1, b + p + r);
+        for &knot in inserted.iter().rev() {
+            while knot <= old_knots[i] && i > a {
+                new_points[k - p - 1] = points[i - p - 1];
+                new_knots[k] = old_knots[i];
+                k -= 1;
+                i -= 1;
+            }
+            new_points[k - p - 1] = new_points[k - p];
+            for l in 1..=p {
+                let index = k - p + l;
+                let alpha = new_knots[k + l] - knot;
+                new_points[index - 1] = if alpha.abs() == 0.0 {
+                    new_points[index]
+                } else {
+                    let alpha = alpha / (new_knots[k + l] - old_knots[i + l - p]);
+                    new_points[index - 1] * alpha + new_points[index] * (1.0 - alpha)
+                };
+            }
+            new_knots[k] = knot;
+            k -= 1;
+        }
+        self.control_points = new_points;
+        self.knots = KnotVector(new_knots);
+    }
+
+    /// Remove an interior knot up to `times` times while the removed control points
+    /// can be recovered within `tolerance`, returns the number of removals.
+    pub fn remove_knot(&mut self, knot: Float, times: usize, tolerance: Float) -> usize {
+        let tolerance = P::removal_tolerance(&self.control_points, tolerance);
+        let p = self.degree();
+        let n = self.control_points.len() - 1;
+        let knots = &mut self.knots.0;
+        let m = knots.len() - 1;
+        let r = match knots
+            .iter()
+            .rposition(|&u| (u - knot).abs() <= utils::TOLERANCE)
+        {
+            Some(r) if r > p && r <= n => r,
+            _ => return 0,
+        };
+        let u = knots[r];
+        let s = knots
+            .iter()
+            .filter(|&&k| (k - u).abs() <= utils::TOLERANCE)
+            .count();
+        let times = times.min(s);
+        let order = p + 1;
+        let first_out = (2 * r - s - p) / 2;
+        let points = &mut self.control_points;
+        let mut temp = vec![P::default(); 2 * p + 3];
+        let (mut first, mut last) = ((r - p) as isize, (r - s) as isize);
+        let mut t = 0;
+        while t < times {
+            let ti = t as isize;
+            let off = first - 1;
+            temp[0] = points[off as usize];
+            temp[(last + 1 - off) as usize] = points[(last + 1) as usize];
+            let (mut i, mut j) = (first, last);
+            let (mut ii, mut jj) = (1, last - off);
+            while j - i > ti {
+                let (iu, ju) = (i as usize, j as usize);
+                let alpha_i = (u - knots[iu]) / (knots[iu + order + t] - knots[iu]);
+                let alpha_j = (u - knots[ju - t]) / (knots[ju + order] - knots[ju - t]);
+                temp[ii as usize] =
+                    (points[iu] - temp[(ii - 1) as usize] * (1.0 - alpha_i)) * (1.0 / alpha_i);
+                temp[jj as usize] =
+                    (points[ju] - temp[(jj + 1) as usize] * alpha_j) * (1.0 / (1.0 - alpha_j));
+                i += 1;
+                ii += 1;
+                j -= 1;
+                jj -= 1;
+            }
+            let removable = if j - i < ti {
+                temp[(ii - 1) as usize].distance(temp[(jj + 1) as usize]) <= tolerance
+            } else {
+                let iu = i as usize;
+                let alpha_i = (u - knots[iu]) / (knots[iu + order + t] - knots[iu]);
+                let point = temp[(ii + ti + 1) as usize] * alpha_i
+                    + temp[(ii - 1) as usize] * (1.0 - alpha_i);
+                points[iu].distance(point) <= tolerance
+            };
+            if !removable {
+                break;
+            }
+            let (mut i, mut j) = (first, last);
+            while j - i > ti {
+                points[i as usize] = temp[(i - off) as usize];
+                points[j as usize] = temp[(j - off) as usize];
+                i += 1;
+                j -= 1;
+            }
+            first -= 1;
+            last += 1;
+            t += 1;
+        }
+        if t == 0 {
+            return 0;
+        }
+        for k in r + 1..=m {
+            knots[k - t] = knots[k];
+        }
+        knots.truncate(m + 1 - t);
+        let (mut i, mut j) = (first_out, first_out);
+        for k in 1..t {
+            if k % 2 == 1 {
+                i += 1;
+            } else {
+                j -= 1;
+            }
+        }
+        for k in i + 1..=n {
+            points[j] = points[k];
+            j += 1;
+        }
+        points.truncate(n + 1 - t);
+        t
+    }
+
+    /// Remove interior knots as often as the tolerance allows, returns the number of removals
+    pub fn remove_knots(&mut self, tolerance: Float) -> usize {
+        remove_knots_from_all(std::slice::from_mut(self), tolerance)
+    }
+
+    /// Raise the degree by `times` keeping the shape and continuity
+    pub fn elevate_degree(&self, times: usize) -> BSplineCurve<P> {
+        elevate_degree_of_all(std::slice::from_ref(self), times)
+            .pop()
+            .unwrap()
+    }
+
+    /// Lower the degree by one, `None` when the control points would deviate more than
+    /// the tolerance
+    pub fn reduce_degree(&self, tolerance: Float) -> Option<BSplineCurve<P>> {
+        reduce_degree_of_all(std::slice::from_ref(self), tolerance)
+            .and_then(|mut curves| curves.pop())
+    }
+}
+
+impl<P: ControlPoint> BezierCurve<P> {
+    /// Raise the degree by `times` keeping the shape
+    pub fn elevate_degree(&self, times: usize) -> BezierCurve<P> {
+        BezierCurve {
+            control_points: elevate_bezier(&self.control_points, times),
+        }
+    }
+}
+
+#[test]
+fn test_knot_removal_and_degree_change() {
+    use crate::curve::Curve;
+    use crate::Point3;
+
+    let curve = BSplineCurve {
+        control_points: vec![
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 2.0, 0.0),
+            Point3::new(3.0, 3.0, 1.0),
+            Point3::new(5.0, 1.0, 2.0),
+            Point3::new(6.0, -1.0, 1.0),
+            Point3::new(8.0, 0.0, 0.0),
+        ],
+        knots: KnotVector::uniform_knot(3, 3),
+        degree: 3,
+    };
+    let same = |other: &BSplineCurve<Point3>, tolerance: Float| {
+        utils::uniform_divide((0.0, 1.0), 50)
+            .into_iter()
+            .all(|u| curve.get_point(u).distance(other.get_point(u)) <= tolerance)
+    };
+
+    let mut refined = curve.clone();
+    refined.refine_knots(&[0.2, 0.5, 0.5, 0.8]);
+    assert_eq!(refined.control_points.len(), 10);
+    assert!(same(&refined, 1e-12));
+    // inserted knots are removable, the original ones are not
+    assert_eq!(refined.remove_knots(1e-9), 4);
+    assert_eq!(refined.knots, curve.knots);
+    assert!(same(&refined, 1e-9));
+
+    let elevated = curve.elevate_degree(2);
+    assert_eq!(elevated.degree(), 5);
+    assert_eq!(elevated.control_points.len(), 12);
+    assert!(same(&elevated, 1e-9));
+    let reduced = elevated.reduce_degree(1e-9).unwrap();
+    assert_eq!(reduced.degree(), 4);
+    assert_eq!(reduced.control_points.len(), 9);
+    assert!(same(&reduced, 1e-9));
+    assert!(curve.reduce_degree(1e-3).is_none());
+}
+
+#[test]
+fn test_rational_knot_removal() {
+    use crate::curve::Curve;
+    use crate::Point4;
+
+    // quarter circle of radius 100, far from the origin compared to its weights
+    let weight = (0.5 as Float).sqrt();
+    let curve = BSplineCurve {
+        control_points: vec![
+            Point4::new(100.0, 0.0, 0.0, 1.0),
+            Point4::new(100.0 * weight, 100.0 * weight, 0.0, weight),
+            Point4::new(0.0, 100.0, 0.0, 1.0),
+        ],
+        knots: KnotVector::bezier_knot(2),
+        degree: 2,
+    };
+    let mut refined = curve.clone();
+    refined.refine_knots(&[0.25, 0.5, 0.5]);
+    assert_eq!(refined.control_points.len(), 6);
+    for u in utils::uniform_divide((0.0, 1.0), 20) {
+        assert!(curve.get_point(u).distance(refined.get_point(u)) < 1e-12);
+    }
+
+    // the tolerance applies to the curve, not to the weighted control points
+    let tolerance = Point4::removal_tolerance(&curve.control_points, 1.0);
+    assert!((tolerance - weight / (1.0 + 100.0 / weight)).abs() < 1e-12);
+    let mut moved = refined.clone();
+    moved.control_points[2] += Point4::new(0.5, 0.0, 0.0, 0.0);
+    assert_eq!(moved.clone().remove_knot(0.25, 1, 1.0), 0);
+    assert_eq!(moved.remove_knot(0.25, 1, 1000.0), 1);
+    assert_eq!(refined.remove_knots(1e-9), 3);
+    assert_eq!(refined.knots, curve.knots);
+}
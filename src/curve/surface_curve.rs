@@ -0,0 +1,34 @@
+use super::Curve;
+use crate::{Float, Point3, Vec3};
+
+/// Curve lying on surfaces, given in 3D space and in the parameter spaces of the surfaces.
+#[derive(Debug)]
+pub struct SurfaceCurve {
+    pub curve: Box<dyn Curve>,
+    /// Parameter space curves with points `(u, v, 0)`, they share the parameter of `curve`
+    pub pcurves: Vec<Box<dyn Curve>>,
+    /// A seam curve lies twice on a periodic surface, with one pcurve on each side of the seam
+    pub seam: bool,
+}
+
+impl Curve for SurfaceCurve {
+    fn get_point(&self, u: Float) -> Point3 {
+        self.curve.get_point(u)
+    }
+
+    fn project(&self, point: Point3) -> Float {
+        self.curve.project(point)
+    }
+
+    fn get_derivative(&self, u: Float, order: usize) -> Vec3 {
+        self.curve.get_derivative(u, order)
+    }
+
+    fn refine_parameter_range(&self, range: (Float, Float), same_sense: bool) -> (Float, Float) {
+        self.curve.refine_parameter_range(range, same_sense)
+    }
+
+    fn period(&self) -> Option<Float> {
+        self.curve.period()
+    }
+}
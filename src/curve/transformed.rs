@@ -0,0 +1,37 @@
+use super::Curve;
+use crate::{Float, Mat4, Point3, Vec3};
+
+/// A curve moved by an affine transformation, it keeps the parameterization of the original curve.
+#[derive(Debug)]
+pub struct TransformedCurve<C: Curve> {
+    pub curve: C,
+    pub matrix: Mat4,
+}
+
+impl<C: Curve> Curve for TransformedCurve<C> {
+    fn get_point(&self, u: Float) -> Point3 {
+        self.matrix.transform_point3(self.curve.get_point(u))
+    }
+
+    fn project(&self, point: Point3) -> Float {
+        self.curve
+            .project(self.matrix.inverse().transform_point3(point))
+    }
+
+    fn get_derivative(&self, u: Float, order: usize) -> Vec3 {
+        if order == 0 {
+            self.get_point(u)
+        } else {
+            self.matrix
+                .transform_vector3(self.curve.get_derivative(u, order))
+        }
+    }
+
+    fn refine_parameter_range(&self, range: (Float, Float), same_sense: bool) -> (Float, Float) {
+        self.curve.refine_parameter_range(range, same_sense)
+    }
+
+    fn period(&self) -> Option<Float> {
+        self.curve.period()
+    }
+}
@@ -0,0 +1,48 @@
+use super::{BSplineCurve, Circle, Curve, Line, ToNurbs};
+use crate::{Float, Point3, Point4, Vec3};
+
+/// Part of a basis curve between two parameters, it keeps the parameterization of the basis curve.
+#[derive(Debug)]
+pub struct TrimmedCurve<C: Curve> {
+    pub basis: C,
+    /// The curve runs from the first parameter to the second one
+    pub parameter_range: (Float, Float),
+}
+
+impl<C: Curve> TrimmedCurve<C> {
+    /// The parameter range in increasing order
+    fn increasing_range(&self) -> (Float, Float) {
+        let (u0, u1) = self.parameter_range;
+        (u0.min(u1), u0.max(u1))
+    }
+}
+
+impl ToNurbs for TrimmedCurve<Line> {
+    fn to_nurbs(&self) -> BSplineCurve<Point4> {
+        self.basis.to_nurbs(self.increasing_range())
+    }
+}
+
+impl ToNurbs for TrimmedCurve<Circle> {
+    fn to_nurbs(&self) -> BSplineCurve<Point4> {
+        self.basis.arc_to_nurbs(self.increasing_range())
+    }
+}
+
+impl<C: Curve> Curve for TrimmedCurve<C> {
+    fn get_point(&self, u: Float) -> Point3 {
+        self.basis.get_point(u)
+    }
+
+    fn project(&self, point: Point3) -> Float {
+        self.basis.project(point)
+    }
+
+    fn get_derivative(&self, u: Float, order: usize) -> Vec3 {
+        self.basis.get_derivative(u, order)
+    }
+
+    fn refine_parameter_range(&self, range: (Float, Float), same_sense: bool) -> (Float, Float) {
+        self.basis.refine_parameter_range(range, same_sense)
+    }
+}
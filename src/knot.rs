@@ -17,12 +17,36 @@ impl KnotVector {
         multiplicities: Vec<usize>,
     ) -> KnotVector {
         let mut knots = Vec::with_capacity(multiplicities.iter().sum());
-        for (value, multiplicity) in values.into_iter().zip(multiplicities.into_iter()) {
-            knots.extend(std::iter::repeat(value).take(multiplicity));
+        for (value, multiplicity) in values.into_iter().zip(multiplicities) {
+            knots.extend(std::iter::repeat_n(value, multiplicity));
         }
         KnotVector(knots)
     }
 
+    /// Split the knot vector into distinct values and their multiplicities
+    /// # Examples
+    /// ```
+    /// use geom3d::*;
+    /// assert_eq!(
+    ///     KnotVector::uniform_knot(2, 2).to_values_and_multiplicities(),
+    ///     (vec![0.0, 0.5, 1.0], vec![3, 1, 3]),
+    /// );
+    /// ```
+    pub fn to_values_and_multiplicities(&self) -> (Vec<Float>, Vec<usize>) {
+        let mut values: Vec<Float> = Vec::new();
+        let mut multiplicities: Vec<usize> = Vec::new();
+        for &knot in self.iter() {
+            match values.last() {
+                Some(&value) if ulps_eq!(value, knot) => *multiplicities.last_mut().unwrap() += 1,
+                _ => {
+                    values.push(knot);
+                    multiplicities.push(1);
+                }
+            }
+        }
+        (values, multiplicities)
+    }
+
     pub fn normalize(&self) -> KnotVector {
         let start = self[0];
         let length = self[self.len() - 1] - self[0];
@@ -69,7 +93,7 @@ impl KnotVector {
         values[index] = 1.0;
 
         for k in 1..=degree {
-            let base = if index < k { 0 } else { index - k };
+            let base = index.saturating_sub(k);
             let delta = self[base + k] - self[base];
             let max = if index + k < n { index } else { n - k - 1 };
             let mut a = inv_or_zero(delta) * (u - self[base]);
@@ -97,8 +121,8 @@ impl KnotVector {
     /// ```
     pub fn bezier_knot(degree: usize) -> KnotVector {
         let mut knots = Vec::with_capacity(degree * 2 + 2);
-        knots.extend(std::iter::repeat(0.0).take(degree + 1));
-        knots.extend(std::iter::repeat(1.0).take(degree + 1));
+        knots.extend(std::iter::repeat_n(0.0, degree + 1));
+        knots.extend(std::iter::repeat_n(1.0, degree + 1));
         KnotVector(knots)
     }
 
@@ -114,9 +138,9 @@ impl KnotVector {
     pub fn uniform_knot(degree: usize, division: usize) -> KnotVector {
         let step = 1.0 / division as Float;
         let mut knots = Vec::with_capacity(degree * 2 + 2);
-        knots.extend(std::iter::repeat(0.0).take(degree + 1));
+        knots.extend(std::iter::repeat_n(0.0, degree + 1));
         knots.extend((1..division).map(|i| (i as Float) * step));
-        knots.extend(std::iter::repeat(1.0).take(degree + 1));
+        knots.extend(std::iter::repeat_n(1.0, degree + 1));
         KnotVector(knots)
     }
 
@@ -7,6 +7,7 @@ pub type Point3 = Vec3;
 pub type Point4 = Vec4;
 pub type Quat = glam::DQuat;
 pub type Mat2 = glam::DMat2;
+pub type Mat4 = glam::DMat4;
 
 mod basis;
 pub mod curve;
@@ -16,13 +17,14 @@ pub mod mesh;
 pub mod model;
 pub mod points;
 pub mod surface;
+pub mod topology;
 pub mod utils;
 
 pub use curve::Polycurve;
 pub use face::Face;
 pub use grid::{grid, Grid};
 pub use knot::KnotVector;
-pub use mesh::TriangleMesh;
+pub use mesh::{QuadMesh, TriangleMesh};
 pub use model::Model;
 
 pub mod consts {
@@ -1,4 +1,4 @@
-use crate::{Grid, Point3, Vec3};
+use crate::{Grid, Mat4, Point3, Vec3};
 
 #[derive(Clone)]
 pub struct TriangleMesh {
@@ -8,6 +8,12 @@ pub struct TriangleMesh {
     pub triangles: Vec<u32>,
 }
 
+impl Default for TriangleMesh {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl TriangleMesh {
     pub fn new() -> TriangleMesh {
         TriangleMesh {
@@ -25,8 +31,7 @@ impl TriangleMesh {
         let triangles = self
             .triangles
             .chunks(3)
-            .map(|t| [t[2], t[1], t[0]])
-            .flatten()
+            .flat_map(|t| [t[2], t[1], t[0]])
             .collect::<Vec<_>>();
         TriangleMesh {
             vertices: self.vertices,
@@ -35,6 +40,66 @@ impl TriangleMesh {
         }
     }
 
+    /// Apply the transformation to vertices and normals
+    pub fn transform(&self, matrix: &Mat4) -> TriangleMesh {
+        TriangleMesh {
+            vertices: self
+                .vertices
+                .iter()
+                .map(|&point| matrix.transform_point3(point))
+                .collect(),
+            normals: self
+                .normals
+                .iter()
+                .map(|&normal| matrix.transform_vector3(normal).normalize())
+                .collect(),
+            triangles: self.triangles.clone(),
+        }
+    }
+
+    /// Join meshes into one mesh, vertices at identical positions are merged.
+    /// Normals are kept only when every mesh has a normal per vertex, a merged vertex
+    /// gets the average of the normals of its copies.
+    pub fn join<I: IntoIterator<Item = TriangleMesh>>(meshes: I) -> TriangleMesh {
+        let mut joined = TriangleMesh::new();
+        let mut indices = std::collections::HashMap::new();
+        let mut has_normals = true;
+        for mesh in meshes {
+            has_normals &= mesh.normals.len() == mesh.vertices.len();
+            let map = mesh
+                .vertices
+                .iter()
+                .enumerate()
+                .map(|(index, point)| {
+                    // adding zero turns -0.0 into 0.0 so both have the same bits
+                    let key = [point.x, point.y, point.z].map(|x| (x + 0.0).to_bits());
+                    let vertex = *indices.entry(key).or_insert_with(|| {
+                        joined.vertices.push(*point);
+                        if has_normals {
+                            joined.normals.push(Vec3::ZERO);
+                        }
+                        joined.vertices.len() as u32 - 1
+                    });
+                    if has_normals {
+                        joined.normals[vertex as usize] += mesh.normals[index];
+                    }
+                    vertex
+                })
+                .collect::<Vec<u32>>();
+            joined
+                .triangles
+                .extend(mesh.triangles.iter().map(|&index| map[index as usize]));
+        }
+        if has_normals {
+            for normal in &mut joined.normals {
+                *normal = normal.normalize_or_zero();
+            }
+        } else {
+            joined.normals.clear();
+        }
+        joined
+    }
+
     pub fn write_obj<W: std::io::Write>(
         &self,
         writer: &mut W,
@@ -104,3 +169,58 @@ pub fn create_triangles(grid: &Grid<Point3>) -> Vec<u32> {
     }
     triangles
 }
+
+/// Quads turning the same way as the triangles of `create_triangles`
+pub fn create_quads(grid: &Grid<Point3>) -> Vec<u32> {
+    let (rows, cols) = grid.size();
+    let mut quads = Vec::with_capacity((rows - 1) * (cols - 1) * 4);
+    for row in 0..rows - 1 {
+        for col in 0..cols - 1 {
+            quads.push((row * cols + col) as u32);
+            quads.push(((row + 1) * cols + col) as u32);
+            quads.push(((row + 1) * cols + col + 1) as u32);
+            quads.push((row * cols + col + 1) as u32);
+        }
+    }
+    quads
+}
+
+#[test]
+fn test_join_meshes() {
+    let triangle = |points: [Point3; 3], normal: Vec3| TriangleMesh {
+        vertices: points.to_vec(),
+        normals: vec![normal; 3],
+        triangles: vec![0, 1, 2],
+    };
+    let floor = triangle(
+        [
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+        ],
+        Vec3::Z,
+    );
+    let wall = triangle(
+        [
+            Point3::new(-0.0, 1.0, -0.0),
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.0, 0.0, 1.0),
+        ],
+        Vec3::X,
+    );
+    let joined = TriangleMesh::join(vec![floor, wall]);
+    assert_eq!(joined.vertices.len(), 4);
+    assert_eq!(joined.triangles, vec![0, 1, 2, 2, 0, 3]);
+    let diagonal = Vec3::new(1.0, 0.0, 1.0).normalize();
+    assert!(joined.normals[0].distance(diagonal) < 1e-12);
+    assert!(joined.normals[2].distance(diagonal) < 1e-12);
+    assert_eq!(joined.normals[1], Vec3::Z);
+    assert_eq!(joined.normals[3], Vec3::X);
+}
+
+mod half_edge;
+mod quad;
+mod validation;
+pub use half_edge::*;
+pub use quad::*;
+pub use validation::*;
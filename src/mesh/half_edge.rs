@@ -0,0 +1,312 @@
+use super::TriangleMesh;
+use crate::{Float, Point3};
+use std::collections::HashMap;
+
+/// Half-edge adjacency of a triangle list,
+/// half-edge `3 * t + k` runs from vertex `k` to the next vertex of triangle `t`
+#[derive(Debug, Clone)]
+pub struct HalfEdges {
+    triangles: Vec<u32>,
+    /// Half-edge on the other side of each edge used by two triangles
+    pub twins: Vec<Option<usize>>,
+    /// Half-edges along each edge keyed by its sorted vertices,
+    /// boundary edges have one and non-manifold edges more than two
+    pub edges: HashMap<(u32, u32), Vec<usize>>,
+}
+
+impl HalfEdges {
+    pub fn new(triangles: &[u32]) -> HalfEdges {
+        let mut edges = HashMap::<_, Vec<usize>>::with_capacity(triangles.len());
+        for half_edge in 0..triangles.len() {
+            let (a, b) = (triangles[half_edge], triangles[next(half_edge)]);
+            edges
+                .entry((a.min(b), a.max(b)))
+                .or_default()
+                .push(half_edge);
+        }
+        let mut twins = vec![None; triangles.len()];
+        for half_edges in edges.values() {
+            if let [a, b] = half_edges[..] {
+                twins[a] = Some(b);
+                twins[b] = Some(a);
+            }
+        }
+        HalfEdges {
+            triangles: triangles.to_vec(),
+            twins,
+            edges,
+        }
+    }
+
+    pub fn start(&self, half_edge: usize) -> u32 {
+        self.triangles[half_edge]
+    }
+
+    pub fn end(&self, half_edge: usize) -> u32 {
+        self.triangles[next(half_edge)]
+    }
+
+    /// Whether the triangles on both sides of the half-edge run along it in opposite directions
+    pub fn is_consistent(&self, half_edge: usize) -> bool {
+        self.twins[half_edge].is_none_or(|twin| self.start(twin) == self.end(half_edge))
+    }
+
+    /// Edges used by one triangle, sorted
+    pub fn boundary_edges(&self) -> Vec<(u32, u32)> {
+        self.edges_where(|half_edges| half_edges.len() == 1)
+    }
+
+    /// Edges used by more than two triangles, sorted
+    pub fn non_manifold_edges(&self) -> Vec<(u32, u32)> {
+        self.edges_where(|half_edges| half_edges.len() > 2)
+    }
+
+    /// Edges whose two triangles run along them in the same direction, sorted
+    pub fn inconsistent_edges(&self) -> Vec<(u32, u32)> {
+        self.edges_where(|half_edges| half_edges.len() == 2 && !self.is_consistent(half_edges[0]))
+    }
+
+    fn edges_where(&self, filter: impl Fn(&[usize]) -> bool) -> Vec<(u32, u32)> {
+        let mut edges = self
+            .edges
+            .iter()
+            .filter(|(_, half_edges)| filter(half_edges))
+            .map(|(&edge, _)| edge)
+            .collect::<Vec<_>>();
+        edges.sort_unstable();
+        edges
+    }
+
+    /// Index of the connected component of each triangle, triangles are connected by
+    /// edges used by two triangles, and the number of components
+    pub fn components(&self) -> (Vec<usize>, usize) {
+        let count = self.triangles.len() / 3;
+        let mut components = vec![usize::MAX; count];
+        let mut component = 0;
+        for seed in 0..count {
+            if components[seed] != usize::MAX {
+                continue;
+            }
+            components[seed] = component;
+            let mut stack = vec![seed];
+            while let Some(triangle) = stack.pop() {
+                for half_edge in 3 * triangle..3 * triangle + 3 {
+                    if let Some(twin) = self.twins[half_edge] {
+                        if components[twin / 3] == usize::MAX {
+                            components[twin / 3] = component;
+                            stack.push(twin / 3);
+                        }
+                    }
+                }
+            }
+            component += 1;
+        }
+        (components, component)
+    }
+}
+
+/// The next half-edge in the triangle
+fn next(half_edge: usize) -> usize {
+    half_edge - half_edge % 3 + (half_edge + 1) % 3
+}
+
+impl TriangleMesh {
+    /// Merge vertices closer than the tolerance, using a spatial hash of cells as large as
+    /// the tolerance. Each vertex joins the first kept vertex within the tolerance, triangles
+    /// collapsed by welding are removed, and normals of the kept vertices are kept.
+    pub fn weld(&self, tolerance: Float) -> TriangleMesh {
+        let size = tolerance.max(Float::MIN_POSITIVE);
+        let cell = |p: Point3| {
+            let p = (p / size).floor();
+            [p.x as i64, p.y as i64, p.z as i64]
+        };
+        let mut cells = HashMap::<_, Vec<u32>>::new();
+        let mut welded = TriangleMesh::new();
+        let has_normals = self.normals.len() == self.vertices.len();
+        let map = self
+            .vertices
+            .iter()
+            .enumerate()
+            .map(|(index, &point)| {
+                let center = cell(point);
+                let mut found = None;
+                'search: for x in -1..=1 {
+                    for y in -1..=1 {
+                        for z in -1..=1 {
+                            let key = [center[0] + x, center[1] + y, center[2] + z];
+                            let near = cells.get(&key).and_then(|kept: &Vec<u32>| {
+                                kept.iter().copied().find(|&k| {
+                                    welded.vertices[k as usize].distance(point) <= tolerance
+                                })
+                            });
+                            if near.is_some() {
+                                found = near;
+                                break 'search;
+                            }
+                        }
+                    }
+                }
+                found.unwrap_or_else(|| {
+                    let kept = welded.vertices.len() as u32;
+                    welded.vertices.push(point);
+                    if has_normals {
+                        welded.normals.push(self.normals[index]);
+                    }
+                    cells.entry(center).or_default().push(kept);
+                    kept
+                })
+            })
+            .collect::<Vec<_>>();
+        welded.triangles = self
+            .triangles
+            .chunks(3)
+            .map(|t| [t[0], t[1], t[2]].map(|index| map[index as usize]))
+            .filter(|[a, b, c]| a != b && b != c && c != a)
+            .flatten()
+            .collect();
+        welded
+    }
+
+    pub fn half_edges(&self) -> HalfEdges {
+        HalfEdges::new(&self.triangles)
+    }
+
+    /// Reverse triangles so neighbours run along their common edges in opposite directions,
+    /// then reverse closed components with negative volume so their triangles face outwards.
+    /// Each component keeps the orientation of its first triangle when it is not closed,
+    /// and conflicts of non-orientable components are left.
+    pub fn fix_orientation(self) -> TriangleMesh {
+        let half_edges = self.half_edges();
+        let count = self.triangle_count();
+        let mut reversed = vec![None; count];
+        let mut components = Vec::new();
+        for seed in 0..count {
+            if reversed[seed].is_some() {
+                continue;
+            }
+            reversed[seed] = Some(false);
+            let mut component = vec![seed];
+            let mut index = 0;
+            while index < component.len() {
+                let triangle = component[index];
+                index += 1;
+                for half_edge in 3 * triangle..3 * triangle + 3 {
+                    if let Some(twin) = half_edges.twins[half_edge] {
+                        if reversed[twin / 3].is_none() {
+                            // an inconsistent neighbour is turned the other way
+                            let flip = reversed[triangle].unwrap();
+                            reversed[twin / 3] = Some(flip == half_edges.is_consistent(half_edge));
+                            component.push(twin / 3);
+                        }
+                    }
+                }
+            }
+            components.push(component);
+        }
+        let mut reversed = reversed.into_iter().map(|r| r.unwrap()).collect::<Vec<_>>();
+        for component in &components {
+            let closed = component
+                .iter()
+                .all(|&t| (3 * t..3 * t + 3).all(|h| half_edges.twins[h].is_some()));
+            if !closed {
+                continue;
+            }
+            let volume = component
+                .iter()
+                .map(|&t| {
+                    let [a, b, c] =
+                        [0, 1, 2].map(|k| self.vertices[self.triangles[3 * t + k] as usize]);
+                    let volume = a.dot(b.cross(c));
+                    if reversed[t] {
+                        -volume
+                    } else {
+                        volume
+                    }
+                })
+                .sum::<Float>();
+            if volume < 0.0 {
+                for &t in component {
+                    reversed[t] = !reversed[t];
+                }
+            }
+        }
+        let triangles = self
+            .triangles
+            .chunks(3)
+            .zip(reversed)
+            .flat_map(|(t, reversed)| {
+                if reversed {
+                    [t[2], t[1], t[0]]
+                } else {
+                    [t[0], t[1], t[2]]
+                }
+            })
+            .collect();
+        TriangleMesh {
+            vertices: self.vertices,
+            normals: self.normals,
+            triangles,
+        }
+    }
+}
+
+#[test]
+fn test_weld_and_orient() {
+    // the faces of a unit cube with their own vertices, some of them facing inwards
+    let corners = |face: usize| {
+        let axis = face % 3;
+        let side = (face / 3) as Float;
+        let mut square = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)].map(|(s, t)| {
+            let mut p = [0.0; 3];
+            p[axis] = side;
+            p[(axis + 1) % 3] = s;
+            p[(axis + 2) % 3] = t;
+            // a perturbation within the welding tolerance
+            Point3::from(p) + Point3::splat(1e-9 * face as Float)
+        });
+        if side == 0.0 {
+            square.reverse();
+        }
+        square
+    };
+    let mut cube = TriangleMesh::new();
+    for face in 0..6 {
+        let start = cube.vertices.len() as u32;
+        cube.vertices.extend_from_slice(&corners(face));
+        cube.triangles
+            .extend([0, 1, 2, 0, 2, 3].iter().map(|&k| start + k));
+    }
+    let welded = cube.weld(1e-6);
+    assert_eq!(welded.vertices.len(), 8);
+    let half_edges = welded.half_edges();
+    assert_eq!(half_edges.edges.len(), 18);
+    assert!(half_edges.boundary_edges().is_empty());
+    assert!(half_edges.inconsistent_edges().is_empty());
+    assert_eq!(half_edges.components().1, 1);
+
+    // flipped triangles are turned back and the cube faces outwards
+    let mut flipped = welded.clone();
+    for t in &[0, 5, 7] {
+        flipped.triangles.swap(3 * t, 3 * t + 1);
+    }
+    assert!(!flipped.half_edges().inconsistent_edges().is_empty());
+    let fixed = flipped.reverse_winding_direction().fix_orientation();
+    let directed = |mesh: &TriangleMesh| {
+        let half_edges = mesh.half_edges();
+        let mut edges = (0..mesh.triangles.len())
+            .map(|h| (half_edges.start(h), half_edges.end(h)))
+            .collect::<Vec<_>>();
+        edges.sort_unstable();
+        edges
+    };
+    assert_eq!(directed(&fixed), directed(&welded));
+
+    // an edge shared by three triangles
+    let mut fin = welded;
+    fin.vertices.push(Point3::new(0.5, -1.0, 0.5));
+    let (a, b) = *half_edges.edges.keys().min().unwrap();
+    fin.triangles.extend_from_slice(&[a, b, 8]);
+    let half_edges = fin.half_edges();
+    assert_eq!(half_edges.non_manifold_edges(), vec![(a, b)]);
+    assert_eq!(half_edges.boundary_edges().len(), 2);
+}
@@ -0,0 +1,376 @@
+use super::TriangleMesh;
+use crate::{Float, Point3, Vec3};
+use std::collections::HashMap;
+use std::io::Write;
+
+/// Corner angles of quads merged from triangles are kept in `[MIN_ANGLE, PI - MIN_ANGLE]`
+const MIN_ANGLE: Float = crate::consts::FRAC_PI_6;
+
+/// Triangles folded by more than this angle are not merged into a quad
+const MAX_FOLD_ANGLE: Float = crate::consts::FRAC_PI_6;
+
+/// Mesh of quads, with triangles where no quads are formed
+#[derive(Clone)]
+pub struct QuadMesh {
+    pub vertices: Vec<Point3>,
+    pub normals: Vec<Vec3>,
+    /// Indices of points forming quad list, corners turn the same way as triangles
+    pub quads: Vec<u32>,
+    /// Indices of points forming triangle list
+    pub triangles: Vec<u32>,
+}
+
+impl Default for QuadMesh {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl QuadMesh {
+    pub fn new() -> QuadMesh {
+        QuadMesh {
+            vertices: Vec::new(),
+            normals: Vec::new(),
+            quads: Vec::new(),
+            triangles: Vec::new(),
+        }
+    }
+
+    pub fn quad_count(&self) -> usize {
+        self.quads.len() / 4
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.triangles.len() / 3
+    }
+
+    /// Merge pairs of triangles sharing an edge into quads, best shaped quads first.
+    /// Pairs are merged when the quad is convex, its corner angles are between 30 and 150
+    /// degrees and the triangles fold by less than 30 degrees, other triangles are kept.
+    pub fn from_triangles(mesh: &TriangleMesh) -> QuadMesh {
+        let triangles = mesh.triangles.chunks(3).collect::<Vec<_>>();
+        let mut edges = HashMap::with_capacity(mesh.triangles.len());
+        for (index, triangle) in triangles.iter().enumerate() {
+            for k in 0..3 {
+                edges.insert((triangle[k], triangle[(k + 1) % 3]), index);
+            }
+        }
+        let point = |index: u32| mesh.vertices[index as usize];
+        let normal = |triangle: &[u32]| {
+            let [a, b, c] = [0, 1, 2].map(|k| point(triangle[k]));
+            (b - a).cross(c - a)
+        };
+        let mut candidates = Vec::new();
+        for (index, triangle) in triangles.iter().enumerate() {
+            for k in 0..3 {
+                let (a, b, c) = (triangle[k], triangle[(k + 1) % 3], triangle[(k + 2) % 3]);
+                let other = match edges.get(&(b, a)) {
+                    Some(&other) if other > index => other,
+                    _ => continue,
+                };
+                let d = *triangles[other]
+                    .iter()
+                    .find(|&&v| v != a && v != b)
+                    .unwrap();
+                let (n0, n1) = (normal(triangle), normal(triangles[other]));
+                if n0 == Vec3::ZERO || n1 == Vec3::ZERO || n0.angle_between(n1) > MAX_FOLD_ANGLE {
+                    continue;
+                }
+                let quad = [a, d, b, c];
+                if let Some(quality) = quad_quality(quad.map(point), n0 + n1) {
+                    candidates.push((quality, index, other, quad));
+                }
+            }
+        }
+        candidates.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
+        let mut merged = vec![false; triangles.len()];
+        let mut quads = Vec::new();
+        for (_, first, second, quad) in candidates {
+            if !merged[first] && !merged[second] {
+                merged[first] = true;
+                merged[second] = true;
+                quads.extend_from_slice(&quad);
+            }
+        }
+        let triangles = triangles
+            .iter()
+            .zip(merged)
+            .filter(|(_, merged)| !merged)
+            .flat_map(|(triangle, _)| triangle.iter().copied())
+            .collect();
+        QuadMesh {
+            vertices: mesh.vertices.clone(),
+            normals: mesh.normals.clone(),
+            quads,
+            triangles,
+        }
+    }
+
+    /// Split each quad along its shorter diagonal
+    pub fn to_triangle_mesh(&self) -> TriangleMesh {
+        let mut triangles = self.triangles.clone();
+        for quad in self.quads.chunks(4) {
+            let [a, b, c, d] = [0, 1, 2, 3].map(|k| quad[k]);
+            let point = |index: u32| self.vertices[index as usize];
+            if point(a).distance(point(c)) <= point(b).distance(point(d)) {
+                triangles.extend_from_slice(&[a, b, c, a, c, d]);
+            } else {
+                triangles.extend_from_slice(&[a, b, d, b, c, d]);
+            }
+        }
+        TriangleMesh {
+            vertices: self.vertices.clone(),
+            normals: self.normals.clone(),
+            triangles,
+        }
+    }
+
+    /// Join meshes into one mesh, vertices at identical positions are merged.
+    /// Normals are kept only when every mesh has a normal per vertex.
+    pub fn join<I: IntoIterator<Item = QuadMesh>>(meshes: I) -> QuadMesh {
+        let mut joined = QuadMesh::new();
+        let mut indices = HashMap::new();
+        let mut has_normals = true;
+        for mesh in meshes {
+            has_normals &= mesh.normals.len() == mesh.vertices.len();
+            let map = mesh
+                .vertices
+                .iter()
+                .enumerate()
+                .map(|(index, point)| {
+                    let key = [point.x.to_bits(), point.y.to_bits(), point.z.to_bits()];
+                    *indices.entry(key).or_insert_with(|| {
+                        joined.vertices.push(*point);
+                        if has_normals {
+                            joined.normals.push(mesh.normals[index]);
+                        }
+                        joined.vertices.len() as u32 - 1
+                    })
+                })
+                .collect::<Vec<u32>>();
+            joined
+                .quads
+                .extend(mesh.quads.iter().map(|&index| map[index as usize]));
+            joined
+                .triangles
+                .extend(mesh.triangles.iter().map(|&index| map[index as usize]));
+        }
+        if !has_normals {
+            joined.normals.clear();
+        }
+        joined
+    }
+
+    /// Elements in the order they are numbered in FE formats, quads before triangles
+    fn elements(&self) -> impl Iterator<Item = &[u32]> {
+        self.quads.chunks(4).chain(self.triangles.chunks(3))
+    }
+
+    pub fn write_obj<W: Write>(
+        &self,
+        writer: &mut W,
+        vertex_start: u32,
+        normal_start: u32,
+    ) -> std::io::Result<()> {
+        for point in &self.vertices {
+            writeln!(writer, "v {} {} {}", point.x, point.y, point.z)?;
+        }
+        for normal in &self.normals {
+            writeln!(writer, "vn {} {} {}", normal.x, normal.y, normal.z)?;
+        }
+        for element in self.elements() {
+            write!(writer, "f")?;
+            for &index in element {
+                if self.normals.is_empty() {
+                    write!(writer, " {}", index + vertex_start)?;
+                } else {
+                    write!(
+                        writer,
+                        " {}//{}",
+                        index + vertex_start,
+                        index + normal_start
+                    )?;
+                }
+            }
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+
+    pub fn save_as_obj<P: AsRef<std::path::Path>>(&self, filename: P) -> std::io::Result<()> {
+        let file = std::fs::File::create(filename)?;
+        let mut writer = std::io::LineWriter::new(file);
+        self.write_obj(&mut writer, 1, 1)
+    }
+
+    /// Write nodes and shell elements in the Abaqus input format,
+    /// quads are S4 elements and triangles are S3 elements
+    pub fn write_inp<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writeln!(writer, "*NODE")?;
+        for (index, point) in self.vertices.iter().enumerate() {
+            writeln!(
+                writer,
+                "{}, {}, {}, {}",
+                index + 1,
+                point.x,
+                point.y,
+                point.z
+            )?;
+        }
+        let mut id = 1;
+        for (size, name) in &[(4, "S4"), (3, "S3")] {
+            let elements = self.elements().filter(|e| e.len() == *size);
+            for (k, element) in elements.enumerate() {
+                if k == 0 {
+                    writeln!(writer, "*ELEMENT, TYPE={}, ELSET=SHELLS", name)?;
+                }
+                write!(writer, "{}", id)?;
+                for &index in element {
+                    write!(writer, ", {}", index + 1)?;
+                }
+                writeln!(writer)?;
+                id += 1;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn save_as_inp<P: AsRef<std::path::Path>>(&self, filename: P) -> std::io::Result<()> {
+        let file = std::fs::File::create(filename)?;
+        let mut writer = std::io::LineWriter::new(file);
+        self.write_inp(&mut writer)
+    }
+
+    /// Write the mesh in the Gmsh 2.2 ASCII format
+    pub fn write_msh<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writeln!(writer, "$MeshFormat\n2.2 0 8\n$EndMeshFormat")?;
+        writeln!(writer, "$Nodes\n{}", self.vertices.len())?;
+        for (index, point) in self.vertices.iter().enumerate() {
+            writeln!(writer, "{} {} {} {}", index + 1, point.x, point.y, point.z)?;
+        }
+        writeln!(writer, "$EndNodes")?;
+        writeln!(
+            writer,
+            "$Elements\n{}",
+            self.quad_count() + self.triangle_count()
+        )?;
+        for (id, element) in self.elements().enumerate() {
+            // element types 3 and 2 are quads and triangles, with physical and geometrical tags
+            let kind = if element.len() == 4 { 3 } else { 2 };
+            write!(writer, "{} {} 2 1 1", id + 1, kind)?;
+            for &index in element {
+                write!(writer, " {}", index + 1)?;
+            }
+            writeln!(writer)?;
+        }
+        writeln!(writer, "$EndElements")
+    }
+
+    pub fn save_as_msh<P: AsRef<std::path::Path>>(&self, filename: P) -> std::io::Result<()> {
+        let file = std::fs::File::create(filename)?;
+        let mut writer = std::io::LineWriter::new(file);
+        self.write_msh(&mut writer)
+    }
+}
+
+/// How close the corner angles are to right angles, from 0 to 1,
+/// `None` when the quad is not convex around the normal or has a sharp corner
+fn quad_quality(corners: [Point3; 4], normal: Vec3) -> Option<Float> {
+    let mut quality: Float = 1.0;
+    for k in 0..4 {
+        let (prev, point, next) = (corners[(k + 3) % 4], corners[k], corners[(k + 1) % 4]);
+        let (to_next, to_prev) = (next - point, prev - point);
+        if to_next.cross(to_prev).dot(normal) <= 0.0 {
+            return None;
+        }
+        let angle = to_next.angle_between(to_prev);
+        if !(MIN_ANGLE..=crate::consts::PI - MIN_ANGLE).contains(&angle) {
+            return None;
+        }
+        quality =
+            quality.min(1.0 - (angle - crate::consts::FRAC_PI_2).abs() / crate::consts::FRAC_PI_2);
+    }
+    Some(quality)
+}
+
+#[test]
+fn test_quad_mesh() {
+    use crate::surface::{quadrangulate, Cylinder};
+    use crate::utils::SurfaceTolerance;
+    use crate::Grid;
+
+    // triangles of a grid are merged back into its quads
+    let points = (0..5)
+        .flat_map(|i| (0..4).map(move |j| Point3::new(i as Float, j as Float * 1.5, 0.0)))
+        .collect::<Vec<_>>();
+    let grid = Grid::from_vec(points.clone(), 4);
+    let mesh = TriangleMesh {
+        vertices: points,
+        normals: Vec::new(),
+        triangles: super::create_triangles(&grid),
+    };
+    let quads = QuadMesh::from_triangles(&mesh);
+    assert_eq!((quads.quad_count(), quads.triangle_count()), (12, 0));
+    let mut expected = super::create_quads(&grid)
+        .chunks(4)
+        .map(|q| {
+            let mut q = q.to_vec();
+            q.sort_unstable();
+            q
+        })
+        .collect::<Vec<_>>();
+    let mut found = quads
+        .quads
+        .chunks(4)
+        .map(|q| {
+            let mut q = q.to_vec();
+            q.sort_unstable();
+            q
+        })
+        .collect::<Vec<_>>();
+    expected.sort();
+    found.sort();
+    assert_eq!(found, expected);
+    for quad in quads.quads.chunks(4) {
+        let [a, b, c] = [0, 1, 2].map(|k| quads.vertices[quad[k] as usize]);
+        assert!((b - a).cross(c - b).z > 0.0);
+    }
+    assert_eq!(quads.to_triangle_mesh().triangle_count(), 24);
+
+    // structured quads of a cylinder meet the chord height and turn from `u` to `v`
+    let cylinder = Cylinder {
+        origin: Point3::ZERO,
+        axis: Vec3::Z,
+        ref_dir: Vec3::X,
+        radius: 2.0,
+    };
+    let tolerance = SurfaceTolerance::with_chord_height(1e-3);
+    let range = ((0.0, 4.0), (0.0, crate::consts::PI));
+    let mesh = quadrangulate(&cylinder, range, &tolerance);
+    assert_eq!(mesh.triangle_count(), 0);
+    assert_eq!(mesh.normals.len(), mesh.vertices.len());
+    for quad in mesh.quads.chunks(4) {
+        let [a, b, c, d] = [0, 1, 2, 3].map(|k| mesh.vertices[quad[k] as usize]);
+        let center = (a + b + c + d) / 4.0;
+        assert!(2.0 - center.truncate().length() < 1e-3 + 1e-9);
+        // along the axis, then around it
+        assert!((b - a).cross(d - a).dot(center.truncate().extend(0.0)) < 0.0);
+    }
+
+    let mut obj = Vec::new();
+    mesh.write_obj(&mut obj, 1, 1).unwrap();
+    let obj = String::from_utf8(obj).unwrap();
+    assert_eq!(
+        obj.lines().filter(|l| l.starts_with("f ")).count(),
+        mesh.quad_count()
+    );
+    let mut inp = Vec::new();
+    quads.write_inp(&mut inp).unwrap();
+    let inp = String::from_utf8(inp).unwrap();
+    assert!(inp.contains("*ELEMENT, TYPE=S4, ELSET=SHELLS\n1, "));
+    assert_eq!(inp.lines().count(), 1 + 20 + 1 + 12);
+    let mut msh = Vec::new();
+    quads.write_msh(&mut msh).unwrap();
+    let msh = String::from_utf8(msh).unwrap();
+    assert!(msh.contains("$Elements\n12\n1 3 2 1 1 "));
+}
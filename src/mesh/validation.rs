@@ -0,0 +1,336 @@
+use super::TriangleMesh;
+use crate::utils::{orient2d, TOLERANCE};
+use crate::{Float, Point2, Point3};
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// Defects and topology of a triangle mesh
+#[derive(Debug, Clone, Default)]
+pub struct MeshReport {
+    /// Vertices used by triangles
+    pub vertex_count: usize,
+    pub edge_count: usize,
+    pub triangle_count: usize,
+    /// Groups of triangles connected through edges
+    pub component_count: usize,
+    /// Edges used by one triangle
+    pub boundary_edges: Vec<(u32, u32)>,
+    /// Edges used by more than two triangles
+    pub non_manifold_edges: Vec<(u32, u32)>,
+    /// Vertices where groups of triangles touch without sharing edges
+    pub non_manifold_vertices: Vec<u32>,
+    /// Edges whose two triangles run along them in the same direction
+    pub inconsistent_edges: Vec<(u32, u32)>,
+    /// Edges shorter than the tolerance
+    pub degenerate_edges: Vec<(u32, u32)>,
+    /// Triangles with repeated vertices or heights below the tolerance
+    pub degenerate_triangles: Vec<usize>,
+    /// Pairs of triangles without common vertices which touch or cross
+    pub self_intersections: Vec<(usize, usize)>,
+}
+
+impl MeshReport {
+    pub fn is_manifold(&self) -> bool {
+        self.non_manifold_edges.is_empty() && self.non_manifold_vertices.is_empty()
+    }
+
+    /// Every edge is shared by two triangles
+    pub fn is_watertight(&self) -> bool {
+        self.boundary_edges.is_empty() && self.is_manifold()
+    }
+
+    /// Neighbouring triangles run along their common edges in opposite directions
+    pub fn is_oriented(&self) -> bool {
+        self.inconsistent_edges.is_empty()
+    }
+
+    /// `V - E + F`, 2 for a closed surface like a sphere, 0 for a torus
+    pub fn euler_characteristic(&self) -> i64 {
+        self.vertex_count as i64 - self.edge_count as i64 + self.triangle_count as i64
+    }
+
+    /// Whether the mesh bounds solids, as needed to print it
+    pub fn is_valid_solid(&self) -> bool {
+        self.is_watertight()
+            && self.is_oriented()
+            && self.degenerate_edges.is_empty()
+            && self.degenerate_triangles.is_empty()
+            && self.self_intersections.is_empty()
+    }
+}
+
+impl TriangleMesh {
+    /// Check the topology and find defects, vertices are compared by index
+    /// so the mesh is usually welded first.
+    pub fn validate(&self) -> MeshReport {
+        let half_edges = self.half_edges();
+        let point = |index: u32| self.vertices[index as usize];
+        let used = self.triangles.iter().collect::<HashSet<_>>();
+        let degenerate_edges = {
+            let mut edges = half_edges
+                .edges
+                .keys()
+                .filter(|&&(a, b)| point(a).distance(point(b)) <= TOLERANCE)
+                .copied()
+                .collect::<Vec<_>>();
+            edges.sort_unstable();
+            edges
+        };
+        let degenerate_triangles = self
+            .triangles
+            .chunks(3)
+            .enumerate()
+            .filter(|(_, t)| {
+                let [a, b, c] = [0, 1, 2].map(|k| point(t[k]));
+                let longest = a.distance(b).max(b.distance(c)).max(c.distance(a));
+                t[0] == t[1]
+                    || t[1] == t[2]
+                    || t[2] == t[0]
+                    || (b - a).cross(c - a).length() <= TOLERANCE * longest
+            })
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        MeshReport {
+            vertex_count: used.len(),
+            edge_count: half_edges.edges.len(),
+            triangle_count: self.triangle_count(),
+            component_count: half_edges.components().1,
+            boundary_edges: half_edges.boundary_edges(),
+            non_manifold_edges: half_edges.non_manifold_edges(),
+            non_manifold_vertices: self.non_manifold_vertices(),
+            inconsistent_edges: half_edges.inconsistent_edges(),
+            self_intersections: self.self_intersections(&degenerate_triangles),
+            degenerate_edges,
+            degenerate_triangles,
+        }
+    }
+
+    /// Vertices whose triangles do not form one fan through shared edges
+    fn non_manifold_vertices(&self) -> Vec<u32> {
+        let half_edges = self.half_edges();
+        let mut fans = HashMap::<u32, Vec<usize>>::new();
+        for (index, &vertex) in self.triangles.iter().enumerate() {
+            fans.entry(vertex).or_default().push(index / 3);
+        }
+        let mut vertices = fans
+            .into_iter()
+            .filter(|(vertex, triangles)| {
+                // turn around the vertex through the edges at it
+                let mut reached = HashSet::new();
+                let mut stack = vec![triangles[0]];
+                reached.insert(triangles[0]);
+                while let Some(triangle) = stack.pop() {
+                    for half_edge in 3 * triangle..3 * triangle + 3 {
+                        let at_vertex = half_edges.start(half_edge) == *vertex
+                            || half_edges.end(half_edge) == *vertex;
+                        if let (true, Some(twin)) = (at_vertex, half_edges.twins[half_edge]) {
+                            if reached.insert(twin / 3) {
+                                stack.push(twin / 3);
+                            }
+                        }
+                    }
+                }
+                reached.len() < triangles.iter().collect::<HashSet<_>>().len()
+            })
+            .map(|(vertex, _)| vertex)
+            .collect::<Vec<_>>();
+        vertices.sort_unstable();
+        vertices
+    }
+
+    /// Pairs of triangles without common vertices which touch or cross,
+    /// candidates are found by hashing the bounding boxes into cells
+    fn self_intersections(&self, degenerate: &[usize]) -> Vec<(usize, usize)> {
+        let degenerate = degenerate.iter().collect::<HashSet<_>>();
+        let triangles = self
+            .triangles
+            .chunks(3)
+            .map(|t| [t[0], t[1], t[2]])
+            .collect::<Vec<_>>();
+        let corners = |t: &[u32; 3]| t.map(|k| self.vertices[k as usize]);
+        let boxes = triangles
+            .iter()
+            .map(|t| {
+                let [a, b, c] = corners(t);
+                (a.min(b).min(c), a.max(b).max(c))
+            })
+            .collect::<Vec<_>>();
+        if boxes.is_empty() {
+            return Vec::new();
+        }
+        let size = boxes
+            .iter()
+            .map(|(min, max)| (*max - *min).max_element())
+            .sum::<Float>()
+            / boxes.len() as Float;
+        let size = size.max(TOLERANCE);
+        let cell = |p: Point3| {
+            let p = (p / size).floor();
+            [p.x as i64, p.y as i64, p.z as i64]
+        };
+        let mut cells = HashMap::<_, Vec<usize>>::new();
+        for (index, (min, max)) in boxes.iter().enumerate() {
+            if degenerate.contains(&index) {
+                continue;
+            }
+            let (min, max) = (cell(*min), cell(*max));
+            for x in min[0]..=max[0] {
+                for y in min[1]..=max[1] {
+                    for z in min[2]..=max[2] {
+                        cells.entry([x, y, z]).or_default().push(index);
+                    }
+                }
+            }
+        }
+        let mut pairs = BTreeSet::new();
+        for indices in cells.values() {
+            for (k, &i) in indices.iter().enumerate() {
+                for &j in &indices[k + 1..] {
+                    let (i, j) = (i.min(j), i.max(j));
+                    let overlap = (boxes[i].0 - boxes[j].1).max_element() <= 0.0
+                        && (boxes[j].0 - boxes[i].1).max_element() <= 0.0;
+                    let shared = triangles[i].iter().any(|v| triangles[j].contains(v));
+                    if overlap
+                        && !shared
+                        && !pairs.contains(&(i, j))
+                        && triangles_intersect(corners(&triangles[i]), corners(&triangles[j]))
+                    {
+                        pairs.insert((i, j));
+                    }
+                }
+            }
+        }
+        pairs.into_iter().collect()
+    }
+}
+
+fn triangles_intersect(a: [Point3; 3], b: [Point3; 3]) -> bool {
+    let normal = |t: &[Point3; 3]| (t[1] - t[0]).cross(t[2] - t[0]);
+    let (na, nb) = (normal(&a), normal(&b));
+    // signed distances scaled by the normal lengths
+    let separated = |t: &[Point3; 3], origin: Point3, n: crate::Vec3| {
+        let distances = t.map(|p| n.dot(p - origin));
+        let limit = TOLERANCE * n.length();
+        (
+            distances.iter().all(|&d| d > limit) || distances.iter().all(|&d| d < -limit),
+            distances.iter().all(|&d| d.abs() <= limit),
+        )
+    };
+    let (apart, coplanar) = separated(&a, b[0], nb);
+    if apart || separated(&b, a[0], na).0 {
+        return false;
+    }
+    if coplanar {
+        return coplanar_triangles_intersect(a, b, nb);
+    }
+    (0..3).any(|k| segment_crosses_triangle(a[k], a[(k + 1) % 3], &b))
+        || (0..3).any(|k| segment_crosses_triangle(b[k], b[(k + 1) % 3], &a))
+}
+
+/// Möller–Trumbore intersection of the segment with the triangle, including its border
+fn segment_crosses_triangle(p: Point3, q: Point3, t: &[Point3; 3]) -> bool {
+    let direction = q - p;
+    let (e1, e2) = (t[1] - t[0], t[2] - t[0]);
+    let pvec = direction.cross(e2);
+    let det = e1.dot(pvec);
+    if det.abs() <= Float::EPSILON * e1.length() * pvec.length() {
+        return false;
+    }
+    let s = p - t[0];
+    let u = s.dot(pvec) / det;
+    let qvec = s.cross(e1);
+    let v = direction.dot(qvec) / det;
+    let along = e2.dot(qvec) / det;
+    (0.0..=1.0).contains(&u) && v >= 0.0 && u + v <= 1.0 && (0.0..=1.0).contains(&along)
+}
+
+fn coplanar_triangles_intersect(a: [Point3; 3], b: [Point3; 3], normal: crate::Vec3) -> bool {
+    // drop the coordinate along which the normal is largest
+    let n = normal.abs();
+    let project = |p: Point3| {
+        if n.x >= n.y && n.x >= n.z {
+            Point2::new(p.y, p.z)
+        } else if n.y >= n.z {
+            Point2::new(p.z, p.x)
+        } else {
+            Point2::new(p.x, p.y)
+        }
+    };
+    let (a, b) = (a.map(project), b.map(project));
+    let crosses = |p: Point2, q: Point2, r: Point2, s: Point2| {
+        let (d1, d2) = (orient2d(p, q, r), orient2d(p, q, s));
+        let (d3, d4) = (orient2d(r, s, p), orient2d(r, s, q));
+        d1 * d2 <= 0.0 && d3 * d4 <= 0.0 && (d1, d2, d3, d4) != (0.0, 0.0, 0.0, 0.0)
+    };
+    let inside = |p: Point2, t: &[Point2; 3]| {
+        let sides = [0, 1, 2].map(|k| orient2d(t[k], t[(k + 1) % 3], p));
+        sides.iter().all(|&s| s >= 0.0) || sides.iter().all(|&s| s <= 0.0)
+    };
+    (0..3).any(|i| (0..3).any(|j| crosses(a[i], a[(i + 1) % 3], b[j], b[(j + 1) % 3])))
+        || inside(a[0], &b)
+        || inside(b[0], &a)
+}
+
+#[test]
+fn test_mesh_validation() {
+    // an octahedron
+    let vertices = vec![
+        Point3::X,
+        Point3::Y,
+        -Point3::X,
+        -Point3::Y,
+        Point3::Z,
+        -Point3::Z,
+    ];
+    let mut triangles = Vec::new();
+    for k in 0..4 {
+        let next = (k + 1) % 4;
+        triangles.extend_from_slice(&[k, next, 4, next, k, 5]);
+    }
+    let octahedron = TriangleMesh {
+        vertices,
+        normals: Vec::new(),
+        triangles,
+    };
+    let report = octahedron.validate();
+    assert!(report.is_valid_solid());
+    assert_eq!(
+        (
+            report.vertex_count,
+            report.edge_count,
+            report.triangle_count
+        ),
+        (6, 12, 8)
+    );
+    assert_eq!(report.euler_characteristic(), 2);
+    assert_eq!(report.component_count, 1);
+
+    // a copy moved by half its width crosses the first one
+    let mut pair = TriangleMesh::join(vec![
+        octahedron.clone(),
+        octahedron.transform(&crate::Mat4::from_translation(crate::Vec3::X)),
+    ]);
+    let report = pair.validate();
+    assert_eq!(report.component_count, 2);
+    assert!(report.is_watertight() && report.is_oriented());
+    assert!(!report.self_intersections.is_empty());
+    assert_eq!(report.euler_characteristic(), 4);
+
+    // a removed triangle opens the surface, a collapsed one is degenerate
+    pair.triangles.truncate(pair.triangles.len() - 3);
+    pair.triangles.extend_from_slice(&[0, 0, 1]);
+    let report = pair.validate();
+    assert_eq!(report.boundary_edges.len(), 3 + 1);
+    assert_eq!(report.degenerate_triangles, vec![15]);
+    assert_eq!(report.degenerate_edges, vec![(0, 0)]);
+    assert!(!report.is_valid_solid());
+
+    // two octahedra sharing only a vertex
+    let far = TriangleMesh::join(vec![
+        octahedron.clone(),
+        octahedron.transform(&crate::Mat4::from_translation(crate::Vec3::X * 2.0)),
+    ]);
+    let report = far.validate();
+    assert_eq!(report.non_manifold_vertices, vec![0]);
+    assert!(report.self_intersections.is_empty());
+    assert!(report.non_manifold_edges.is_empty() && !report.is_manifold());
+}
@@ -1,11 +1,18 @@
+use crate::utils::SurfaceTolerance;
 use crate::Point3;
-use crate::{curve, Face, Polycurve};
+use crate::{curve, Face, Polycurve, QuadMesh, TriangleMesh};
 
 pub struct Model<F: Face> {
     pub faces: Vec<F>,
     pub curves: Vec<Polycurve>,
 }
 
+impl<F: Face> Default for Model<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<F: Face> Model<F> {
     pub fn new() -> Model<F> {
         Model {
@@ -22,7 +29,33 @@ impl<F: Face> Model<F> {
         self.curves.push(curve);
     }
 
+    /// Triangle mesh of all faces with vertices closer than `weld_tolerance` merged,
+    /// faces are tessellated with the tolerance or give their own meshes if it is `None`
+    pub fn welded_mesh(
+        &self,
+        tolerance: Option<&SurfaceTolerance>,
+        weld_tolerance: crate::Float,
+    ) -> TriangleMesh {
+        TriangleMesh::join(self.faces.iter().map(|face| face_mesh(face, tolerance)))
+            .weld(weld_tolerance)
+    }
+
+    /// Quad dominant mesh of all faces, vertices shared by faces are merged
+    pub fn quad_mesh(&self, tolerance: &SurfaceTolerance) -> QuadMesh {
+        QuadMesh::join(self.faces.iter().map(|face| face.quad_mesh(tolerance)))
+    }
+
     pub fn save_as_stl<P: AsRef<std::path::Path>>(&self, filename: P) -> std::io::Result<()> {
+        self.save_as_stl_with_tolerance(filename, None)
+    }
+
+    /// Save faces as a binary STL file, they are tessellated with the tolerance,
+    /// or their own triangle meshes are taken if it is `None`.
+    pub fn save_as_stl_with_tolerance<P: AsRef<std::path::Path>>(
+        &self,
+        filename: P,
+        tolerance: Option<&SurfaceTolerance>,
+    ) -> std::io::Result<()> {
         use std::io::{Seek, SeekFrom, Write};
         let path = std::path::Path::new(filename.as_ref());
         let name = path.file_stem().unwrap().to_string_lossy();
@@ -33,44 +66,53 @@ impl<F: Face> Model<F> {
         let mut header = Vec::with_capacity(header_size);
         writeln!(header, "Binary STL file\nName: {:57}", name)?;
         header.truncate(header_size);
-        writer.write(&header)?;
+        writer.write_all(&header)?;
 
         let mut triangle_count: usize = 0;
-        writer.write(&(triangle_count as u32).to_le_bytes())?;
+        writer.write_all(&(triangle_count as u32).to_le_bytes())?;
 
         for face in &self.faces {
-            let mesh = face.get_triangle_mesh();
+            let mesh = face_mesh(face, tolerance);
             triangle_count += mesh.triangle_count();
             for triangle in mesh.triangles.chunks(3) {
                 // normal
-                writer.write(&0.0_f32.to_le_bytes())?;
-                writer.write(&0.0_f32.to_le_bytes())?;
-                writer.write(&0.0_f32.to_le_bytes())?;
+                writer.write_all(&0.0_f32.to_le_bytes())?;
+                writer.write_all(&0.0_f32.to_le_bytes())?;
+                writer.write_all(&0.0_f32.to_le_bytes())?;
                 // vertices
                 for index in triangle {
                     let point = &mesh.vertices[*index as usize];
-                    writer.write(&(point.x as f32).to_le_bytes())?;
-                    writer.write(&(point.y as f32).to_le_bytes())?;
-                    writer.write(&(point.z as f32).to_le_bytes())?;
+                    writer.write_all(&(point.x as f32).to_le_bytes())?;
+                    writer.write_all(&(point.y as f32).to_le_bytes())?;
+                    writer.write_all(&(point.z as f32).to_le_bytes())?;
                 }
                 // attribute byte count
-                writer.write(&[0u8, 0u8])?;
+                writer.write_all(&[0u8, 0u8])?;
             }
         }
         writer.seek(SeekFrom::Start(header_size as u64))?;
-        dbg!(triangle_count);
-        writer.write(&(triangle_count as u32).to_le_bytes())?;
+        writer.write_all(&(triangle_count as u32).to_le_bytes())?;
         Ok(())
     }
 
     pub fn save_as_obj<P: AsRef<std::path::Path>>(&self, filename: P) -> std::io::Result<()> {
+        self.save_as_obj_with_tolerance(filename, None)
+    }
+
+    /// Save faces as an OBJ file, they are tessellated with the tolerance,
+    /// or their own triangle meshes are taken if it is `None`.
+    pub fn save_as_obj_with_tolerance<P: AsRef<std::path::Path>>(
+        &self,
+        filename: P,
+        tolerance: Option<&SurfaceTolerance>,
+    ) -> std::io::Result<()> {
         let file = std::fs::File::create(filename)?;
         let mut writer = std::io::LineWriter::new(file);
 
         let mut vertex_start = 1;
         let mut normal_start = 1;
         for face in &self.faces {
-            let mesh = face.get_triangle_mesh();
+            let mesh = face_mesh(face, tolerance);
             mesh.write_obj(&mut writer, vertex_start, normal_start)?;
             vertex_start += mesh.vertices.len() as u32;
             normal_start += mesh.normals.len() as u32;
@@ -79,9 +121,20 @@ impl<F: Face> Model<F> {
     }
 
     pub fn save_as_svg<P: AsRef<std::path::Path>>(
+        &self,
+        filename: P,
+        size: (f64, f64),
+    ) -> std::io::Result<()> {
+        self.save_as_svg_with_tolerance(filename, size, None)
+    }
+
+    /// Save curves as SVG paths, curves which are not lines, polylines or cubic B-splines
+    /// are discretized with the tolerance, or the tolerance of each segment if it is `None`.
+    pub fn save_as_svg_with_tolerance<P: AsRef<std::path::Path>>(
         &self,
         filename: P,
         (width, height): (f64, f64),
+        tolerance: Option<&crate::utils::CurveTolerance>,
     ) -> std::io::Result<()> {
         use curve::Curve;
         use svg::{
@@ -146,9 +199,12 @@ impl<F: Face> Model<F> {
                     }
                 }
 
-                let points = segment.get_points();
+                let points = match tolerance {
+                    Some(tolerance) => segment.discretize(tolerance),
+                    None => segment.get_points(),
+                };
                 for point in points {
-                    if data.len() == 0 {
+                    if data.is_empty() {
                         data.push_str(&format!("M {:.2},{:.2}", point.x, point.y));
                     } else {
                         data.push_str(&format!(" L {:.2},{:.2}", point.x, point.y));
@@ -164,5 +220,18 @@ impl<F: Face> Model<F> {
     }
 }
 
+mod assembly;
+mod step_error;
 mod step_reader;
-pub use step_reader::ModelReader as StepReader;
+mod step_writer;
+pub use assembly::{Assembly, Instance, Part};
+pub use step_error::StepError;
+pub use step_reader::{ModelReader as StepReader, StepModel};
+pub use step_writer::{StepSchema, StepWriter};
+
+fn face_mesh<F: Face>(face: &F, tolerance: Option<&SurfaceTolerance>) -> TriangleMesh {
+    match tolerance {
+        Some(tolerance) => face.tessellate(tolerance),
+        None => face.get_triangle_mesh(),
+    }
+}
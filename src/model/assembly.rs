@@ -0,0 +1,60 @@
+use crate::Mat4;
+
+/// Product structure of a model, repeated parts are stored once and placed by instances.
+#[derive(Debug, Default)]
+pub struct Assembly {
+    pub parts: Vec<Part>,
+    /// Parts which are not used by other parts
+    pub roots: Vec<usize>,
+}
+
+/// A part or sub-assembly.
+#[derive(Debug, Default)]
+pub struct Part {
+    pub name: String,
+    /// Indices of the model faces forming the shape of the part itself
+    pub faces: Vec<usize>,
+    /// Placed uses of other parts
+    pub children: Vec<Instance>,
+}
+
+/// A use of a part in its parent.
+#[derive(Debug)]
+pub struct Instance {
+    pub name: String,
+    pub part: usize,
+    /// Transformation from the coordinate system of the part to its parent
+    pub placement: Mat4,
+}
+
+impl Assembly {
+    pub fn new() -> Assembly {
+        Assembly::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parts.is_empty()
+    }
+
+    /// Every occurrence of parts in the tree with its placement in world coordinates
+    pub fn occurrences(&self) -> Vec<(usize, Mat4)> {
+        let mut occurrences = Vec::new();
+        let mut stack = self
+            .roots
+            .iter()
+            .rev()
+            .map(|&root| (root, Mat4::IDENTITY, 0))
+            .collect::<Vec<_>>();
+        while let Some((part, placement, depth)) = stack.pop() {
+            occurrences.push((part, placement));
+            // guard against cyclic product structure
+            if depth > self.parts.len() {
+                continue;
+            }
+            for instance in self.parts[part].children.iter().rev() {
+                stack.push((instance.part, placement * instance.placement, depth + 1));
+            }
+        }
+        occurrences
+    }
+}
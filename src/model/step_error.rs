@@ -0,0 +1,97 @@
+use std::fmt;
+
+/// Errors raised while converting STEP entities to geometry.
+#[derive(Debug)]
+pub enum StepError {
+    /// The file can not be read.
+    Io(std::io::Error),
+    /// A referenced entity does not exist in the file.
+    MissingEntity { id: i64 },
+    /// A referenced entity is not of the expected type.
+    UnexpectedType {
+        id: i64,
+        type_name: &'static str,
+        expected: &'static str,
+    },
+    /// An optional attribute which is required for the conversion is not set.
+    MissingAttribute {
+        id: i64,
+        type_name: &'static str,
+        attribute: &'static str,
+    },
+    /// The entity type is not supported yet.
+    Unsupported { id: i64, type_name: &'static str },
+    /// The entity holds inconsistent data.
+    InvalidData {
+        id: i64,
+        type_name: &'static str,
+        reason: String,
+    },
+}
+
+impl StepError {
+    /// Id of the entity which caused the error
+    pub fn entity_id(&self) -> Option<i64> {
+        match self {
+            StepError::Io(_) => None,
+            StepError::MissingEntity { id }
+            | StepError::UnexpectedType { id, .. }
+            | StepError::MissingAttribute { id, .. }
+            | StepError::Unsupported { id, .. }
+            | StepError::InvalidData { id, .. } => Some(*id),
+        }
+    }
+
+    /// Type name of the entity which caused the error
+    pub fn type_name(&self) -> Option<&'static str> {
+        match self {
+            StepError::Io(_) | StepError::MissingEntity { .. } => None,
+            StepError::UnexpectedType { type_name, .. }
+            | StepError::MissingAttribute { type_name, .. }
+            | StepError::Unsupported { type_name, .. }
+            | StepError::InvalidData { type_name, .. } => Some(type_name),
+        }
+    }
+}
+
+impl fmt::Display for StepError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StepError::Io(err) => write!(f, "{}", err),
+            StepError::MissingEntity { id } => write!(f, "#{}: entity does not exist", id),
+            StepError::UnexpectedType {
+                id,
+                type_name,
+                expected,
+            } => write!(f, "#{}: {} is not a {}", id, type_name, expected),
+            StepError::MissingAttribute {
+                id,
+                type_name,
+                attribute,
+            } => write!(f, "#{}: {} has no {}", id, type_name, attribute),
+            StepError::Unsupported { id, type_name } => {
+                write!(f, "#{}: {} is unsupported", id, type_name)
+            }
+            StepError::InvalidData {
+                id,
+                type_name,
+                reason,
+            } => write!(f, "#{}: {} {}", id, type_name, reason),
+        }
+    }
+}
+
+impl std::error::Error for StepError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StepError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for StepError {
+    fn from(err: std::io::Error) -> Self {
+        StepError::Io(err)
+    }
+}
@@ -1,121 +1,223 @@
-use super::Model;
+use super::{Assembly, Instance, Model, Part, StepError};
 use crate::consts::TAU;
 use crate::curve::{Curve, CurveSegment, Polycurve};
 use crate::surface::{Surface, SurfacePatch, TrimmedSurface};
-use crate::{Float, Grid, KnotVector, Point3, Point4, Vec3, Vec4};
+use crate::topology::Topology;
+use crate::{Face, Float, Grid, KnotVector, Mat4, Point3, Point4, TriangleMesh, Vec3, Vec4};
 use iso_10303::step::{EntityRef, Real, StepReader};
 use iso_10303_parts::ap214::*;
-use std::any::Any;
+use std::any::{Any, TypeId};
+use std::collections::{HashMap, HashSet};
 
-fn vec3(coordinates: &Vec<Real>) -> Vec3 {
-    Vec3::new(
-        coordinates[0].0 as Float,
-        coordinates[1].0 as Float,
-        coordinates[2].0 as Float,
-    )
+/// Short name of a Rust type, e.g. `CartesianPoint`
+fn short_type_name(name: &'static str) -> &'static str {
+    if name.starts_with("alloc::vec::Vec") {
+        "complex entity"
+    } else {
+        name.rsplit("::").next().unwrap_or(name)
+    }
 }
 
-fn vec4(coordinates: &Vec<Real>, weight: &Real) -> Vec4 {
-    let weight = weight.0 as Float;
-    Vec4::new(
-        (coordinates[0].0 as Float) * weight,
-        (coordinates[1].0 as Float) * weight,
-        (coordinates[2].0 as Float) * weight,
-        weight,
-    )
+fn type_name(reader: &Ap214Reader, id: i64) -> &'static str {
+    if reader.entities.contains_key(&id) {
+        short_type_name(reader.get_type_name(id))
+    } else {
+        "unknown entity"
+    }
 }
 
-fn vertex_point(reader: &Ap214Reader, vertex_ref: &EntityRef) -> Point3 {
-    reader
-        .get_entity::<VertexPoint>(vertex_ref)
-        .and_then(|vertex| reader.get_entity::<CartesianPoint>(vertex.vertex_geometry()))
-        .map(|point| vec3(point.coordinates()))
-        .unwrap()
+fn unsupported(reader: &Ap214Reader, id: i64) -> StepError {
+    if reader.entities.contains_key(&id) {
+        StepError::Unsupported {
+            id,
+            type_name: type_name(reader, id),
+        }
+    } else {
+        StepError::MissingEntity { id }
+    }
 }
 
-fn axis1_placement(reader: &Ap214Reader, placement_ref: &EntityRef) -> (Point3, Option<Vec3>) {
-    let placement = reader.get_entity::<Axis1Placement>(placement_ref).unwrap();
-    let location = reader
-        .get_entity::<CartesianPoint>(placement.location())
-        .map(|point| vec3(point.coordinates()))
-        .unwrap();
-    let axis = reader
-        .get_entity::<Direction>(placement.axis().as_ref().unwrap())
-        .map(|direction| vec3(direction.direction_ratios()));
-    (location, axis)
+fn invalid_data(reader: &Ap214Reader, id: i64, reason: impl Into<String>) -> StepError {
+    StepError::InvalidData {
+        id,
+        type_name: type_name(reader, id),
+        reason: reason.into(),
+    }
+}
+
+/// Get the referenced entity, which should be of type `T`
+fn get<'a, T: Any>(reader: &'a Ap214Reader, entity_ref: &EntityRef) -> Result<&'a T, StepError> {
+    let id = entity_ref.0;
+    let entity = reader
+        .entities
+        .get(&id)
+        .ok_or(StepError::MissingEntity { id })?;
+    entity
+        .downcast_ref::<T>()
+        .ok_or_else(|| StepError::UnexpectedType {
+            id,
+            type_name: type_name(reader, id),
+            expected: short_type_name(std::any::type_name::<T>()),
+        })
+}
+
+/// Find the part of type `T` in a complex entity
+fn complex_part<T: Any>(parts: &[Box<dyn Any>]) -> Option<&T> {
+    parts.iter().find_map(|part| part.downcast_ref::<T>())
+}
+
+/// 2D coordinates of parameter space geometry get zero z coordinate
+fn vec3(reader: &Ap214Reader, id: i64, coordinates: &[Real]) -> Result<Vec3, StepError> {
+    if let [x, y, z] = coordinates {
+        Ok(Vec3::new(x.0 as Float, y.0 as Float, z.0 as Float))
+    } else if let [x, y] = coordinates {
+        Ok(Vec3::new(x.0 as Float, y.0 as Float, 0.0))
+    } else {
+        Err(invalid_data(
+            reader,
+            id,
+            format!("has {} coordinates instead of 3", coordinates.len()),
+        ))
+    }
+}
+
+fn cartesian_point(reader: &Ap214Reader, point_ref: &EntityRef) -> Result<Point3, StepError> {
+    let point = get::<CartesianPoint>(reader, point_ref)?;
+    vec3(reader, point_ref.0, point.coordinates())
+}
+
+fn direction(reader: &Ap214Reader, direction_ref: &EntityRef) -> Result<Vec3, StepError> {
+    let direction = get::<Direction>(reader, direction_ref)?;
+    let vector = vec3(reader, direction_ref.0, direction.direction_ratios())?;
+    if vector.length_squared() == 0.0 {
+        return Err(invalid_data(reader, direction_ref.0, "has zero length"));
+    }
+    Ok(vector)
+}
+
+fn vertex_point(reader: &Ap214Reader, vertex_ref: &EntityRef) -> Result<Point3, StepError> {
+    let vertex = get::<VertexPoint>(reader, vertex_ref)?;
+    cartesian_point(reader, vertex.vertex_geometry())
 }
 
+/// Returns location and axis, the axis defaults to z-axis
+fn axis1_placement(
+    reader: &Ap214Reader,
+    placement_ref: &EntityRef,
+) -> Result<(Point3, Vec3), StepError> {
+    let placement = get::<Axis1Placement>(reader, placement_ref)?;
+    let location = cartesian_point(reader, placement.location())?;
+    let axis = match placement.axis() {
+        Some(axis) => direction(reader, axis)?.normalize(),
+        None => Vec3::Z,
+    };
+    Ok((location, axis))
+}
+
+/// Returns location, z-axis and x-axis, missing axes are defaulted as in ISO 10303-42.
+/// A 2D placement lies on z=0 plane.
 fn axis2_placement_3d(
     reader: &Ap214Reader,
     placement_ref: &EntityRef,
-) -> (Point3, Option<Vec3>, Option<Vec3>) {
-    let placement = reader
-        .get_entity::<Axis2Placement3d>(placement_ref)
-        .unwrap();
-    let location = reader
-        .get_entity::<CartesianPoint>(placement.location())
-        .map(|point| vec3(point.coordinates()))
-        .unwrap();
-    let z_axis = reader
-        .get_entity::<Direction>(placement.axis().as_ref().unwrap())
-        .map(|direction| vec3(direction.direction_ratios()));
-    let x_axis = reader
-        .get_entity::<Direction>(placement.ref_direction().as_ref().unwrap())
-        .map(|direction| vec3(direction.direction_ratios()));
-    (location, z_axis, x_axis)
+) -> Result<(Point3, Vec3, Vec3), StepError> {
+    if let Ok(placement) = get::<Axis2Placement2d>(reader, placement_ref) {
+        let location = cartesian_point(reader, placement.location())?;
+        let x_axis = match placement.ref_direction() {
+            Some(ref_direction) => direction(reader, ref_direction)?.normalize(),
+            None => Vec3::X,
+        };
+        return Ok((location, Vec3::Z, x_axis));
+    }
+    let placement = get::<Axis2Placement3d>(reader, placement_ref)?;
+    let location = cartesian_point(reader, placement.location())?;
+    let z_axis = match placement.axis() {
+        Some(axis) => direction(reader, axis)?.normalize(),
+        None => Vec3::Z,
+    };
+    let ref_direction = match placement.ref_direction() {
+        Some(ref_direction) => direction(reader, ref_direction)?,
+        None if z_axis.x.abs() < 0.9 => Vec3::X,
+        None => Vec3::Y,
+    };
+    let x_axis = ref_direction - z_axis * ref_direction.dot(z_axis);
+    if x_axis.length_squared() == 0.0 {
+        return Err(invalid_data(
+            reader,
+            placement_ref.0,
+            "has ref_direction parallel to axis",
+        ));
+    }
+    Ok((location, z_axis, x_axis.normalize()))
 }
 
-fn extract_points(reader: &Ap214Reader, points_list: &Vec<EntityRef>) -> Vec<Point3> {
+fn extract_points(
+    reader: &Ap214Reader,
+    points_list: &[EntityRef],
+) -> Result<Vec<Point3>, StepError> {
     points_list
         .iter()
-        .map(|point| {
-            reader
-                .get_entity::<CartesianPoint>(point)
-                .map(|point| vec3(point.coordinates()))
-                .unwrap()
-        })
-        .collect::<Vec<_>>()
+        .map(|point| cartesian_point(reader, point))
+        .collect()
+}
+
+fn check_control_net<T>(
+    reader: &Ap214Reader,
+    id: i64,
+    control_points_list: &[Vec<T>],
+) -> Result<usize, StepError> {
+    let cols = control_points_list.first().map_or(0, Vec::len);
+    if control_points_list.len() < 2 || cols < 2 {
+        return Err(invalid_data(reader, id, "has too few control points"));
+    }
+    if control_points_list.iter().any(|row| row.len() != cols) {
+        return Err(invalid_data(reader, id, "has rows of unequal length"));
+    }
+    Ok(cols)
 }
 
 fn extract_control_points(
     reader: &Ap214Reader,
-    control_points_list: &Vec<Vec<EntityRef>>,
-) -> Vec<Point3> {
-    control_points_list
+    id: i64,
+    control_points_list: &[Vec<EntityRef>],
+) -> Result<Grid<Point3>, StepError> {
+    let cols = check_control_net(reader, id, control_points_list)?;
+    let points = control_points_list
         .iter()
-        .map(|row| {
-            row.iter().map(|point| {
-                reader
-                    .get_entity::<CartesianPoint>(point)
-                    .map(|point| vec3(point.coordinates()))
-                    .unwrap()
-            })
-        })
-        .flatten()
-        .collect()
+        .flat_map(|row| row.iter().map(|point| cartesian_point(reader, point)))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Grid::from_vec(points, cols))
 }
 
 fn extract_weighted_control_points(
     reader: &Ap214Reader,
-    control_points_list: &Vec<Vec<EntityRef>>,
-    weights_data: &Vec<Vec<Real>>,
-) -> Vec<Point4> {
-    control_points_list
+    id: i64,
+    control_points_list: &[Vec<EntityRef>],
+    weights_data: &[Vec<Real>],
+) -> Result<Grid<Point4>, StepError> {
+    let cols = check_control_net(reader, id, control_points_list)?;
+    if weights_data.len() != control_points_list.len()
+        || weights_data.iter().any(|row| row.len() != cols)
+    {
+        return Err(invalid_data(
+            reader,
+            id,
+            "has weights not matching control points",
+        ));
+    }
+    let points = control_points_list
         .iter()
         .zip(weights_data.iter())
-        .map(|(points, weights)| {
+        .flat_map(|(points, weights)| {
             points.iter().zip(weights.iter()).map(|(point, weight)| {
-                reader
-                    .get_entity::<CartesianPoint>(point)
-                    .map(|point| vec4(point.coordinates(), weight))
-                    .unwrap()
+                let weight = weight.0 as Float;
+                cartesian_point(reader, point).map(|point| (point * weight).extend(weight))
             })
         })
-        .flatten()
-        .collect()
+        .collect::<Result<Vec<Vec4>, _>>()?;
+    Ok(Grid::from_vec(points, cols))
 }
 
-fn extract_knot_vector(knots: &Vec<Real>, multiplicities: &Vec<i64>) -> KnotVector {
+fn extract_knot_vector(knots: &[Real], multiplicities: &[i64]) -> KnotVector {
     KnotVector::from_values_and_multiplicities(
         knots.iter().map(|value| value.0 as Float).collect(),
         multiplicities.iter().map(|&value| value as usize).collect(),
@@ -123,170 +225,366 @@ fn extract_knot_vector(knots: &Vec<Real>, multiplicities: &Vec<i64>) -> KnotVect
     // .normalize()
 }
 
-fn extract_curve(reader: &Ap214Reader, curve_ref: &EntityRef) -> Option<Box<dyn Curve>> {
-    if let Some(line) = reader.get_entity::<Line>(curve_ref) {
-        let origin = reader
-            .get_entity::<CartesianPoint>(line.pnt())
-            .map(|point| vec3(point.coordinates()))
-            .unwrap();
-        let direction = reader
-            .get_entity::<Vector>(line.dir())
-            .and_then(|vector| reader.get_entity::<Direction>(vector.orientation()))
-            .map(|dir| vec3(dir.direction_ratios()))
-            .unwrap();
-        return Some(Box::new(crate::curve::Line { origin, direction }));
-    }
-    if let Some(circle) = reader.get_entity::<Circle>(curve_ref) {
-        let (center, axis, ref_dir) = axis2_placement_3d(reader, circle.position());
-        return Some(Box::new(crate::curve::Circle {
+fn check_knot_vector(
+    reader: &Ap214Reader,
+    id: i64,
+    knots: &KnotVector,
+    control_point_count: usize,
+    degree: usize,
+) -> Result<(), StepError> {
+    if knots.len() != control_point_count + degree + 1 {
+        return Err(invalid_data(
+            reader,
+            id,
+            format!(
+                "has {} knots for {} control points of degree {}",
+                knots.len(),
+                control_point_count,
+                degree
+            ),
+        ));
+    }
+    Ok(())
+}
+
+fn extract_curve(reader: &Ap214Reader, curve_ref: &EntityRef) -> Result<Box<dyn Curve>, StepError> {
+    let id = curve_ref.0;
+    if let Ok(line) = get::<Line>(reader, curve_ref) {
+        let origin = cartesian_point(reader, line.pnt())?;
+        let vector = get::<Vector>(reader, line.dir())?;
+        let direction = direction(reader, vector.orientation())?.normalize();
+        return Ok(Box::new(crate::curve::Line {
+            origin,
+            direction: direction * vector.magnitude().0,
+        }));
+    }
+    if let Ok(circle) = get::<Circle>(reader, curve_ref) {
+        let (center, axis, ref_dir) = axis2_placement_3d(reader, circle.position())?;
+        return Ok(Box::new(crate::curve::Circle {
             center,
-            axis: axis.unwrap(),
-            ref_dir: ref_dir.unwrap().normalize(),
+            axis,
+            ref_dir,
             radius: circle.radius().0,
         }));
     }
-    if let Some(polyline) = reader.get_entity::<Polyline>(curve_ref) {
-        let points = extract_points(reader, polyline.points());
-        return Some(Box::new(crate::curve::Polyline::new(points)));
+    if let Ok(polyline) = get::<Polyline>(reader, curve_ref) {
+        let points = extract_points(reader, polyline.points())?;
+        if points.len() < 2 {
+            return Err(invalid_data(reader, id, "has less than 2 points"));
+        }
+        return Ok(Box::new(crate::curve::Polyline::new(points)));
     }
-    if let Some(bspline) = reader.get_entity::<BSplineCurveWithKnots>(curve_ref) {
-        let control_points = extract_points(reader, bspline.control_points_list());
+    if let Ok(bspline) = get::<BSplineCurveWithKnots>(reader, curve_ref) {
+        let control_points = extract_points(reader, bspline.control_points_list())?;
         let knots = extract_knot_vector(bspline.knots(), bspline.knot_multiplicities());
+        let degree = bspline.degree() as usize;
+        check_knot_vector(reader, id, &knots, control_points.len(), degree)?;
         // let closed = bspline.closed_curve() == Some(true);
-        return Some(Box::new(crate::curve::BSplineCurve {
+        return Ok(Box::new(crate::curve::BSplineCurve {
             control_points,
             knots,
-            degree: bspline.degree() as u8,
+            degree: degree as u8,
         }));
     }
-    let id = curve_ref.0;
-    println!("{}: {} is unrecoginzed", id, reader.get_type_name(id));
-    None
+    if let Ok(ellipse) = get::<Ellipse>(reader, curve_ref) {
+        let (center, axis, ref_dir) = axis2_placement_3d(reader, ellipse.position())?;
+        return Ok(Box::new(crate::curve::Ellipse {
+            center,
+            semi_axis_1: ellipse.semi_axis_1().0,
+            semi_axis_2: ellipse.semi_axis_2().0,
+            axis,
+            ref_dir,
+        }));
+    }
+    if let Ok(parabola) = get::<Parabola>(reader, curve_ref) {
+        let (center, axis, ref_dir) = axis2_placement_3d(reader, parabola.position())?;
+        return Ok(Box::new(crate::curve::Parabola {
+            center,
+            focal_dist: parabola.focal_dist().0,
+            axis,
+            ref_dir,
+        }));
+    }
+    if let Ok(hyperbola) = get::<Hyperbola>(reader, curve_ref) {
+        let (center, axis, ref_dir) = axis2_placement_3d(reader, hyperbola.position())?;
+        return Ok(Box::new(crate::curve::Hyperbola {
+            center,
+            semi_axis: hyperbola.semi_axis().0,
+            semi_imag_axis: hyperbola.semi_imag_axis().0,
+            axis,
+            ref_dir,
+        }));
+    }
+    if let Ok(curves) = get::<Vec<Box<dyn Any>>>(reader, curve_ref) {
+        // rational B-Spline curve with knots is a complex entity
+        if let (Some(bspline), Some(with_knots), Some(rational)) = (
+            complex_part::<BSplineCurve>(curves),
+            complex_part::<BSplineCurveWithKnots>(curves),
+            complex_part::<RationalBSplineCurve>(curves),
+        ) {
+            let points = extract_points(reader, bspline.control_points_list())?;
+            let weights = rational.weights_data();
+            if weights.len() != points.len() {
+                return Err(invalid_data(
+                    reader,
+                    id,
+                    "has weights not matching control points",
+                ));
+            }
+            let control_points = points
+                .into_iter()
+                .zip(weights)
+                .map(|(point, weight)| (point * weight.0).extend(weight.0))
+                .collect::<Vec<Point4>>();
+            let knots = extract_knot_vector(with_knots.knots(), with_knots.knot_multiplicities());
+            let degree = bspline.degree() as usize;
+            check_knot_vector(reader, id, &knots, control_points.len(), degree)?;
+            return Ok(Box::new(crate::curve::BSplineCurve {
+                control_points,
+                knots,
+                degree: degree as u8,
+            }));
+        }
+    }
+    if get::<TrimmedCurve>(reader, curve_ref).is_ok() {
+        let segment = extract_trimmed_curve(reader, curve_ref)?;
+        return Ok(Box::new(crate::curve::TrimmedCurve {
+            basis: segment.curve,
+            parameter_range: segment.parameter_range,
+        }));
+    }
+    if let Ok(offset) = get::<OffsetCurve3d>(reader, curve_ref) {
+        return Ok(Box::new(crate::curve::OffsetCurve {
+            basis: extract_curve(reader, offset.basis_curve())?,
+            distance: offset.distance().0,
+            ref_direction: direction(reader, offset.ref_direction())?,
+        }));
+    }
+    let surface_curve = match get::<SurfaceCurve>(reader, curve_ref) {
+        Ok(curve) => Some((curve.curve_3d(), curve.associated_geometry(), false)),
+        Err(_) => get::<SeamCurve>(reader, curve_ref)
+            .ok()
+            .map(|curve| (curve.curve_3d(), curve.associated_geometry(), true)),
+    };
+    if let Some((curve_3d, associated_geometry, seam)) = surface_curve {
+        let curve = extract_curve(reader, curve_3d)?;
+        let pcurves = associated_geometry
+            .iter()
+            .filter_map(|geometry| get::<Pcurve>(reader, geometry).ok())
+            .map(|pcurve| extract_pcurve(reader, pcurve))
+            .collect::<Result<Vec<_>, StepError>>()?;
+        return Ok(Box::new(crate::curve::SurfaceCurve {
+            curve,
+            pcurves,
+            seam,
+        }));
+    }
+    Err(unsupported(reader, id))
+}
+
+/// Parameter space curve of a PCURVE, it is the single curve in its definitional representation
+fn extract_pcurve(reader: &Ap214Reader, pcurve: &Pcurve) -> Result<Box<dyn Curve>, StepError> {
+    let representation_ref = pcurve.reference_to_curve();
+    let representation = get::<DefinitionalRepresentation>(reader, representation_ref)?;
+    let curve_ref = representation
+        .items()
+        .iter()
+        .next()
+        .ok_or(StepError::MissingAttribute {
+            id: representation_ref.0,
+            type_name: "DefinitionalRepresentation",
+            attribute: "items",
+        })?;
+    extract_curve(reader, curve_ref)
 }
 
 fn extract_edge_curve(
     reader: &Ap214Reader,
     edge_ref: &EntityRef,
-) -> Option<CurveSegment<Box<dyn Curve>>> {
+) -> Result<CurveSegment<Box<dyn Curve>>, StepError> {
     // The curve is implicitly trimmed by the vertices of the edge, this defines the edge domain. Multiple edges can reference the same curve.
     // The sense of an edge is from the edge start vertex to the edge end vertex; the sense of a curve is in the direction of increasing parameter.
-    if let Some(edge) = reader.get_entity::<EdgeCurve>(edge_ref) {
-        if let Some(curve) = extract_curve(reader, edge.edge_geometry()) {
-            let start = vertex_point(reader, edge.edge_start());
-            let end = vertex_point(reader, edge.edge_end());
-            let u0 = curve.project(start);
-            let u1 = curve.project(end);
-            let parameter_range = curve.refine_parameter_range((u0, u1), edge.same_sense());
-            return Some(CurveSegment {
-                curve,
-                parameter_range,
-                tolerance: 0.01,
-                parameter_division: 16,
-            });
-        }
-    }
-    None
+    let edge = get::<EdgeCurve>(reader, edge_ref)?;
+    let curve = extract_curve(reader, edge.edge_geometry())?;
+    let start = vertex_point(reader, edge.edge_start())?;
+    let end = vertex_point(reader, edge.edge_end())?;
+    let u0 = curve.project(start);
+    let u1 = curve.project(end);
+    let parameter_range = curve.refine_parameter_range((u0, u1), edge.same_sense());
+    Ok(CurveSegment {
+        curve,
+        parameter_range,
+        tolerance: 0.01,
+        parameter_division: 16,
+    })
 }
 
 fn extract_surface(
     reader: &Ap214Reader,
     face: &AdvancedFace,
-) -> Option<SurfacePatch<Box<dyn Surface>>> {
+) -> Result<SurfacePatch<Box<dyn Surface>>, StepError> {
     // whether the sense of the surface normal agrees with the sense of the topological normal to the face
-    let same_sense = face.same_sense();
-    if let Some(plane) = reader.get_entity::<Plane>(face.face_geometry()) {
-        let (origin, z_axis, x_axis) = axis2_placement_3d(reader, plane.position());
-        let normal = if same_sense {
-            z_axis.unwrap()
-        } else {
-            -z_axis.unwrap()
-        };
-        let u_axis = x_axis.unwrap();
+    extract_surface_geometry(reader, face.face_geometry(), face.same_sense())
+}
+
+fn extract_surface_geometry(
+    reader: &Ap214Reader,
+    surface_ref: &EntityRef,
+    same_sense: bool,
+) -> Result<SurfacePatch<Box<dyn Surface>>, StepError> {
+    let id = surface_ref.0;
+    if let Ok(plane) = get::<Plane>(reader, surface_ref) {
+        let (origin, z_axis, u_axis) = axis2_placement_3d(reader, plane.position())?;
+        let normal = if same_sense { z_axis } else { -z_axis };
         let surface = crate::surface::Plane {
             origin,
             normal,
             u_axis,
             v_axis: normal.cross(u_axis),
         };
-        return Some(SurfacePatch {
+        return Ok(SurfacePatch {
             surface: Box::new(surface) as Box<dyn Surface>,
             parameter_range: ((0.0, 1.0), (0.0, 1.0)),
             parameter_division: (16, 16),
         });
     }
-    if let Some(cylinder) = reader.get_entity::<CylindricalSurface>(face.face_geometry()) {
-        let (origin, axis, ref_dir) = axis2_placement_3d(reader, cylinder.position());
+    if let Ok(cylinder) = get::<CylindricalSurface>(reader, surface_ref) {
+        let (origin, axis, ref_dir) = axis2_placement_3d(reader, cylinder.position())?;
         let surface = crate::surface::Cylinder {
             origin,
-            axis: axis.unwrap(),
-            ref_dir: ref_dir.unwrap().normalize(),
+            axis,
+            ref_dir,
             radius: cylinder.radius().0,
         };
-        return Some(SurfacePatch {
+        return Ok(SurfacePatch {
             surface: Box::new(surface) as Box<dyn Surface>,
             parameter_range: ((0.0, 1.0), (0.0, TAU)),
             parameter_division: (16, 16),
         });
     }
-    if let Some(bezier_surface) = reader.get_entity::<BezierSurface>(face.face_geometry()) {
-        let control_points = extract_control_points(reader, bezier_surface.control_points_list());
-        let surface = crate::surface::BezierSurface::new(Grid::from_vec(
-            control_points,
-            bezier_surface.control_points_list()[0].len(),
-        ));
-        return Some(SurfacePatch {
+    if let Ok(cone) = get::<ConicalSurface>(reader, surface_ref) {
+        let (origin, axis, ref_dir) = axis2_placement_3d(reader, cone.position())?;
+        let semi_angle = cone.semi_angle().0;
+        if semi_angle <= 0.0 || semi_angle >= TAU / 4.0 {
+            return Err(invalid_data(reader, id, "has semi angle out of (0, π/2)"));
+        }
+        let surface = crate::surface::Cone {
+            origin,
+            axis,
+            ref_dir,
+            radius: cone.radius().0,
+            semi_angle,
+        };
+        return Ok(SurfacePatch {
+            surface: Box::new(surface) as Box<dyn Surface>,
+            parameter_range: ((0.0, 1.0), (0.0, TAU)),
+            parameter_division: (16, 16),
+        });
+    }
+    if let Ok(sphere) = get::<SphericalSurface>(reader, surface_ref) {
+        let (center, axis, ref_dir) = axis2_placement_3d(reader, sphere.position())?;
+        let surface = crate::surface::Sphere {
+            center,
+            axis,
+            ref_dir,
+            radius: sphere.radius().0,
+        };
+        return Ok(SurfacePatch {
+            surface: Box::new(surface) as Box<dyn Surface>,
+            parameter_range: ((-TAU / 4.0, TAU / 4.0), (0.0, TAU)),
+            parameter_division: (16, 16),
+        });
+    }
+    if let Ok(torus) = get::<ToroidalSurface>(reader, surface_ref) {
+        let (center, axis, ref_dir) = axis2_placement_3d(reader, torus.position())?;
+        let surface = crate::surface::Torus {
+            center,
+            axis,
+            ref_dir,
+            major_radius: torus.major_radius().0,
+            minor_radius: torus.minor_radius().0,
+        };
+        return Ok(SurfacePatch {
+            surface: Box::new(surface) as Box<dyn Surface>,
+            parameter_range: ((0.0, TAU), (0.0, TAU)),
+            parameter_division: (16, 16),
+        });
+    }
+    if let Ok(extrusion) = get::<SurfaceOfLinearExtrusion>(reader, surface_ref) {
+        let curve = extract_curve(reader, extrusion.swept_curve())?;
+        let vector = get::<Vector>(reader, extrusion.extrusion_axis())?;
+        let direction = direction(reader, vector.orientation())?.normalize() * vector.magnitude().0;
+        let surface = crate::surface::SurfaceOfLinearExtrusion { curve, direction };
+        return Ok(SurfacePatch {
+            surface: Box::new(surface) as Box<dyn Surface>,
+            parameter_range: ((0.0, 1.0), (0.0, 1.0)),
+            parameter_division: (16, 16),
+        });
+    }
+    if let Ok(offset) = get::<OffsetSurface>(reader, surface_ref) {
+        let basis = extract_surface_geometry(reader, offset.basis_surface(), true)?;
+        let surface = crate::surface::OffsetSurface {
+            basis: basis.surface,
+            distance: offset.distance().0,
+            parameter_range: basis.parameter_range,
+        };
+        return Ok(SurfacePatch {
+            surface: Box::new(surface) as Box<dyn Surface>,
+            parameter_range: basis.parameter_range,
+            parameter_division: basis.parameter_division,
+        });
+    }
+    if let Ok(bezier_surface) = get::<BezierSurface>(reader, surface_ref) {
+        let control_points =
+            extract_control_points(reader, id, bezier_surface.control_points_list())?;
+        let surface = crate::surface::BezierSurface::new(control_points);
+        return Ok(SurfacePatch {
             surface: Box::new(surface) as Box<dyn Surface>,
             parameter_range: ((0.0, 1.0), (0.0, 1.0)),
             parameter_division: (16, 16),
         });
     }
-    if let Some(bspline_surface) = reader.get_entity::<BSplineSurface>(face.face_geometry()) {
-        let control_points = extract_control_points(reader, bspline_surface.control_points_list());
+    if let Ok(bspline_surface) = get::<BSplineSurface>(reader, surface_ref) {
+        let control_points =
+            extract_control_points(reader, id, bspline_surface.control_points_list())?;
         let degree = (
             bspline_surface.u_degree() as usize,
             bspline_surface.v_degree() as usize,
         );
-        let surface = crate::surface::BSplineSurface::uniform_clamped(
-            Grid::from_vec(
-                control_points,
-                bspline_surface.control_points_list()[0].len(),
-            ),
-            degree,
-        );
-        return Some(SurfacePatch {
+        if control_points.rows() <= degree.0 || control_points.cols() <= degree.1 {
+            return Err(invalid_data(reader, id, "has too few control points"));
+        }
+        let surface = crate::surface::BSplineSurface::uniform_clamped(control_points, degree);
+        return Ok(SurfacePatch {
             surface: Box::new(surface) as Box<dyn Surface>,
             parameter_range: ((0.0, 1.0), (0.0, 1.0)),
             parameter_division: (16, 16),
         });
     }
-    if let Some(bspline_surface) = reader.get_entity::<RationalBSplineSurface>(face.face_geometry())
-    {
+    if let Ok(bspline_surface) = get::<RationalBSplineSurface>(reader, surface_ref) {
         let control_points = extract_weighted_control_points(
             reader,
+            id,
             bspline_surface.control_points_list(),
             bspline_surface.weights_data(),
-        );
+        )?;
         let degree = (
             bspline_surface.u_degree() as usize,
             bspline_surface.v_degree() as usize,
         );
-        let surface = crate::surface::BSplineSurface::uniform_clamped(
-            Grid::from_vec(
-                control_points,
-                bspline_surface.control_points_list()[0].len(),
-            ),
-            degree,
-        );
-        return Some(SurfacePatch {
+        if control_points.rows() <= degree.0 || control_points.cols() <= degree.1 {
+            return Err(invalid_data(reader, id, "has too few control points"));
+        }
+        let surface = crate::surface::BSplineSurface::uniform_clamped(control_points, degree);
+        return Ok(SurfacePatch {
             surface: Box::new(surface) as Box<dyn Surface>,
             parameter_range: ((0.0, 1.0), (0.0, 1.0)),
             parameter_division: (16, 16),
         });
     }
-    if let Some(bspline_surface) =
-        reader.get_entity::<BSplineSurfaceWithKnots>(face.face_geometry())
-    {
-        let control_points = extract_control_points(reader, bspline_surface.control_points_list());
+    if let Ok(bspline_surface) = get::<BSplineSurfaceWithKnots>(reader, surface_ref) {
+        let control_points =
+            extract_control_points(reader, id, bspline_surface.control_points_list())?;
         let u_knots = extract_knot_vector(
             bspline_surface.u_knots(),
             bspline_surface.u_multiplicities(),
@@ -299,169 +597,1259 @@ fn extract_surface(
             bspline_surface.u_degree() as usize,
             bspline_surface.v_degree() as usize,
         );
-        let surface = crate::surface::BSplineSurface::new(
-            Grid::from_vec(
-                control_points,
-                bspline_surface.control_points_list()[0].len(),
-            ),
-            (u_knots, v_knots),
-            degree,
-        );
-        return Some(SurfacePatch {
+        check_knot_vector(reader, id, &u_knots, control_points.rows(), degree.0)?;
+        check_knot_vector(reader, id, &v_knots, control_points.cols(), degree.1)?;
+        let surface =
+            crate::surface::BSplineSurface::new(control_points, (u_knots, v_knots), degree);
+        return Ok(SurfacePatch {
             surface: Box::new(surface) as Box<dyn Surface>,
             parameter_range: ((0.0, 1.0), (0.0, 1.0)),
             parameter_division: (16, 16),
         });
     }
-    if let Some(surfaces) = reader.get_entity::<Vec<Box<dyn Any>>>(face.face_geometry()) {
-        let mut control_points_list = None;
-        let mut degree = None;
-        let mut u_knots = None;
-        let mut v_knots = None;
-        let mut weighted_control_points = None;
-        for surface in surfaces {
-            if let Some(bspline_surface) = surface.downcast_ref::<BSplineSurface>() {
-                control_points_list = Some(bspline_surface.control_points_list());
-                degree = Some((
-                    bspline_surface.u_degree() as usize,
-                    bspline_surface.v_degree() as usize,
-                ));
-            }
-            if let Some(bspline_surface) = surface.downcast_ref::<BSplineSurfaceWithKnots>() {
-                u_knots = Some(extract_knot_vector(
-                    bspline_surface.u_knots(),
-                    bspline_surface.u_multiplicities(),
-                ));
-                v_knots = Some(extract_knot_vector(
-                    bspline_surface.v_knots(),
-                    bspline_surface.v_multiplicities(),
-                ));
-            }
-            if let Some(bspline_surface) = surface.downcast_ref::<RationalBSplineSurface>() {
-                weighted_control_points = Some(extract_weighted_control_points(
+    if let Ok(surfaces) = get::<Vec<Box<dyn Any>>>(reader, surface_ref) {
+        // rational B-Spline surface with knots is a complex entity
+        if let (Some(bspline_surface), Some(with_knots), Some(rational)) = (
+            complex_part::<BSplineSurface>(surfaces),
+            complex_part::<BSplineSurfaceWithKnots>(surfaces),
+            complex_part::<RationalBSplineSurface>(surfaces),
+        ) {
+            let control_points = extract_weighted_control_points(
+                reader,
+                id,
+                bspline_surface.control_points_list(),
+                rational.weights_data(),
+            )?;
+            let u_knots = extract_knot_vector(with_knots.u_knots(), with_knots.u_multiplicities());
+            let v_knots = extract_knot_vector(with_knots.v_knots(), with_knots.v_multiplicities());
+            let degree = (
+                bspline_surface.u_degree() as usize,
+                bspline_surface.v_degree() as usize,
+            );
+            check_knot_vector(reader, id, &u_knots, control_points.rows(), degree.0)?;
+            check_knot_vector(reader, id, &v_knots, control_points.cols(), degree.1)?;
+            let surface =
+                crate::surface::BSplineSurface::new(control_points, (u_knots, v_knots), degree);
+            return Ok(SurfacePatch {
+                surface: Box::new(surface) as Box<dyn Surface>,
+                parameter_range: ((0.0, 1.0), (0.0, 1.0)),
+                parameter_division: (16, 16),
+            });
+        }
+    }
+    if let Ok(revolution) = get::<SurfaceOfRevolution>(reader, surface_ref) {
+        let section = extract_curve(reader, revolution.swept_curve())?;
+        let (origin, axis) = axis1_placement(reader, revolution.axis_position())?;
+        // turning the other way around the axis keeps the normal `∂S/∂angle × ∂S/∂u` of STEP
+        let surface = crate::surface::SpinSurface::new(origin, -axis, section);
+        return Ok(SurfacePatch {
+            surface: Box::new(surface) as Box<dyn Surface>,
+            parameter_range: ((0.0, 1.0), (0.0, TAU)),
+            parameter_division: (16, 16),
+        });
+    }
+    Err(unsupported(reader, id))
+}
+
+/// Get the loop referenced by a face bound or a face outer bound, and the orientation of the loop
+fn face_bound_loop<'a>(
+    reader: &'a Ap214Reader,
+    bound_ref: &EntityRef,
+) -> Result<(&'a EntityRef, bool), StepError> {
+    if let Ok(face_bound) = get::<FaceOuterBound>(reader, bound_ref) {
+        return Ok((face_bound.bound(), face_bound.orientation()));
+    }
+    get::<FaceBound>(reader, bound_ref)
+        .map(|face_bound| (face_bound.bound(), face_bound.orientation()))
+}
+
+/// Matrix mapping STEP parameters of a surface to the parameters of the extracted surface
+pub(super) fn parameter_matrix(surface: &dyn Surface, same_sense: bool) -> Mat4 {
+    use crate::surface::{Cone, Cylinder, OffsetSurface, Plane, Sphere, SpinSurface, Torus};
+    // STEP puts the angle around axis first
+    let swap = Mat4::from_cols(Vec4::Y, Vec4::X, Vec4::Z, Vec4::W);
+    if surface.is::<Plane>() && !same_sense {
+        // the plane of a reversed face has reversed normal and v axis
+        return Mat4::from_scale(Vec3::new(1.0, -1.0, 1.0));
+    }
+    if surface.is::<Cylinder>()
+        || surface.is::<Cone>()
+        || surface.is::<Sphere>()
+        || surface.is::<Torus>()
+    {
+        return swap;
+    }
+    if surface.is::<SpinSurface<Box<dyn Curve>>>() {
+        // the spin surface turns the other way around the axis
+        return Mat4::from_cols(-Vec4::Y, Vec4::X, Vec4::Z, Vec4::W);
+    }
+    if let Some(offset) = surface.downcast_ref::<OffsetSurface<Box<dyn Surface>>>() {
+        return parameter_matrix(offset.basis.as_ref(), true);
+    }
+    Mat4::IDENTITY
+}
+
+/// Parameter space curve of an edge on the surface, it is taken from the PCURVE of a
+/// SURFACE_CURVE or SEAM_CURVE. The first PCURVE of a seam is used by the edge in its own sense.
+fn extract_edge_pcurve(
+    reader: &Ap214Reader,
+    edge: &OrientedEdge,
+    surface_ref: &EntityRef,
+    matrix: &Mat4,
+) -> Option<Box<dyn Curve>> {
+    let edge_curve = get::<EdgeCurve>(reader, edge.edge_element()).ok()?;
+    let curve_ref = edge_curve.edge_geometry();
+    let (associated_geometry, seam) = match get::<SurfaceCurve>(reader, curve_ref) {
+        Ok(curve) => (curve.associated_geometry(), false),
+        Err(_) => (
+            get::<SeamCurve>(reader, curve_ref)
+                .ok()?
+                .associated_geometry(),
+            true,
+        ),
+    };
+    let pcurves = associated_geometry
+        .iter()
+        .filter_map(|geometry| get::<Pcurve>(reader, geometry).ok())
+        .filter(|pcurve| pcurve.basis_surface().0 == surface_ref.0)
+        .collect::<Vec<_>>();
+    let index = if seam && !edge.orientation() { 1 } else { 0 };
+    let pcurve = extract_pcurve(reader, pcurves.get(index)?).ok()?;
+    if *matrix == Mat4::IDENTITY {
+        Some(pcurve)
+    } else {
+        Some(Box::new(crate::curve::TransformedCurve {
+            curve: pcurve,
+            matrix: *matrix,
+        }))
+    }
+}
+
+fn extract_edge_loop(
+    reader: &Ap214Reader,
+    bound_ref: &EntityRef,
+    surface_ref: &EntityRef,
+    matrix: &Mat4,
+) -> Result<crate::surface::EdgeLoop, StepError> {
+    let (loop_ref, orientation) = face_bound_loop(reader, bound_ref)?;
+    let edge_loop = get::<EdgeLoop>(reader, loop_ref)?;
+    let mut edges = Vec::with_capacity(edge_loop.edge_list().len());
+    let mut pcurves = Vec::with_capacity(edge_loop.edge_list().len());
+    for edge in edge_loop.edge_list() {
+        let edge = get::<OrientedEdge>(reader, edge)?;
+        let mut segment = extract_edge_curve(reader, edge.edge_element())?;
+        // an oriented edge against its edge curve runs from the edge end to the edge start
+        if edge.orientation() != orientation {
+            let (u0, u1) = segment.parameter_range;
+            segment.parameter_range = (u1, u0);
+        }
+        edges.push(segment);
+        pcurves.push(extract_edge_pcurve(reader, edge, surface_ref, matrix));
+    }
+    if !orientation {
+        edges.reverse();
+        pcurves.reverse();
+    }
+    Ok(crate::surface::EdgeLoop { edges, pcurves })
+}
+
+fn extract_face(
+    reader: &Ap214Reader,
+    face: &AdvancedFace,
+) -> Result<TrimmedSurface<Box<dyn Surface>>, StepError> {
+    let surface = extract_surface(reader, face)?;
+    let matrix = parameter_matrix(surface.surface.as_ref(), face.same_sense());
+    let bounds = face
+        .bounds()
+        .iter()
+        .map(|bound| extract_edge_loop(reader, bound, face.face_geometry(), &matrix))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(TrimmedSurface {
+        surface: surface.surface,
+        bounds,
+    })
+}
+
+/// Builds shared topology, entities referenced by several faces are converted once.
+#[derive(Default)]
+struct TopologyBuilder {
+    topology: Topology,
+    vertices: HashMap<i64, usize>,
+    edges: HashMap<i64, usize>,
+    faces: HashMap<i64, usize>,
+    shells: HashMap<i64, usize>,
+}
+
+impl TopologyBuilder {
+    fn vertex(&mut self, reader: &Ap214Reader, vertex_ref: &EntityRef) -> Result<usize, StepError> {
+        if let Some(&vertex) = self.vertices.get(&vertex_ref.0) {
+            return Ok(vertex);
+        }
+        let vertex = self.topology.add_vertex(vertex_point(reader, vertex_ref)?);
+        self.vertices.insert(vertex_ref.0, vertex);
+        Ok(vertex)
+    }
+
+    fn edge(&mut self, reader: &Ap214Reader, edge_ref: &EntityRef) -> Result<usize, StepError> {
+        if let Some(&edge) = self.edges.get(&edge_ref.0) {
+            return Ok(edge);
+        }
+        let edge_curve = get::<EdgeCurve>(reader, edge_ref)?;
+        let start = self.vertex(reader, edge_curve.edge_start())?;
+        let end = self.vertex(reader, edge_curve.edge_end())?;
+        let curve = extract_edge_curve(reader, edge_ref)?;
+        let edge = self.topology.add_edge(start, end, curve);
+        self.edges.insert(edge_ref.0, edge);
+        Ok(edge)
+    }
+
+    /// Add topology of a face, loops are in the same order as bounds of the extracted face.
+    /// `matrix` maps STEP parameters of the face surface to the extracted surface.
+    fn add_face(
+        &mut self,
+        reader: &Ap214Reader,
+        id: i64,
+        face: &AdvancedFace,
+        matrix: &Mat4,
+    ) -> Result<(), StepError> {
+        let mut loops = Vec::with_capacity(face.bounds().len());
+        for bound in face.bounds() {
+            let (loop_ref, orientation) = face_bound_loop(reader, bound)?;
+            let edge_loop = get::<EdgeLoop>(reader, loop_ref)?;
+            let mut coedges = Vec::with_capacity(edge_loop.edge_list().len());
+            let mut pcurves = Vec::with_capacity(edge_loop.edge_list().len());
+            for edge in edge_loop.edge_list() {
+                let edge = get::<OrientedEdge>(reader, edge)?;
+                coedges.push((self.edge(reader, edge.edge_element())?, edge.orientation()));
+                pcurves.push(extract_edge_pcurve(
                     reader,
-                    control_points_list.unwrap(),
-                    bspline_surface.weights_data(),
+                    edge,
+                    face.face_geometry(),
+                    matrix,
                 ));
             }
+            loops.push((coedges, pcurves, orientation));
         }
-        if let Some(weighted_control_points) = weighted_control_points {
-            if let (Some(u_knots), Some(v_knots)) = (u_knots, v_knots) {
-                let surface = crate::surface::BSplineSurface::new(
-                    Grid::from_vec(
-                        weighted_control_points,
-                        control_points_list.unwrap()[0].len(),
-                    ),
-                    (u_knots, v_knots),
-                    degree.unwrap(),
-                );
-                return Some(SurfacePatch {
-                    surface: Box::new(surface) as Box<dyn Surface>,
-                    parameter_range: ((0.0, 1.0), (0.0, 1.0)),
-                    parameter_division: (16, 16),
-                });
+        let index = self.topology.add_face();
+        self.topology.faces[index].same_sense = face.same_sense();
+        for (coedges, pcurves, orientation) in loops {
+            let edge_loop = self.topology.add_loop(index, &coedges, orientation);
+            for (&coedge, pcurve) in self.topology.loops[edge_loop].coedges.iter().zip(pcurves) {
+                self.topology.coedges[coedge].pcurve = pcurve;
             }
         }
+        self.faces.insert(id, index);
+        Ok(())
     }
-    if let Some(revolution) = reader.get_entity::<SurfaceOfRevolution>(face.face_geometry()) {
-        if let Some(section) = extract_curve(reader, revolution.swept_curve()) {
-            let (origin, axis) = axis1_placement(reader, revolution.axis_position());
-            let surface = crate::surface::SpinSurface {
-                origin,
-                axis: axis.unwrap(),
-                section,
-            };
-            return Some(SurfacePatch {
-                surface: Box::new(surface) as Box<dyn Surface>,
-                parameter_range: ((0.0, 1.0), (0.0, TAU)),
-                parameter_division: (16, 16),
-            });
-        }
+
+    /// Add a shell of the successfully converted faces
+    fn add_shell(&mut self, id: i64, faces: &std::collections::HashSet<EntityRef>) {
+        let mut faces = faces
+            .iter()
+            .filter_map(|face| self.faces.get(&face.0).copied())
+            .collect::<Vec<_>>();
+        faces.sort_unstable();
+        let shell = self.topology.add_shell(faces);
+        self.shells.insert(id, shell);
+    }
+
+    fn shell(&self, reader: &Ap214Reader, shell_ref: &EntityRef) -> Result<usize, StepError> {
+        let id = match get::<OrientedClosedShell>(reader, shell_ref) {
+            Ok(oriented_shell) => oriented_shell.closed_shell_element().0,
+            Err(_) => shell_ref.0,
+        };
+        self.shells
+            .get(&id)
+            .copied()
+            .ok_or_else(|| unsupported(reader, id))
+    }
+
+    fn add_solid(
+        &mut self,
+        reader: &Ap214Reader,
+        outer: &EntityRef,
+        voids: &[&EntityRef],
+    ) -> Result<(), StepError> {
+        let outer = self.shell(reader, outer)?;
+        let voids = voids
+            .iter()
+            .map(|&void| self.shell(reader, void))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.topology.add_solid(outer, voids);
+        Ok(())
     }
-    return None;
 }
 
-fn get_trimming_parameter_value(measure: &TrimmingSelect) -> Option<f64> {
-    if let TrimmingSelect::ParameterValue(value) = measure {
-        Some(value.0)
-    } else {
-        None
+fn entity_ids<T: Any>(reader: &Ap214Reader) -> &[i64] {
+    reader
+        .type_ids
+        .get(&TypeId::of::<T>())
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+fn get_trimming_parameter_value(
+    reader: &Ap214Reader,
+    curve: &dyn Curve,
+    measures: &std::collections::HashSet<TrimmingSelect>,
+) -> Result<Option<Float>, StepError> {
+    // prefer parameter value to cartesian point
+    for measure in measures {
+        if let TrimmingSelect::ParameterValue(value) = measure {
+            return Ok(Some(value.0));
+        }
+    }
+    for measure in measures {
+        if let TrimmingSelect::CartesianPoint(point) = measure {
+            return cartesian_point(reader, point).map(|point| Some(curve.project(point)));
+        }
     }
+    Ok(None)
 }
 
 fn extract_curve_segment(
     reader: &Ap214Reader,
     segment_ref: &EntityRef,
-) -> Option<CurveSegment<Box<dyn Curve>>> {
-    if let Some(segment) = reader.get_entity::<CompositeCurveSegment>(segment_ref) {
-        if let Some(trimmed_curve) = reader.get_entity::<TrimmedCurve>(segment.parent_curve()) {
-            if let Some(basis_curve) = extract_curve(reader, trimmed_curve.basis_curve()) {
-                let param1 =
-                    get_trimming_parameter_value(trimmed_curve.trim_1().iter().next().unwrap())
-                        .unwrap();
-                let param2 =
-                    get_trimming_parameter_value(trimmed_curve.trim_2().iter().next().unwrap())
-                        .unwrap();
-                let parameter_range = basis_curve
-                    .refine_parameter_range((param1, param2), trimmed_curve.sense_agreement());
-                return Some(CurveSegment {
-                    curve: basis_curve,
-                    parameter_range,
-                    tolerance: 0.01,
-                    parameter_division: 16,
-                });
+) -> Result<CurveSegment<Box<dyn Curve>>, StepError> {
+    let segment = get::<CompositeCurveSegment>(reader, segment_ref)?;
+    extract_trimmed_curve(reader, segment.parent_curve())
+}
+
+fn extract_trimmed_curve(
+    reader: &Ap214Reader,
+    curve_ref: &EntityRef,
+) -> Result<CurveSegment<Box<dyn Curve>>, StepError> {
+    let trimmed_curve = get::<TrimmedCurve>(reader, curve_ref)?;
+    let basis_curve = extract_curve(reader, trimmed_curve.basis_curve())?;
+    let trim = |measures| {
+        get_trimming_parameter_value(reader, basis_curve.as_ref(), measures)?.ok_or(
+            StepError::MissingAttribute {
+                id: curve_ref.0,
+                type_name: "TrimmedCurve",
+                attribute: "trimming value",
+            },
+        )
+    };
+    let param1 = trim(trimmed_curve.trim_1())?;
+    let param2 = trim(trimmed_curve.trim_2())?;
+    let parameter_range =
+        basis_curve.refine_parameter_range((param1, param2), trimmed_curve.sense_agreement());
+    Ok(CurveSegment {
+        curve: basis_curve,
+        parameter_range,
+        tolerance: 0.01,
+        parameter_division: 16,
+    })
+}
+
+fn placement_matrix(reader: &Ap214Reader, placement_ref: &EntityRef) -> Result<Mat4, StepError> {
+    let (origin, z_axis, x_axis) = axis2_placement_3d(reader, placement_ref)?;
+    Ok(Mat4::from_cols(
+        x_axis.extend(0.0),
+        z_axis.cross(x_axis).extend(0.0),
+        z_axis.extend(0.0),
+        origin.extend(1.0),
+    ))
+}
+
+/// Transformation from the context of the first representation to the second
+fn transformation_matrix(
+    reader: &Ap214Reader,
+    operator_ref: &EntityRef,
+) -> Result<Mat4, StepError> {
+    if let Ok(transformation) = get::<ItemDefinedTransformation>(reader, operator_ref) {
+        let from = placement_matrix(reader, transformation.transform_item_1())?;
+        let to = placement_matrix(reader, transformation.transform_item_2())?;
+        return Ok(to * from.inverse());
+    }
+    if let Ok(operator) = get::<CartesianTransformationOperator3d>(reader, operator_ref) {
+        let origin = cartesian_point(reader, operator.local_origin())?;
+        let z_axis = match operator.axis3() {
+            Some(axis) => direction(reader, axis)?.normalize(),
+            None => Vec3::Z,
+        };
+        let x_axis = match operator.axis1() {
+            Some(axis) => direction(reader, axis)?,
+            None if z_axis.x.abs() < 0.9 => Vec3::X,
+            None => Vec3::Y,
+        };
+        let x_axis = (x_axis - z_axis * x_axis.dot(z_axis)).normalize();
+        let scale = operator.scale().map_or(1.0, |scale| scale.0);
+        return Ok(Mat4::from_cols(
+            (x_axis * scale).extend(0.0),
+            (z_axis.cross(x_axis) * scale).extend(0.0),
+            (z_axis * scale).extend(0.0),
+            origin.extend(1.0),
+        ));
+    }
+    Err(unsupported(reader, operator_ref.0))
+}
+
+/// Returns the related representations and the transformation from `rep_1` to `rep_2`
+fn representation_transformation(
+    reader: &Ap214Reader,
+    relation_ref: &EntityRef,
+) -> Result<(i64, i64, Mat4), StepError> {
+    if let Ok(relation) = get::<RepresentationRelationshipWithTransformation>(reader, relation_ref)
+    {
+        let matrix = transformation_matrix(reader, relation.transformation_operator())?;
+        return Ok((relation.rep_1().0, relation.rep_2().0, matrix));
+    }
+    let parts = get::<Vec<Box<dyn Any>>>(reader, relation_ref)?;
+    let relation = complex_part::<RepresentationRelationship>(parts);
+    let transformation = complex_part::<RepresentationRelationshipWithTransformation>(parts);
+    match (relation, transformation) {
+        (Some(relation), Some(transformation)) => {
+            let matrix = transformation_matrix(reader, transformation.transformation_operator())?;
+            Ok((relation.rep_1().0, relation.rep_2().0, matrix))
+        }
+        _ => Err(unsupported(reader, relation_ref.0)),
+    }
+}
+
+fn representation_items<'a>(
+    reader: &'a Ap214Reader,
+    representation_ref: &EntityRef,
+) -> Result<&'a HashSet<EntityRef>, StepError> {
+    if let Ok(representation) = get::<ShapeRepresentation>(reader, representation_ref) {
+        return Ok(representation.items());
+    }
+    if let Ok(representation) = get::<AdvancedBrepShapeRepresentation>(reader, representation_ref) {
+        return Ok(representation.items());
+    }
+    if let Ok(representation) =
+        get::<ManifoldSurfaceShapeRepresentation>(reader, representation_ref)
+    {
+        return Ok(representation.items());
+    }
+    Err(unsupported(reader, representation_ref.0))
+}
+
+/// Name of the product defined by a product definition
+fn product_name(reader: &Ap214Reader, definition: &ProductDefinition) -> Result<String, StepError> {
+    let formation_ref = definition.formation();
+    let product_ref = match get::<ProductDefinitionFormation>(reader, formation_ref) {
+        Ok(formation) => formation.of_product(),
+        Err(_) => get::<ProductDefinitionFormationWithSpecifiedSource>(reader, formation_ref)?
+            .of_product(),
+    };
+    Ok(get::<Product>(reader, product_ref)?.name().to_string())
+}
+
+/// Collects STEP ids of the faces of shells, solids and surface models
+fn collect_item_faces(reader: &Ap214Reader, item_ref: &EntityRef, faces: &mut Vec<i64>) {
+    if let Ok(solid) = get::<ManifoldSolidBrep>(reader, item_ref) {
+        collect_item_faces(reader, solid.outer(), faces);
+    } else if let Ok(solid) = get::<BrepWithVoids>(reader, item_ref) {
+        collect_item_faces(reader, solid.outer(), faces);
+        for void in solid.voids() {
+            collect_item_faces(reader, void, faces);
+        }
+    } else if let Ok(model) = get::<ShellBasedSurfaceModel>(reader, item_ref) {
+        for shell in model.sbsm_boundary() {
+            collect_item_faces(reader, shell, faces);
+        }
+    } else if let Ok(shell) = get::<ClosedShell>(reader, item_ref) {
+        faces.extend(shell.cfs_faces().iter().map(|face| face.0));
+    } else if let Ok(shell) = get::<OpenShell>(reader, item_ref) {
+        faces.extend(shell.cfs_faces().iter().map(|face| face.0));
+    } else if let Ok(shell) = get::<OrientedClosedShell>(reader, item_ref) {
+        collect_item_faces(reader, shell.closed_shell_element(), faces);
+    } else if get::<AdvancedFace>(reader, item_ref).is_ok() {
+        faces.push(item_ref.0);
+    }
+}
+
+/// Build the product structure, `faces` maps ids of converted faces to model faces
+fn extract_assembly(
+    reader: &Ap214Reader,
+    faces: &HashMap<i64, usize>,
+    diagnostics: &mut Vec<StepError>,
+) -> Assembly {
+    let mut assembly = Assembly::new();
+    let mut parts = HashMap::new();
+    for &id in entity_ids::<ProductDefinition>(reader) {
+        let definition = match get::<ProductDefinition>(reader, &EntityRef(id)) {
+            Ok(definition) => definition,
+            Err(err) => {
+                diagnostics.push(err);
+                continue;
+            }
+        };
+        let name = product_name(reader, definition).unwrap_or_else(|err| {
+            diagnostics.push(err);
+            String::new()
+        });
+        parts.insert(id, assembly.parts.len());
+        assembly.parts.push(Part {
+            name,
+            ..Default::default()
+        });
+    }
+
+    // shape representations of product definitions and assembly usages
+    let mut definitions = HashMap::new();
+    for &id in entity_ids::<ProductDefinitionShape>(reader) {
+        let shape = match get::<ProductDefinitionShape>(reader, &EntityRef(id)) {
+            Ok(shape) => shape,
+            Err(err) => {
+                diagnostics.push(err);
+                continue;
+            }
+        };
+        definitions.insert(id, shape.definition().0);
+    }
+    let mut representations = HashMap::<usize, Vec<i64>>::new();
+    for shape in reader.get_entities::<ShapeDefinitionRepresentation>() {
+        let definition = definitions.get(&shape.definition().0);
+        if let Some(&part) = definition.and_then(|definition| parts.get(definition)) {
+            representations
+                .entry(part)
+                .or_default()
+                .push(shape.used_representation().0);
+        }
+    }
+    let mut related_representations = HashMap::<i64, Vec<i64>>::new();
+    for relation in reader.get_entities::<ShapeRepresentationRelationship>() {
+        let (rep_1, rep_2) = (relation.rep_1().0, relation.rep_2().0);
+        related_representations
+            .entry(rep_1)
+            .or_default()
+            .push(rep_2);
+        related_representations
+            .entry(rep_2)
+            .or_default()
+            .push(rep_1);
+    }
+
+    for (&part, part_representations) in &representations {
+        let mut visited = part_representations.clone();
+        let mut index = 0;
+        let mut face_ids = Vec::new();
+        while index < visited.len() {
+            let representation = visited[index];
+            index += 1;
+            match representation_items(reader, &EntityRef(representation)) {
+                Ok(items) => {
+                    for item in items {
+                        collect_item_faces(reader, item, &mut face_ids);
+                    }
+                }
+                Err(err) => diagnostics.push(err),
+            }
+            for &related in related_representations
+                .get(&representation)
+                .into_iter()
+                .flatten()
+            {
+                if !visited.contains(&related) {
+                    visited.push(related);
+                }
+            }
+        }
+        let part = &mut assembly.parts[part];
+        part.faces = face_ids
+            .iter()
+            .filter_map(|id| faces.get(id).copied())
+            .collect();
+        part.faces.sort_unstable();
+        part.faces.dedup();
+    }
+
+    let mut placements = HashMap::new();
+    for shape in reader.get_entities::<ContextDependentShapeRepresentation>() {
+        if let Some(&usage) = definitions.get(&shape.represented_product_relation().0) {
+            placements.insert(usage, shape.representation_relation());
+        }
+    }
+    let mut children = HashSet::new();
+    for &id in entity_ids::<NextAssemblyUsageOccurrence>(reader) {
+        let usage = match get::<NextAssemblyUsageOccurrence>(reader, &EntityRef(id)) {
+            Ok(usage) => usage,
+            Err(err) => {
+                diagnostics.push(err);
+                continue;
+            }
+        };
+        let parent = parts.get(&usage.relating_product_definition().0);
+        let child = parts.get(&usage.related_product_definition().0);
+        let (&parent, &child) = match (parent, child) {
+            (Some(parent), Some(child)) => (parent, child),
+            _ => {
+                diagnostics.push(invalid_data(
+                    reader,
+                    id,
+                    "does not relate two product definitions",
+                ));
+                continue;
+            }
+        };
+        let placement = match placements.get(&id) {
+            Some(relation) => match representation_transformation(reader, relation) {
+                // the transformation places rep_1 in rep_2, which is usually the child in the parent
+                Ok((rep_1, _, matrix)) => {
+                    let child_representations = representations.get(&child);
+                    if child_representations.is_none_or(|reps| reps.contains(&rep_1)) {
+                        matrix
+                    } else {
+                        matrix.inverse()
+                    }
+                }
+                Err(err) => {
+                    diagnostics.push(err);
+                    Mat4::IDENTITY
+                }
+            },
+            None => Mat4::IDENTITY,
+        };
+        let name = if usage.name().is_empty() {
+            usage.id().to_string()
+        } else {
+            usage.name().to_string()
+        };
+        assembly.parts[parent].children.push(Instance {
+            name,
+            part: child,
+            placement,
+        });
+        children.insert(child);
+    }
+    assembly.roots = (0..assembly.parts.len())
+        .filter(|part| !children.contains(part))
+        .collect();
+    assembly
+}
+
+/// Model read from a STEP file.
+pub struct StepModel {
+    pub model: Model<TrimmedSurface<Box<dyn Surface>>>,
+    /// Shared vertices and edges of the faces, topological faces have the same indices as model faces
+    pub topology: Topology,
+    /// Parts and their placements, parts refer to faces of the model
+    pub assembly: Assembly,
+    /// Errors of entities which are skipped while reading the model
+    pub diagnostics: Vec<StepError>,
+}
+
+impl std::ops::Deref for StepModel {
+    type Target = Model<TrimmedSurface<Box<dyn Surface>>>;
+    fn deref(&self) -> &Self::Target {
+        &self.model
+    }
+}
+
+impl std::ops::DerefMut for StepModel {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.model
+    }
+}
+
+impl StepModel {
+    /// Tessellate all faces into one mesh without cracks between adjacent faces.
+    ///
+    /// Each topological edge is discretized once and the same points bound both faces,
+    /// so the boundary vertices of neighbouring faces are welded.
+    /// The assembly tree is flattened, faces of a part appear once per occurrence.
+    pub fn get_triangle_mesh(&self) -> TriangleMesh {
+        let edge_parameters = self.topology.discretize_edge_parameters();
+        let edge_points = self.topology.discretize_edges();
+        let meshes = self
+            .model
+            .faces
+            .iter()
+            .enumerate()
+            .map(|(index, face)| {
+                if index < self.topology.faces.len() {
+                    // prefer exact parameters of boundary points given by pcurves
+                    match self.topology.face_parameter_polygons(
+                        index,
+                        &edge_parameters,
+                        &edge_points,
+                    ) {
+                        Some((polygons, params)) => {
+                            face.surface.trim_parameter_polygons(&polygons, &params)
+                        }
+                        None => {
+                            let polygons = self.topology.face_polygons(index, &edge_points);
+                            face.surface.trim_polygons(&polygons)
+                        }
+                    }
+                } else {
+                    face.get_triangle_mesh()
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mut placed = vec![false; meshes.len()];
+        let mut occurrences = Vec::new();
+        for (part, placement) in self.assembly.occurrences() {
+            for &face in &self.assembly.parts[part].faces {
+                placed[face] = true;
+                occurrences.push(meshes[face].transform(&placement));
             }
         }
+        let unplaced = meshes
+            .into_iter()
+            .zip(placed)
+            .filter(|(_, placed)| !placed)
+            .map(|(mesh, _)| mesh);
+        TriangleMesh::join(occurrences.into_iter().chain(unplaced))
+    }
+
+    fn welded_model(&self) -> Model<TriangleMesh> {
+        let mut model = Model::new();
+        model.add_face(self.get_triangle_mesh());
+        model
+    }
+
+    /// Save the welded mesh of faces as a binary STL file
+    pub fn save_as_stl<P: AsRef<std::path::Path>>(&self, filename: P) -> std::io::Result<()> {
+        self.welded_model().save_as_stl(filename)
+    }
+
+    /// Save the welded mesh of faces as an OBJ file
+    pub fn save_as_obj<P: AsRef<std::path::Path>>(&self, filename: P) -> std::io::Result<()> {
+        self.welded_model().save_as_obj(filename)
     }
-    return None;
 }
 
 pub struct ModelReader {}
-pub type StepModel = Model<TrimmedSurface<Box<dyn Surface>>>;
 
 impl ModelReader {
-    pub fn read_model<P: AsRef<std::path::Path>>(file: P) -> std::io::Result<StepModel> {
+    /// Read faces and curves from a STEP file.
+    ///
+    /// Only failure of reading the file is fatal, faces and curve segments which can not be
+    /// converted are skipped and the errors are collected in `StepModel::diagnostics`.
+    pub fn read_model<P: AsRef<std::path::Path>>(file: P) -> Result<StepModel, StepError> {
         let mut reader = Ap214Reader::new();
         reader.read(file)?;
 
         let mut model = Model::new();
+        let mut diagnostics = Vec::new();
         for composite_curve in reader.get_entities::<CompositeCurve>() {
-            let segments = composite_curve
-                .segments()
-                .iter()
-                .filter_map(|segment| extract_curve_segment(&reader, segment))
-                .collect();
+            let mut segments = Vec::with_capacity(composite_curve.segments().len());
+            for segment in composite_curve.segments() {
+                match extract_curve_segment(&reader, segment) {
+                    Ok(segment) => segments.push(segment),
+                    Err(err) => diagnostics.push(err),
+                }
+            }
             model.add_curve(Polycurve { segments });
         }
-        for advanced_face in reader.get_entities::<AdvancedFace>() {
-            if let Some(surface) = extract_surface(&reader, advanced_face) {
-                let mut bounds = Vec::with_capacity(advanced_face.bounds().len());
-                for bound in advanced_face.bounds() {
-                    if let Some(face_bound) = reader.get_entity::<FaceBound>(bound) {
-                        if let Some(edge_loop) = reader.get_entity::<EdgeLoop>(face_bound.bound()) {
-                            let edges = edge_loop
-                                .edge_list()
-                                .iter()
-                                .filter_map(|edge| {
-                                    reader.get_entity::<OrientedEdge>(edge).and_then(|edge| {
-                                        extract_edge_curve(&reader, edge.edge_element())
-                                    })
-                                })
-                                .collect::<Vec<_>>();
-                            bounds.push(crate::surface::EdgeLoop { edges });
-                        }
-                    }
-                }
-                let trimmed_surface = TrimmedSurface {
-                    surface: surface.surface,
-                    bounds,
-                };
-                model.add_face(trimmed_surface);
-            } else {
-                let id = advanced_face.face_geometry().0;
-                println!("{}: {} is unrecoginzed", id, reader.get_type_name(id));
+
+        let mut builder = TopologyBuilder::default();
+        for &id in entity_ids::<AdvancedFace>(&reader) {
+            let advanced_face = get::<AdvancedFace>(&reader, &EntityRef(id))?;
+            let face = extract_face(&reader, advanced_face).and_then(|face| {
+                let matrix = parameter_matrix(face.surface.as_ref(), advanced_face.same_sense());
+                builder
+                    .add_face(&reader, id, advanced_face, &matrix)
+                    .map(|_| face)
+            });
+            match face {
+                Ok(face) => model.add_face(face),
+                Err(err) => diagnostics.push(err),
+            }
+        }
+        for &id in entity_ids::<ClosedShell>(&reader) {
+            let shell = get::<ClosedShell>(&reader, &EntityRef(id))?;
+            builder.add_shell(id, shell.cfs_faces());
+        }
+        for &id in entity_ids::<OpenShell>(&reader) {
+            let shell = get::<OpenShell>(&reader, &EntityRef(id))?;
+            builder.add_shell(id, shell.cfs_faces());
+        }
+        for &id in entity_ids::<ManifoldSolidBrep>(&reader) {
+            let solid = get::<ManifoldSolidBrep>(&reader, &EntityRef(id))?;
+            if let Err(err) = builder.add_solid(&reader, solid.outer(), &[]) {
+                diagnostics.push(err);
+            }
+        }
+        for &id in entity_ids::<BrepWithVoids>(&reader) {
+            let solid = get::<BrepWithVoids>(&reader, &EntityRef(id))?;
+            let mut voids = solid.voids().iter().collect::<Vec<_>>();
+            voids.sort_unstable_by_key(|void| void.0);
+            if let Err(err) = builder.add_solid(&reader, solid.outer(), &voids) {
+                diagnostics.push(err);
             }
         }
-        Ok(model)
+        let assembly = extract_assembly(&reader, &builder.faces, &mut diagnostics);
+        Ok(StepModel {
+            model,
+            topology: builder.topology,
+            assembly,
+            diagnostics,
+        })
+    }
+}
+
+#[test]
+fn test_read_model_diagnostics() {
+    let content = r"ISO-10303-21;
+HEADER;
+FILE_DESCRIPTION((''),'2;1');
+FILE_NAME('square','',(''),(''),'','','');
+FILE_SCHEMA(('AUTOMOTIVE_DESIGN'));
+ENDSEC;
+DATA;
+#1=CARTESIAN_POINT('',(0.,0.,0.));
+#2=DIRECTION('',(0.,0.,1.));
+#3=DIRECTION('',(1.,0.,0.));
+#4=AXIS2_PLACEMENT_3D('',#1,#2,#3);
+#5=PLANE('',#4);
+#10=CARTESIAN_POINT('',(0.,0.,0.));
+#11=CARTESIAN_POINT('',(1.,0.,0.));
+#12=CARTESIAN_POINT('',(1.,1.,0.));
+#13=CARTESIAN_POINT('',(0.,1.,0.));
+#20=VERTEX_POINT('',#10);
+#21=VERTEX_POINT('',#11);
+#22=VERTEX_POINT('',#12);
+#23=VERTEX_POINT('',#13);
+#30=DIRECTION('',(1.,0.,0.));
+#31=DIRECTION('',(0.,1.,0.));
+#32=VECTOR('',#30,1.);
+#33=VECTOR('',#31,1.);
+#34=LINE('',#10,#32);
+#35=LINE('',#11,#33);
+#36=LINE('',#13,#32);
+#37=LINE('',#10,#33);
+#40=EDGE_CURVE('',#20,#21,#34,.T.);
+#41=EDGE_CURVE('',#21,#22,#35,.T.);
+#42=EDGE_CURVE('',#23,#22,#36,.T.);
+#43=EDGE_CURVE('',#20,#23,#37,.T.);
+#50=ORIENTED_EDGE('',*,*,#40,.T.);
+#51=ORIENTED_EDGE('',*,*,#41,.T.);
+#52=ORIENTED_EDGE('',*,*,#42,.F.);
+#53=ORIENTED_EDGE('',*,*,#43,.F.);
+#60=EDGE_LOOP('',(#50,#51,#52,#53));
+#70=FACE_OUTER_BOUND('',#60,.T.);
+#80=ADVANCED_FACE('',(#70),#5,.T.);
+#81=ADVANCED_FACE('',(#70),#99,.T.);
+#82=ADVANCED_FACE('',(#70),#1,.T.);
+ENDSEC;
+END-ISO-10303-21;
+";
+    std::fs::write("tmp/square.stp", content).unwrap();
+    let model = ModelReader::read_model("tmp/square.stp").unwrap();
+    assert_eq!(model.faces.len(), 1);
+    assert_eq!(model.faces[0].bounds[0].edges.len(), 4);
+    assert_eq!(model.topology.faces.len(), 1);
+    assert_eq!(model.topology.vertices.len(), 4);
+    assert_eq!(model.topology.free_edges().len(), 4);
+    assert_eq!(model.diagnostics.len(), 2);
+    assert!(matches!(
+        model.diagnostics[0],
+        StepError::MissingEntity { id: 99 }
+    ));
+    assert_eq!(model.diagnostics[1].entity_id(), Some(1));
+    assert_eq!(model.diagnostics[1].type_name(), Some("CartesianPoint"));
+
+    assert!(matches!(
+        ModelReader::read_model("tmp/missing.stp"),
+        Err(StepError::Io(_))
+    ));
+}
+
+#[test]
+fn test_watertight_mesh() {
+    let content = r"ISO-10303-21;
+HEADER;
+FILE_DESCRIPTION((''),'2;1');
+FILE_NAME('cylinder','',(''),(''),'','','');
+FILE_SCHEMA(('AUTOMOTIVE_DESIGN'));
+ENDSEC;
+DATA;
+#1=CARTESIAN_POINT('',(0.,0.,0.));
+#2=DIRECTION('',(0.,0.,1.));
+#3=DIRECTION('',(1.,0.,0.));
+#4=AXIS2_PLACEMENT_3D('',#1,#2,#3);
+#5=CARTESIAN_POINT('',(0.,0.,2.));
+#6=AXIS2_PLACEMENT_3D('',#5,#2,#3);
+#7=DIRECTION('',(0.,0.,-1.));
+#8=AXIS2_PLACEMENT_3D('',#1,#7,#3);
+#10=CIRCLE('',#4,1.);
+#11=CIRCLE('',#6,1.);
+#12=CARTESIAN_POINT('',(1.,0.,0.));
+#13=CARTESIAN_POINT('',(1.,0.,2.));
+#14=VERTEX_POINT('',#12);
+#15=VERTEX_POINT('',#13);
+#16=EDGE_CURVE('',#14,#14,#10,.T.);
+#17=EDGE_CURVE('',#15,#15,#11,.T.);
+#20=CYLINDRICAL_SURFACE('',#4,1.);
+#21=ORIENTED_EDGE('',*,*,#16,.T.);
+#22=EDGE_LOOP('',(#21));
+#23=FACE_BOUND('',#22,.T.);
+#24=ORIENTED_EDGE('',*,*,#17,.F.);
+#25=EDGE_LOOP('',(#24));
+#26=FACE_BOUND('',#25,.T.);
+#27=ADVANCED_FACE('',(#23,#26),#20,.T.);
+#30=PLANE('',#8);
+#31=ORIENTED_EDGE('',*,*,#16,.F.);
+#32=EDGE_LOOP('',(#31));
+#33=FACE_OUTER_BOUND('',#32,.T.);
+#34=ADVANCED_FACE('',(#33),#30,.T.);
+#40=PLANE('',#6);
+#41=ORIENTED_EDGE('',*,*,#17,.T.);
+#42=EDGE_LOOP('',(#41));
+#43=FACE_OUTER_BOUND('',#42,.T.);
+#44=ADVANCED_FACE('',(#43),#40,.T.);
+#50=CLOSED_SHELL('',(#27,#34,#44));
+#51=MANIFOLD_SOLID_BREP('',#50);
+ENDSEC;
+END-ISO-10303-21;
+";
+    let check = |content: &str, edge_count: usize| {
+        std::fs::write("tmp/cylinder.stp", content).unwrap();
+        let model = ModelReader::read_model("tmp/cylinder.stp").unwrap();
+        assert!(model.diagnostics.is_empty());
+        assert_eq!(model.topology.edges.len(), edge_count);
+        assert!(model.topology.is_shell_closed(0));
+        assert!(model.topology.free_edges().is_empty());
+
+        // every mesh edge is shared by two triangles with consistent winding
+        let mesh = model.get_triangle_mesh();
+        assert!(mesh.triangle_count() > 0);
+        let mut edges = std::collections::HashSet::new();
+        for triangle in mesh.triangles.chunks(3) {
+            for i in 0..3 {
+                assert!(edges.insert((triangle[i], triangle[(i + 1) % 3])));
+            }
+        }
+        assert!(edges.iter().all(|&(a, b)| edges.contains(&(b, a))));
+        let report = mesh.validate();
+        assert!(report.is_watertight() && report.is_oriented());
+        assert_eq!(
+            (report.component_count, report.euler_characteristic()),
+            (1, 2)
+        );
+        model
+    };
+    let model = check(content, 2);
+    assert_eq!(model.topology.solids.len(), 1);
+    assert!(model.topology.seam_edges(0).is_empty());
+
+    // the cylinder bounded by one loop running along both sides of a seam line
+    let seam = content
+        .replace(
+            "#27=ADVANCED_FACE('',(#23,#26),#20,.T.);",
+            "#27=ADVANCED_FACE('',(#29),#20,.T.);",
+        )
+        .replace(
+            "ENDSEC;\nEND",
+            "#18=LINE('',#12,#19);
+#19=VECTOR('',#2,1.);
+#9=EDGE_CURVE('',#14,#15,#18,.T.);
+#28=EDGE_LOOP('',(#21,#35,#24,#36));
+#29=FACE_OUTER_BOUND('',#28,.T.);
+#35=ORIENTED_EDGE('',*,*,#9,.T.);
+#36=ORIENTED_EDGE('',*,*,#9,.F.);
+ENDSEC;
+END",
+        );
+    let model = check(&seam, 3);
+    let seam_edges = model.topology.seam_edges(0);
+    assert_eq!(seam_edges.len(), 1);
+    let (edge, coedges) = seam_edges[0];
+    assert!(coedges
+        .iter()
+        .all(|&coedge| model.topology.coedges[coedge].edge == edge));
+
+    // circles as surface curves with (angle, length) pcurves on the cylinder
+    let content = content
+        .replace(
+            "#16=EDGE_CURVE('',#14,#14,#10,.T.);",
+            "#16=EDGE_CURVE('',#14,#14,#62,.T.);",
+        )
+        .replace(
+            "#17=EDGE_CURVE('',#15,#15,#11,.T.);",
+            "#17=EDGE_CURVE('',#15,#15,#65,.T.);",
+        )
+        .replace(
+            "ENDSEC;\nEND",
+            "#60=LINE('',#70,#73);
+#61=PCURVE('',#20,#75);
+#62=SURFACE_CURVE('',#10,(#61),.CURVE_3D.);
+#63=LINE('',#71,#73);
+#64=PCURVE('',#20,#76);
+#65=SURFACE_CURVE('',#11,(#64),.CURVE_3D.);
+#70=CARTESIAN_POINT('',(0.,0.));
+#71=CARTESIAN_POINT('',(0.,2.));
+#72=DIRECTION('',(1.,0.));
+#73=VECTOR('',#72,1.);
+#75=DEFINITIONAL_REPRESENTATION('',(#60),#1);
+#76=DEFINITIONAL_REPRESENTATION('',(#63),#1);
+ENDSEC;
+END",
+        );
+    let check_pcurves = |model: &StepModel| {
+        for bound in &model.faces[0].bounds {
+            let (points, params) = bound.to_parameter_polygon().unwrap();
+            for (point, param) in points.iter().zip(&params) {
+                let surface_point = model.faces[0].surface.get_point(param.x, param.y);
+                assert!(point.distance(surface_point) < 1e-9);
+            }
+        }
+        assert!(
+            model
+                .topology
+                .coedges
+                .iter()
+                .filter(|c| c.pcurve.is_some())
+                .count()
+                == 2
+        );
+    };
+    let model = check(&content, 2);
+    check_pcurves(&model);
+
+    // pcurves are written back with the edges
+    use super::StepSchema;
+    model
+        .save_as_step("tmp/cylinder_copy.stp", StepSchema::Ap214)
+        .unwrap();
+    let copy = std::fs::read_to_string("tmp/cylinder_copy.stp").unwrap();
+    assert_eq!(copy.matches("=SURFACE_CURVE(").count(), 2);
+    check_pcurves(&check(&copy, 2));
+    model
+        .model
+        .save_as_step("tmp/cylinder_copy.stp", StepSchema::Ap214)
+        .unwrap();
+    let copy = std::fs::read_to_string("tmp/cylinder_copy.stp").unwrap();
+    assert_eq!(copy.matches("=SURFACE_CURVE(").count(), 2);
+    check_pcurves(&check(&copy, 2));
+}
+
+#[test]
+fn test_read_assembly() {
+    let content = r"ISO-10303-21;
+HEADER;
+FILE_DESCRIPTION((''),'2;1');
+FILE_NAME('assembly','',(''),(''),'','','');
+FILE_SCHEMA(('AUTOMOTIVE_DESIGN'));
+ENDSEC;
+DATA;
+#1=CARTESIAN_POINT('',(0.,0.,0.));
+#2=DIRECTION('',(0.,0.,1.));
+#3=DIRECTION('',(1.,0.,0.));
+#4=AXIS2_PLACEMENT_3D('',#1,#2,#3);
+#5=PLANE('',#4);
+#10=CARTESIAN_POINT('',(0.,0.,0.));
+#11=CARTESIAN_POINT('',(1.,0.,0.));
+#12=CARTESIAN_POINT('',(1.,1.,0.));
+#13=CARTESIAN_POINT('',(0.,1.,0.));
+#20=VERTEX_POINT('',#10);
+#21=VERTEX_POINT('',#11);
+#22=VERTEX_POINT('',#12);
+#23=VERTEX_POINT('',#13);
+#30=DIRECTION('',(1.,0.,0.));
+#31=DIRECTION('',(0.,1.,0.));
+#32=DIRECTION('',(-1.,0.,0.));
+#33=DIRECTION('',(0.,-1.,0.));
+#34=VECTOR('',#30,1.);
+#35=VECTOR('',#31,1.);
+#36=VECTOR('',#32,1.);
+#37=VECTOR('',#33,1.);
+#40=LINE('',#10,#34);
+#41=LINE('',#11,#35);
+#42=LINE('',#12,#36);
+#43=LINE('',#13,#37);
+#50=EDGE_CURVE('',#20,#21,#40,.T.);
+#51=EDGE_CURVE('',#21,#22,#41,.T.);
+#52=EDGE_CURVE('',#22,#23,#42,.T.);
+#53=EDGE_CURVE('',#23,#20,#43,.T.);
+#60=ORIENTED_EDGE('',*,*,#50,.T.);
+#61=ORIENTED_EDGE('',*,*,#51,.T.);
+#62=ORIENTED_EDGE('',*,*,#52,.T.);
+#63=ORIENTED_EDGE('',*,*,#53,.T.);
+#64=EDGE_LOOP('',(#60,#61,#62,#63));
+#65=FACE_OUTER_BOUND('',#64,.T.);
+#66=ADVANCED_FACE('',(#65),#5,.T.);
+#67=OPEN_SHELL('',(#66));
+#68=SHELL_BASED_SURFACE_MODEL('',(#67));
+#70=SHAPE_REPRESENTATION('bolt',(#4,#68),#99);
+#80=PRODUCT('bolt','bolt','',(#98));
+#81=PRODUCT_DEFINITION_FORMATION('','',#80);
+#82=PRODUCT_DEFINITION('design','',#81,#96);
+#83=PRODUCT_DEFINITION_SHAPE('','',#82);
+#84=SHAPE_DEFINITION_REPRESENTATION(#83,#70);
+#96=PRODUCT_DEFINITION_CONTEXT('part definition',#97,'design');
+#97=APPLICATION_CONTEXT('automotive design');
+#98=PRODUCT_CONTEXT('',#97,'mechanical');
+#99=REPRESENTATION_CONTEXT('','');
+#101=AXIS2_PLACEMENT_3D('',#102,#2,#3);
+#102=CARTESIAN_POINT('',(5.,0.,0.));
+#103=CARTESIAN_POINT('',(0.,5.,0.));
+#104=AXIS2_PLACEMENT_3D('',#103,#2,#105);
+#105=DIRECTION('',(0.,1.,0.));
+#110=SHAPE_REPRESENTATION('assembly',(#4,#101,#104),#99);
+#120=PRODUCT('frame','frame','',(#98));
+#121=PRODUCT_DEFINITION_FORMATION('','',#120);
+#122=PRODUCT_DEFINITION('design','',#121,#96);
+#123=PRODUCT_DEFINITION_SHAPE('','',#122);
+#124=SHAPE_DEFINITION_REPRESENTATION(#123,#110);
+#130=NEXT_ASSEMBLY_USAGE_OCCURRENCE('1','bolt-1','',#122,#82,$);
+#131=PRODUCT_DEFINITION_SHAPE('','',#130);
+#132=ITEM_DEFINED_TRANSFORMATION('','',#4,#101);
+#133=(REPRESENTATION_RELATIONSHIP('','',#70,#110)REPRESENTATION_RELATIONSHIP_WITH_TRANSFORMATION(#132)SHAPE_REPRESENTATION_RELATIONSHIP());
+#134=CONTEXT_DEPENDENT_SHAPE_REPRESENTATION(#133,#131);
+#140=NEXT_ASSEMBLY_USAGE_OCCURRENCE('2','bolt-2','',#122,#82,$);
+#141=PRODUCT_DEFINITION_SHAPE('','',#140);
+#142=ITEM_DEFINED_TRANSFORMATION('','',#4,#104);
+#143=(REPRESENTATION_RELATIONSHIP('','',#70,#110)REPRESENTATION_RELATIONSHIP_WITH_TRANSFORMATION(#142)SHAPE_REPRESENTATION_RELATIONSHIP());
+#144=CONTEXT_DEPENDENT_SHAPE_REPRESENTATION(#143,#141);
+ENDSEC;
+END-ISO-10303-21;
+";
+    std::fs::write("tmp/assembly.stp", content).unwrap();
+    let model = ModelReader::read_model("tmp/assembly.stp").unwrap();
+    assert!(model.diagnostics.is_empty(), "{:?}", model.diagnostics);
+    assert_eq!(model.faces.len(), 1);
+
+    let assembly = &model.assembly;
+    assert_eq!(assembly.parts.len(), 2);
+    assert_eq!(assembly.parts[0].name, "bolt");
+    assert_eq!(assembly.parts[0].faces, vec![0]);
+    assert_eq!(assembly.roots, vec![1]);
+    let instances = &assembly.parts[1].children;
+    assert_eq!(instances.len(), 2);
+    assert_eq!(instances[0].name, "bolt-1");
+    assert!(instances.iter().all(|instance| instance.part == 0));
+    assert_eq!(assembly.occurrences().len(), 3);
+
+    let mesh = model.get_triangle_mesh();
+    let contains = |point: Point3| mesh.vertices.iter().any(|v| v.distance(point) < 1e-9);
+    assert!(contains(Point3::new(6.0, 1.0, 0.0)));
+    assert!(contains(Point3::new(-1.0, 6.0, 0.0)));
+    assert!(!contains(Point3::new(1.0, 1.0, 0.0)));
+}
+
+#[test]
+fn test_read_curves() {
+    use crate::curve::{BSplineCurve, OffsetCurve, SurfaceCurve, TrimmedCurve};
+
+    // offset of the line through E and A moved away along the negative offset direction
+    let normal = Vec3::new(1.0, -3.0, 0.0) / Float::sqrt(10.0);
+    let basis = Point3::new(-1.0, -1.0, 0.0) - normal;
+    let content = format!(
+        r"ISO-10303-21;
+HEADER;
+FILE_DESCRIPTION((''),'2;1');
+FILE_NAME('curves','',(''),(''),'','','');
+FILE_SCHEMA(('AUTOMOTIVE_DESIGN'));
+ENDSEC;
+DATA;
+#1=CARTESIAN_POINT('',(0.,0.,0.));
+#2=DIRECTION('',(0.,0.,1.));
+#3=DIRECTION('',(1.,0.,0.));
+#4=AXIS2_PLACEMENT_3D('',#1,#2,#3);
+#5=PLANE('',#4);
+#10=CARTESIAN_POINT('',(2.,0.,0.));
+#11=CARTESIAN_POINT('',(0.,1.,0.));
+#12=CARTESIAN_POINT('',(-1.,0.,0.));
+#13=CARTESIAN_POINT('',(-1.,-1.,0.));
+#20=VERTEX_POINT('',#10);
+#21=VERTEX_POINT('',#11);
+#22=VERTEX_POINT('',#12);
+#23=VERTEX_POINT('',#13);
+#30=ELLIPSE('',#4,2.,1.);
+#31=CARTESIAN_POINT('',(0.,0.));
+#32=DIRECTION('',(-2.,1.));
+#33=VECTOR('',#32,1.);
+#34=LINE('',#31,#33);
+#35=DEFINITIONAL_REPRESENTATION('',(#34),#1);
+#36=PCURVE('',#5,#35);
+#37=SURFACE_CURVE('',#30,(#36),.CURVE_3D.);
+#40=CARTESIAN_POINT('',(-1.,1.,0.));
+#41=(BOUNDED_CURVE() B_SPLINE_CURVE(2,(#11,#40,#12),.CIRCULAR_ARC.,.F.,.F.) B_SPLINE_CURVE_WITH_KNOTS((3,3),(0.,1.),.UNSPECIFIED.) CURVE() GEOMETRIC_REPRESENTATION_ITEM() RATIONAL_B_SPLINE_CURVE((1.,0.707106781186548,1.)) REPRESENTATION_ITEM(''));
+#50=CARTESIAN_POINT('',(-1.,5.,0.));
+#51=DIRECTION('',(0.,-1.,0.));
+#52=VECTOR('',#51,1.);
+#53=LINE('',#50,#52);
+#54=TRIMMED_CURVE('',#53,(PARAMETER_VALUE(5.)),(PARAMETER_VALUE(6.)),.T.,.PARAMETER.);
+#60=CARTESIAN_POINT('',({},{},0.));
+#61=DIRECTION('',(3.,1.,0.));
+#62=VECTOR('',#61,1.);
+#63=LINE('',#60,#62);
+#64=OFFSET_CURVE_3D('',#63,1.,.F.,#2);
+#70=EDGE_CURVE('',#20,#21,#37,.T.);
+#71=EDGE_CURVE('',#21,#22,#41,.T.);
+#72=EDGE_CURVE('',#22,#23,#54,.T.);
+#73=EDGE_CURVE('',#23,#20,#64,.T.);
+#80=ORIENTED_EDGE('',*,*,#70,.T.);
+#81=ORIENTED_EDGE('',*,*,#71,.T.);
+#82=ORIENTED_EDGE('',*,*,#72,.T.);
+#83=ORIENTED_EDGE('',*,*,#73,.T.);
+#90=EDGE_LOOP('',(#80,#81,#82,#83));
+#91=FACE_OUTER_BOUND('',#90,.T.);
+#92=ADVANCED_FACE('',(#91),#5,.T.);
+ENDSEC;
+END-ISO-10303-21;
+",
+        basis.x, basis.y
+    );
+    std::fs::write("tmp/curves.stp", content).unwrap();
+    let model = ModelReader::read_model("tmp/curves.stp").unwrap();
+    assert!(model.diagnostics.is_empty(), "{:?}", model.diagnostics);
+    let edges = &model.faces[0].bounds[0].edges;
+    assert_eq!(edges.len(), 4);
+    let surface_curve = edges[0].curve.downcast_ref::<SurfaceCurve>().unwrap();
+    assert_eq!(surface_curve.pcurves.len(), 1);
+    assert!(edges[1].curve.is::<BSplineCurve<Point4>>());
+    assert!(edges[2].curve.is::<TrimmedCurve<Box<dyn Curve>>>());
+    assert!(edges[3].curve.is::<OffsetCurve<Box<dyn Curve>>>());
+
+    let corners = [
+        Point3::new(2.0, 0.0, 0.0),
+        Point3::new(0.0, 1.0, 0.0),
+        Point3::new(-1.0, 0.0, 0.0),
+        Point3::new(-1.0, -1.0, 0.0),
+    ];
+    let middles = [
+        Point3::new(Float::sqrt(2.0), Float::sqrt(0.5), 0.0),
+        Point3::new(-Float::sqrt(0.5), Float::sqrt(0.5), 0.0),
+        Point3::new(-1.0, -0.5, 0.0),
+        Point3::new(0.5, -0.5, 0.0),
+    ];
+    for (i, edge) in edges.iter().enumerate() {
+        let points = edge.get_points();
+        assert!(points[0].distance(corners[i]) < 1e-6);
+        assert!(points[points.len() - 1].distance(corners[(i + 1) % 4]) < 1e-6);
+        let middle = edge.curve.get_point(edge.curve.project(middles[i]));
+        assert!(middle.distance(middles[i]) < 1e-6, "{:?}", middle);
+    }
+}
+
+#[test]
+fn test_revolution_parameters() {
+    use crate::curve::Line;
+    use crate::surface::SpinSurface;
+    use crate::{Point2, Quat};
+
+    // SURFACE_OF_REVOLUTION turns the section by the angle around the axis,
+    // its parameters are (angle, section parameter)
+    let (origin, axis) = (Point3::new(1.0, 0.0, 0.0), Vec3::Z);
+    let section = || Line {
+        origin: Point3::new(3.0, 0.0, 0.0),
+        direction: Vec3::new(-1.0, 0.0, 1.0),
+    };
+    let step_point = |angle: Float, t: Float| {
+        origin + Quat::from_axis_angle(axis, angle) * (section().get_point(t) - origin)
+    };
+    let surface: Box<dyn Surface> = Box::new(SpinSurface::new(
+        origin,
+        -axis,
+        Box::new(section()) as Box<dyn Curve>,
+    ));
+    let matrix = parameter_matrix(surface.as_ref(), true);
+    for &(angle, t) in &[(0.0, 0.0), (0.5, 0.3), (2.0, 1.5), (5.0, -0.5)] {
+        let param = matrix.transform_point3(Point3::new(angle, t, 0.0));
+        let point = surface.get_point(param.x, param.y);
+        assert!(point.distance(step_point(angle, t)) < 1e-12);
+        // the normal of STEP is ∂S/∂angle × ∂S/∂t
+        let delta = 1e-6;
+        let du = (step_point(angle + delta, t) - step_point(angle, t)) / delta;
+        let dv = (step_point(angle, t + delta) - step_point(angle, t)) / delta;
+        let normal = surface.get_normals(&[Point2::new(param.x, param.y)])[0];
+        assert!(normal.dot(du.cross(dv).normalize()) > 1.0 - 1e-6);
     }
 }
@@ -0,0 +1,1392 @@
+use super::step_reader::parameter_matrix;
+use super::{Model, StepModel};
+use crate::curve::{
+    BSplineCurve, Circle, Curve, CurveSegment, Ellipse, Hyperbola, Line, OffsetCurve, Parabola,
+    Polycurve, Polyline, SurfaceCurve, TransformedCurve, TrimmedCurve,
+};
+use crate::surface::{
+    BSplineSurface, BezierSurface, Cone, Cylinder, EdgeLoop, OffsetSurface, Plane, Sphere,
+    SpinSurface, Surface, SurfaceOfLinearExtrusion, Torus, TrimmedSurface,
+};
+use crate::topology::Topology;
+use crate::utils::TOLERANCE;
+use crate::{Float, Grid, KnotVector, Mat4, Point3, Point4, Vec3};
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind, Write};
+
+/// Application protocol of a written STEP file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepSchema {
+    /// AP214, automotive design
+    Ap214,
+    /// AP242, managed model based 3D engineering
+    Ap242,
+}
+
+impl StepSchema {
+    fn file_schema(self) -> &'static str {
+        match self {
+            StepSchema::Ap214 => "AUTOMOTIVE_DESIGN { 1 0 10303 214 1 1 1 1 }",
+            StepSchema::Ap242 => {
+                "AP242_MANAGED_MODEL_BASED_3D_ENGINEERING_MIM_LF { 1 0 10303 442 1 1 4 }"
+            }
+        }
+    }
+
+    fn application_context(self) -> &'static str {
+        match self {
+            StepSchema::Ap214 => "automotive design",
+            StepSchema::Ap242 => "managed model based 3d engineering",
+        }
+    }
+
+    fn application_protocol(self) -> (&'static str, i32) {
+        match self {
+            StepSchema::Ap214 => ("automotive_design", 2000),
+            StepSchema::Ap242 => ("ap242_managed_model_based_3d_engineering", 2014),
+        }
+    }
+}
+
+/// Format a real number, which must contain a decimal point in STEP files
+fn real(value: Float) -> String {
+    let text = format!("{:?}", value);
+    match text.split_once('e') {
+        Some((mantissa, exponent)) if mantissa.contains('.') => {
+            format!("{}E{}", mantissa, exponent)
+        }
+        Some((mantissa, exponent)) => format!("{}.E{}", mantissa, exponent),
+        None => text,
+    }
+}
+
+fn string(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "''"))
+}
+
+fn boolean(value: bool) -> &'static str {
+    if value {
+        ".T."
+    } else {
+        ".F."
+    }
+}
+
+fn list<T, F: Fn(&T) -> String>(items: &[T], format: F) -> String {
+    let items = items.iter().map(format).collect::<Vec<_>>();
+    format!("({})", items.join(","))
+}
+
+fn reference(id: &usize) -> String {
+    format!("#{}", id)
+}
+
+fn unsupported(kind: &str, item: &dyn std::fmt::Debug) -> Error {
+    let description = format!("{:?}", item);
+    let name = description
+        .split(|c: char| !c.is_alphanumeric())
+        .next()
+        .unwrap_or_default();
+    Error::new(
+        ErrorKind::InvalidInput,
+        format!("{} {} can not be written to STEP file", kind, name),
+    )
+}
+
+/// Serializes geometric and topological entities into the data section of a STEP file.
+pub struct StepWriter {
+    schema: StepSchema,
+    entities: Vec<String>,
+    /// Written vertices by their cell in a grid of `TOLERANCE` spacing
+    vertices: HashMap<[i64; 3], Vec<(Point3, usize)>>,
+    /// Written edges by their sorted end vertices
+    edges: HashMap<(usize, usize), Vec<WrittenEdge>>,
+    /// Geometry of the written edges by their ids
+    edge_geometries: HashMap<usize, EdgeGeometry>,
+    /// Context of parameter space curves
+    parameter_context: Option<usize>,
+}
+
+/// An edge curve already in the data section
+struct WrittenEdge {
+    id: usize,
+    start: usize,
+    quarter: Point3,
+    three_quarter: Point3,
+}
+
+/// Entities of an edge curve, its curve becomes a surface curve when pcurves are added
+struct EdgeGeometry {
+    start: usize,
+    end: usize,
+    curve: usize,
+    parameter_range: (Float, Float),
+    surface_curve: Option<usize>,
+    /// Surface and PCURVE ids, the pcurve of the edge in its own sense comes first on a seam
+    pcurves: Vec<(usize, usize)>,
+}
+
+fn edge_curve(geometry: &EdgeGeometry) -> String {
+    let (u0, u1) = geometry.parameter_range;
+    format!(
+        "EDGE_CURVE('',#{},#{},#{},{})",
+        geometry.start,
+        geometry.end,
+        geometry.surface_curve.unwrap_or(geometry.curve),
+        boolean(u0 <= u1)
+    )
+}
+
+fn same_point(a: Point3, b: Point3) -> bool {
+    a.distance_squared(b) <= TOLERANCE * TOLERANCE
+}
+
+fn cell(point: Point3) -> [i64; 3] {
+    let cell = (point / TOLERANCE).floor();
+    [cell.x as i64, cell.y as i64, cell.z as i64]
+}
+
+impl StepWriter {
+    pub fn new(schema: StepSchema) -> StepWriter {
+        StepWriter {
+            schema,
+            entities: Vec::new(),
+            vertices: HashMap::new(),
+            edges: HashMap::new(),
+            edge_geometries: HashMap::new(),
+            parameter_context: None,
+        }
+    }
+
+    /// Add an entity and return its id
+    pub fn add_entity(&mut self, entity: String) -> usize {
+        self.entities.push(entity);
+        self.entities.len()
+    }
+
+    fn point(&mut self, point: Point3) -> usize {
+        self.add_entity(format!(
+            "CARTESIAN_POINT('',({},{},{}))",
+            real(point.x),
+            real(point.y),
+            real(point.z)
+        ))
+    }
+
+    fn point_2d(&mut self, point: Point3) -> usize {
+        self.add_entity(format!(
+            "CARTESIAN_POINT('',({},{}))",
+            real(point.x),
+            real(point.y)
+        ))
+    }
+
+    fn direction(&mut self, direction: Vec3) -> usize {
+        self.add_entity(format!(
+            "DIRECTION('',({},{},{}))",
+            real(direction.x),
+            real(direction.y),
+            real(direction.z)
+        ))
+    }
+
+    fn axis1_placement(&mut self, origin: Point3, axis: Vec3) -> usize {
+        let location = self.point(origin);
+        let axis = self.direction(axis);
+        self.add_entity(format!("AXIS1_PLACEMENT('',#{},#{})", location, axis))
+    }
+
+    fn axis2_placement_3d(&mut self, origin: Point3, axis: Vec3, ref_dir: Vec3) -> usize {
+        let location = self.point(origin);
+        let axis = self.direction(axis);
+        let ref_dir = self.direction(ref_dir);
+        self.add_entity(format!(
+            "AXIS2_PLACEMENT_3D('',#{},#{},#{})",
+            location, axis, ref_dir
+        ))
+    }
+
+    /// Vertices closer than `TOLERANCE` are shared
+    fn vertex(&mut self, point: Point3) -> usize {
+        let [x, y, z] = cell(point);
+        for key in (x - 1..=x + 1).flat_map(|x| {
+            (y - 1..=y + 1).flat_map(move |y| (z - 1..=z + 1).map(move |z| [x, y, z]))
+        }) {
+            let found = self.vertices.get(&key).and_then(|vertices| {
+                vertices
+                    .iter()
+                    .find(|(position, _)| same_point(*position, point))
+                    .map(|&(_, id)| id)
+            });
+            if let Some(id) = found {
+                return id;
+            }
+        }
+        let position = self.point(point);
+        let id = self.add_entity(format!("VERTEX_POINT('',#{})", position));
+        self.vertices
+            .entry([x, y, z])
+            .or_default()
+            .push((point, id));
+        id
+    }
+
+    fn knots(&self, knots: &KnotVector) -> (String, String) {
+        let (values, multiplicities) = knots.to_values_and_multiplicities();
+        (
+            list(&multiplicities, |m| m.to_string()),
+            list(&values, |&v| real(v)),
+        )
+    }
+
+    fn control_points(&mut self, points: &[Point3]) -> String {
+        let ids = points
+            .iter()
+            .map(|&point| self.point(point))
+            .collect::<Vec<_>>();
+        list(&ids, reference)
+    }
+
+    fn weighted_control_points(&mut self, points: &[Point4]) -> (String, String) {
+        let ids = points
+            .iter()
+            .map(|point| self.point(point.truncate() / point.w))
+            .collect::<Vec<_>>();
+        (list(&ids, reference), list(points, |p| real(p.w)))
+    }
+
+    fn control_net(&mut self, grid: &Grid<Point3>) -> String {
+        let rows = (0..grid.rows())
+            .map(|row| self.control_points(&grid.iter_row(row).copied().collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+        format!("({})", rows.join(","))
+    }
+
+    fn weighted_control_net(&mut self, grid: &Grid<Point4>) -> (String, String) {
+        let (rows, weights): (Vec<_>, Vec<_>) = (0..grid.rows())
+            .map(|row| {
+                self.weighted_control_points(&grid.iter_row(row).copied().collect::<Vec<_>>())
+            })
+            .unzip();
+        (
+            format!("({})", rows.join(",")),
+            format!("({})", weights.join(",")),
+        )
+    }
+
+    /// Add a curve and return its id
+    pub fn add_curve(&mut self, curve: &dyn Curve) -> std::io::Result<usize> {
+        if let Some(line) = curve.downcast_ref::<Line>() {
+            let origin = self.point(line.origin);
+            let direction = self.direction(line.direction.normalize());
+            let vector = self.add_entity(format!(
+                "VECTOR('',#{},{})",
+                direction,
+                real(line.direction.length())
+            ));
+            return Ok(self.add_entity(format!("LINE('',#{},#{})", origin, vector)));
+        }
+        if let Some(circle) = curve.downcast_ref::<Circle>() {
+            let position = self.axis2_placement_3d(circle.center, circle.axis, circle.ref_dir);
+            return Ok(self.add_entity(format!(
+                "CIRCLE('',#{},{})",
+                position,
+                real(circle.radius)
+            )));
+        }
+        if let Some(ellipse) = curve.downcast_ref::<Ellipse>() {
+            let position = self.axis2_placement_3d(ellipse.center, ellipse.axis, ellipse.ref_dir);
+            return Ok(self.add_entity(format!(
+                "ELLIPSE('',#{},{},{})",
+                position,
+                real(ellipse.semi_axis_1),
+                real(ellipse.semi_axis_2)
+            )));
+        }
+        if let Some(parabola) = curve.downcast_ref::<Parabola>() {
+            let position =
+                self.axis2_placement_3d(parabola.center, parabola.axis, parabola.ref_dir);
+            return Ok(self.add_entity(format!(
+                "PARABOLA('',#{},{})",
+                position,
+                real(parabola.focal_dist)
+            )));
+        }
+        if let Some(hyperbola) = curve.downcast_ref::<Hyperbola>() {
+            let position =
+                self.axis2_placement_3d(hyperbola.center, hyperbola.axis, hyperbola.ref_dir);
+            return Ok(self.add_entity(format!(
+                "HYPERBOLA('',#{},{},{})",
+                position,
+                real(hyperbola.semi_axis),
+                real(hyperbola.semi_imag_axis)
+            )));
+        }
+        if let Some(trimmed) = curve.downcast_ref::<TrimmedCurve<Box<dyn Curve>>>() {
+            let (u0, u1) = trimmed.parameter_range;
+            let basis = self.add_curve(trimmed.basis.as_ref())?;
+            return Ok(self.add_entity(format!(
+                "TRIMMED_CURVE('',#{},(PARAMETER_VALUE({})),(PARAMETER_VALUE({})),{},.PARAMETER.)",
+                basis,
+                real(u0),
+                real(u1),
+                boolean(u0 <= u1)
+            )));
+        }
+        if let Some(offset) = curve.downcast_ref::<OffsetCurve<Box<dyn Curve>>>() {
+            let basis = self.add_curve(offset.basis.as_ref())?;
+            let ref_direction = self.direction(offset.ref_direction);
+            return Ok(self.add_entity(format!(
+                "OFFSET_CURVE_3D('',#{},{},.F.,#{})",
+                basis,
+                real(offset.distance),
+                ref_direction
+            )));
+        }
+        if let Some(surface_curve) = curve.downcast_ref::<SurfaceCurve>() {
+            // parameter space curves are not written without their surfaces
+            return self.add_curve(surface_curve.curve.as_ref());
+        }
+        if let Some(polyline) = curve.downcast_ref::<Polyline>() {
+            let points = self.control_points(&polyline.vertices);
+            return Ok(self.add_entity(format!("POLYLINE('',{})", points)));
+        }
+        if let Some(bspline) = curve.downcast_ref::<BSplineCurve<Point3>>() {
+            let points = self.control_points(&bspline.control_points);
+            let (multiplicities, knots) = self.knots(&bspline.knots);
+            return Ok(self.add_entity(format!(
+                "B_SPLINE_CURVE_WITH_KNOTS('',{},{},.UNSPECIFIED.,.U.,.U.,{},{},.UNSPECIFIED.)",
+                bspline.degree, points, multiplicities, knots
+            )));
+        }
+        if let Some(bspline) = curve.downcast_ref::<BSplineCurve<Point4>>() {
+            let (points, weights) = self.weighted_control_points(&bspline.control_points);
+            let (multiplicities, knots) = self.knots(&bspline.knots);
+            return Ok(self.add_entity(format!(
+                "(BOUNDED_CURVE() B_SPLINE_CURVE({},{},.UNSPECIFIED.,.U.,.U.) \
+                 B_SPLINE_CURVE_WITH_KNOTS({},{},.UNSPECIFIED.) CURVE() \
+                 GEOMETRIC_REPRESENTATION_ITEM() RATIONAL_B_SPLINE_CURVE({}) \
+                 REPRESENTATION_ITEM(''))",
+                bspline.degree, points, multiplicities, knots, weights
+            )));
+        }
+        Err(unsupported("curve", curve))
+    }
+
+    /// Add a surface and return its id
+    pub fn add_surface(&mut self, surface: &dyn Surface) -> std::io::Result<usize> {
+        if let Some(plane) = surface.downcast_ref::<Plane>() {
+            let position = self.axis2_placement_3d(plane.origin, plane.normal, plane.u_axis);
+            return Ok(self.add_entity(format!("PLANE('',#{})", position)));
+        }
+        if let Some(cylinder) = surface.downcast_ref::<Cylinder>() {
+            let position =
+                self.axis2_placement_3d(cylinder.origin, cylinder.axis, cylinder.ref_dir);
+            return Ok(self.add_entity(format!(
+                "CYLINDRICAL_SURFACE('',#{},{})",
+                position,
+                real(cylinder.radius)
+            )));
+        }
+        if let Some(cone) = surface.downcast_ref::<Cone>() {
+            let position = self.axis2_placement_3d(cone.origin, cone.axis, cone.ref_dir);
+            return Ok(self.add_entity(format!(
+                "CONICAL_SURFACE('',#{},{},{})",
+                position,
+                real(cone.radius),
+                real(cone.semi_angle)
+            )));
+        }
+        if let Some(sphere) = surface.downcast_ref::<Sphere>() {
+            let position = self.axis2_placement_3d(sphere.center, sphere.axis, sphere.ref_dir);
+            return Ok(self.add_entity(format!(
+                "SPHERICAL_SURFACE('',#{},{})",
+                position,
+                real(sphere.radius)
+            )));
+        }
+        if let Some(torus) = surface.downcast_ref::<Torus>() {
+            let position = self.axis2_placement_3d(torus.center, torus.axis, torus.ref_dir);
+            return Ok(self.add_entity(format!(
+                "TOROIDAL_SURFACE('',#{},{},{})",
+                position,
+                real(torus.major_radius),
+                real(torus.minor_radius)
+            )));
+        }
+        if let Some(extrusion) = surface.downcast_ref::<SurfaceOfLinearExtrusion<Box<dyn Curve>>>()
+        {
+            let curve = self.add_curve(extrusion.curve.as_ref())?;
+            let direction = self.direction(extrusion.direction.normalize());
+            let vector = self.add_entity(format!(
+                "VECTOR('',#{},{})",
+                direction,
+                real(extrusion.direction.length())
+            ));
+            return Ok(self.add_entity(format!(
+                "SURFACE_OF_LINEAR_EXTRUSION('',#{},#{})",
+                curve, vector
+            )));
+        }
+        if let Some(offset) = surface.downcast_ref::<OffsetSurface<Box<dyn Surface>>>() {
+            let basis = self.add_surface(offset.basis.as_ref())?;
+            return Ok(self.add_entity(format!(
+                "OFFSET_SURFACE('',#{},{},.F.)",
+                basis,
+                real(offset.distance)
+            )));
+        }
+        if let Some(bezier) = surface.downcast_ref::<BezierSurface<Point3>>() {
+            let (rows, cols) = bezier.control_points.size();
+            let points = self.control_net(&bezier.control_points);
+            return Ok(self.add_entity(format!(
+                "BEZIER_SURFACE('',{},{},{},.UNSPECIFIED.,.U.,.U.,.U.)",
+                rows - 1,
+                cols - 1,
+                points
+            )));
+        }
+        if let Some(bspline) = surface.downcast_ref::<BSplineSurface<Point3>>() {
+            let points = self.control_net(&bspline.control_points);
+            let (u_multiplicities, u_knots) = self.knots(&bspline.knots.0);
+            let (v_multiplicities, v_knots) = self.knots(&bspline.knots.1);
+            return Ok(self.add_entity(format!(
+                "B_SPLINE_SURFACE_WITH_KNOTS('',{},{},{},.UNSPECIFIED.,.U.,.U.,.U.,{},{},{},{},.UNSPECIFIED.)",
+                bspline.degree.0,
+                bspline.degree.1,
+                points,
+                u_multiplicities,
+                v_multiplicities,
+                u_knots,
+                v_knots
+            )));
+        }
+        if let Some(bspline) = surface.downcast_ref::<BSplineSurface<Point4>>() {
+            let (points, weights) = self.weighted_control_net(&bspline.control_points);
+            let (u_multiplicities, u_knots) = self.knots(&bspline.knots.0);
+            let (v_multiplicities, v_knots) = self.knots(&bspline.knots.1);
+            return Ok(self.add_entity(format!(
+                "(BOUNDED_SURFACE() B_SPLINE_SURFACE({},{},{},.UNSPECIFIED.,.U.,.U.,.U.) \
+                 B_SPLINE_SURFACE_WITH_KNOTS({},{},{},{},.UNSPECIFIED.) \
+                 GEOMETRIC_REPRESENTATION_ITEM() RATIONAL_B_SPLINE_SURFACE({}) \
+                 REPRESENTATION_ITEM('') SURFACE())",
+                bspline.degree.0,
+                bspline.degree.1,
+                points,
+                u_multiplicities,
+                v_multiplicities,
+                u_knots,
+                v_knots,
+                weights
+            )));
+        }
+        if let Some(spin) = surface.downcast_ref::<SpinSurface<Box<dyn Curve>>>() {
+            let section = self.add_curve(spin.section.as_ref())?;
+            // STEP turns the other way for its normal `∂S/∂angle × ∂S/∂u`
+            let axis = self.axis1_placement(spin.origin, -spin.axis);
+            return Ok(self.add_entity(format!("SURFACE_OF_REVOLUTION('',#{},#{})", section, axis)));
+        }
+        Err(unsupported("surface", surface))
+    }
+
+    /// Add an edge curve trimmed by its end vertices and return its id and whether the segment runs along it.
+    ///
+    /// A segment matching an edge written before, in either direction, reuses that edge, so faces sharing
+    /// a boundary reference the same edge curve.
+    pub fn add_edge(
+        &mut self,
+        edge: &CurveSegment<Box<dyn Curve>>,
+    ) -> std::io::Result<(usize, bool)> {
+        let (u0, u1) = edge.parameter_range;
+        let start = self.vertex(edge.curve.get_point(u0));
+        let end = self.vertex(edge.curve.get_point(u1));
+        let quarter = edge.curve.get_point(u0 + 0.25 * (u1 - u0));
+        let three_quarter = edge.curve.get_point(u0 + 0.75 * (u1 - u0));
+        let key = (start.min(end), start.max(end));
+        if let Some(written) = self.edges.get(&key) {
+            for written in written {
+                if written.start == start
+                    && same_point(written.quarter, quarter)
+                    && same_point(written.three_quarter, three_quarter)
+                {
+                    return Ok((written.id, true));
+                }
+                if (written.start == end || start == end)
+                    && same_point(written.quarter, three_quarter)
+                    && same_point(written.three_quarter, quarter)
+                {
+                    return Ok((written.id, false));
+                }
+            }
+        }
+        let curve = self.add_curve(edge.curve.as_ref())?;
+        let id = self.add_edge_curve(start, end, curve, edge.parameter_range);
+        self.edges.entry(key).or_default().push(WrittenEdge {
+            id,
+            start,
+            quarter,
+            three_quarter,
+        });
+        Ok((id, true))
+    }
+
+    fn add_edge_curve(
+        &mut self,
+        start: usize,
+        end: usize,
+        curve: usize,
+        parameter_range: (Float, Float),
+    ) -> usize {
+        let geometry = EdgeGeometry {
+            start,
+            end,
+            curve,
+            parameter_range,
+            surface_curve: None,
+            pcurves: Vec::new(),
+        };
+        let id = self.add_entity(edge_curve(&geometry));
+        self.edge_geometries.insert(id, geometry);
+        id
+    }
+
+    fn parameter_context(&mut self) -> usize {
+        if let Some(context) = self.parameter_context {
+            return context;
+        }
+        let context = self.add_entity(
+            "(GEOMETRIC_REPRESENTATION_CONTEXT(2) PARAMETRIC_REPRESENTATION_CONTEXT() \
+             REPRESENTATION_CONTEXT('2D SPACE',''))"
+                .to_string(),
+        );
+        self.parameter_context = Some(context);
+        context
+    }
+
+    /// Add a parameter space curve moved by the matrix and return its id,
+    /// `None` when the curve has no two dimensional entity
+    fn add_parameter_curve(&mut self, curve: &dyn Curve, matrix: Mat4) -> Option<usize> {
+        if let Some(transformed) = curve.downcast_ref::<TransformedCurve<Box<dyn Curve>>>() {
+            return self
+                .add_parameter_curve(transformed.curve.as_ref(), matrix * transformed.matrix);
+        }
+        if let Some(line) = curve.downcast_ref::<Line>() {
+            let direction = matrix.transform_vector3(line.direction);
+            let origin = self.point_2d(matrix.transform_point3(line.origin));
+            let unit = direction.truncate().normalize();
+            let unit =
+                self.add_entity(format!("DIRECTION('',({},{}))", real(unit.x), real(unit.y)));
+            let vector = self.add_entity(format!(
+                "VECTOR('',#{},{})",
+                unit,
+                real(direction.truncate().length())
+            ));
+            return Some(self.add_entity(format!("LINE('',#{},#{})", origin, vector)));
+        }
+        if let Some(polyline) = curve.downcast_ref::<Polyline>() {
+            let points = polyline
+                .vertices
+                .iter()
+                .map(|&point| self.point_2d(matrix.transform_point3(point)))
+                .collect::<Vec<_>>();
+            return Some(self.add_entity(format!("POLYLINE('',{})", list(&points, reference))));
+        }
+        if let Some(bspline) = curve.downcast_ref::<BSplineCurve<Point3>>() {
+            let points = bspline
+                .control_points
+                .iter()
+                .map(|&point| self.point_2d(matrix.transform_point3(point)))
+                .collect::<Vec<_>>();
+            let (multiplicities, knots) = self.knots(&bspline.knots);
+            return Some(self.add_entity(format!(
+                "B_SPLINE_CURVE_WITH_KNOTS('',{},{},.UNSPECIFIED.,.U.,.U.,{},{},.UNSPECIFIED.)",
+                bspline.degree,
+                list(&points, reference),
+                multiplicities,
+                knots
+            )));
+        }
+        if let Some(bspline) = curve.downcast_ref::<BSplineCurve<Point4>>() {
+            let points = bspline
+                .control_points
+                .iter()
+                .map(|point| self.point_2d(matrix.transform_point3(point.truncate() / point.w)))
+                .collect::<Vec<_>>();
+            let (multiplicities, knots) = self.knots(&bspline.knots);
+            return Some(self.add_entity(format!(
+                "(BOUNDED_CURVE() B_SPLINE_CURVE({},{},.UNSPECIFIED.,.U.,.U.) \
+                 B_SPLINE_CURVE_WITH_KNOTS({},{},.UNSPECIFIED.) CURVE() \
+                 GEOMETRIC_REPRESENTATION_ITEM() RATIONAL_B_SPLINE_CURVE({}) \
+                 REPRESENTATION_ITEM(''))",
+                bspline.degree,
+                list(&points, reference),
+                multiplicities,
+                knots,
+                list(&bspline.control_points, |p| real(p.w))
+            )));
+        }
+        None
+    }
+
+    /// Add the pcurve of an edge use on a surface, `matrix` maps STEP parameters of the surface
+    /// to the parameters of the pcurve.
+    ///
+    /// The pcurve is skipped when it can not be written or does not share the parameters
+    /// of the written edge curve.
+    fn add_edge_pcurve(
+        &mut self,
+        edge: usize,
+        same_sense: bool,
+        parameter_range: (Float, Float),
+        surface: usize,
+        pcurve: &dyn Curve,
+        matrix: &Mat4,
+    ) {
+        let (u0, u1) = self.edge_geometries[&edge].parameter_range;
+        if parameter_range != (u0, u1) && parameter_range != (u1, u0) {
+            return;
+        }
+        let curve = match self.add_parameter_curve(pcurve, matrix.inverse()) {
+            Some(curve) => curve,
+            None => return,
+        };
+        let context = self.parameter_context();
+        let representation = self.add_entity(format!(
+            "DEFINITIONAL_REPRESENTATION('',(#{}),#{})",
+            curve, context
+        ));
+        let pcurve = self.add_entity(format!("PCURVE('',#{},#{})", surface, representation));
+
+        let geometry = self.edge_geometries.get_mut(&edge).unwrap();
+        let index = if same_sense {
+            geometry
+                .pcurves
+                .iter()
+                .position(|&(other, _)| other == surface)
+                .unwrap_or(geometry.pcurves.len())
+        } else {
+            geometry.pcurves.len()
+        };
+        geometry.pcurves.insert(index, (surface, pcurve));
+        // a seam lies twice on the same surface
+        let seam = geometry
+            .pcurves
+            .iter()
+            .any(|&(other, id)| other == surface && id != pcurve);
+        let surface_curve = format!(
+            "{}('',#{},{},.CURVE_3D.)",
+            if seam { "SEAM_CURVE" } else { "SURFACE_CURVE" },
+            geometry.curve,
+            list(&geometry.pcurves, |(_, id)| reference(id))
+        );
+        match geometry.surface_curve {
+            Some(id) => self.entities[id - 1] = surface_curve,
+            None => {
+                self.entities.push(surface_curve);
+                let geometry = self.edge_geometries.get_mut(&edge).unwrap();
+                geometry.surface_curve = Some(self.entities.len());
+                self.entities[edge - 1] = edge_curve(geometry);
+            }
+        }
+    }
+
+    fn add_edge_loop(
+        &mut self,
+        edge_loop: &EdgeLoop,
+        surface: usize,
+        matrix: &Mat4,
+    ) -> std::io::Result<usize> {
+        let mut oriented_edges = Vec::with_capacity(edge_loop.edges.len());
+        for (index, edge) in edge_loop.edges.iter().enumerate() {
+            let parameter_range = edge.parameter_range;
+            let (edge, same_sense) = self.add_edge(edge)?;
+            if let Some(Some(pcurve)) = edge_loop.pcurves.get(index) {
+                self.add_edge_pcurve(
+                    edge,
+                    same_sense,
+                    parameter_range,
+                    surface,
+                    pcurve.as_ref(),
+                    matrix,
+                );
+            }
+            oriented_edges.push(self.add_entity(format!(
+                "ORIENTED_EDGE('',*,*,#{},{})",
+                edge,
+                boolean(same_sense)
+            )));
+        }
+        Ok(self.add_entity(format!(
+            "EDGE_LOOP('',{})",
+            list(&oriented_edges, reference)
+        )))
+    }
+
+    /// Add an advanced face and return its id
+    pub fn add_face(&mut self, face: &TrimmedSurface<Box<dyn Surface>>) -> std::io::Result<usize> {
+        let surface = self.add_surface(face.surface.as_ref())?;
+        let matrix = parameter_matrix(face.surface.as_ref(), true);
+        let mut bounds = Vec::with_capacity(face.bounds.len());
+        for edge_loop in &face.bounds {
+            let edge_loop = self.add_edge_loop(edge_loop, surface, &matrix)?;
+            bounds.push(self.add_entity(format!("FACE_BOUND('',#{},.T.)", edge_loop)));
+        }
+        Ok(self.add_entity(format!(
+            "ADVANCED_FACE('',{},#{},.T.)",
+            list(&bounds, reference),
+            surface
+        )))
+    }
+
+    /// Add a composite curve made of trimmed curves and return its id
+    pub fn add_polycurve(&mut self, polycurve: &Polycurve) -> std::io::Result<usize> {
+        let mut segments = Vec::with_capacity(polycurve.segments.len());
+        for segment in &polycurve.segments {
+            let (u0, u1) = segment.parameter_range;
+            let curve = self.add_curve(segment.curve.as_ref())?;
+            let trimmed_curve = self.add_entity(format!(
+                "TRIMMED_CURVE('',#{},(PARAMETER_VALUE({})),(PARAMETER_VALUE({})),{},.PARAMETER.)",
+                curve,
+                real(u0),
+                real(u1),
+                boolean(u0 <= u1)
+            ));
+            segments.push(self.add_entity(format!(
+                "COMPOSITE_CURVE_SEGMENT(.CONTINUOUS.,.T.,#{})",
+                trimmed_curve
+            )));
+        }
+        Ok(self.add_entity(format!(
+            "COMPOSITE_CURVE('',{},.F.)",
+            list(&segments, reference)
+        )))
+    }
+
+    /// Add geometric representation context with millimetre and radian units
+    fn add_representation_context(&mut self) -> usize {
+        let length_unit =
+            self.add_entity("(LENGTH_UNIT() NAMED_UNIT(*) SI_UNIT(.MILLI.,.METRE.))".to_string());
+        let angle_unit =
+            self.add_entity("(NAMED_UNIT(*) PLANE_ANGLE_UNIT() SI_UNIT($,.RADIAN.))".to_string());
+        let solid_angle_unit = self
+            .add_entity("(NAMED_UNIT(*) SI_UNIT($,.STERADIAN.) SOLID_ANGLE_UNIT())".to_string());
+        let uncertainty = self.add_entity(format!(
+            "UNCERTAINTY_MEASURE_WITH_UNIT(LENGTH_MEASURE({}),#{},'distance_accuracy_value','confusion accuracy')",
+            real(crate::utils::TOLERANCE),
+            length_unit
+        ));
+        self.add_entity(format!(
+            "(GEOMETRIC_REPRESENTATION_CONTEXT(3) GLOBAL_UNCERTAINTY_ASSIGNED_CONTEXT((#{})) \
+             GLOBAL_UNIT_ASSIGNED_CONTEXT((#{},#{},#{})) REPRESENTATION_CONTEXT('',''))",
+            uncertainty, length_unit, angle_unit, solid_angle_unit
+        ))
+    }
+
+    /// Add a product whose shape is given by the representation
+    pub fn add_product(&mut self, name: &str, representation: usize) -> usize {
+        let context = self.add_entity(format!(
+            "APPLICATION_CONTEXT({})",
+            string(self.schema.application_context())
+        ));
+        let (protocol, year) = self.schema.application_protocol();
+        self.add_entity(format!(
+            "APPLICATION_PROTOCOL_DEFINITION('international standard',{},{},#{})",
+            string(protocol),
+            year,
+            context
+        ));
+        let product_context =
+            self.add_entity(format!("PRODUCT_CONTEXT('',#{},'mechanical')", context));
+        let product = self.add_entity(format!(
+            "PRODUCT({},{},'',(#{}))",
+            string(name),
+            string(name),
+            product_context
+        ));
+        let formation =
+            self.add_entity(format!("PRODUCT_DEFINITION_FORMATION('','',#{})", product));
+        let definition_context = self.add_entity(format!(
+            "PRODUCT_DEFINITION_CONTEXT('part definition',#{},'design')",
+            context
+        ));
+        let definition = self.add_entity(format!(
+            "PRODUCT_DEFINITION('design','',#{},#{})",
+            formation, definition_context
+        ));
+        let shape = self.add_entity(format!("PRODUCT_DEFINITION_SHAPE('','',#{})", definition));
+        self.add_entity(format!(
+            "SHAPE_DEFINITION_REPRESENTATION(#{},#{})",
+            shape, representation
+        ))
+    }
+
+    /// Add an edge curve of the topology and return its id
+    fn add_topological_edge(&mut self, topology: &Topology, edge: usize) -> std::io::Result<usize> {
+        let edge = &topology.edges[edge];
+        let (u0, u1) = edge.curve.parameter_range;
+        let curve = self.add_curve(edge.curve.curve.as_ref())?;
+        let start = self.vertex(topology.vertices[edge.start].point);
+        let end = self.vertex(topology.vertices[edge.end].point);
+        Ok(self.add_edge_curve(start, end, curve, (u0, u1)))
+    }
+
+    /// Add an advanced face bounded by the shared edges of the topology and return its id,
+    /// `edges` holds the ids of the topological edges written so far.
+    fn add_topological_face(
+        &mut self,
+        face: &TrimmedSurface<Box<dyn Surface>>,
+        topology: &Topology,
+        index: usize,
+        edges: &mut [Option<usize>],
+    ) -> std::io::Result<usize> {
+        let same_sense = topology.faces[index].same_sense;
+        let surface = match face.surface.downcast_ref::<Plane>() {
+            // the reader turns a plane to the face normal, write it back against the face
+            Some(plane) if !same_sense => self.add_surface(&Plane {
+                origin: plane.origin,
+                normal: -plane.normal,
+                u_axis: plane.u_axis,
+                v_axis: -plane.v_axis,
+            })?,
+            _ => self.add_surface(face.surface.as_ref())?,
+        };
+        let matrix = parameter_matrix(face.surface.as_ref(), same_sense);
+        let mut bounds = Vec::with_capacity(topology.faces[index].loops.len());
+        for &edge_loop in &topology.faces[index].loops {
+            let edge_loop = &topology.loops[edge_loop];
+            let mut oriented_edges = Vec::with_capacity(edge_loop.coedges.len());
+            for &coedge in &edge_loop.coedges {
+                let coedge = &topology.coedges[coedge];
+                let edge = match edges[coedge.edge] {
+                    Some(edge) => edge,
+                    None => {
+                        let edge = self.add_topological_edge(topology, coedge.edge)?;
+                        edges[coedge.edge] = Some(edge);
+                        edge
+                    }
+                };
+                if let Some(pcurve) = &coedge.pcurve {
+                    let parameter_range = topology.edges[coedge.edge].curve.parameter_range;
+                    self.add_edge_pcurve(
+                        edge,
+                        coedge.same_sense,
+                        parameter_range,
+                        surface,
+                        pcurve.as_ref(),
+                        &matrix,
+                    );
+                }
+                oriented_edges.push(self.add_entity(format!(
+                    "ORIENTED_EDGE('',*,*,#{},{})",
+                    edge,
+                    boolean(coedge.same_sense)
+                )));
+            }
+            let loop_id = self.add_entity(format!(
+                "EDGE_LOOP('',{})",
+                list(&oriented_edges, reference)
+            ));
+            bounds.push(self.add_entity(format!(
+                "FACE_BOUND('',#{},{})",
+                loop_id,
+                boolean(edge_loop.same_sense)
+            )));
+        }
+        Ok(self.add_entity(format!(
+            "ADVANCED_FACE('',{},#{},{})",
+            list(&bounds, reference),
+            surface,
+            boolean(same_sense)
+        )))
+    }
+
+    /// Add faces as shells of a surface model and curves as composite curves,
+    /// returns id of the shape representation.
+    ///
+    /// Faces with topology are grouped by its shells and reference its edges, the other faces
+    /// make one open shell.
+    fn add_shape(
+        &mut self,
+        model: &Model<TrimmedSurface<Box<dyn Surface>>>,
+        topology: Option<&Topology>,
+    ) -> std::io::Result<usize> {
+        let context = self.add_representation_context();
+        let origin = self.axis2_placement_3d(Point3::ZERO, Vec3::Z, Vec3::X);
+        let mut items = vec![origin];
+        if !model.faces.is_empty() {
+            let mut edges = vec![None; topology.map_or(0, |topology| topology.edges.len())];
+            let mut faces = Vec::with_capacity(model.faces.len());
+            for (index, face) in model.faces.iter().enumerate() {
+                faces.push(match topology {
+                    Some(topology) if index < topology.faces.len() => {
+                        self.add_topological_face(face, topology, index, &mut edges)?
+                    }
+                    _ => self.add_face(face)?,
+                });
+            }
+            let mut shells = Vec::new();
+            let mut free_faces = vec![true; faces.len()];
+            if let Some(topology) = topology {
+                for (index, shell) in topology.shells.iter().enumerate() {
+                    let shell_faces = shell
+                        .faces
+                        .iter()
+                        .filter_map(|&face| {
+                            let id = faces.get(face).copied();
+                            if id.is_some() {
+                                free_faces[face] = false;
+                            }
+                            id
+                        })
+                        .collect::<Vec<_>>();
+                    let keyword = if topology.is_shell_closed(index) {
+                        "CLOSED_SHELL"
+                    } else {
+                        "OPEN_SHELL"
+                    };
+                    shells.push(self.add_entity(format!(
+                        "{}('',{})",
+                        keyword,
+                        list(&shell_faces, reference)
+                    )));
+                }
+            }
+            let free_faces = faces
+                .iter()
+                .zip(free_faces)
+                .filter(|(_, free)| *free)
+                .map(|(&face, _)| face)
+                .collect::<Vec<_>>();
+            if !free_faces.is_empty() {
+                shells.push(
+                    self.add_entity(format!("OPEN_SHELL('',{})", list(&free_faces, reference))),
+                );
+            }
+            items.push(self.add_entity(format!(
+                "SHELL_BASED_SURFACE_MODEL('',{})",
+                list(&shells, reference)
+            )));
+        }
+        for polycurve in &model.curves {
+            items.push(self.add_polycurve(polycurve)?);
+        }
+        Ok(self.add_entity(format!(
+            "MANIFOLD_SURFACE_SHAPE_REPRESENTATION('',{},#{})",
+            list(&items, reference),
+            context
+        )))
+    }
+
+    /// Add faces as an open shell of a surface model and curves as composite curves,
+    /// returns id of the shape representation
+    pub fn add_model(
+        &mut self,
+        model: &Model<TrimmedSurface<Box<dyn Surface>>>,
+    ) -> std::io::Result<usize> {
+        self.add_shape(model, None)
+    }
+
+    /// Add faces sharing the edges and shells of the topology and curves as composite curves,
+    /// returns id of the shape representation
+    pub fn add_step_model(&mut self, model: &StepModel) -> std::io::Result<usize> {
+        self.add_shape(&model.model, Some(&model.topology))
+    }
+
+    /// Write the exchange file
+    pub fn write<W: Write>(&self, writer: &mut W, name: &str) -> std::io::Result<()> {
+        writeln!(writer, "ISO-10303-21;")?;
+        writeln!(writer, "HEADER;")?;
+        writeln!(writer, "FILE_DESCRIPTION((''),'2;1');")?;
+        writeln!(
+            writer,
+            "FILE_NAME({},'',(''),(''),'geom3d','geom3d','');",
+            string(name)
+        )?;
+        writeln!(
+            writer,
+            "FILE_SCHEMA(({}));",
+            string(self.schema.file_schema())
+        )?;
+        writeln!(writer, "ENDSEC;")?;
+        writeln!(writer, "DATA;")?;
+        for (index, entity) in self.entities.iter().enumerate() {
+            writeln!(writer, "#{}={};", index + 1, entity)?;
+        }
+        writeln!(writer, "ENDSEC;")?;
+        writeln!(writer, "END-ISO-10303-21;")?;
+        Ok(())
+    }
+}
+
+fn save_as_step<P, F>(filename: P, schema: StepSchema, add_shape: F) -> std::io::Result<()>
+where
+    P: AsRef<std::path::Path>,
+    F: FnOnce(&mut StepWriter) -> std::io::Result<usize>,
+{
+    let path = filename.as_ref();
+    let name = path.file_stem().unwrap_or_default().to_string_lossy();
+    let mut step = StepWriter::new(schema);
+    let representation = add_shape(&mut step)?;
+    step.add_product(&name, representation);
+
+    let file = std::fs::File::create(path)?;
+    let mut writer = std::io::BufWriter::new(file);
+    step.write(&mut writer, &name)?;
+    writer.flush()
+}
+
+impl Model<TrimmedSurface<Box<dyn Surface>>> {
+    /// Save faces and curves as B-rep entities of a STEP file
+    pub fn save_as_step<P: AsRef<std::path::Path>>(
+        &self,
+        filename: P,
+        schema: StepSchema,
+    ) -> std::io::Result<()> {
+        save_as_step(filename, schema, |step| step.add_model(self))
+    }
+}
+
+impl StepModel {
+    /// Save faces and curves as B-rep entities of a STEP file, keeping the shared edges,
+    /// senses and shells of the topology
+    pub fn save_as_step<P: AsRef<std::path::Path>>(
+        &self,
+        filename: P,
+        schema: StepSchema,
+    ) -> std::io::Result<()> {
+        save_as_step(filename, schema, |step| step.add_step_model(self))
+    }
+}
+
+#[test]
+fn test_step_round_trip() {
+    use super::StepReader;
+    use crate::Vec2;
+
+    fn segment(
+        curve: Box<dyn Curve>,
+        parameter_range: (Float, Float),
+    ) -> CurveSegment<Box<dyn Curve>> {
+        CurveSegment {
+            curve,
+            parameter_range,
+            tolerance: 0.01,
+            parameter_division: 16,
+        }
+    }
+    let corners = [
+        Point3::new(0.0, 0.0, 0.0),
+        Point3::new(2.0, 0.0, 0.0),
+        Point3::new(2.0, 1.0, 0.0),
+        Point3::new(0.0, 1.0, 0.0),
+    ];
+    let square = EdgeLoop::new(
+        (0..4)
+            .map(|i| {
+                let line = Line {
+                    origin: corners[i],
+                    direction: corners[(i + 1) % 4] - corners[i],
+                };
+                segment(Box::new(line), (0.0, 1.0))
+            })
+            .collect(),
+    );
+    let plane = Plane {
+        origin: Point3::ZERO,
+        normal: Vec3::Z,
+        u_axis: Vec3::X,
+        v_axis: Vec3::Y,
+    };
+    let circle = |z: Float| -> Box<dyn Curve> {
+        Box::new(Circle {
+            center: Point3::new(0.0, 0.0, z),
+            radius: 1.5,
+            axis: Vec3::Z,
+            ref_dir: Vec3::X,
+        })
+    };
+    let cylinder = Cylinder {
+        origin: Point3::ZERO,
+        axis: Vec3::Z,
+        ref_dir: Vec3::X,
+        radius: 1.5,
+    };
+    let weighted = |x: Float, y: Float, w: Float| Point4::new(x * w, y * w, 0.0, w);
+    let rational = BSplineSurface::<Point4>::uniform_clamped(
+        Grid::from_vec(
+            vec![
+                weighted(0.0, 0.0, 1.0),
+                weighted(0.0, 1.0, 0.5),
+                weighted(0.0, 2.0, 1.0),
+                weighted(1.0, 0.0, 1.0),
+                weighted(1.0, 1.0, 2.0),
+                weighted(1.0, 2.0, 1.0),
+            ],
+            3,
+        ),
+        (1, 2),
+    );
+    let boundary = BSplineCurve {
+        control_points: vec![
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.5, 0.0),
+            Point3::new(0.0, 2.0, 0.0),
+        ],
+        knots: KnotVector::bezier_knot(2),
+        degree: 2,
+    };
+
+    let mut model = Model::new();
+    model.add_face(TrimmedSurface {
+        surface: Box::new(plane) as Box<dyn Surface>,
+        bounds: vec![square],
+    });
+    model.add_face(TrimmedSurface {
+        surface: Box::new(cylinder),
+        bounds: vec![
+            EdgeLoop::new(vec![segment(circle(0.0), (0.0, crate::consts::TAU))]),
+            EdgeLoop::new(vec![segment(circle(3.0), (crate::consts::TAU, 0.0))]),
+        ],
+    });
+    model.add_face(TrimmedSurface {
+        surface: Box::new(rational),
+        bounds: vec![EdgeLoop::new(vec![
+            segment(Box::new(boundary), (0.0, 1.0)),
+            segment(
+                Box::new(Polyline::new(vec![
+                    Point3::new(0.0, 2.0, 0.0),
+                    Point3::new(1.0, 2.0, 0.0),
+                    Point3::new(0.0, 0.0, 0.0),
+                ])),
+                (0.0, 1.0),
+            ),
+        ])],
+    });
+    let circle_at = |z: Float, radius: Float| -> Box<dyn Curve> {
+        Box::new(Circle {
+            center: Point3::new(0.0, 0.0, z),
+            radius,
+            axis: Vec3::Z,
+            ref_dir: Vec3::X,
+        })
+    };
+    let cone = Cone {
+        origin: Point3::new(0.0, 0.0, 3.0),
+        axis: Vec3::Z,
+        ref_dir: Vec3::X,
+        radius: 1.5,
+        semi_angle: 0.3,
+    };
+    model.add_face(TrimmedSurface {
+        bounds: vec![
+            EdgeLoop::new(vec![segment(circle(3.0), (0.0, crate::consts::TAU))]),
+            EdgeLoop::new(vec![segment(
+                circle_at(4.0, cone.radius_at(1.0)),
+                (crate::consts::TAU, 0.0),
+            )]),
+        ],
+        surface: Box::new(cone),
+    });
+    let sphere = Sphere {
+        center: Point3::new(0.0, 0.0, 4.0),
+        axis: Vec3::Z,
+        ref_dir: Vec3::X,
+        radius: 2.0,
+    };
+    model.add_face(TrimmedSurface {
+        bounds: vec![EdgeLoop::new(vec![segment(
+            circle_at(4.0 + sphere.radius * 0.6, sphere.radius * 0.8),
+            (0.0, crate::consts::TAU),
+        )])],
+        surface: Box::new(sphere),
+    });
+    let extrusion = SurfaceOfLinearExtrusion {
+        curve: Box::new(Line {
+            origin: Point3::new(5.0, 0.0, 0.0),
+            direction: Vec3::new(2.0, 0.0, 0.0),
+        }) as Box<dyn Curve>,
+        direction: Vec3::new(0.0, 0.0, 2.0),
+    };
+    let rectangle = [
+        Point3::new(5.0, 0.0, 0.0),
+        Point3::new(7.0, 0.0, 0.0),
+        Point3::new(7.0, 0.0, 2.0),
+        Point3::new(5.0, 0.0, 2.0),
+    ];
+    model.add_face(TrimmedSurface {
+        surface: Box::new(extrusion),
+        bounds: vec![EdgeLoop::new(
+            (0..4)
+                .map(|i| {
+                    let line = Line {
+                        origin: rectangle[i],
+                        direction: rectangle[(i + 1) % 4] - rectangle[i],
+                    };
+                    segment(Box::new(line), (0.0, 1.0))
+                })
+                .collect(),
+        )],
+    });
+    model
+        .save_as_step("tmp/round_trip.stp", StepSchema::Ap242)
+        .unwrap();
+
+    let copy = StepReader::read_model("tmp/round_trip.stp").unwrap();
+    assert!(copy.diagnostics.is_empty());
+    assert_eq!(copy.faces.len(), model.faces.len());
+    assert_eq!(copy.topology.shells[0].faces, vec![0, 1, 2, 3, 4, 5]);
+    for (face, copied) in model.faces.iter().zip(&copy.faces) {
+        assert_eq!(face.bounds.len(), copied.bounds.len());
+        for &(u, v) in &[(0.0, 0.0), (0.25, 0.5), (1.0, 0.75)] {
+            let p = face.surface.get_point(u, v);
+            let q = copied.surface.get_point(u, v);
+            assert!(p.distance(q) < 1e-9, "{:?} != {:?}", p, q);
+        }
+        // bounds of a face are unordered in STEP files
+        let points = |face: &TrimmedSurface<Box<dyn Surface>>| {
+            let mut points = face
+                .bounds
+                .iter()
+                .flat_map(|bound| &bound.edges)
+                .flat_map(|edge| {
+                    let (u0, u1) = edge.parameter_range;
+                    let middle = (u0 + u1) / 2.0;
+                    vec![
+                        edge.curve.get_point(u0),
+                        edge.curve.get_point(middle),
+                        edge.curve.get_point(u1),
+                    ]
+                })
+                .map(|p| Vec2::new(p.x * 7.0 + p.y * 3.0 + p.z, p.length()))
+                .collect::<Vec<_>>();
+            points.sort_by(|a, b| a.x.partial_cmp(&b.x).unwrap());
+            points
+        };
+        let (expected, actual) = (points(face), points(copied));
+        assert_eq!(expected.len(), actual.len());
+        for (p, q) in expected.iter().zip(&actual) {
+            assert!(p.distance(*q) < 1e-6, "{:?} != {:?}", p, q);
+        }
+    }
+    // lines keep their direction and the edges their parameter range
+    for edge in &copy.faces[0].bounds[0].edges {
+        let line = edge.curve.downcast_ref::<Line>().unwrap();
+        let i = corners
+            .iter()
+            .position(|corner| corner.distance(line.origin) < 1e-9)
+            .unwrap();
+        let expected = corners[(i + 1) % 4] - corners[i];
+        assert!(line.direction.distance(expected) < 1e-9, "{:?}", line);
+        assert_eq!(edge.parameter_range, (0.0, 1.0));
+    }
+}
+
+#[test]
+fn test_step_shared_edges() {
+    use super::StepReader;
+
+    let square = |x: Float| {
+        let corners = [
+            Point3::new(x, 0.0, 0.0),
+            Point3::new(x + 1.0, 0.0, 0.0),
+            Point3::new(x + 1.0, 1.0, 0.0),
+            Point3::new(x, 1.0, 0.0),
+        ];
+        let edges = (0..4)
+            .map(|i| CurveSegment {
+                curve: Box::new(Line {
+                    origin: corners[i],
+                    direction: corners[(i + 1) % 4] - corners[i],
+                }) as Box<dyn Curve>,
+                parameter_range: (0.0, 1.0),
+                tolerance: 0.01,
+                parameter_division: 16,
+            })
+            .collect();
+        TrimmedSurface {
+            surface: Box::new(Plane {
+                origin: Point3::ZERO,
+                normal: Vec3::Z,
+                u_axis: Vec3::X,
+                v_axis: Vec3::Y,
+            }) as Box<dyn Surface>,
+            bounds: vec![EdgeLoop::new(edges)],
+        }
+    };
+    let mut model = Model::new();
+    model.add_face(square(0.0));
+    // the shared edge runs backwards along an equal but separate line
+    let mut right = square(1.0);
+    right.bounds[0].edges[3] = CurveSegment {
+        curve: Box::new(Line {
+            origin: Point3::new(1.0, 0.0, 0.0),
+            direction: Vec3::Y,
+        }),
+        parameter_range: (1.0, 0.0),
+        tolerance: 0.01,
+        parameter_division: 16,
+    };
+    model.add_face(right);
+    model
+        .save_as_step("tmp/shared_edges.stp", StepSchema::Ap214)
+        .unwrap();
+
+    let text = std::fs::read_to_string("tmp/shared_edges.stp").unwrap();
+    assert_eq!(text.matches("=VERTEX_POINT(").count(), 6);
+    assert_eq!(text.matches("=EDGE_CURVE(").count(), 7);
+    assert_eq!(text.matches("=ORIENTED_EDGE(").count(), 8);
+    assert_eq!(text.matches(",.F.);").count(), 1);
+
+    let copy = StepReader::read_model("tmp/shared_edges.stp").unwrap();
+    assert!(copy.diagnostics.is_empty());
+    let edge = &copy.faces[1].bounds[0].edges[3];
+    let (u0, u1) = edge.parameter_range;
+    assert!(
+        edge.curve
+            .get_point(u0)
+            .distance(Point3::new(1.0, 1.0, 0.0))
+            < 1e-9
+    );
+    assert!(
+        edge.curve
+            .get_point(u1)
+            .distance(Point3::new(1.0, 0.0, 0.0))
+            < 1e-9
+    );
+
+    // the topology keeps shared edges and senses when written back
+    let face = text
+        .lines()
+        .find(|line| line.contains("=ADVANCED_FACE("))
+        .unwrap();
+    let reversed = text.replace(face, &face.replace(",.T.);", ",.F.);"));
+    std::fs::write("tmp/shared_edges.stp", reversed).unwrap();
+    let copy = StepReader::read_model("tmp/shared_edges.stp").unwrap();
+    assert!(!copy.topology.faces[0].same_sense);
+    copy.save_as_step("tmp/shared_edges_copy.stp", StepSchema::Ap214)
+        .unwrap();
+    let text = std::fs::read_to_string("tmp/shared_edges_copy.stp").unwrap();
+    assert_eq!(text.matches("=EDGE_CURVE(").count(), 7);
+    assert_eq!(text.matches("=ORIENTED_EDGE(").count(), 8);
+    assert_eq!(text.matches(",.F.);").count(), 2);
+    let twice = StepReader::read_model("tmp/shared_edges_copy.stp").unwrap();
+    assert_eq!(twice.topology.edges.len(), 7);
+    assert_eq!(twice.topology.free_edges().len(), 6);
+    assert!(!twice.topology.faces[0].same_sense);
+    for (face, copied) in copy.faces.iter().zip(&twice.faces) {
+        let plane = face.surface.downcast_ref::<Plane>().unwrap();
+        let copied = copied.surface.downcast_ref::<Plane>().unwrap();
+        assert!(plane.origin.distance(copied.origin) < 1e-9);
+        assert!(plane.normal.distance(copied.normal) < 1e-9);
+        assert!(plane.u_axis.distance(copied.u_axis) < 1e-9);
+    }
+    assert!(
+        copy.faces[0]
+            .surface
+            .downcast_ref::<Plane>()
+            .unwrap()
+            .normal
+            .distance(-Vec3::Z)
+            < 1e-9
+    );
+}
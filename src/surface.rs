@@ -7,14 +7,86 @@ pub trait Surface: std::fmt::Debug + Downcast {
     /// Get a point on the surface with parameters `(u,v)`
     fn get_point(&self, u: Float, v: Float) -> Point3;
 
-    fn get_normals(&self, _params: &[Point2]) -> Vec<Vec3> {
+    /// Point and first and second partial derivatives at parameters `(u,v)`,
+    /// approximated by finite differences unless the surface knows them exactly
+    fn derivatives(&self, u: Float, v: Float) -> SurfaceDerivatives {
+        approximate_derivatives(self, u, v)
+    }
+
+    /// Normals at the parameters, `∂S/∂u × ∂S/∂v` unless the surface has its own orientation
+    fn get_normals(&self, params: &[Point2]) -> Vec<Vec3> {
+        params
+            .iter()
+            .map(|p| self.derivatives(p.x, p.y).normal())
+            .collect()
+    }
+
+    /// Curvature at parameters `(u,v)` relative to the normal of [`Surface::get_normals`],
+    /// `None` at singular points
+    fn curvature(&self, u: Float, v: Float) -> Option<SurfaceCurvature> {
+        let derivatives = self.derivatives(u, v);
+        let curvature = derivatives.curvature()?;
+        let normal = self.get_normals(&[Point2::new(u, v)])[0];
+        if normal.dot(derivatives.normal()) < 0.0 {
+            Some(curvature.reversed())
+        } else {
+            Some(curvature)
+        }
+    }
+
+    /// Periods of the `(u, v)` parameters in which the surface is closed
+    fn periods(&self) -> (Option<Float>, Option<Float>) {
+        (None, None)
+    }
+
+    /// Values of `u` where the surface collapses to a point while `v` runs around it
+    fn poles(&self) -> Vec<Float> {
         Vec::new()
     }
 
-    /// Trim the surface with an edge loop
-    fn trim(&self, _bounds: &[EdgeLoop]) -> TriangleMesh {
+    /// Parameters of the nearest surface point, `None` unless the surface projects points itself
+    fn nearest_parameters(&self, _point: Point3) -> Option<Point2> {
+        None
+    }
+
+    /// Trim the surface with an edge loop, parameter space curves of the edges are preferred
+    fn trim(&self, bounds: &[EdgeLoop]) -> TriangleMesh {
+        let parameter_polygons = bounds
+            .iter()
+            .map(EdgeLoop::to_parameter_polygon)
+            .collect::<Option<Vec<_>>>();
+        if let Some(parameter_polygons) = parameter_polygons {
+            let (polygons, params): (Vec<_>, Vec<_>) = parameter_polygons.into_iter().unzip();
+            return self.trim_parameter_polygons(&polygons, &params);
+        }
+        let polygons = bounds.iter().map(EdgeLoop::to_polygon).collect::<Vec<_>>();
+        self.trim_polygons(&polygons)
+    }
+
+    /// Trim the surface with boundary polygons, the polygon vertices are kept in the mesh
+    fn trim_polygons(&self, _bounds: &[Vec<Point3>]) -> TriangleMesh {
         TriangleMesh::new()
     }
+
+    /// Trim the surface with boundary polygons whose vertex parameters are known
+    fn trim_parameter_polygons(
+        &self,
+        bounds: &[Vec<Point3>],
+        _params: &[Vec<Point2>],
+    ) -> TriangleMesh {
+        self.trim_polygons(bounds)
+    }
+}
+
+/// Parameters of the polygon vertices found by `project`
+pub(crate) fn project_polygons(
+    bounds: &[Vec<Point3>],
+    project: impl Fn(Point3) -> Point2,
+) -> Vec<Vec<Point2>> {
+    bounds
+        .iter()
+        .map(|bound| bound.iter().map(|&point| project(point)).collect())
+        .collect()
 }
 
 impl_downcast!(Surface);
@@ -24,9 +96,45 @@ impl Surface for Box<dyn Surface> {
         self.as_ref().get_point(u, v)
     }
 
+    fn derivatives(&self, u: Float, v: Float) -> SurfaceDerivatives {
+        self.as_ref().derivatives(u, v)
+    }
+
+    fn get_normals(&self, params: &[Point2]) -> Vec<Vec3> {
+        self.as_ref().get_normals(params)
+    }
+
+    fn curvature(&self, u: Float, v: Float) -> Option<SurfaceCurvature> {
+        self.as_ref().curvature(u, v)
+    }
+
+    fn periods(&self) -> (Option<Float>, Option<Float>) {
+        self.as_ref().periods()
+    }
+
+    fn poles(&self) -> Vec<Float> {
+        self.as_ref().poles()
+    }
+
+    fn nearest_parameters(&self, point: Point3) -> Option<Point2> {
+        self.as_ref().nearest_parameters(point)
+    }
+
     fn trim(&self, bounds: &[EdgeLoop]) -> TriangleMesh {
         self.as_ref().trim(bounds)
     }
+
+    fn trim_polygons(&self, bounds: &[Vec<Point3>]) -> TriangleMesh {
+        self.as_ref().trim_polygons(bounds)
+    }
+
+    fn trim_parameter_polygons(
+        &self,
+        bounds: &[Vec<Point3>],
+        params: &[Vec<Point2>],
+    ) -> TriangleMesh {
+        self.as_ref().trim_parameter_polygons(bounds, params)
+    }
 }
 
 /// A piece of surface with natural boundaries defined by parameter ranges.
@@ -35,7 +143,7 @@ pub struct SurfacePatch<S: Surface> {
     pub surface: S,
     /// (u_range, v_range)
     pub parameter_range: ((Float, Float), (Float, Float)),
-    /// (u_division, v_division)
+    /// (u_division, v_division) of the point grid
     pub parameter_division: (usize, usize),
 }
 
@@ -55,12 +163,11 @@ impl<S: Surface> SurfacePatch<S> {
         let v_parameters = utils::uniform_divide(v_range, v_div);
         let points = u_parameters
             .into_iter()
-            .map(|u| {
+            .flat_map(|u| {
                 v_parameters
                     .iter()
                     .map(move |&v| self.surface.get_point(u, v))
             })
-            .flatten()
             .collect::<Vec<Point3>>();
         Grid::from_vec(points, v_div + 1)
     }
@@ -69,24 +176,71 @@ impl<S: Surface> SurfacePatch<S> {
 pub struct EdgeLoop {
     /// The edges should form a closed loop.
     pub edges: Vec<CurveSegment<Box<dyn Curve>>>,
+    /// Curves in the parameter space of the trimmed surface with points `(u, v, 0)`,
+    /// either empty or one for each edge sharing its parameters.
+    pub pcurves: Vec<Option<Box<dyn Curve>>>,
 }
 
 impl EdgeLoop {
+    pub fn new(edges: Vec<CurveSegment<Box<dyn Curve>>>) -> EdgeLoop {
+        EdgeLoop {
+            edges,
+            pcurves: Vec::new(),
+        }
+    }
+
     /// Approximate the edge loop with a polygon
     pub fn to_polygon(&self) -> Vec<Point3> {
-        let mut vertices = Vec::new();
-        for edge in &self.edges {
-            vertices.extend(edge.get_points());
+        join_edges(self.edges.iter().map(|edge| edge.get_points()), |&p| p)
+    }
+
+    /// Approximate the edge loop with a polygon satisfying the tolerance
+    pub fn discretize(&self, tolerance: &utils::CurveTolerance) -> Vec<Point3> {
+        join_edges(
+            self.edges.iter().map(|edge| edge.discretize(tolerance)),
+            |&p| p,
+        )
+    }
+
+    /// Approximate the edge loop with a polygon and its parameters on the surface,
+    /// returns `None` when some edge has no parameter space curve.
+    pub fn to_parameter_polygon(&self) -> Option<(Vec<Point3>, Vec<Point2>)> {
+        if self.pcurves.len() != self.edges.len() {
+            return None;
         }
-        vertices.dedup_by(|a, b| a.distance_squared(*b).near(0.0));
-        if vertices[0]
-            .distance_squared(vertices[vertices.len() - 1])
+        let edges = self
+            .edges
+            .iter()
+            .zip(&self.pcurves)
+            .map(|(edge, pcurve)| {
+                let pcurve = pcurve.as_ref()?;
+                let points = edge
+                    .get_parameters()
+                    .into_iter()
+                    .map(|u| (edge.curve.get_point(u), pcurve.get_point(u).truncate()))
+                    .collect::<Vec<_>>();
+                Some(points)
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(join_edges(edges.into_iter(), |p| p.0).into_iter().unzip())
+    }
+}
+
+/// Join points of edges into a polygon, coincident points are merged.
+pub(crate) fn join_edges<T, I: Iterator<Item = Vec<T>>>(
+    edges: I,
+    position: impl Fn(&T) -> Point3,
+) -> Vec<T> {
+    let mut vertices = edges.flatten().collect::<Vec<_>>();
+    vertices.dedup_by(|a, b| position(a).distance_squared(position(b)).near(0.0));
+    if vertices.len() > 1
+        && position(&vertices[0])
+            .distance_squared(position(&vertices[vertices.len() - 1]))
             .near(0.0)
-        {
-            vertices.pop();
-        }
-        vertices
+    {
+        vertices.pop();
     }
+    vertices
 }
 
 pub struct TrimmedSurface<S> {
@@ -96,18 +250,638 @@ pub struct TrimmedSurface<S> {
     pub bounds: Vec<EdgeLoop>,
 }
 
+/// Point and partial derivatives of a surface at some parameters `(u, v)`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SurfaceDerivatives {
+    pub point: Point3,
+    /// ∂S/∂u
+    pub du: Vec3,
+    /// ∂S/∂v
+    pub dv: Vec3,
+    /// ∂²S/∂u²
+    pub duu: Vec3,
+    /// ∂²S/∂u∂v
+    pub duv: Vec3,
+    /// ∂²S/∂v²
+    pub dvv: Vec3,
+}
+
+/// Curvature of a surface at a point, relative to the normal `∂S/∂u × ∂S/∂v`.
+///
+/// A curvature is positive where the surface bends towards the normal.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SurfaceCurvature {
+    /// Product of the principal curvatures
+    pub gaussian: Float,
+    /// Average of the principal curvatures
+    pub mean: Float,
+    /// (maximum, minimum) normal curvatures
+    pub principal: (Float, Float),
+    /// Unit tangent directions of the principal curvatures
+    pub directions: (Vec3, Vec3),
+}
+
+impl SurfaceCurvature {
+    /// Curvature relative to the opposite normal
+    pub fn reversed(&self) -> Self {
+        Self {
+            gaussian: self.gaussian,
+            mean: -self.mean,
+            principal: (-self.principal.1, -self.principal.0),
+            directions: (self.directions.1, self.directions.0),
+        }
+    }
+}
+
+impl SurfaceDerivatives {
+    /// Unit normal `∂S/∂u × ∂S/∂v`, zero at singular points
+    pub fn normal(&self) -> Vec3 {
+        self.du.cross(self.dv).normalize_or_zero()
+    }
+
+    /// Coefficients `(E, F, G)` of the first fundamental form
+    pub fn first_fundamental_form(&self) -> (Float, Float, Float) {
+        (
+            self.du.dot(self.du),
+            self.du.dot(self.dv),
+            self.dv.dot(self.dv),
+        )
+    }
+
+    /// Coefficients `(L, M, N)` of the second fundamental form
+    pub fn second_fundamental_form(&self) -> (Float, Float, Float) {
+        let normal = self.normal();
+        (
+            self.duu.dot(normal),
+            self.duv.dot(normal),
+            self.dvv.dot(normal),
+        )
+    }
+
+    /// Curvature at the point, `None` at singular points
+    pub fn curvature(&self) -> Option<SurfaceCurvature> {
+        let (e, f, g) = self.first_fundamental_form();
+        let (l, m, n) = self.second_fundamental_form();
+        let determinant = e * g - f * f;
+        if determinant <= Float::EPSILON * (e + g) * (e + g) {
+            return None;
+        }
+        let gaussian = (l * n - m * m) / determinant;
+        let mean = (e * n - 2.0 * f * m + g * l) / (2.0 * determinant);
+        let discriminant = (mean * mean - gaussian).max(0.0).sqrt();
+        let principal = (mean + discriminant, mean - discriminant);
+        // (a, b) in the kernel of II - k I gives the tangent a ∂S/∂u + b ∂S/∂v
+        let k = principal.0;
+        let rows = [(l - k * e, m - k * f), (m - k * f, n - k * g)];
+        let row = if rows[0].0.hypot(rows[0].1) >= rows[1].0.hypot(rows[1].1) {
+            rows[0]
+        } else {
+            rows[1]
+        };
+        let scale = l.abs().max(m.abs()).max(n.abs()) + k.abs() * e.max(g);
+        let first = if row.0.hypot(row.1) <= 1e-9 * scale {
+            // umbilical point, every direction is principal
+            self.du.normalize()
+        } else {
+            (self.du * row.1 - self.dv * row.0).normalize()
+        };
+        let second = self.normal().cross(first);
+        Some(SurfaceCurvature {
+            gaussian,
+            mean,
+            principal,
+            directions: (first, second),
+        })
+    }
+}
+
+/// Point and partial derivatives approximated by central differences
+pub(crate) fn approximate_derivatives<S: Surface + ?Sized>(
+    surface: &S,
+    u: Float,
+    v: Float,
+) -> SurfaceDerivatives {
+    let point = surface.get_point(u, v);
+    let hu = 1e-6 * u.abs().max(1.0);
+    let hv = 1e-6 * v.abs().max(1.0);
+    let du = (surface.get_point(u + hu, v) - surface.get_point(u - hu, v)) / (2.0 * hu);
+    let dv = (surface.get_point(u, v + hv) - surface.get_point(u, v - hv)) / (2.0 * hv);
+    // larger steps for second derivatives to limit rounding errors
+    let hu = 1e-4 * u.abs().max(1.0);
+    let hv = 1e-4 * v.abs().max(1.0);
+    let at = |i: Float, j: Float| surface.get_point(u + i * hu, v + j * hv);
+    let duu = (at(1.0, 0.0) - point * 2.0 + at(-1.0, 0.0)) / (hu * hu);
+    let dvv = (at(0.0, 1.0) - point * 2.0 + at(0.0, -1.0)) / (hv * hv);
+    let duv = (at(1.0, 1.0) - at(1.0, -1.0) - at(-1.0, 1.0) + at(-1.0, -1.0)) / (4.0 * hu * hv);
+    SurfaceDerivatives {
+        point,
+        du,
+        dv,
+        duu,
+        duv,
+        dvv,
+    }
+}
+
+/// Find parameters of the nearest surface point by sampling the parameter range
+/// and refining with Gauss-Newton iteration.
+pub fn find_nearest_parameters<S: Surface + ?Sized>(
+    surface: &S,
+    point: Point3,
+    (u_range, v_range): ((Float, Float), (Float, Float)),
+    (u_division, v_division): (usize, usize),
+    trials: usize,
+) -> Point2 {
+    let mut nearest = Point2::new(u_range.0, v_range.0);
+    let mut min_distance = Float::MAX;
+    for u in utils::uniform_divide(u_range, u_division) {
+        for v in utils::uniform_divide(v_range, v_division) {
+            let distance = surface.get_point(u, v).distance_squared(point);
+            if distance < min_distance {
+                min_distance = distance;
+                nearest = Point2::new(u, v);
+            }
+        }
+    }
+    refine_nearest_parameters(surface, point, nearest, (u_range, v_range), trials)
+}
+
+/// Refine parameters of the nearest surface point by Gauss-Newton iteration
+pub(crate) fn refine_nearest_parameters<S: Surface + ?Sized>(
+    surface: &S,
+    point: Point3,
+    mut nearest: Point2,
+    (u_range, v_range): ((Float, Float), (Float, Float)),
+    trials: usize,
+) -> Point2 {
+    for _ in 0..trials {
+        let delta = surface.get_point(nearest.x, nearest.y) - point;
+        if delta.length_squared().near2(0.0) {
+            break;
+        }
+        let SurfaceDerivatives { du, dv, .. } = surface.derivatives(nearest.x, nearest.y);
+        let (a, b, c) = (du.dot(du), du.dot(dv), dv.dot(dv));
+        let determinant = a * c - b * b;
+        if determinant.near(0.0) {
+            break;
+        }
+        let (fu, fv) = (du.dot(delta), dv.dot(delta));
+        let step = Point2::new(c * fu - b * fv, a * fv - b * fu) / determinant;
+        nearest = Point2::new(
+            utils::clamp_in_range(nearest.x - step.x, u_range),
+            utils::clamp_in_range(nearest.y - step.y, v_range),
+        );
+        if step.length_squared().near(0.0) {
+            break;
+        }
+    }
+    nearest
+}
+
+/// Shift the periodic coordinates of `param` by whole periods to be nearest to `reference`.
+fn unwrap_parameter(
+    mut param: Point2,
+    reference: Point2,
+    periods: (Option<Float>, Option<Float>),
+) -> Point2 {
+    if let Some(period) = periods.0 {
+        param.x += ((reference.x - param.x) / period).round() * period;
+    }
+    if let Some(period) = periods.1 {
+        param.y += ((reference.y - param.y) / period).round() * period;
+    }
+    param
+}
+
+fn signed_area(points: &[Point2]) -> Float {
+    let n = points.len();
+    (0..n)
+        .map(|i| points[i].perp_dot(points[(i + 1) % n]))
+        .sum::<Float>()
+        / 2.0
+}
+
+/// Unwrap the periodic parameters of a closed polygon to change continuously along it,
+/// returns the number of periods the polygon winds around in each direction.
+pub fn unwrap_polygon(
+    params: &mut [Point2],
+    periods: (Option<Float>, Option<Float>),
+) -> (i64, i64) {
+    if params.is_empty() {
+        return (0, 0);
+    }
+    for i in 1..params.len() {
+        params[i] = unwrap_parameter(params[i], params[i - 1], periods);
+    }
+    let first = params[0];
+    let closing = unwrap_parameter(first, params[params.len() - 1], periods);
+    let turns = |delta: Float, period: Option<Float>| {
+        period.map_or(0, |period| (delta / period).round() as i64)
+    };
+    (
+        turns(closing.x - first.x, periods.0),
+        turns(closing.y - first.y, periods.1),
+    )
+}
+
+/// Parameter periods of a surface closed along its knot ranges
+pub(crate) fn closed_periods<S: Surface + ?Sized>(
+    surface: &S,
+    (u_range, v_range): ((Float, Float), (Float, Float)),
+) -> (Option<Float>, Option<Float>) {
+    let samples = 8;
+    let u_closed = utils::uniform_divide(v_range, samples)
+        .into_iter()
+        .all(|v| {
+            let distance = surface.get_point(u_range.0, v) - surface.get_point(u_range.1, v);
+            distance.length_squared().near2(0.0)
+        });
+    let v_closed = utils::uniform_divide(u_range, samples)
+        .into_iter()
+        .all(|u| {
+            let distance = surface.get_point(u, v_range.0) - surface.get_point(u, v_range.1);
+            distance.length_squared().near2(0.0)
+        });
+    (
+        Some(u_range.1 - u_range.0).filter(|_| u_closed),
+        Some(v_range.1 - v_range.0).filter(|_| v_closed),
+    )
+}
+
+fn mean(points: &[Point2]) -> Point2 {
+    points.iter().fold(Point2::ZERO, |sum, &p| sum + p) / points.len() as Float
+}
+
+fn shift_polygon(params: &mut [Point2], offset: Point2) {
+    params.iter_mut().for_each(|param| *param += offset);
+}
+
+/// Trim a surface by meshing the boundary polygons in its parameter space,
+/// `params` are the parameters of the polygon vertices.
+///
+/// The periodic parameters are unwrapped along each loop and the loops are shifted
+/// by whole periods into one domain, so seam edges may be crossed or used twice.
+/// Loops winding around the period of one parameter are meshed on a ring whose angle
+/// is that parameter, a pole of the surface closes a region bounded by a single
+/// winding loop. The face lies on the left of the loops seen from the surface normals,
+/// triangles keep the orientation of the loops. Loops which bound no region, like a seam
+/// used in both directions or loops winding around both periods, leave the whole closed
+/// domain of the surface to the face.
+pub(crate) fn trim_in_parameter_space<S: Surface + ?Sized>(
+    surface: &S,
+    bounds: &[Vec<Point3>],
+    params: &[Vec<Point2>],
+) -> TriangleMesh {
+    trim_with_poles(surface, bounds, params, &surface.poles())
+}
+
+/// Trim in parameter space with the poles given for these loops,
+/// for surfaces which can only find their poles near the loops
+pub(crate) fn trim_with_poles<S: Surface + ?Sized>(
+    surface: &S,
+    bounds: &[Vec<Point3>],
+    params: &[Vec<Point2>],
+    poles: &[Float],
+) -> TriangleMesh {
+    let periods = surface.periods();
+    let (mut bounds, mut params): (Vec<_>, Vec<_>) = bounds
+        .iter()
+        .zip(params)
+        .filter(|(bound, _)| bound.len() > 2)
+        .map(|(bound, params)| (bound.to_vec(), params.to_vec()))
+        .unzip();
+    if bounds.is_empty() {
+        return trim_whole_domain(surface, &[], &[], poles);
+    }
+    // loops are counter-clockwise seen from the surface normal,
+    // reverse them when the parameters turn the other way around the normal
+    let flipped = normals_flipped(surface, params[0][0]);
+    if flipped {
+        bounds.iter_mut().for_each(|bound| bound.reverse());
+        params.iter_mut().for_each(|params| params.reverse());
+    }
+    let windings = params
+        .iter_mut()
+        .map(|params| unwrap_polygon(params, periods))
+        .collect::<Vec<_>>();
+
+    let winds_u = windings.iter().any(|w| w.0 != 0);
+    let winds_v = windings.iter().any(|w| w.1 != 0);
+    // (to plane, from plane) mappings of the parameter domain
+    type Mapping = (Box<dyn Fn(Point2) -> Point2>, Box<dyn Fn(Point2) -> Point2>);
+    let (to_plane, from_plane): Mapping = if winds_u && winds_v {
+        // loops around both periods cut the domain into strips, keep the whole domain
+        // with the loops not winding around as holes
+        let holes = (0..bounds.len()).filter(|&index| windings[index] == (0, 0));
+        let (mut bounds, mut params): (Vec<_>, Vec<_>) = holes
+            .map(|index| (bounds[index].clone(), params[index].clone()))
+            .unzip();
+        if flipped {
+            bounds.iter_mut().for_each(|bound| bound.reverse());
+            params.iter_mut().for_each(|params| params.reverse());
+        }
+        return trim_whole_domain(surface, &bounds, &params, poles);
+    } else if winds_u || winds_v {
+        // (radial, angular) coordinates of parameters
+        let swap = winds_u;
+        let split = move |p: Point2| if swap { (p.y, p.x) } else { (p.x, p.y) };
+        let join = move |radial: Float, angular: Float| {
+            if swap {
+                Point2::new(angular, radial)
+            } else {
+                Point2::new(radial, angular)
+            }
+        };
+        let period = if swap { periods.0 } else { periods.1 }.unwrap();
+        // a periodic radial parameter puts the other loops below the upper bound
+        let radial_period = if swap { periods.1 } else { periods.0 };
+        let upper = windings.iter().position(|w| {
+            let turns = if swap { w.0 } else { w.1 };
+            turns != 0 && (turns > 0) != swap
+        });
+        if let (Some(radial_period), Some(upper)) = (radial_period, upper) {
+            let top = split(mean(&params[upper])).0;
+            for params in params.iter_mut() {
+                let radial = split(mean(params)).0;
+                let shift = -((radial - top) / radial_period).ceil() * radial_period;
+                let offset = join(shift, 0.0);
+                shift_polygon(params, offset);
+            }
+        }
+        let turns = windings
+            .iter()
+            .map(|w| if swap { w.0 } else { w.1 })
+            .sum::<i64>();
+        let radials = params.iter().flatten().map(|&p| split(p).0);
+        let (min, max) = radials.fold((Float::MAX, Float::MIN), |(min, max), r| {
+            (min.min(r), max.max(r))
+        });
+        // the ring center and the direction of increasing radial parameter
+        let (center, sign) = if turns == 0 {
+            (min - (max - min).max(Float::EPSILON.sqrt()), 1.0)
+        } else {
+            // the domain lies on the left of the loops
+            let pole_below = (turns > 0) != swap;
+            let pole = if pole_below {
+                poles
+                    .iter()
+                    .copied()
+                    .filter(|&p| p <= min)
+                    .fold(None, |a: Option<Float>, p| Some(a.map_or(p, |a| a.max(p))))
+            } else {
+                poles
+                    .iter()
+                    .copied()
+                    .filter(|&p| p >= max)
+                    .fold(None, |a: Option<Float>, p| Some(a.map_or(p, |a| a.min(p))))
+            };
+            match pole {
+                Some(pole) if pole_below => (pole, 1.0),
+                Some(pole) => (pole, -1.0),
+                None => return TriangleMesh::new(),
+            }
+        };
+        let scale = crate::consts::TAU / period;
+        (
+            Box::new(move |p: Point2| {
+                let (radial, angular) = split(p);
+                let (sin, cos) = (angular * scale).sin_cos();
+                Point2::new(cos, sin) * ((radial - center) * sign)
+            }),
+            Box::new(move |p: Point2| {
+                let angle = p.y.atan2(p.x);
+                join(center + p.length() * sign, angle / scale)
+            }),
+        )
+    } else {
+        // shift the loops by whole periods next to the largest one
+        let outer = (0..params.len())
+            .max_by(|&a, &b| {
+                let (a, b) = (signed_area(&params[a]).abs(), signed_area(&params[b]).abs());
+                a.total_cmp(&b)
+            })
+            .unwrap();
+        let reference = mean(&params[outer]);
+        for params in params.iter_mut() {
+            let center = mean(params);
+            shift_polygon(
+                params,
+                unwrap_parameter(center, reference, periods) - center,
+            );
+        }
+        // scale the parameters by the mean first fundamental form, so lengths in the plane
+        // approximate lengths on the surface
+        let metrics = params
+            .iter()
+            .flatten()
+            .map(|p| {
+                let derivatives = surface.derivatives(p.x, p.y);
+                utils::Metric::from_derivatives(derivatives.du, derivatives.dv)
+            })
+            .collect::<Vec<_>>();
+        let metric = utils::Metric::mean(&metrics);
+        (
+            Box::new(move |p| metric.to_euclidean(p)),
+            Box::new(move |p| metric.from_euclidean(p)),
+        )
+    };
+
+    let mut loops = params
+        .into_iter()
+        .map(|params| params.into_iter().map(&to_plane).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let mut vertices = bounds;
+    let areas = loops.iter().map(|l| signed_area(l)).collect::<Vec<_>>();
+    let outer = (0..areas.len())
+        .max_by(|&a, &b| areas[a].abs().total_cmp(&areas[b].abs()))
+        .unwrap();
+    // loops through undefined parameters are not meshed
+    if !areas.iter().all(|area| area.is_finite()) {
+        return TriangleMesh::new();
+    }
+    if areas[outer].abs().near(0.0) {
+        // the loops run forth and back along seams or collapse to poles
+        return if winds_u || winds_v {
+            TriangleMesh::new()
+        } else {
+            trim_whole_domain(surface, &[], &[], poles)
+        };
+    }
+    // the mesher expects a counter-clockwise outer loop and clockwise holes
+    let reversed = (areas[outer] < 0.0) != flipped;
+    for (index, area) in areas.iter().enumerate() {
+        if (index == outer) == (*area < 0.0) {
+            loops[index].reverse();
+            vertices[index].reverse();
+        }
+    }
+    loops.swap(0, outer);
+    vertices.swap(0, outer);
+
+    let mut polygons = vec![0];
+    for polygon in &loops {
+        polygons.push(polygons[polygons.len() - 1] + polygon.len());
+    }
+    // interior points are spaced like the boundary points, which are kept to match adjacent faces
+    let (length, count) = vertices
+        .iter()
+        .flat_map(|polygon| polygon.iter().zip(polygon.iter().cycle().skip(1)))
+        .fold((0.0, 0), |(length, count), (a, b)| {
+            (length + a.distance(*b), count + 1)
+        });
+    let quality = utils::MeshQuality {
+        max_length: length / count as Float,
+        split_segments: false,
+        ..Default::default()
+    };
+    let points = loops.concat();
+    let mut vertices = vertices.concat();
+    let boundary_point_count = points.len();
+    // the first fundamental form pulled back to the plane, derivatives of the mapping
+    // are taken by central differences
+    let (min, max) = points.iter().fold(
+        (Point2::splat(Float::MAX), Point2::splat(Float::MIN)),
+        |(min, max), p| (min.min(*p), max.max(*p)),
+    );
+    let step = (max - min).max_element() * 1e-6;
+    let metric = |p: Point2| {
+        let param = from_plane(p);
+        let derivative = |offset: Point2| {
+            let forward = unwrap_parameter(from_plane(p + offset), param, periods);
+            let backward = unwrap_parameter(from_plane(p - offset), param, periods);
+            (forward - backward) / (2.0 * step)
+        };
+        let (dx, dy) = (derivative(Point2::X * step), derivative(Point2::Y * step));
+        let derivatives = surface.derivatives(param.x, param.y);
+        let (du, dv) = (derivatives.du, derivatives.dv);
+        utils::Metric::from_derivatives(du * dx.x + dv * dx.y, du * dy.x + dv * dy.y)
+    };
+    let mut mesh = utils::ConstrainedDelaunay::new(&points, &polygons);
+    mesh.refine_in_metric(&quality, metric);
+    let (points, triangles) = (mesh.points(), mesh.triangles());
+    let params = points.iter().map(|&p| from_plane(p)).collect::<Vec<_>>();
+    vertices.extend(
+        params[boundary_point_count..]
+            .iter()
+            .map(|param| surface.get_point(param.x, param.y)),
+    );
+    let mut normals = surface.get_normals(&params);
+    borrow_missing_normals(&mut normals, triangles.chunks(3));
+    let mesh = TriangleMesh {
+        vertices,
+        triangles,
+        normals,
+    };
+    if reversed {
+        mesh.reverse_winding_direction()
+    } else {
+        mesh
+    }
+}
+
+/// Loops are given counter-clockwise seen from the surface normals,
+/// whether they turn clockwise in the parameter plane
+fn normals_flipped<S: Surface + ?Sized>(surface: &S, param: Point2) -> bool {
+    surface
+        .get_normals(&[param])
+        .pop()
+        .is_some_and(|normal| normal.dot(surface.derivatives(param.x, param.y).normal()) < 0.0)
+}
+
+/// Mesh the whole parameter domain of a closed surface with the loops as holes,
+/// the mesh is empty when the domain is not bounded by periods and poles.
+///
+/// A domain periodic in both parameters is the rectangle of the periods. A domain
+/// periodic in `v` between two poles of `u` is split into two caps around the poles.
+fn trim_whole_domain<S: Surface + ?Sized>(
+    surface: &S,
+    holes: &[Vec<Point3>],
+    hole_params: &[Vec<Point2>],
+    poles: &[Float],
+) -> TriangleMesh {
+    let division = 32;
+    let side = |from: Point2, to: Point2| {
+        (0..division).map(move |i| from.lerp(to, i as Float / division as Float))
+    };
+    // the loops are counter-clockwise in parameters, like seen from the surface normals
+    // unless these point the other way
+    let trim = |params: Vec<Point2>, inner: &[usize]| {
+        let mut params = params;
+        if normals_flipped(surface, params[0]) {
+            params.reverse();
+        }
+        let mut bounds = vec![params
+            .iter()
+            .map(|p| surface.get_point(p.x, p.y))
+            .collect::<Vec<_>>()];
+        let mut loop_params = vec![params];
+        for &hole in inner {
+            bounds.push(holes[hole].clone());
+            loop_params.push(hole_params[hole].clone());
+        }
+        trim_with_poles(surface, &bounds, &loop_params, poles)
+    };
+    match surface.periods() {
+        (Some(u_period), Some(v_period)) => {
+            let corners = [
+                Point2::new(0.0, 0.0),
+                Point2::new(u_period, 0.0),
+                Point2::new(u_period, v_period),
+                Point2::new(0.0, v_period),
+            ];
+            let rectangle = (0..4)
+                .flat_map(|i| side(corners[i], corners[(i + 1) % 4]))
+                .collect();
+            trim(rectangle, &(0..holes.len()).collect::<Vec<_>>())
+        }
+        (None, Some(v_period)) => {
+            let mut poles = poles.to_vec();
+            poles.sort_by(|a, b| a.total_cmp(b));
+            if poles.len() < 2 {
+                return TriangleMesh::new();
+            }
+            // a loop around the period bounds the cap of the pole on its left
+            let middle = (poles[0] + poles[1]) / 2.0;
+            let (lower, upper): (Vec<_>, Vec<_>) =
+                (0..holes.len()).partition(|&hole| mean(&hole_params[hole]).x < middle);
+            let start = Point2::new(middle, 0.0);
+            let end = Point2::new(middle, v_period);
+            TriangleMesh::join(vec![
+                trim(side(start, end).collect(), &lower),
+                trim(side(end, start).collect(), &upper),
+            ])
+        }
+        _ => TriangleMesh::new(),
+    }
+}
+
 mod bezier;
 mod bspline;
+mod cone;
 mod cylinder;
+mod extrusion;
+mod fitting;
+mod interpolation;
+mod offset;
 mod plane;
+mod sphere;
 mod spin;
 mod sweep;
+mod tessellation;
+mod torus;
 mod umbrella;
 
 pub use bezier::*;
 pub use bspline::*;
+pub use cone::*;
 pub use cylinder::*;
+pub use extrusion::*;
+pub use fitting::*;
+pub use offset::*;
 pub use plane::*;
+pub use sphere::*;
 pub use spin::*;
 pub use sweep::*;
+pub use tessellation::*;
+pub use torus::*;
 pub use umbrella::*;
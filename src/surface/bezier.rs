@@ -20,10 +20,10 @@ impl Surface for BezierSurface<Point3> {
         let basis_u = bernstein(n, u); // n rows
         let basis_v = bernstein(m, v); // m cols
         let mut point = Point3::ZERO;
-        for i in 0..n {
-            for j in 0..m {
+        for (i, bu) in basis_u.iter().enumerate() {
+            for (j, bv) in basis_v.iter().enumerate() {
                 let p = self.control_points[i][j];
-                point += basis_u[i] * basis_v[j] * p;
+                point += bu * bv * p;
             }
         }
         point
@@ -37,10 +37,10 @@ impl Surface for BezierSurface<Point4> {
         let basis_u = bernstein(n, u); // n rows
         let basis_v = bernstein(m, v); // m cols
         let mut point = Point4::ZERO;
-        for i in 0..n {
-            for j in 0..m {
+        for (i, bu) in basis_u.iter().enumerate() {
+            for (j, bv) in basis_v.iter().enumerate() {
                 let p = self.control_points[i][j];
-                point += basis_u[i] * basis_v[j] * p;
+                point += bu * bv * p;
             }
         }
         (1.0 / point.w) * point.truncate()
@@ -1,7 +1,10 @@
-use crate::surface::{EdgeLoop, Surface};
+use crate::curve::{
+    elevate_degree_of_all, reduce_degree_of_all, remove_knots_from_all, BSplineCurve, ControlPoint,
+};
+use crate::surface::{closed_periods, trim_in_parameter_space, Surface, SurfaceDerivatives};
 use crate::{
-    face::Face, utils, utils::Tolerance, Float, Grid, KnotVector, Mat2, Point2, Point3, Point4,
-    TriangleMesh, Vec2, Vec3,
+    utils, utils::Tolerance, Float, Grid, KnotVector, Mat2, Point2, Point3, Point4, TriangleMesh,
+    Vec2, Vec3,
 };
 
 #[derive(Debug, Clone)]
@@ -60,10 +63,9 @@ impl<P: std::ops::Sub<Output = P> + std::ops::Mul<Float, Output = P> + Copy + De
         let p = p as Float;
         let (n, m) = self.control_points.size();
         let mut points = Vec::with_capacity((n - 1) * m);
-        for i in 0..n - 1 {
+        for (i, &span) in u_spans.iter().take(n - 1).enumerate() {
             for j in 0..m {
                 let point = self.control_points[i + 1][j] - self.control_points[i][j];
-                let span = u_spans[i];
                 points.push(point * p * utils::inv_or_zero(span));
             }
         }
@@ -91,9 +93,8 @@ impl<P: std::ops::Sub<Output = P> + std::ops::Mul<Float, Output = P> + Copy + De
         let (n, m) = self.control_points.size();
         let mut points = Vec::with_capacity(n * (m - 1));
         for i in 0..n {
-            for j in 0..m - 1 {
+            for (j, &span) in v_spans.iter().take(m - 1).enumerate() {
                 let point = self.control_points[i][j + 1] - self.control_points[i][j];
-                let span = v_spans[j];
                 points.push(point * q * utils::inv_or_zero(span));
             }
         }
@@ -106,63 +107,243 @@ impl<P: std::ops::Sub<Output = P> + std::ops::Mul<Float, Output = P> + Copy + De
     }
 }
 
-impl BSplineSurface<Point3> {
-    pub fn project_points(&self, points: &[Point3]) -> Vec<Point2> {
+impl<P: std::ops::Sub<Output = P> + std::ops::Mul<Float, Output = P> + Copy + Default>
+    BSplineSurface<P>
+{
+    /// Derivative surfaces `[S_u, S_v, S_uu, S_uv, S_vv]`
+    fn derivative_surfaces(&self) -> [BSplineSurface<P>; 5] {
         let der_u = self.derivative_u();
         let der_v = self.derivative_v();
         let der_uu = der_u.derivative_u();
         let der_uv = der_u.derivative_v();
-        let der_vu = der_v.derivative_u();
         let der_vv = der_v.derivative_v();
+        [der_u, der_v, der_uu, der_uv, der_vv]
+    }
 
+    /// Parameters sampled over the knot ranges finely enough to start projections
+    fn sample_parameters(&self) -> (Vec<Float>, Vec<Float>) {
         let (n, m) = self.control_points.size();
-        let (u_range, v_range) = (self.knots.0.range(), self.knots.1.range());
-        let u_parameters = utils::uniform_divide(u_range, n * 4);
-        let v_parameters = utils::uniform_divide(v_range, m * 4);
-        let vertices = Grid::from_vec(
-            u_parameters
-                .iter()
-                .map(|&u| v_parameters.iter().map(move |&v| self.get_point(u, v)))
-                .flatten()
-                .collect::<Vec<Point3>>(),
-            m * 4 + 1,
-        );
+        (
+            utils::uniform_divide(self.knots.0.range(), n * 4),
+            utils::uniform_divide(self.knots.1.range(), m * 4),
+        )
+    }
+}
+
+impl<P: ControlPoint> BSplineSurface<P> {
+    /// Curves along u through each column of control points, they share the u knots
+    fn u_curves(&self) -> Vec<BSplineCurve<P>> {
+        (0..self.control_points.cols())
+            .map(|j| BSplineCurve {
+                control_points: self.control_points.iter_col(j).copied().collect(),
+                knots: self.knots.0.clone(),
+                degree: self.degree.0,
+            })
+            .collect()
+    }
 
-        let trials = 20;
-        points
+    fn with_u_curves(&self, curves: Vec<BSplineCurve<P>>) -> Self {
+        let rows = curves[0].control_points.len();
+        let columns = curves
             .iter()
-            .map(|point| {
-                let (i, j) = utils::find_nearest_point_in_grid(&vertices, *point);
-                let (mut u, mut v) = (u_parameters[i], v_parameters[j]);
-                for _ in 0..trials {
-                    let r = self.get_point(u, v) - *point;
-                    if r.length_squared().near(0.0) {
-                        return Point2::new(u, v);
-                    }
-                    let su = der_u.get_point(u, v);
-                    let sv = der_v.get_point(u, v);
-                    if su.dot(r).near(0.0) && sv.dot(r).near(0.0) {
-                        return Point2::new(u, v);
-                    }
-
-                    let suu = der_uu.get_point(u, v);
-                    let suv = der_uv.get_point(u, v);
-                    let svu = der_vu.get_point(u, v);
-                    let svv = der_vv.get_point(u, v);
-                    let fu = su.length_squared() + r.dot(suu);
-                    let gu = su.dot(sv) + r.dot(svu);
-                    let fv = su.dot(sv) + r.dot(suv);
-                    let gv = sv.length_squared() + r.dot(svv);
-
-                    let j = Mat2::from_cols_array(&[fu, gu, fv, gv]);
-                    let k = Vec2::new(-r.dot(su), -r.dot(sv));
-                    let delta = j.inverse() * k;
-                    u = utils::clamp_in_range(u + delta.x, u_range);
-                    v = utils::clamp_in_range(v + delta.y, v_range);
+            .flat_map(|curve| curve.control_points.iter().copied())
+            .collect();
+        BSplineSurface {
+            control_points: Grid::from_vec(columns, rows).transpose(),
+            knots: (curves[0].knots.clone(), self.knots.1.clone()),
+            degree: (curves[0].degree, self.degree.1),
+        }
+    }
+
+    /// The same surface with u and v swapped
+    fn transpose(&self) -> Self {
+        BSplineSurface {
+            control_points: self.control_points.transpose(),
+            knots: (self.knots.1.clone(), self.knots.0.clone()),
+            degree: (self.degree.1, self.degree.0),
+        }
+    }
+
+    /// Insert the u knots keeping the shape
+    pub fn refine_knots_u(&mut self, knots: &[Float]) {
+        let mut curves = self.u_curves();
+        for curve in &mut curves {
+            curve.refine_knots(knots);
+        }
+        *self = self.with_u_curves(curves);
+    }
+
+    /// Insert the v knots keeping the shape
+    pub fn refine_knots_v(&mut self, knots: &[Float]) {
+        let mut surface = self.transpose();
+        surface.refine_knots_u(knots);
+        *self = surface.transpose();
+    }
+
+    /// Remove interior u knots as often as the tolerance allows for every column,
+    /// returns the number of removals
+    pub fn remove_knots_u(&mut self, tolerance: Float) -> usize {
+        let mut curves = self.u_curves();
+        let count = remove_knots_from_all(&mut curves, tolerance);
+        *self = self.with_u_curves(curves);
+        count
+    }
+
+    /// Remove interior v knots as often as the tolerance allows for every row,
+    /// returns the number of removals
+    pub fn remove_knots_v(&mut self, tolerance: Float) -> usize {
+        let mut surface = self.transpose();
+        let count = surface.remove_knots_u(tolerance);
+        *self = surface.transpose();
+        count
+    }
+
+    /// Raise the u degree by `times` keeping the shape
+    pub fn elevate_degree_u(&self, times: usize) -> Self {
+        self.with_u_curves(elevate_degree_of_all(&self.u_curves(), times))
+    }
+
+    /// Raise the v degree by `times` keeping the shape
+    pub fn elevate_degree_v(&self, times: usize) -> Self {
+        self.transpose().elevate_degree_u(times).transpose()
+    }
+
+    /// Lower the u degree by one, `None` when the control points would deviate more than
+    /// the tolerance
+    pub fn reduce_degree_u(&self, tolerance: Float) -> Option<Self> {
+        reduce_degree_of_all(&self.u_curves(), tolerance).map(|curves| self.with_u_curves(curves))
+    }
+
+    /// Lower the v degree by one, `None` when the control points would deviate more than
+    /// the tolerance
+    pub fn reduce_degree_v(&self, tolerance: Float) -> Option<Self> {
+        self.transpose()
+            .reduce_degree_u(tolerance)
+            .map(|surface| surface.transpose())
+    }
+}
+
+/// Project points to the surface by Newton iteration started from the nearest sample points
+fn project_to_surface<S: Surface>(
+    surface: &S,
+    (u_parameters, v_parameters): (Vec<Float>, Vec<Float>),
+    points: &[Point3],
+    derivatives: impl Fn(Float, Float) -> SurfaceDerivatives,
+) -> Vec<Point2> {
+    let u_range = (u_parameters[0], u_parameters[u_parameters.len() - 1]);
+    let v_range = (v_parameters[0], v_parameters[v_parameters.len() - 1]);
+    let vertices = Grid::from_vec(
+        u_parameters
+            .iter()
+            .flat_map(|&u| v_parameters.iter().map(move |&v| surface.get_point(u, v)))
+            .collect::<Vec<Point3>>(),
+        v_parameters.len(),
+    );
+
+    let trials = 20;
+    points
+        .iter()
+        .map(|point| {
+            let (i, j) = utils::find_nearest_point_in_grid(&vertices, *point);
+            let (mut u, mut v) = (u_parameters[i], v_parameters[j]);
+            for _ in 0..trials {
+                let SurfaceDerivatives {
+                    point: p,
+                    du: su,
+                    dv: sv,
+                    duu: suu,
+                    duv: suv,
+                    dvv: svv,
+                } = derivatives(u, v);
+                let r = p - *point;
+                if r.length_squared().near2(0.0) {
+                    return Point2::new(u, v);
                 }
-                return Point2::new(u, v);
-            })
-            .collect::<Vec<_>>()
+                if su.dot(r).near(0.0) && sv.dot(r).near(0.0) {
+                    return Point2::new(u, v);
+                }
+
+                let fu = su.length_squared() + r.dot(suu);
+                let gu = su.dot(sv) + r.dot(suv);
+                let fv = su.dot(sv) + r.dot(suv);
+                let gv = sv.length_squared() + r.dot(svv);
+
+                let j = Mat2::from_cols_array(&[fu, gu, fv, gv]);
+                let k = Vec2::new(-r.dot(su), -r.dot(sv));
+                let delta = j.inverse() * k;
+                u = utils::clamp_in_range(u + delta.x, u_range);
+                v = utils::clamp_in_range(v + delta.y, v_range);
+            }
+            Point2::new(u, v)
+        })
+        .collect::<Vec<_>>()
+}
+
+impl BSplineSurface<Point3> {
+    pub fn project_points(&self, points: &[Point3]) -> Vec<Point2> {
+        let [der_u, der_v, der_uu, der_uv, der_vv] = self.derivative_surfaces();
+        project_to_surface(self, self.sample_parameters(), points, |u, v| {
+            SurfaceDerivatives {
+                point: self.get_point(u, v),
+                du: der_u.get_point(u, v),
+                dv: der_v.get_point(u, v),
+                duu: der_uu.get_point(u, v),
+                duv: der_uv.get_point(u, v),
+                dvv: der_vv.get_point(u, v),
+            }
+        })
+    }
+}
+
+impl BSplineSurface<Point4> {
+    /// Get the weighted point `(wx,wy,wz,w)` with parameters `(u,v)`
+    pub fn get_weighted_point(&self, u: Float, v: Float) -> Point4 {
+        let (u_knots, v_knots) = &self.knots;
+        let (p, q) = self.degree;
+        let basis_u = u_knots.bspline_basis(p as usize, u);
+        let basis_v = v_knots.bspline_basis(q as usize, v);
+        let mut point = Point4::ZERO;
+        for (i, bu) in basis_u.iter().enumerate() {
+            for (j, bv) in basis_v.iter().enumerate() {
+                let p = self.control_points[i][j];
+                point += bu * bv * p;
+            }
+        }
+        point
+    }
+
+    // derivatives of the quotient of weighted point and weight
+    fn rational_derivatives(
+        &self,
+        [der_u, der_v, der_uu, der_uv, der_vv]: &[BSplineSurface<Point4>; 5],
+        u: Float,
+        v: Float,
+    ) -> SurfaceDerivatives {
+        let a = self.get_weighted_point(u, v);
+        let a_u = der_u.get_weighted_point(u, v);
+        let a_v = der_v.get_weighted_point(u, v);
+        let a_uu = der_uu.get_weighted_point(u, v);
+        let a_uv = der_uv.get_weighted_point(u, v);
+        let a_vv = der_vv.get_weighted_point(u, v);
+        let w = a.w;
+        let point = a.truncate() / w;
+        let du = (a_u.truncate() - point * a_u.w) / w;
+        let dv = (a_v.truncate() - point * a_v.w) / w;
+        SurfaceDerivatives {
+            point,
+            du,
+            dv,
+            duu: (a_uu.truncate() - du * (2.0 * a_u.w) - point * a_uu.w) / w,
+            duv: (a_uv.truncate() - du * a_v.w - dv * a_u.w - point * a_uv.w) / w,
+            dvv: (a_vv.truncate() - dv * (2.0 * a_v.w) - point * a_vv.w) / w,
+        }
+    }
+
+    pub fn project_points(&self, points: &[Point3]) -> Vec<Point2> {
+        let surfaces = self.derivative_surfaces();
+        project_to_surface(self, self.sample_parameters(), points, |u, v| {
+            self.rational_derivatives(&surfaces, u, v)
+        })
     }
 }
 
@@ -173,17 +354,28 @@ impl Surface for BSplineSurface<Point3> {
         let (p, q) = self.degree;
         let basis_u = u_knots.bspline_basis(p as usize, u);
         let basis_v = v_knots.bspline_basis(q as usize, v);
-        let (n, m) = self.control_points.size();
         let mut point = Point3::ZERO;
-        for i in 0..n {
-            for j in 0..m {
+        for (i, bu) in basis_u.iter().enumerate() {
+            for (j, bv) in basis_v.iter().enumerate() {
                 let p = self.control_points[i][j];
-                point += basis_u[i] * basis_v[j] * p;
+                point += bu * bv * p;
             }
         }
         point
     }
 
+    fn derivatives(&self, u: Float, v: Float) -> SurfaceDerivatives {
+        let [der_u, der_v, der_uu, der_uv, der_vv] = self.derivative_surfaces();
+        SurfaceDerivatives {
+            point: self.get_point(u, v),
+            du: der_u.get_point(u, v),
+            dv: der_v.get_point(u, v),
+            duu: der_uu.get_point(u, v),
+            duv: der_uv.get_point(u, v),
+            dvv: der_vv.get_point(u, v),
+        }
+    }
+
     fn get_normals(&self, params: &[Point2]) -> Vec<Vec3> {
         let der_u = self.derivative_u();
         let der_v = self.derivative_v();
@@ -199,101 +391,57 @@ impl Surface for BSplineSurface<Point3> {
             .collect()
     }
 
-    fn trim(&self, bounds: &[EdgeLoop]) -> TriangleMesh {
-        // let patch = crate::surface::SurfacePatch {
-        //     surface: self.clone(),
-        //     parameter_range: (self.knots.0.range(), self.knots.1.range()),
-        //     parameter_division: (16, 16),
-        // };
+    fn periods(&self) -> (Option<Float>, Option<Float>) {
+        closed_periods(self, (self.knots.0.range(), self.knots.1.range()))
+    }
 
-        // patch.get_points().into()
-        let mut polygons = Vec::with_capacity(bounds.len() + 1);
-        polygons.push(0);
-        let mut end = 0;
-        let mut vertices = bounds
+    fn trim_polygons(&self, bounds: &[Vec<Point3>]) -> TriangleMesh {
+        let params = bounds
             .iter()
-            .map(|bound| {
-                let polygon = bound.to_polygon();
-                if polygon.len() > 0 {
-                    end += polygon.len();
-                    polygons.push(end);
-                }
-                polygon
-            })
-            .flatten()
+            .map(|bound| self.project_points(bound))
             .collect::<Vec<_>>();
-        save_bound_as_obj(&vertices, "bound.obj").unwrap();
-        let mut points: Vec<Point2> = self.project_points(&vertices);
-        let boundary_point_count = points.len();
-
-        if polygons.len() == 2 {
-            if utils::is_polygon_counter_clockwise(&points) {
-                let (points, triangles) = utils::generate_triangular_mesh(&points, &polygons);
-                vertices.extend(
-                    points[boundary_point_count..]
-                        .iter()
-                        .map(|&p| self.get_point(p.x, p.y)),
-                );
-
-                return TriangleMesh {
-                    vertices,
-                    triangles,
-                    normals: Vec::new(),
-                }
-                .reverse_winding_direction();
-            } else {
-                vertices.reverse();
-                points.reverse();
-                let (points, triangles) = utils::generate_triangular_mesh(&points, &polygons);
-                vertices.extend(
-                    points[boundary_point_count..]
-                        .iter()
-                        .map(|&p| self.get_point(p.x, p.y)),
-                );
-
-                return TriangleMesh {
-                    vertices,
-                    triangles,
-                    normals: Vec::new(),
-                };
-            }
-        } else if polygons.len() > 2 {
-            // triangulate polygon with holes
-            dbg!(polygons.len());
-            let (points, triangles) = utils::generate_triangular_mesh(&points, &polygons);
-            vertices.extend(
-                points[boundary_point_count..]
-                    .iter()
-                    .map(|p| self.get_point(p.x, p.y)),
-            );
-            return TriangleMesh {
-                vertices,
-                triangles,
-                normals: Vec::new(),
-            };
-        } else {
-            return TriangleMesh::new();
-        }
+        self.trim_parameter_polygons(bounds, &params)
+    }
+
+    fn trim_parameter_polygons(
+        &self,
+        bounds: &[Vec<Point3>],
+        params: &[Vec<Point2>],
+    ) -> TriangleMesh {
+        trim_in_parameter_space(self, bounds, params)
     }
 }
 
 /// Rational BSpline Surface, point (x,y,z) with weight w is (wx,wy,wz,w)
 impl Surface for BSplineSurface<Point4> {
     fn get_point(&self, u: Float, v: Float) -> Point3 {
-        let (u_knots, v_knots) = &self.knots;
-        let (p, q) = self.degree;
-        let basis_u = u_knots.bspline_basis(p as usize, u);
-        let basis_v = v_knots.bspline_basis(q as usize, v);
-        let (n, m) = self.control_points.size();
-        let mut point = Point4::ZERO;
-        for i in 0..n {
-            for j in 0..m {
-                let p = self.control_points[i][j];
-                point += basis_u[i] * basis_v[j] * p;
-            }
-        }
+        let point = self.get_weighted_point(u, v);
         (1.0 / point.w) * point.truncate()
     }
+
+    fn derivatives(&self, u: Float, v: Float) -> SurfaceDerivatives {
+        self.rational_derivatives(&self.derivative_surfaces(), u, v)
+    }
+
+    fn periods(&self) -> (Option<Float>, Option<Float>) {
+        closed_periods(self, (self.knots.0.range(), self.knots.1.range()))
+    }
+
+    fn trim_polygons(&self, bounds: &[Vec<Point3>]) -> TriangleMesh {
+        let params = bounds
+            .iter()
+            .map(|bound| self.project_points(bound))
+            .collect::<Vec<_>>();
+        self.trim_parameter_polygons(bounds, &params)
+    }
+
+    fn trim_parameter_polygons(
+        &self,
+        bounds: &[Vec<Point3>],
+        params: &[Vec<Point2>],
+    ) -> TriangleMesh {
+        trim_in_parameter_space(self, bounds, params)
+    }
 }
 
 pub fn save_bound_as_obj<P: AsRef<std::path::Path>>(
@@ -313,3 +461,132 @@ pub fn save_bound_as_obj<P: AsRef<std::path::Path>>(
     writeln!(writer, "l {} {}", vertices.len(), 1)?;
     Ok(())
 }
+
+#[test]
+fn test_rational_surface_derivatives() {
+    use crate::surface::approximate_derivatives;
+
+    // quarter of a cylinder with radius 2 along z axis
+    let weight = 0.5_f64.sqrt();
+    let control_points = Grid::from_vec(
+        vec![
+            Point4::new(2.0, 0.0, 0.0, 1.0),
+            Point4::new(2.0, 0.0, 3.0, 1.0),
+            Point4::new(2.0 * weight, 2.0 * weight, 0.0, weight),
+            Point4::new(2.0 * weight, 2.0 * weight, 3.0 * weight, weight),
+            Point4::new(0.0, 2.0, 0.0, 1.0),
+            Point4::new(0.0, 2.0, 3.0, 1.0),
+        ],
+        2,
+    );
+    let surface = BSplineSurface::uniform_clamped(control_points, (2, 1));
+    let (u, v) = (0.3, 0.6);
+    let exact = surface.derivatives(u, v);
+    let approximate = approximate_derivatives(&surface, u, v);
+    for (a, b) in [
+        (exact.du, approximate.du),
+        (exact.dv, approximate.dv),
+        (exact.duu, approximate.duu),
+        (exact.duv, approximate.duv),
+        (exact.dvv, approximate.dvv),
+    ] {
+        assert!(a.distance(b) < 1e-5);
+    }
+    assert!((exact.point.truncate().length() - 2.0).abs() < 1e-12);
+
+    let curvature = surface.curvature(u, v).unwrap();
+    let (k1, k2) = curvature.principal;
+    assert!(curvature.gaussian.abs() < 1e-9);
+    assert!((k1.abs().max(k2.abs()) - 0.5).abs() < 1e-9);
+    assert!(k1.abs().min(k2.abs()) < 1e-9);
+    let straight = if k1.abs() < k2.abs() {
+        curvature.directions.0
+    } else {
+        curvature.directions.1
+    };
+    assert!((straight.dot(Vec3::Z).abs() - 1.0).abs() < 1e-9);
+}
+
+#[test]
+fn test_rational_surface_projection() {
+    // quarter of a cylinder with radius 2 along z axis
+    let weight = 0.5_f64.sqrt();
+    let control_points = Grid::from_vec(
+        vec![
+            Point4::new(2.0, 0.0, 0.0, 1.0),
+            Point4::new(2.0, 0.0, 3.0, 1.0),
+            Point4::new(2.0 * weight, 2.0 * weight, 0.0, weight),
+            Point4::new(2.0 * weight, 2.0 * weight, 3.0 * weight, weight),
+            Point4::new(0.0, 2.0, 0.0, 1.0),
+            Point4::new(0.0, 2.0, 3.0, 1.0),
+        ],
+        2,
+    );
+    let surface = BSplineSurface::uniform_clamped(control_points, (2, 1));
+    let params = [Point2::new(0.2, 0.4), Point2::new(0.75, 0.9)];
+    let points = params
+        .iter()
+        .map(|p| surface.get_point(p.x, p.y))
+        .collect::<Vec<_>>();
+    for (param, projected) in params.iter().zip(surface.project_points(&points)) {
+        assert!(param.distance(projected) < 1e-9);
+    }
+
+    // a square hole cut out of the patch
+    let bound = [(0.1, 0.1), (0.9, 0.1), (0.9, 0.9), (0.1, 0.9)]
+        .iter()
+        .map(|&(u, v)| surface.get_point(u, v))
+        .collect::<Vec<_>>();
+    let mesh = surface.trim_polygons(std::slice::from_ref(&bound));
+    assert!(mesh.triangle_count() >= 2);
+    for vertex in &mesh.vertices {
+        assert!((vertex.truncate().length() - 2.0).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn test_surface_degree_elevation() {
+    let weight = 0.5_f64.sqrt();
+    let control_points = Grid::from_vec(
+        vec![
+            Point4::new(2.0, 0.0, 0.0, 1.0),
+            Point4::new(2.0, 0.0, 3.0, 1.0),
+            Point4::new(2.0 * weight, 2.0 * weight, 0.0, weight),
+            Point4::new(2.0 * weight, 2.0 * weight, 3.0 * weight, weight),
+            Point4::new(0.0, 2.0, 0.0, 1.0),
+            Point4::new(0.0, 2.0, 3.0, 1.0),
+        ],
+        2,
+    );
+    let surface = BSplineSurface::uniform_clamped(control_points, (2, 1));
+    let same = |other: &BSplineSurface<Point4>| {
+        let (us, vs) = (
+            utils::uniform_divide((0.0, 1.0), 8),
+            utils::uniform_divide((0.0, 1.0), 8),
+        );
+        us.iter().all(|&u| {
+            vs.iter()
+                .all(|&v| surface.get_point(u, v).distance(other.get_point(u, v)) < 1e-9)
+        })
+    };
+
+    let mut refined = surface.clone();
+    refined.refine_knots_u(&[0.25, 0.5]);
+    refined.refine_knots_v(&[0.4]);
+    assert_eq!(refined.control_points.size(), (5, 3));
+    assert!(same(&refined));
+    assert_eq!(
+        refined.remove_knots_u(1e-9) + refined.remove_knots_v(1e-9),
+        3
+    );
+    assert_eq!(refined.control_points.size(), (3, 2));
+
+    let elevated = surface.elevate_degree_u(1).elevate_degree_v(2);
+    assert_eq!(elevated.degree, (3, 3));
+    assert_eq!(elevated.control_points.size(), (4, 4));
+    assert!(same(&elevated));
+    let reduced = elevated.reduce_degree_v(1e-9).unwrap();
+    assert_eq!(reduced.degree, (3, 2));
+    assert!(same(&reduced));
+    assert!(surface.reduce_degree_u(1e-3).is_none());
+}
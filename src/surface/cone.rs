@@ -0,0 +1,124 @@
+use crate::surface::{project_polygons, trim_in_parameter_space, Surface, SurfaceDerivatives};
+use crate::{Float, Point2, Point3, Quat, TriangleMesh, Vec3};
+
+#[derive(Debug)]
+pub struct Cone {
+    /// Center of the circle at length zero
+    pub origin: Point3,
+    pub axis: Vec3,
+    /// Normalized direction for angle start
+    pub ref_dir: Vec3,
+    /// Radius at length zero
+    pub radius: Float,
+    /// Angle between the axis and the generating lines
+    pub semi_angle: Float,
+}
+
+impl Cone {
+    /// Radius of the circle at the length along axis
+    pub fn radius_at(&self, length: Float) -> Float {
+        self.radius + length * self.semi_angle.tan()
+    }
+
+    /// Length along axis of the apex
+    pub fn apex_length(&self) -> Float {
+        -self.radius / self.semi_angle.tan()
+    }
+
+    pub fn project(&self, point: Point3) -> Point2 {
+        let vector = point - self.origin;
+        let x = vector.dot(self.ref_dir);
+        let y = vector.dot(self.axis.cross(self.ref_dir));
+        let angle = y.atan2(x);
+        let angle = if angle >= 0.0 {
+            angle
+        } else {
+            angle + crate::consts::TAU
+        };
+        // nearest point on the generating line at the angle
+        let radial = (vector - self.axis * vector.dot(self.axis)).normalize_or_zero();
+        let radial = if radial == Vec3::ZERO {
+            self.ref_dir
+        } else {
+            radial
+        };
+        let direction = self.axis + radial * self.semi_angle.tan();
+        let length = (vector - radial * self.radius).dot(direction) / direction.length_squared();
+        Point2::new(length, angle)
+    }
+}
+
+impl Surface for Cone {
+    fn get_point(&self, length: Float, angle: Float) -> Point3 {
+        let rotation = Quat::from_axis_angle(self.axis, angle);
+        self.origin + self.axis * length + rotation * self.ref_dir * self.radius_at(length)
+    }
+
+    fn derivatives(&self, length: Float, angle: Float) -> SurfaceDerivatives {
+        let radial = Quat::from_axis_angle(self.axis, angle) * self.ref_dir;
+        let tangent = self.axis.cross(radial);
+        let slope = self.semi_angle.tan();
+        let radius = self.radius_at(length);
+        SurfaceDerivatives {
+            point: self.get_point(length, angle),
+            du: self.axis + radial * slope,
+            dv: tangent * radius,
+            duu: Vec3::ZERO,
+            duv: tangent * slope,
+            dvv: -radial * radius,
+        }
+    }
+
+    /// Normals point away from the axis
+    fn get_normals(&self, params: &[Point2]) -> Vec<Vec3> {
+        let (sin, cos) = self.semi_angle.sin_cos();
+        params
+            .iter()
+            .map(|p| {
+                let radial = Quat::from_axis_angle(self.axis, p.y) * self.ref_dir;
+                radial * cos - self.axis * sin
+            })
+            .collect()
+    }
+
+    fn periods(&self) -> (Option<Float>, Option<Float>) {
+        (None, Some(crate::consts::TAU))
+    }
+
+    fn poles(&self) -> Vec<Float> {
+        vec![self.apex_length()]
+    }
+
+    fn nearest_parameters(&self, point: Point3) -> Option<Point2> {
+        Some(self.project(point))
+    }
+
+    fn trim_polygons(&self, bounds: &[Vec<Point3>]) -> TriangleMesh {
+        let params = project_polygons(bounds, |point| self.project(point));
+        self.trim_parameter_polygons(bounds, &params)
+    }
+
+    fn trim_parameter_polygons(
+        &self,
+        bounds: &[Vec<Point3>],
+        params: &[Vec<Point2>],
+    ) -> TriangleMesh {
+        trim_in_parameter_space(self, bounds, params)
+    }
+}
+
+#[test]
+fn test_cone_projection() {
+    let cone = Cone {
+        origin: Point3::new(1.0, 2.0, 3.0),
+        axis: Vec3::Z,
+        ref_dir: Vec3::X,
+        radius: 1.0,
+        semi_angle: 0.5,
+    };
+    let point = cone.get_point(2.0, crate::consts::PI / 2.0);
+    let expected = Point3::new(1.0, 3.0 + 2.0 * 0.5_f64.tan(), 5.0);
+    assert!(point.distance(expected) < 1e-9, "{:?}", point);
+    let param = cone.project(point);
+    assert!(param.distance(Point2::new(2.0, crate::consts::PI / 2.0)) < 1e-9);
+}
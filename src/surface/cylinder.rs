@@ -1,6 +1,8 @@
-use crate::surface::{EdgeLoop, Surface};
-use crate::utils;
-use crate::{Float, Point2, Point3, Quat, TriangleMesh, Vec3};
+use crate::curve::unit_arc;
+use crate::surface::{
+    project_polygons, trim_in_parameter_space, BSplineSurface, Surface, SurfaceDerivatives,
+};
+use crate::{Float, Grid, KnotVector, Point2, Point3, Point4, Quat, TriangleMesh, Vec3};
 
 #[derive(Debug)]
 pub struct Cylinder {
@@ -26,22 +28,25 @@ impl Cylinder {
         Point2::new(length, angle)
     }
 
-    /// Project the cylinder to a ring on z=0 plane to keep its periodic nature
-    pub fn project_to_ring(&self, point: Point3, d: Float) -> Point2 {
-        let vector = point - self.origin;
-        let z = vector.dot(self.axis);
-        let ratio = d / (d - z);
-        let x = vector.dot(self.ref_dir) * ratio;
-        let y = vector.dot(self.axis.cross(self.ref_dir)) * ratio;
-        Point2::new(x, y)
-    }
-
-    pub fn generate_point_from_ring(&self, point: Point2, d: Float) -> Point3 {
-        let ratio = self.radius / point.length();
-        let x = self.ref_dir * (point.x * ratio);
-        let y = self.axis.cross(self.ref_dir) * (point.y * ratio);
-        let z = self.axis * (d * (1.0 - ratio));
-        self.origin + x + y + z
+    /// Rational B-spline of the cylinder between the lengths along the axis,
+    /// exact at the knots of the angle
+    pub fn to_nurbs(&self, (l0, l1): (Float, Float)) -> BSplineSurface<Point4> {
+        let (arc, angle_knots) = unit_arc((0.0, crate::consts::TAU));
+        let x_axis = self.ref_dir * self.radius;
+        let y_axis = self.axis.cross(self.ref_dir) * self.radius;
+        let points = [l0, l1]
+            .iter()
+            .flat_map(|&length| {
+                let center = self.origin + self.axis * length;
+                arc.iter()
+                    .map(move |p| (center + x_axis * p.x + y_axis * p.y).extend(1.0) * p.z)
+            })
+            .collect();
+        BSplineSurface {
+            control_points: Grid::from_vec(points, arc.len()),
+            knots: (KnotVector(vec![l0, l0, l1, l1]), angle_knots),
+            degree: (1, 2),
+        }
     }
 }
 
@@ -51,137 +56,146 @@ impl Surface for Cylinder {
         self.origin + self.axis * length + rotation * self.ref_dir * self.radius
     }
 
-    fn trim(&self, bounds: &[EdgeLoop]) -> TriangleMesh {
-        let mut polygons = Vec::with_capacity(bounds.len() + 1);
-        polygons.push(0);
-        let mut end = 0;
-        let mut vertices = bounds
-            .iter()
-            .map(|bound| {
-                let polygon = bound.to_polygon();
-                if polygon.len() > 0 {
-                    end += polygon.len();
-                    polygons.push(end);
-                }
-                polygon
-            })
-            .flatten()
-            .collect::<Vec<_>>();
-        let points: Vec<Point2> = vertices.iter().map(|v| self.project(*v)).collect();
-        let (min_z, max_z) = utils::get_min_max_by_key(&points, |p| p.x);
-        let d = (max_z - min_z) * 2.0;
+    /// Normals point away from the axis
+    fn derivatives(&self, length: Float, angle: Float) -> SurfaceDerivatives {
+        let radial = Quat::from_axis_angle(self.axis, angle) * self.ref_dir;
+        SurfaceDerivatives {
+            point: self.get_point(length, angle),
+            du: self.axis,
+            dv: self.axis.cross(radial) * self.radius,
+            duu: Vec3::ZERO,
+            duv: Vec3::ZERO,
+            dvv: -radial * self.radius,
+        }
+    }
 
-        let mut points: Vec<Point2> = vertices
+    fn get_normals(&self, params: &[Point2]) -> Vec<Vec3> {
+        params
             .iter()
-            .map(|v| self.project_to_ring(*v, d))
-            .collect();
-        let boundary_point_count = points.len();
+            .map(|p| Quat::from_axis_angle(self.axis, p.y) * self.ref_dir)
+            .collect()
+    }
 
-        if polygons.len() == 2 {
-            if utils::is_polygon_counter_clockwise(&points) {
-                let (points, triangles) = utils::generate_triangular_mesh(&points, &polygons);
-                vertices.extend(
-                    points[boundary_point_count..]
-                        .iter()
-                        .map(|&p| self.generate_point_from_ring(p, d)),
-                );
+    fn periods(&self) -> (Option<Float>, Option<Float>) {
+        (None, Some(crate::consts::TAU))
+    }
 
-                return TriangleMesh {
-                    vertices,
-                    triangles,
-                    normals: Vec::new(),
-                }
-                .reverse_winding_direction();
-            } else {
-                vertices.reverse();
-                points.reverse();
-                let (points, triangles) = utils::generate_triangular_mesh(&points, &polygons);
-                vertices.extend(
-                    points[boundary_point_count..]
-                        .iter()
-                        .map(|&p| self.generate_point_from_ring(p, d)),
-                );
+    fn nearest_parameters(&self, point: Point3) -> Option<Point2> {
+        Some(self.project(point))
+    }
 
-                return TriangleMesh {
-                    vertices,
-                    triangles,
-                    normals: Vec::new(),
-                };
-            }
-        } else if polygons.len() > 2 {
-            // triangulate polygon with holes
-            let (points, triangles) = utils::generate_triangular_mesh(&points, &polygons);
-            vertices.extend(
-                points[boundary_point_count..]
-                    .iter()
-                    .map(|p| self.get_point(p.x, p.y)),
-            );
-            return TriangleMesh {
-                vertices,
-                triangles,
-                normals: Vec::new(),
-            };
-        } else {
-            return TriangleMesh::new();
-        }
+    fn trim_polygons(&self, bounds: &[Vec<Point3>]) -> TriangleMesh {
+        let params = project_polygons(bounds, |point| self.project(point));
+        self.trim_parameter_polygons(bounds, &params)
+    }
+
+    fn trim_parameter_polygons(
+        &self,
+        bounds: &[Vec<Point3>],
+        params: &[Vec<Point2>],
+    ) -> TriangleMesh {
+        trim_in_parameter_space(self, bounds, params)
     }
 }
 
 #[test]
 fn test_cylinder_projection() {
-    use crate::face::Face;
-    use crate::utils::Tolerance;
-    let cylinder = crate::surface::SurfacePatch {
-        surface: Cylinder {
-            origin: Point3::new(-1.899979869, -30.57252185, 54.63893742),
-            axis: Vec3::X,
-            ref_dir: Vec3::new(0.0, 0.568151535920372, -0.8229239528846649),
-            radius: 3.0,
-        },
-        parameter_range: ((0.0, 10.0), (0.0, crate::consts::PI)),
-        parameter_division: (16, 16),
+    use crate::utils::{self, Tolerance};
+    let cylinder = Cylinder {
+        origin: Point3::new(-1.899979869, -30.57252185, 54.63893742),
+        axis: Vec3::X,
+        ref_dir: Vec3::new(0.0, 0.568151535920372, -0.8229239528846649),
+        radius: 3.0,
     };
-    let mesh = cylinder.get_triangle_mesh();
-    mesh.save_as_obj("tmp/mesh.obj").unwrap();
-
-    let grid = cylinder.get_point_grid();
-    // look from inside, extract boundary points in ccw order
-    // so that projected points are in ccw order
-    let mut vertices = Vec::<Point3>::new();
-    vertices.extend(grid.iter_row(0).rev());
-    vertices.extend(grid.iter_col(0));
-    vertices.extend(grid.iter_row(grid.rows() - 1));
-    vertices.extend(grid.iter_col(grid.cols() - 1).rev());
-    vertices.dedup_by(|a, b| a.distance_squared(*b).near(0.0));
-    vertices.pop();
-
-    let d = 20.0;
-    let points = vertices
+    let (length, angle) = (10.0, crate::consts::PI);
+    // half cylinder bounded counter-clockwise seen from outside
+    let mut params = Vec::new();
+    params.extend(
+        utils::uniform_divide((0.0, angle), 16)
+            .into_iter()
+            .map(|a| Point2::new(0.0, a)),
+    );
+    params.extend(
+        utils::uniform_divide((0.0, length), 16)
+            .into_iter()
+            .map(|l| Point2::new(l, angle)),
+    );
+    params.extend(
+        utils::uniform_divide((0.0, angle), 16)
+            .into_iter()
+            .rev()
+            .map(|a| Point2::new(length, a)),
+    );
+    params.extend(
+        utils::uniform_divide((0.0, length), 16)
+            .into_iter()
+            .rev()
+            .map(|l| Point2::new(l, 0.0)),
+    );
+    params.dedup_by(|a, b| a.distance_squared(*b).near(0.0));
+    params.pop();
+    let bound = params
         .iter()
-        .map(|&v| cylinder.surface.project_to_ring(v, d))
+        .map(|p| cylinder.get_point(p.x, p.y))
         .collect::<Vec<_>>();
 
-    let (points, triangles) = utils::generate_triangular_mesh(&points, &[0, points.len()]);
-    let ring = TriangleMesh {
-        vertices: points.iter().map(|p| p.extend(0.0)).collect(),
-        triangles,
-        normals: Vec::new(),
+    let mesh = cylinder.trim_polygons(std::slice::from_ref(&bound));
+    assert!(bound
+        .iter()
+        .all(|p| mesh.vertices[..bound.len()].contains(p)));
+    let mut area = 0.0;
+    for triangle in mesh.triangles.chunks(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| mesh.vertices[triangle[i] as usize]);
+        let normal = (b - a).cross(c - a);
+        let radial = cylinder.get_normals(&[cylinder.project((a + b + c) / 3.0)])[0];
+        assert!(normal.dot(radial) > 0.0);
+        area += normal.length() / 2.0;
     }
-    .reverse_winding_direction();
-    ring.save_as_obj("tmp/ring.obj").unwrap();
+    assert_eq!(mesh.normals.len(), mesh.vertices.len());
+    for (vertex, normal) in mesh.vertices.iter().zip(&mesh.normals) {
+        let param = cylinder.project(*vertex);
+        let point = cylinder.get_point(param.x, param.y);
+        assert!(vertex.distance(point).near(0.0));
+        assert!(normal.distance(cylinder.get_normals(&[param])[0]) < 1e-9);
+    }
+    assert!((area / (cylinder.radius * angle * length) - 1.0).abs() < 0.02);
 
-    let cylinder = TriangleMesh {
-        vertices: points
-            .iter()
-            .map(|&p| cylinder.surface.generate_point_from_ring(p, d))
-            .collect(),
-        triangles: ring.triangles,
-        normals: Vec::new(),
+    // a strip of a wide cylinder is much shorter along the axis than around it in parameters,
+    // triangles are still well shaped on the surface
+    let cylinder = Cylinder {
+        radius: 100.0,
+        ..cylinder
     };
-
-    cylinder.save_as_obj("tmp/cylinder.obj").unwrap();
-
-    for i in 0..vertices.len() {
-        assert!((vertices[i] - cylinder.vertices[i]).length().near(0.0));
+    let (length, angle) = (1.0, 0.2);
+    let mut params = Vec::new();
+    let corners = [(0.0, 0.0), (0.0, angle), (length, angle), (length, 0.0)];
+    for (i, &(l0, a0)) in corners.iter().enumerate() {
+        let (l1, a1) = corners[(i + 1) % 4];
+        let division = if l0 == l1 { 80 } else { 4 };
+        params.extend((0..division).map(|k| {
+            let t = k as Float / division as Float;
+            Point2::new(l0 + (l1 - l0) * t, a0 + (a1 - a0) * t)
+        }));
     }
+    let bound = params
+        .iter()
+        .map(|p| cylinder.get_point(p.x, p.y))
+        .collect::<Vec<_>>();
+    let mesh = cylinder.trim_polygons(std::slice::from_ref(&bound));
+    assert!(mesh.vertices.len() > bound.len());
+    let mut min_angle = crate::consts::PI;
+    for triangle in mesh.triangles.chunks(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| mesh.vertices[triangle[i] as usize]);
+        for &(p, q, r) in &[(a, b, c), (b, c, a), (c, a, b)] {
+            min_angle = min_angle.min((q - p).angle_between(r - p));
+        }
+        let radial = cylinder.get_normals(&[cylinder.project((a + b + c) / 3.0)])[0];
+        assert!((b - a).cross(c - a).dot(radial) > 0.0);
+    }
+    assert!(min_angle > crate::consts::PI / 9.0, "{}", min_angle);
+
+    // a bound through undefined points is skipped instead of panicking
+    let mut bound = bound;
+    bound[3] = Point3::splat(Float::NAN);
+    assert!(cylinder.trim_polygons(&[bound]).triangles.is_empty());
 }
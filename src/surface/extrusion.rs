@@ -0,0 +1,79 @@
+use crate::curve::Curve;
+use crate::surface::{project_polygons, trim_in_parameter_space, Surface};
+use crate::{Float, Point2, Point3, TriangleMesh, Vec3};
+
+/// Surface swept by moving a curve along a direction.
+#[derive(Debug)]
+pub struct SurfaceOfLinearExtrusion<C: Curve> {
+    pub curve: C,
+    /// Extrusion vector for unit parameter
+    pub direction: Vec3,
+}
+
+impl<C: Curve> SurfaceOfLinearExtrusion<C> {
+    pub fn project(&self, point: Point3) -> Point2 {
+        let length_squared = self.direction.length_squared();
+        let mut v = 0.0;
+        let mut u = self.curve.project(point);
+        // alternate projections to the curve and along the direction
+        for _ in 0..8 {
+            let next_v = (point - self.curve.get_point(u)).dot(self.direction) / length_squared;
+            u = self.curve.project(point - self.direction * next_v);
+            if (next_v - v).abs() * length_squared.sqrt() < 1e-9 {
+                v = next_v;
+                break;
+            }
+            v = next_v;
+        }
+        Point2::new(u, v)
+    }
+}
+
+impl<C: Curve> Surface for SurfaceOfLinearExtrusion<C> {
+    fn get_point(&self, u: Float, v: Float) -> Point3 {
+        self.curve.get_point(u) + self.direction * v
+    }
+
+    fn periods(&self) -> (Option<Float>, Option<Float>) {
+        (self.curve.period(), None)
+    }
+
+    fn nearest_parameters(&self, point: Point3) -> Option<Point2> {
+        Some(self.project(point))
+    }
+
+    fn trim_polygons(&self, bounds: &[Vec<Point3>]) -> TriangleMesh {
+        let params = project_polygons(bounds, |point| self.project(point));
+        self.trim_parameter_polygons(bounds, &params)
+    }
+
+    fn trim_parameter_polygons(
+        &self,
+        bounds: &[Vec<Point3>],
+        params: &[Vec<Point2>],
+    ) -> TriangleMesh {
+        trim_in_parameter_space(self, bounds, params)
+    }
+}
+
+#[test]
+fn test_extrusion_projection() {
+    use crate::curve::Circle;
+    let extrusion = SurfaceOfLinearExtrusion {
+        curve: Circle {
+            center: Point3::ZERO,
+            radius: 2.0,
+            axis: Vec3::Z,
+            ref_dir: Vec3::X,
+        },
+        direction: Vec3::new(0.0, 0.0, 3.0),
+    };
+    let point = extrusion.get_point(crate::consts::PI / 2.0, 0.5);
+    assert!(
+        point.distance(Point3::new(0.0, 2.0, 1.5)) < 1e-9,
+        "{:?}",
+        point
+    );
+    let param = extrusion.project(point);
+    assert!(param.distance(Point2::new(crate::consts::PI / 2.0, 0.5)) < 1e-9);
+}
@@ -0,0 +1,200 @@
+use super::{BSplineSurface, Plane, Surface};
+use crate::{utils, Float, Grid, KnotVector, Point2, Point3};
+
+/// Settings of fitting a B-spline surface to scattered points
+#[derive(Debug, Clone)]
+pub struct SurfaceFitting {
+    pub degree: (usize, usize),
+    /// Numbers of control points along u and v
+    pub size: (usize, usize),
+    /// Weight of the bending energy of the control net per point and control point,
+    /// keeps the surface smooth where the points are sparse
+    pub smoothing: Float,
+    /// Times to refit after projecting the points to the fitted surface
+    pub iterations: usize,
+}
+
+/// Fitted surface with the parameters of the points on it and their distances to it
+#[derive(Debug)]
+pub struct FittedSurface {
+    pub surface: BSplineSurface<Point3>,
+    pub parameters: Vec<Point2>,
+    pub max_deviation: Float,
+    pub rms_deviation: Float,
+}
+
+impl SurfaceFitting {
+    pub fn new(degree: (usize, usize), size: (usize, usize)) -> Self {
+        assert!(size.0 > degree.0 && size.1 > degree.1);
+        SurfaceFitting {
+            degree,
+            size,
+            smoothing: 1e-4,
+            iterations: 3,
+        }
+    }
+
+    /// Fit starting from the parameters of the points projected to the plane
+    pub fn fit_on_plane(&self, points: &[Point3], plane: &Plane) -> Option<FittedSurface> {
+        self.fit(points, points.iter().map(|&p| plane.project(p)).collect())
+    }
+
+    /// Fit starting from the parameters of the points projected to an initial surface
+    pub fn fit_on_surface(
+        &self,
+        points: &[Point3],
+        base: &BSplineSurface<Point3>,
+    ) -> Option<FittedSurface> {
+        self.fit(points, base.project_points(points))
+    }
+
+    /// Fit starting from the parameters of the points, they are scaled to the unit square.
+    /// Returns `None` when the points do not determine the surface.
+    pub fn fit(&self, points: &[Point3], parameters: Vec<Point2>) -> Option<FittedSurface> {
+        assert_eq!(points.len(), parameters.len());
+        let min = parameters
+            .iter()
+            .fold(Point2::splat(Float::MAX), |min, p| min.min(*p));
+        let max = parameters
+            .iter()
+            .fold(Point2::splat(Float::MIN), |max, p| max.max(*p));
+        let scale = (max - min).recip();
+        let mut parameters = parameters
+            .into_iter()
+            .map(|p| ((p - min) * scale).clamp(Point2::ZERO, Point2::ONE))
+            .collect::<Vec<_>>();
+        let mut surface = self.solve(points, &parameters)?;
+        for _ in 0..self.iterations {
+            parameters = surface.project_points(points);
+            surface = self.solve(points, &parameters)?;
+        }
+        let parameters = surface.project_points(points);
+        let squares = points
+            .iter()
+            .zip(&parameters)
+            .map(|(point, p)| point.distance_squared(surface.get_point(p.x, p.y)))
+            .collect::<Vec<_>>();
+        let max_deviation = squares.iter().fold(0.0, |max: Float, &d| max.max(d)).sqrt();
+        let rms_deviation = (squares.iter().sum::<Float>() / squares.len() as Float).sqrt();
+        Some(FittedSurface {
+            surface,
+            parameters,
+            max_deviation,
+            rms_deviation,
+        })
+    }
+
+    /// Control points minimizing the squared deviations at the parameters and the weighted
+    /// second differences of the control net
+    fn solve(&self, points: &[Point3], parameters: &[Point2]) -> Option<BSplineSurface<Point3>> {
+        let (p, q) = self.degree;
+        let (n, m) = self.size;
+        let knots = (
+            KnotVector::uniform_knot(p, n - p),
+            KnotVector::uniform_knot(q, m - q),
+        );
+        let size = n * m;
+        let mut normal = Grid::init(size, size, 0.0);
+        let mut rhs = vec![Point3::ZERO; size];
+        let mut add_row = |row: &[(usize, Float)], weight: Float, point: Point3| {
+            for &(a, value_a) in row {
+                rhs[a] += point * (weight * value_a);
+                for &(b, value_b) in row {
+                    normal[a][b] += weight * value_a * value_b;
+                }
+            }
+        };
+
+        for (point, param) in points.iter().zip(parameters) {
+            let u_basis = knots.0.bspline_basis(p, param.x);
+            let v_basis = knots.1.bspline_basis(q, param.y);
+            let row = u_basis
+                .iter()
+                .enumerate()
+                .filter(|(_, &bu)| bu != 0.0)
+                .flat_map(|(i, &bu)| {
+                    v_basis
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, &bv)| bv != 0.0)
+                        .map(move |(j, &bv)| (i * m + j, bu * bv))
+                })
+                .collect::<Vec<_>>();
+            add_row(&row, 1.0, *point);
+        }
+
+        let weight = self.smoothing * points.len() as Float / size as Float;
+        for i in 0..n {
+            for j in 0..m {
+                let index = i * m + j;
+                if i > 0 && i + 1 < n {
+                    add_row(
+                        &[(index - m, 1.0), (index, -2.0), (index + m, 1.0)],
+                        weight,
+                        Point3::ZERO,
+                    );
+                }
+                if j > 0 && j + 1 < m {
+                    add_row(
+                        &[(index - 1, 1.0), (index, -2.0), (index + 1, 1.0)],
+                        weight,
+                        Point3::ZERO,
+                    );
+                }
+                if i + 1 < n && j + 1 < m {
+                    let twist = [
+                        (index, 1.0),
+                        (index + 1, -1.0),
+                        (index + m, -1.0),
+                        (index + m + 1, 1.0),
+                    ];
+                    add_row(&twist, 2.0 * weight, Point3::ZERO);
+                }
+            }
+        }
+
+        let control_points = utils::solve_linear_system(normal, rhs)?;
+        Some(BSplineSurface {
+            control_points: Grid::from_vec(control_points, m),
+            knots,
+            degree: (p as u8, q as u8),
+        })
+    }
+}
+
+#[test]
+fn test_surface_fitting() {
+    use crate::Vec3;
+
+    // scattered points on a wavy sheet tilted in space
+    let mut seed = 12345_u64;
+    let mut random = || {
+        seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (seed >> 11) as Float / (1u64 << 53) as Float
+    };
+    let (x_axis, y_axis) = (Vec3::new(0.6, 0.0, 0.8), Vec3::Y);
+    let z_axis = x_axis.cross(y_axis);
+    let sheet = |x: Float, y: Float| {
+        let z = 0.3 * (x * 1.5).sin() * (y * 0.8).cos();
+        Point3::new(1.0, 2.0, 3.0) + x_axis * x + y_axis * y + z_axis * z
+    };
+    let points = (0..800)
+        .map(|_| sheet(random() * 4.0, random() * 3.0))
+        .collect::<Vec<_>>();
+
+    let plane = Plane::best_fit(&points);
+    assert!(plane.normal.dot(z_axis).abs() > 0.99);
+    let fitting = SurfaceFitting::new((3, 3), (8, 8));
+    let fitted = fitting.fit_on_plane(&points, &plane).unwrap();
+    assert!(fitted.max_deviation < 5e-3);
+    assert!(fitted.rms_deviation <= fitted.max_deviation);
+    for (point, param) in points.iter().zip(&fitted.parameters) {
+        let distance = point.distance(fitted.surface.get_point(param.x, param.y));
+        assert!(distance <= fitted.max_deviation + 1e-12);
+    }
+
+    let refitted = fitting.fit_on_surface(&points, &fitted.surface).unwrap();
+    assert!(refitted.max_deviation < 5e-3);
+}
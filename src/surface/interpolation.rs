@@ -0,0 +1,208 @@
+use super::BSplineSurface;
+use crate::curve::{
+    interpolate_control_points, interpolation_knots, BSplineCurve, ControlPoint, Parameterization,
+};
+use crate::{utils, Float, Grid, KnotVector, Point3};
+
+/// Parameters averaged over the lines of points
+fn averaged_parameters<P: ControlPoint>(
+    lines: impl Iterator<Item = Vec<P>>,
+    parameterization: Parameterization,
+) -> Vec<Float> {
+    let mut sum = Vec::new();
+    let mut count = 0;
+    for line in lines {
+        let parameters = parameterization.parameters(&line);
+        sum.resize(parameters.len(), 0.0);
+        for (s, u) in sum.iter_mut().zip(parameters) {
+            *s += u;
+        }
+        count += 1;
+    }
+    sum.into_iter().map(|s| s / count as Float).collect()
+}
+
+impl BSplineSurface<Point3> {
+    /// Interpolate the grid of points, rows along u and columns along v,
+    /// `None` when points coincide
+    pub fn interpolate(
+        points: &Grid<Point3>,
+        degree: (usize, usize),
+        parameterization: Parameterization,
+    ) -> Option<Self> {
+        let (rows, cols) = points.size();
+        assert!(rows > degree.0 && cols > degree.1 && degree.0 >= 1 && degree.1 >= 1);
+        let columns = (0..cols).map(|j| points.iter_col(j).copied().collect::<Vec<_>>());
+        let u_parameters = averaged_parameters(columns, parameterization);
+        let row_lines = (0..rows).map(|i| points.iter_row(i).copied().collect::<Vec<_>>());
+        let v_parameters = averaged_parameters(row_lines, parameterization);
+        let u_knots = interpolation_knots(&u_parameters, degree.0);
+        let v_knots = interpolation_knots(&v_parameters, degree.1);
+
+        // interpolate every row along v, then every column of the results along u
+        let mut control_points = Vec::with_capacity(rows * cols);
+        for i in 0..rows {
+            let row = points.iter_row(i).copied().collect::<Vec<_>>();
+            let row = interpolate_control_points(&row, &v_parameters, &v_knots, degree.1)?;
+            control_points.extend(row);
+        }
+        let mut control_points = Grid::from_vec(control_points, cols);
+        for j in 0..cols {
+            let column = control_points.iter_col(j).copied().collect::<Vec<_>>();
+            let column = interpolate_control_points(&column, &u_parameters, &u_knots, degree.0)?;
+            for (target, point) in control_points.iter_col_mut(j).zip(column) {
+                *target = point;
+            }
+        }
+        Some(BSplineSurface {
+            control_points,
+            knots: (u_knots, v_knots),
+            degree: (degree.0 as u8, degree.1 as u8),
+        })
+    }
+}
+
+impl<P: ControlPoint> BSplineSurface<P> {
+    /// Skin the section curves into a surface passing through them, the sections run
+    /// along u over `[0, 1]` and follow each other along v. The sections are raised to
+    /// a common degree and refined to common knots first.
+    pub fn skin(
+        sections: &[BSplineCurve<P>],
+        degree: usize,
+        parameterization: Parameterization,
+    ) -> Option<Self> {
+        assert!(degree >= 1 && sections.len() > degree);
+        let sections = compatible_curves(sections);
+        let n = sections[0].control_points.len();
+        let lines = (0..n).map(|i| {
+            sections
+                .iter()
+                .map(|section| section.control_points[i])
+                .collect::<Vec<_>>()
+        });
+        let v_parameters = averaged_parameters(lines, parameterization);
+        let v_knots = interpolation_knots(&v_parameters, degree);
+        let mut control_points = Vec::with_capacity(n * sections.len());
+        for i in 0..n {
+            let line = sections
+                .iter()
+                .map(|section| section.control_points[i])
+                .collect::<Vec<_>>();
+            control_points.extend(interpolate_control_points(
+                &line,
+                &v_parameters,
+                &v_knots,
+                degree,
+            )?);
+        }
+        Some(BSplineSurface {
+            control_points: Grid::from_vec(control_points, sections.len()),
+            knots: (sections[0].knots.clone(), v_knots),
+            degree: (sections[0].degree, degree as u8),
+        })
+    }
+}
+
+/// The curves with knots mapped to `[0, 1]`, raised to the highest degree and
+/// refined to the union of their knots
+fn compatible_curves<P: ControlPoint>(curves: &[BSplineCurve<P>]) -> Vec<BSplineCurve<P>> {
+    let degree = curves.iter().map(|curve| curve.degree()).max().unwrap();
+    let mut curves = curves
+        .iter()
+        .map(|curve| {
+            let (start, end) = curve.knots.range();
+            let scale = utils::inv_or_zero(end - start);
+            let knots = curve.knots.iter().map(|&u| (u - start) * scale).collect();
+            BSplineCurve {
+                control_points: curve.control_points.clone(),
+                knots: KnotVector(knots),
+                degree: curve.degree,
+            }
+            .elevate_degree(degree - curve.degree())
+        })
+        .collect::<Vec<_>>();
+
+    // the largest multiplicity of each interior knot value among the curves
+    let mut merged: Vec<(Float, usize)> = Vec::new();
+    for curve in &curves {
+        let (values, multiplicities) = curve.knots.to_values_and_multiplicities();
+        let last = values.len() - 1;
+        for (&value, &multiplicity) in values[1..last].iter().zip(&multiplicities[1..last]) {
+            match merged
+                .iter_mut()
+                .find(|(u, _)| (u - value).abs() <= utils::TOLERANCE)
+            {
+                Some(entry) => entry.1 = entry.1.max(multiplicity),
+                None => merged.push((value, multiplicity)),
+            }
+        }
+    }
+    for curve in &mut curves {
+        let mut knots = Vec::new();
+        for &(value, multiplicity) in &merged {
+            let existing = curve
+                .knots
+                .iter()
+                .filter(|&&u| (u - value).abs() <= utils::TOLERANCE)
+                .count();
+            knots.extend(std::iter::repeat_n(value, multiplicity - existing));
+        }
+        curve.refine_knots(&knots);
+    }
+    curves
+}
+
+#[test]
+fn test_interpolation_and_skinning() {
+    use crate::curve::Curve;
+    use crate::surface::Surface;
+
+    let sheet = |x: Float, y: Float| Point3::new(x, y, (x * 0.7).sin() * (y * 1.3).cos());
+    let points = Grid::from_vec(
+        (0..6)
+            .flat_map(|i| {
+                (0..5).map(move |j| sheet(i as Float * 0.8, j as Float * 0.5 + 0.1 * i as Float))
+            })
+            .collect(),
+        5,
+    );
+    let surface =
+        BSplineSurface::interpolate(&points, (3, 2), Parameterization::ChordLength).unwrap();
+    let (us, vs) = (surface.knots.0.range(), surface.knots.1.range());
+    assert_eq!((us, vs), ((0.0, 1.0), (0.0, 1.0)));
+    for point in points.iter() {
+        let param = surface.project_points(&[*point])[0];
+        assert!(surface.get_point(param.x, param.y).distance(*point) < 1e-6);
+    }
+
+    // duct through sections of different degrees and knots
+    let section = |z: Float, radius: Float, degree: usize, count: usize| {
+        let control_points = (0..count)
+            .map(|i| {
+                let angle = i as Float / (count - 1) as Float * crate::consts::PI;
+                Point3::new(angle.cos() * radius, angle.sin() * radius, z)
+            })
+            .collect::<Vec<_>>();
+        let knots = KnotVector::uniform_knot(degree, count - degree);
+        BSplineCurve {
+            control_points,
+            knots,
+            degree: degree as u8,
+        }
+    };
+    let sections = vec![
+        section(0.0, 1.0, 2, 5),
+        section(1.0, 1.5, 3, 6),
+        section(2.5, 1.2, 2, 4),
+        section(3.0, 0.8, 3, 4),
+    ];
+    let surface = BSplineSurface::skin(&sections, 2, Parameterization::ChordLength).unwrap();
+    assert_eq!(surface.degree, (3, 2));
+    // every section is an iso-curve of the surface
+    for curve in &sections {
+        let v = surface.project_points(&[curve.get_point(0.3)])[0].y;
+        for u in utils::uniform_divide((0.0, 1.0), 20) {
+            assert!(surface.get_point(u, v).distance(curve.get_point(u)) < 1e-6);
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use crate::surface::{find_nearest_parameters, project_polygons, trim_in_parameter_space, Surface};
+use crate::{Float, Point2, Point3, TriangleMesh, Vec3};
+
+/// Surface at a constant distance from a basis surface along its normal.
+#[derive(Debug)]
+pub struct OffsetSurface<S: Surface> {
+    pub basis: S,
+    /// Signed distance along the normal of basis surface
+    pub distance: Float,
+    /// Parameter range of basis surface searched by projection
+    pub parameter_range: ((Float, Float), (Float, Float)),
+}
+
+impl<S: Surface> OffsetSurface<S> {
+    fn basis_normal(&self, u: Float, v: Float) -> Vec3 {
+        self.basis
+            .get_normals(&[Point2::new(u, v)])
+            .pop()
+            .unwrap_or_else(|| self.basis.derivatives(u, v).normal())
+    }
+
+    /// Points on the offset surface share parameters with their nearest basis points,
+    /// the parameter range is only searched when the basis can not project points itself
+    pub fn project(&self, point: Point3) -> Point2 {
+        self.basis.nearest_parameters(point).unwrap_or_else(|| {
+            find_nearest_parameters(self, point, self.parameter_range, (16, 16), 16)
+        })
+    }
+}
+
+impl<S: Surface> Surface for OffsetSurface<S> {
+    fn get_point(&self, u: Float, v: Float) -> Point3 {
+        self.basis.get_point(u, v) + self.basis_normal(u, v) * self.distance
+    }
+
+    fn get_normals(&self, params: &[Point2]) -> Vec<Vec3> {
+        params.iter().map(|p| self.basis_normal(p.x, p.y)).collect()
+    }
+
+    fn periods(&self) -> (Option<Float>, Option<Float>) {
+        self.basis.periods()
+    }
+
+    fn poles(&self) -> Vec<Float> {
+        self.basis.poles()
+    }
+
+    fn nearest_parameters(&self, point: Point3) -> Option<Point2> {
+        Some(self.project(point))
+    }
+
+    fn trim_polygons(&self, bounds: &[Vec<Point3>]) -> TriangleMesh {
+        let params = project_polygons(bounds, |point| self.project(point));
+        self.trim_parameter_polygons(bounds, &params)
+    }
+
+    fn trim_parameter_polygons(
+        &self,
+        bounds: &[Vec<Point3>],
+        params: &[Vec<Point2>],
+    ) -> TriangleMesh {
+        trim_in_parameter_space(self, bounds, params)
+    }
+}
+
+#[test]
+fn test_offset_projection() {
+    use crate::surface::{Cylinder, Plane};
+    let plane = OffsetSurface {
+        basis: Plane {
+            origin: Point3::ZERO,
+            normal: Vec3::Z,
+            u_axis: Vec3::X,
+            v_axis: Vec3::Y,
+        },
+        distance: 0.5,
+        parameter_range: ((0.0, 1.0), (0.0, 1.0)),
+    };
+    let point = plane.get_point(3.0, -2.0);
+    assert!(
+        point.distance(Point3::new(3.0, -2.0, 0.5)) < 1e-9,
+        "{:?}",
+        point
+    );
+    assert!(plane.project(point).distance(Point2::new(3.0, -2.0)) < 1e-9);
+
+    // a face beyond the nominal parameter range of the basis keeps its extents
+    let bound = [(-1.0, -1.0), (3.0, -1.0), (3.0, 3.0), (-1.0, 3.0)]
+        .iter()
+        .map(|&(x, y)| Point3::new(x, y, 0.5))
+        .collect::<Vec<_>>();
+    let mesh = plane.trim_polygons(&[bound]);
+    assert!(mesh.triangle_count() > 0);
+    let (min, max) = mesh.vertices.iter().fold(
+        (Point3::splat(Float::MAX), Point3::splat(Float::MIN)),
+        |(min, max), &p| (min.min(p), max.max(p)),
+    );
+    assert!(
+        min.distance(Point3::new(-1.0, -1.0, 0.5)) < 1e-9,
+        "{:?}",
+        min
+    );
+    assert!(max.distance(Point3::new(3.0, 3.0, 0.5)) < 1e-9, "{:?}", max);
+
+    let cylinder = OffsetSurface {
+        basis: Cylinder {
+            origin: Point3::ZERO,
+            axis: Vec3::Z,
+            ref_dir: Vec3::X,
+            radius: 1.0,
+        },
+        distance: 1.0,
+        parameter_range: ((0.0, 1.0), (0.0, crate::consts::TAU)),
+    };
+    let point = cylinder.get_point(5.0, crate::consts::PI);
+    assert!(
+        point.distance(Point3::new(-2.0, 0.0, 5.0)) < 1e-9,
+        "{:?}",
+        point
+    );
+    let param = cylinder.project(point);
+    assert!(param.distance(Point2::new(5.0, crate::consts::PI)) < 1e-9);
+}
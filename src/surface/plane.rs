@@ -1,6 +1,6 @@
-use crate::surface::{EdgeLoop, Surface};
+use crate::surface::{BSplineSurface, Surface, SurfaceDerivatives};
 use crate::utils;
-use crate::{Float, Point2, Point3, TriangleMesh, Vec3};
+use crate::{Float, Grid, KnotVector, Point2, Point3, Point4, TriangleMesh, Vec3};
 
 #[derive(Debug)]
 pub struct Plane {
@@ -11,10 +11,52 @@ pub struct Plane {
 }
 
 impl Plane {
+    /// Plane through the centroid minimizing squared distances to the points,
+    /// `u_axis` is along the largest spread of the points
+    pub fn best_fit(points: &[Point3]) -> Plane {
+        let origin = points.iter().sum::<Point3>() / points.len() as Float;
+        let mut covariance = [[0.0; 3]; 3];
+        for point in points {
+            let d: [Float; 3] = (*point - origin).into();
+            for (i, row) in covariance.iter_mut().enumerate() {
+                for (j, value) in row.iter_mut().enumerate() {
+                    *value += d[i] * d[j];
+                }
+            }
+        }
+        let [(_, normal), _, (_, u_axis)] = utils::symmetric_eigen3(covariance);
+        Plane {
+            origin,
+            normal,
+            u_axis,
+            v_axis: normal.cross(u_axis),
+        }
+    }
+
     pub fn project(&self, point: Point3) -> Point2 {
         let vector = point - self.origin;
         Point2::new(vector.dot(self.u_axis), vector.dot(self.v_axis))
     }
+
+    /// Rational B-spline of the rectangle over the parameter ranges
+    pub fn to_nurbs(
+        &self,
+        (u0, u1): (Float, Float),
+        (v0, v1): (Float, Float),
+    ) -> BSplineSurface<Point4> {
+        let points = [(u0, v0), (u0, v1), (u1, v0), (u1, v1)]
+            .iter()
+            .map(|&(u, v)| self.get_point(u, v).extend(1.0))
+            .collect();
+        BSplineSurface {
+            control_points: Grid::from_vec(points, 2),
+            knots: (
+                KnotVector(vec![u0, u0, u1, u1]),
+                KnotVector(vec![v0, v0, v1, v1]),
+            ),
+            degree: (1, 1),
+        }
+    }
 }
 
 impl Surface for Plane {
@@ -22,71 +64,57 @@ impl Surface for Plane {
         self.origin + self.u_axis * u + self.v_axis * v
     }
 
-    fn trim(&self, bounds: &[EdgeLoop]) -> TriangleMesh {
+    fn derivatives(&self, u: Float, v: Float) -> SurfaceDerivatives {
+        SurfaceDerivatives {
+            point: self.get_point(u, v),
+            du: self.u_axis,
+            dv: self.v_axis,
+            duu: Vec3::ZERO,
+            duv: Vec3::ZERO,
+            dvv: Vec3::ZERO,
+        }
+    }
+
+    fn get_normals(&self, params: &[Point2]) -> Vec<Vec3> {
+        vec![self.normal; params.len()]
+    }
+
+    fn nearest_parameters(&self, point: Point3) -> Option<Point2> {
+        Some(self.project(point))
+    }
+
+    fn trim_polygons(&self, bounds: &[Vec<Point3>]) -> TriangleMesh {
         let mut polygons = Vec::with_capacity(bounds.len() + 1);
         polygons.push(0);
         let mut end = 0;
-        let mut vertices = bounds
+        let vertices = bounds
             .iter()
-            .map(|bound| {
-                let polygon = bound.to_polygon();
-                if polygon.len() > 0 {
+            .flat_map(|bound| {
+                let polygon = bound.clone();
+                if !polygon.is_empty() {
                     end += polygon.len();
                     polygons.push(end);
                 }
                 polygon
             })
-            .flatten()
             .collect::<Vec<_>>();
         let points: Vec<Point2> = vertices.iter().map(|v| self.project(*v)).collect();
 
+        if polygons.len() < 2 {
+            return TriangleMesh::new();
+        }
         if polygons.len() == 2 {
             let (vertex_indices, concave_points) = utils::compute_vertex_convexity(&points);
-
             if vertex_indices.len() == concave_points.len() {
                 // clockwise polygon
-                // dbg!(concave_points.len());
                 return TriangleMesh::new();
             }
-
-            let triangles = if concave_points.is_empty() {
-                // convex polygon
-                let n = vertices.len();
-                let center = vertices.iter().sum::<Point3>() / (n as Float);
-                vertices.push(center);
-                let mut triangles = Vec::with_capacity(n * 3);
-                for i in 0..n - 1 {
-                    triangles.push(n as u32);
-                    triangles.push(i as u32);
-                    triangles.push(i as u32 + 1);
-                }
-                triangles.push(n as u32);
-                triangles.push(n as u32 - 1);
-                triangles.push(0);
-                triangles
-            } else {
-                // polygon has both convex and concave vertices
-                utils::trianglate_polygon(&points, vertex_indices, concave_points)
-            };
-
-            TriangleMesh {
-                vertices,
-                triangles,
-                normals: Vec::new(),
-            }
-        } else if polygons.len() > 2 {
-            // triangulate polygon with holes
-            let mut vertex_indices = utils::merge_polygons(&points, &polygons);
-            let concave_points = utils::find_concave_vertices(&points, &mut vertex_indices);
-            let triangles = utils::trianglate_polygon(&points, vertex_indices, concave_points);
-
-            TriangleMesh {
-                vertices,
-                triangles,
-                normals: Vec::new(),
-            }
-        } else {
-            TriangleMesh::new()
+        }
+        let (_, triangles) = utils::triangulate_domain(&points, &polygons, None);
+        TriangleMesh {
+            normals: vec![self.normal; vertices.len()],
+            vertices,
+            triangles,
         }
     }
 }
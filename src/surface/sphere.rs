@@ -0,0 +1,186 @@
+use crate::consts::{PI, TAU};
+use crate::surface::{project_polygons, trim_in_parameter_space, Surface, SurfaceDerivatives};
+use crate::{Float, Point2, Point3, Quat, TriangleMesh, Vec3};
+
+#[derive(Debug)]
+pub struct Sphere {
+    pub center: Point3,
+    /// Direction to the north pole
+    pub axis: Vec3,
+    /// Normalized direction for longitude start
+    pub ref_dir: Vec3,
+    pub radius: Float,
+}
+
+impl Sphere {
+    /// Get (latitude, longitude) of the point, latitude is in [-π/2, π/2], longitude in [0, 2π)
+    pub fn project(&self, point: Point3) -> Point2 {
+        let vector = point - self.center;
+        let x = vector.dot(self.ref_dir);
+        let y = vector.dot(self.axis.cross(self.ref_dir));
+        let z = vector.dot(self.axis);
+        let latitude = z.atan2((x * x + y * y).sqrt());
+        let longitude = y.atan2(x);
+        let longitude = if longitude >= 0.0 {
+            longitude
+        } else {
+            longitude + TAU
+        };
+        Point2::new(latitude, longitude)
+    }
+}
+
+impl Surface for Sphere {
+    fn get_point(&self, latitude: Float, longitude: Float) -> Point3 {
+        let rotation = Quat::from_axis_angle(self.axis, longitude);
+        let (sin, cos) = latitude.sin_cos();
+        self.center + (rotation * self.ref_dir * cos + self.axis * sin) * self.radius
+    }
+
+    fn derivatives(&self, latitude: Float, longitude: Float) -> SurfaceDerivatives {
+        let radial = Quat::from_axis_angle(self.axis, longitude) * self.ref_dir;
+        let tangent = self.axis.cross(radial);
+        let (sin, cos) = latitude.sin_cos();
+        let outward = (radial * cos + self.axis * sin) * self.radius;
+        SurfaceDerivatives {
+            point: self.center + outward,
+            du: (self.axis * cos - radial * sin) * self.radius,
+            dv: tangent * (cos * self.radius),
+            duu: -outward,
+            duv: tangent * (-sin * self.radius),
+            dvv: radial * (-cos * self.radius),
+        }
+    }
+
+    /// Normals point away from the center
+    fn get_normals(&self, params: &[Point2]) -> Vec<Vec3> {
+        params
+            .iter()
+            .map(|p| (self.get_point(p.x, p.y) - self.center) / self.radius)
+            .collect()
+    }
+
+    fn periods(&self) -> (Option<Float>, Option<Float>) {
+        (None, Some(TAU))
+    }
+
+    fn poles(&self) -> Vec<Float> {
+        vec![-PI / 2.0, PI / 2.0]
+    }
+
+    fn nearest_parameters(&self, point: Point3) -> Option<Point2> {
+        Some(self.project(point))
+    }
+
+    fn trim_polygons(&self, bounds: &[Vec<Point3>]) -> TriangleMesh {
+        let params = project_polygons(bounds, |point| self.project(point));
+        self.trim_parameter_polygons(bounds, &params)
+    }
+
+    fn trim_parameter_polygons(
+        &self,
+        bounds: &[Vec<Point3>],
+        params: &[Vec<Point2>],
+    ) -> TriangleMesh {
+        trim_in_parameter_space(self, bounds, params)
+    }
+}
+
+#[test]
+fn test_sphere_cap_trim() {
+    use crate::utils::Tolerance;
+
+    let sphere = Sphere {
+        center: Point3::new(1.0, 2.0, 3.0),
+        axis: Vec3::Z,
+        ref_dir: Vec3::X,
+        radius: 2.0,
+    };
+    let param = sphere.project(sphere.get_point(0.5, 4.0));
+    assert!(param.x.near(0.5) && param.y.near(4.0));
+
+    // counter-clockwise circle seen from outside bounds the cap around north pole
+    let latitude = 0.6;
+    let bound = (0..32)
+        .map(|i| sphere.get_point(latitude, TAU * i as Float / 32.0))
+        .collect::<Vec<_>>();
+    let mesh = sphere.trim_polygons(std::slice::from_ref(&bound));
+    assert!(mesh.triangle_count() > 32);
+    for vertex in &mesh.vertices {
+        assert!((vertex.distance(sphere.center) - sphere.radius).abs() < 1e-9);
+        assert!(vertex.z - sphere.center.z >= sphere.radius * latitude.sin() - 1e-9);
+    }
+    for triangle in mesh.triangles.chunks(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| mesh.vertices[triangle[i] as usize]);
+        assert!((b - a).cross(c - a).dot(a - sphere.center) > 0.0);
+    }
+
+    // the reversed circle bounds the rest of the sphere
+    let rest = sphere.trim_polygons(&[bound.into_iter().rev().collect()]);
+    assert!(rest.vertices.iter().any(|v| v.z - sphere.center.z < -1.9));
+
+    // a face without loops or bounded by its seam forth and back is the whole sphere
+    let seam = (0..=16)
+        .map(|i| -PI / 2.0 + PI * i as Float / 16.0)
+        .map(|latitude| sphere.get_point(latitude, 0.0))
+        .collect::<Vec<_>>();
+    let seam = seam
+        .iter()
+        .chain(seam.iter().rev().skip(1))
+        .copied()
+        .collect();
+    for bounds in &[vec![], vec![seam]] {
+        let mesh = sphere.trim_polygons(bounds);
+        let mut area = 0.0;
+        for triangle in mesh.triangles.chunks(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| mesh.vertices[triangle[i] as usize]);
+            let normal = (b - a).cross(c - a);
+            assert!(normal.dot(a - sphere.center) > 0.0);
+            area += normal.length() / 2.0;
+        }
+        let sphere_area = 2.0 * TAU * sphere.radius * sphere.radius;
+        assert!((area / sphere_area - 1.0).abs() < 0.02, "{}", area);
+        assert_eq!(mesh.normals.len(), mesh.vertices.len());
+        assert!(mesh
+            .vertices
+            .iter()
+            .zip(&mesh.normals)
+            .all(|(&v, normal)| normal.dot(v - sphere.center) > 0.99 * sphere.radius));
+    }
+}
+
+#[test]
+fn test_sphere_curvature() {
+    use crate::surface::approximate_derivatives;
+
+    let sphere = Sphere {
+        center: Point3::new(1.0, 2.0, 3.0),
+        axis: Vec3::Z,
+        ref_dir: Vec3::X,
+        radius: 2.0,
+    };
+    let exact = sphere.derivatives(0.5, 4.0);
+    let approximate = approximate_derivatives(&sphere, 0.5, 4.0);
+    for (a, b) in [
+        (exact.du, approximate.du),
+        (exact.dv, approximate.dv),
+        (exact.duu, approximate.duu),
+        (exact.duv, approximate.duv),
+        (exact.dvv, approximate.dvv),
+    ] {
+        assert!(a.distance(b) < 1e-5);
+    }
+
+    // (latitude, longitude) normals point to the center, the sphere bends towards them
+    let curvature = exact.curvature().unwrap();
+    assert!((curvature.principal.0 - 0.5).abs() < 1e-9);
+    assert!((curvature.principal.1 - 0.5).abs() < 1e-9);
+    // the outward normals of get_normals see the sphere bend away
+    let curvature = sphere.curvature(0.5, 4.0).unwrap();
+    assert!((curvature.principal.0 + 0.5).abs() < 1e-9);
+    assert!((curvature.principal.1 + 0.5).abs() < 1e-9);
+    assert!((curvature.mean + 0.5).abs() < 1e-9);
+    assert!((curvature.gaussian - 0.25).abs() < 1e-9);
+    assert!(curvature.directions.0.dot(exact.normal()).abs() < 1e-9);
+    assert!(sphere.curvature(PI / 2.0, 0.0).is_none());
+}
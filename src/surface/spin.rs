@@ -1,7 +1,12 @@
-use super::{Curve, Surface};
-use crate::{Float, Point3, Quat, Vec3};
+use super::{BSplineSurface, Curve, Surface, SurfaceDerivatives};
+use crate::consts::TAU;
+use crate::curve::{unit_arc, ToNurbs};
+use crate::surface::{project_polygons, refine_nearest_parameters, trim_with_poles};
+use crate::utils::Tolerance;
+use crate::{Float, Grid, Point2, Point3, Point4, Quat, TriangleMesh, Vec3};
 
 /// Spin surface is created by spin a section curve along a rotation axis.
+/// Its parameters are the section parameter and the angle around the axis.
 #[derive(Debug)]
 pub struct SpinSurface<C: Curve> {
     /// Origin point on the rotation axis
@@ -10,6 +15,159 @@ pub struct SpinSurface<C: Curve> {
     pub axis: Vec3,
     /// Section curve of spin surface
     pub section: C,
+    /// Angles where the revolution starts and ends, the angle is periodic for a whole turn
+    pub angle_range: (Float, Float),
+}
+
+impl<C: Curve> SpinSurface<C> {
+    /// Spin surface of a whole turn
+    pub fn new(origin: Point3, axis: Vec3, section: C) -> Self {
+        SpinSurface {
+            origin,
+            axis,
+            section,
+            angle_range: (0.0, TAU),
+        }
+    }
+
+    fn is_whole_turn(&self) -> bool {
+        let (start, end) = self.angle_range;
+        (end - start).abs().near(TAU) || (end - start).abs() > TAU
+    }
+
+    /// Component of the vector perpendicular to the axis
+    fn radial(&self, vector: Vec3) -> Vec3 {
+        vector - self.axis * vector.dot(self.axis)
+    }
+
+    /// Angle turning the section point at the parameter towards the radial direction,
+    /// outside a partial revolution the nearer end of the angle range is taken
+    fn angle_to(&self, param: Float, direction: Vec3) -> Float {
+        let radial = self.radial(self.section.get_point(param) - self.origin);
+        let angle = self
+            .axis
+            .dot(radial.cross(direction))
+            .atan2(radial.dot(direction));
+        let (start, end) = self.angle_range;
+        let angle = start + (angle - start).rem_euclid(TAU);
+        if self.is_whole_turn() || angle <= end {
+            angle
+        } else if angle - end < start + TAU - angle {
+            end
+        } else {
+            start
+        }
+    }
+
+    /// Parameters `(section parameter, angle)` of the nearest point, the point is turned
+    /// back onto the section from a few angles and the nearest result is refined
+    pub fn project(&self, point: Point3) -> Point2 {
+        let direction = self.radial(point - self.origin);
+        let turn_back = |angle: Float| {
+            let rotation = Quat::from_axis_angle(self.axis, -angle);
+            let param = self
+                .section
+                .project(self.origin + rotation * (point - self.origin));
+            (param, self.angle_to(param, direction))
+        };
+        let (mut param, mut angle) = (0..8)
+            .map(|i| turn_back(i as Float * TAU / 8.0))
+            .min_by(|a, b| {
+                let distance = |(param, angle)| self.get_point(param, angle).distance(point);
+                distance(*a).total_cmp(&distance(*b))
+            })
+            .unwrap();
+        for _ in 0..4 {
+            let next = turn_back(angle);
+            param = next.0;
+            angle = next.1;
+        }
+        let angle_range = if self.is_whole_turn() {
+            (Float::MIN, Float::MAX)
+        } else {
+            self.angle_range
+        };
+        let ranges = ((Float::MIN, Float::MAX), angle_range);
+        refine_nearest_parameters(self, point, Point2::new(param, angle), ranges, 16)
+    }
+
+    /// Section parameters where the section meets the axis, searched from the
+    /// lowest and highest section parameters of each loop
+    fn find_poles(&self, params: &[Vec<Point2>]) -> Vec<Float> {
+        let mut poles: Vec<Float> = Vec::new();
+        for params in params {
+            let (min, max) = params
+                .iter()
+                .fold((Float::MAX, Float::MIN), |(min, max), p| {
+                    (min.min(p.x), max.max(p.x))
+                });
+            for &start in &[min, max] {
+                if let Some(pole) = self.meet_axis(start) {
+                    if !poles.iter().any(|p| p.near(pole)) {
+                        poles.push(pole);
+                    }
+                }
+            }
+        }
+        poles
+    }
+
+    /// Gauss-Newton iteration of the section parameter towards the axis
+    fn meet_axis(&self, mut param: Float) -> Option<Float> {
+        let radial = |param| self.radial(self.section.get_point(param) - self.origin);
+        let mut offset = radial(param);
+        for _ in 0..32 {
+            if offset.length_squared().near2(0.0) {
+                return Some(param);
+            }
+            let derivative = self.radial(self.section.get_derivative(param, 1));
+            if derivative.length_squared() == 0.0 {
+                return None;
+            }
+            // halve the step until the section gets closer to the axis
+            let mut step = -offset.dot(derivative) / derivative.length_squared();
+            loop {
+                if step.abs() <= Float::EPSILON * param.abs().max(1.0) {
+                    return None;
+                }
+                let next = radial(param + step);
+                if next.length_squared() < offset.length_squared() {
+                    param += step;
+                    offset = next;
+                    break;
+                }
+                step *= 0.5;
+            }
+        }
+        None
+    }
+}
+
+impl<C: Curve + ToNurbs> SpinSurface<C> {
+    /// Rational B-spline of the revolution over the angle range, rows follow the section and
+    /// columns the angle which is exact at its knots
+    pub fn to_nurbs(&self) -> BSplineSurface<Point4> {
+        let section = self.section.to_nurbs();
+        let (arc, angle_knots) = unit_arc(self.angle_range);
+        let points = section
+            .control_points
+            .iter()
+            .flat_map(|weighted| {
+                let vector = weighted.truncate() / weighted.w - self.origin;
+                let center = self.origin + self.axis * vector.dot(self.axis);
+                let x_axis = vector - self.axis * vector.dot(self.axis);
+                let y_axis = self.axis.cross(x_axis);
+                arc.iter().map(move |p| {
+                    (center + x_axis * p.x + y_axis * p.y).extend(1.0) * (p.z * weighted.w)
+                })
+            })
+            .collect();
+        BSplineSurface {
+            control_points: Grid::from_vec(points, arc.len()),
+            knots: (section.knots, angle_knots),
+            degree: (section.degree, 2),
+        }
+    }
 }
 
 impl<C: Curve> Surface for SpinSurface<C> {
@@ -20,4 +178,225 @@ impl<C: Curve> Surface for SpinSurface<C> {
         let rotation = Quat::from_axis_angle(self.axis, angle);
         self.origin + parallel_component + rotation * perpendicular_component
     }
+
+    fn derivatives(&self, param: Float, angle: Float) -> SurfaceDerivatives {
+        let rotation = Quat::from_axis_angle(self.axis, angle);
+        // the component along the axis stays and the radial one turns,
+        // turning a vector differentiates it by the angle
+        let spin = |vector: Vec3| {
+            let radial = rotation * self.radial(vector);
+            (
+                vector - self.radial(vector) + radial,
+                self.axis.cross(radial),
+            )
+        };
+        let (vector, dv) = spin(self.section.get_point(param) - self.origin);
+        let (du, duv) = spin(self.section.get_derivative(param, 1));
+        let (duu, _) = spin(self.section.get_derivative(param, 2));
+        SurfaceDerivatives {
+            point: self.origin + vector,
+            du,
+            dv,
+            duu,
+            duv,
+            dvv: self.axis.cross(dv),
+        }
+    }
+
+    fn periods(&self) -> (Option<Float>, Option<Float>) {
+        let angle_period = if self.is_whole_turn() {
+            Some(TAU)
+        } else {
+            None
+        };
+        (self.section.period(), angle_period)
+    }
+
+    fn nearest_parameters(&self, point: Point3) -> Option<Point2> {
+        Some(self.project(point))
+    }
+
+    fn trim_polygons(&self, bounds: &[Vec<Point3>]) -> TriangleMesh {
+        let params = project_polygons(bounds, |point| self.project(point));
+        self.trim_parameter_polygons(bounds, &params)
+    }
+
+    /// Poles are where the section meets the axis, the section is followed from the loops to find them
+    fn trim_parameter_polygons(
+        &self,
+        bounds: &[Vec<Point3>],
+        params: &[Vec<Point2>],
+    ) -> TriangleMesh {
+        trim_with_poles(self, bounds, params, &self.find_poles(params))
+    }
+}
+
+#[test]
+fn test_spin_surface_to_nurbs() {
+    use crate::curve::{Circle, Line, Polyline, TrimmedCurve};
+    use crate::surface::{Cylinder, SweepFrame, SweepSurface};
+
+    let spin = SpinSurface::new(
+        Point3::new(1.0, 0.0, 0.0),
+        Vec3::Z,
+        Polyline::new(vec![
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(3.0, 0.0, 0.0),
+            Point3::new(3.0, 0.0, 4.0),
+            Point3::new(1.0, 0.0, 5.0),
+        ]),
+    );
+    let nurbs = spin.to_nurbs();
+    for &u in &[0.0, 0.1, 0.35, 0.8, 1.0] {
+        for i in 0..=4 {
+            let angle = i as Float * crate::consts::FRAC_PI_2;
+            assert!(nurbs.get_point(u, angle).distance(spin.get_point(u, angle)) < 1e-12);
+        }
+        let point = nurbs.get_point(u, 2.0);
+        let expected = spin.get_point(u, 0.0) - spin.origin;
+        let radial = |v: Vec3| v.truncate().length();
+        assert!((radial(point - spin.origin) - radial(expected)).abs() < 1e-12);
+    }
+
+    // the same cylinder as a cylinder, a spin surface and a sweep surface
+    let cylinder = Cylinder {
+        origin: Point3::new(0.0, 0.0, 1.0),
+        axis: Vec3::Z,
+        ref_dir: Vec3::X,
+        radius: 2.0,
+    }
+    .to_nurbs((0.0, 3.0));
+    let spin = SpinSurface::new(
+        Point3::ZERO,
+        Vec3::Z,
+        TrimmedCurve {
+            basis: Line {
+                origin: Point3::new(2.0, 0.0, 1.0),
+                direction: Vec3::Z,
+            },
+            parameter_range: (0.0, 3.0),
+        },
+    )
+    .to_nurbs();
+    let sweep = SweepSurface::new(
+        TrimmedCurve {
+            basis: Line {
+                origin: Point3::ZERO,
+                direction: Vec3::Z,
+            },
+            parameter_range: (0.0, 3.0),
+        },
+        Circle {
+            center: Point3::new(0.0, 0.0, 1.0),
+            radius: 2.0,
+            axis: Vec3::Z,
+            ref_dir: Vec3::X,
+        },
+        (0.0, 3.0),
+        SweepFrame::Translation,
+    )
+    .to_nurbs()
+    .unwrap();
+    for &(u, v) in &[(0.0, 0.0), (1.2, 0.7), (3.0, 4.0), (2.5, 6.0)] {
+        let point = cylinder.get_point(u, v);
+        assert!(point.distance(spin.get_point(u, v)) < 1e-12);
+        assert!(point.distance(sweep.get_point(u, v)) < 1e-12);
+        assert!((point.truncate().length() - 2.0).abs() < 1e-12);
+    }
+}
+
+#[test]
+fn test_spin_surface_trim() {
+    use crate::consts::{FRAC_PI_2, PI};
+    use crate::curve::{Circle, Polyline};
+
+    // a quarter circle from the equator to the north pole spins to a half sphere
+    let mut spin = SpinSurface::new(
+        Point3::ZERO,
+        Vec3::Z,
+        Circle {
+            center: Point3::ZERO,
+            radius: 2.0,
+            axis: -Vec3::Y,
+            ref_dir: Vec3::X,
+        },
+    );
+    let point = spin.get_point(0.7, 2.0);
+    let param = spin.project(point);
+    assert!(spin.get_point(param.x, param.y).distance(point) < 1e-7);
+    // the section runs up and the angle turns counter-clockwise, normals point inside
+    let normal = spin.get_normals(&[Point2::new(0.7, 2.0)])[0];
+    assert!(normal.dot(point / 2.0) < -1.0 + 1e-9);
+    let derivatives = spin.derivatives(0.7, 2.0);
+    let difference = (spin.get_point(0.7 + 1e-6, 2.0) - point) / 1e-6;
+    assert!(derivatives.du.distance(difference) < 1e-5);
+
+    // clockwise circle seen from outside, counter-clockwise seen from the normals inside,
+    // bounds the cap around the apex of a cone
+    let mut cone = SpinSurface::new(
+        Point3::ZERO,
+        Vec3::Z,
+        Polyline::new(vec![
+            Point3::new(2.0, 0.0, 0.0),
+            Point3::new(2.0, 0.0, 1.0),
+            Point3::new(0.0, 0.0, 2.0),
+        ]),
+    );
+    let height = cone.get_point(0.75, 0.0).z;
+    let bound = (0..32)
+        .map(|i| cone.get_point(0.75, -TAU * i as Float / 32.0))
+        .collect::<Vec<_>>();
+    let mesh = cone.trim_polygons(&[bound]);
+    assert!(mesh.triangle_count() > 32);
+    assert!(mesh.vertices.iter().any(|v| v.z > height + 0.1));
+    for vertex in &mesh.vertices {
+        assert!((vertex.truncate().length() - 2.0 * (2.0 - vertex.z)).abs() < 1e-9);
+        assert!(vertex.z >= height - 1e-9);
+    }
+
+    // half of the revolution is not periodic and keeps the angle in its range
+    spin.angle_range = (0.0, PI);
+    assert_eq!(spin.periods().1, None);
+    let nurbs = spin.to_nurbs();
+    for &angle in &[0.0, FRAC_PI_2, PI] {
+        assert!(
+            nurbs
+                .get_point(0.0, angle)
+                .distance(spin.get_point(0.0, angle))
+                < 1e-12
+        );
+        assert!((nurbs.get_point(0.3, angle).length() - 2.0).abs() < 1e-12);
+    }
+    assert!(nurbs.get_point(0.3, 1.0).y > 0.0);
+
+    cone.angle_range = (0.0, PI);
+    let param = cone.project(Point3::new(1.8, -0.5, 0.5));
+    assert_eq!(param.y, 0.0);
+    let side = |u0: Float, u1: Float, v0: Float, v1: Float| {
+        (0..8).map(move |i| {
+            let t = i as Float / 8.0;
+            Point2::new(u0 + (u1 - u0) * t, v0 + (v1 - v0) * t)
+        })
+    };
+    // counter-clockwise in parameters is counter-clockwise seen from the normals
+    let params = side(0.1, 0.5, 0.5, 0.5)
+        .chain(side(0.5, 0.5, 0.5, 2.5))
+        .chain(side(0.5, 0.1, 2.5, 2.5))
+        .chain(side(0.1, 0.1, 2.5, 0.5))
+        .collect::<Vec<_>>();
+    let bound = params
+        .iter()
+        .map(|p| cone.get_point(p.x, p.y))
+        .collect::<Vec<_>>();
+    let mesh = cone.trim_polygons(&[bound]);
+    assert!(!mesh.triangles.is_empty());
+    for vertex in &mesh.vertices {
+        let radius = if vertex.z < 1.0 {
+            2.0
+        } else {
+            2.0 * (2.0 - vertex.z)
+        };
+        assert!((vertex.truncate().length() - radius).abs() < 1e-9);
+        assert!(vertex.y > -1e-9);
+    }
 }
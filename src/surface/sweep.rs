@@ -1,15 +1,496 @@
-use super::{Curve, Surface};
-use crate::{Float, Point3};
+use super::{BSplineSurface, Curve, Surface};
+use crate::curve::{rotation_minimizing_frames, Frame, ToNurbs};
+use crate::surface::{project_polygons, refine_nearest_parameters, trim_in_parameter_space};
+use crate::{
+    utils, utils::Tolerance, Float, Grid, Mat4, Point2, Point3, Point4, TriangleMesh, Vec3,
+};
+
+/// How the section turns while moving along the path
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SweepFrame {
+    /// The section keeps its orientation and is added to the path point, `path(p) + section(s)`,
+    /// twist and scale turn it about its point at the start of the path `path(p0)`
+    Translation,
+    /// The section keeps its orientation and moves with the path point from its place at the
+    /// start of the path range, `section(s) - path(p0) + path(p)`
+    AnchoredTranslation,
+    /// The section follows the Frenet frame, the last defined normal is carried over
+    /// where the path is straight
+    Frenet,
+    /// The section follows rotation minimizing frames, it does not spin around the path
+    RotationMinimizing,
+    /// The section keeps the binormal of the frame perpendicular to the path and
+    /// as close to the direction as possible, the last defined normal is carried over
+    /// where the path runs along the direction
+    FixedBinormal(Vec3),
+}
+
+/// Value changing along the path parameter
+#[derive(Debug, Clone, PartialEq)]
+pub enum Law {
+    Constant(Float),
+    /// `a + b·p` at the path parameter `p`
+    Linear(Float, Float),
+    /// Piecewise linear through `(parameter, value)` pairs sorted by parameter,
+    /// constant beyond both ends
+    Table(Vec<(Float, Float)>),
+}
+
+impl Law {
+    pub fn value(&self, p: Float) -> Float {
+        match self {
+            Law::Constant(value) => *value,
+            Law::Linear(a, b) => a + b * p,
+            Law::Table(table) => {
+                let index = table.partition_point(|&(q, _)| q <= p);
+                if index == 0 {
+                    table[0].1
+                } else if index == table.len() {
+                    table[index - 1].1
+                } else {
+                    let ((p0, v0), (p1, v1)) = (table[index - 1], table[index]);
+                    v0 + (v1 - v0) * (p - p0) / (p1 - p0)
+                }
+            }
+        }
+    }
+}
+
+// number of frames sampled along the path range
+const FRAME_DIVISION: usize = 128;
 
 /// Sweep surface is created by moving a section curve along a path curve.
+/// Unless it is translated by the path point, the section is placed at the start of the path
+/// range and moves with the path point, it is turned by the frame, twisted around the path
+/// tangent and scaled on the way.
+///
+/// The path and the section are no longer public fields, sweeps are built by
+/// [`SweepSurface::new`] which caches the frames sampled from the path.
 #[derive(Debug)]
 pub struct SweepSurface<P: Curve, S: Curve> {
-    pub path: P,
-    pub section: S,
+    path: P,
+    section: S,
+    /// Twist angle of the section around the path tangent
+    pub twist: Law,
+    /// Scale factor of the section
+    pub scale: Law,
+    path_range: (Float, Float),
+    frame: SweepFrame,
+    /// Frame at the start of the path range
+    start: Frame,
+    /// Rotation minimizing frames, or frames carrying the last defined normal,
+    /// sampled over the path range
+    frames: Vec<Frame>,
+}
+
+impl<P: Curve, S: Curve> SweepSurface<P, S> {
+    pub fn new(path: P, section: S, path_range: (Float, Float), frame: SweepFrame) -> Self {
+        let tangent = path.tangent(path_range.0);
+        let start = normal_frame(
+            path.get_point(path_range.0),
+            tangent,
+            tangent.any_orthonormal_vector(),
+        );
+        let mut sweep = SweepSurface {
+            path,
+            section,
+            twist: Law::Constant(0.0),
+            scale: Law::Constant(1.0),
+            path_range,
+            frame,
+            start,
+            frames: Vec::new(),
+        };
+        let parameters = utils::uniform_divide(path_range, FRAME_DIVISION);
+        match frame {
+            SweepFrame::RotationMinimizing => {
+                sweep.frames = rotation_minimizing_frames(&sweep.path, &parameters, start.normal);
+            }
+            SweepFrame::Frenet | SweepFrame::FixedBinormal(_) => {
+                sweep.frames = sweep.carried_frames(&parameters);
+            }
+            SweepFrame::Translation | SweepFrame::AnchoredTranslation => {}
+        }
+        sweep.start = sweep.frame_at(path_range.0);
+        sweep
+    }
+
+    pub fn path(&self) -> &P {
+        &self.path
+    }
+
+    pub fn section(&self) -> &S {
+        &self.section
+    }
+
+    /// Path parameters where the sweep starts and ends
+    pub fn path_range(&self) -> (Float, Float) {
+        self.path_range
+    }
+
+    pub fn frame(&self) -> SweepFrame {
+        self.frame
+    }
+
+    /// Frame of the path at the parameter, the section plane is spanned by its normal and binormal
+    pub fn frame_at(&self, p: Float) -> Frame {
+        let origin = self.path.get_point(p);
+        match self.frame {
+            SweepFrame::Translation | SweepFrame::AnchoredTranslation => Frame {
+                origin,
+                ..self.start
+            },
+            SweepFrame::RotationMinimizing => {
+                // one more step of the double reflection from the sample before
+                let index = self.sample_index(p);
+                let sample =
+                    utils::range_at(self.path_range, index as Float / FRAME_DIVISION as Float);
+                rotation_minimizing_frames(&self.path, &[sample, p], self.frames[index].normal)[1]
+            }
+            SweepFrame::Frenet | SweepFrame::FixedBinormal(_) => {
+                let tangent = self.path.tangent(p);
+                let normal = self
+                    .defined_normal(p)
+                    .unwrap_or(self.frames[self.sample_index(p)].normal);
+                normal_frame(origin, tangent, normal)
+            }
+        }
+    }
+
+    /// Index of the frame sampled at or before the path parameter
+    fn sample_index(&self, p: Float) -> usize {
+        let (p0, p1) = self.path_range;
+        let ratio = if p1 == p0 { 0.0 } else { (p - p0) / (p1 - p0) };
+        ((ratio * FRAME_DIVISION as Float).floor().max(0.0) as usize).min(FRAME_DIVISION - 1)
+    }
+
+    /// Normal of the Frenet or fixed binormal frame, `None` where it is not defined
+    fn defined_normal(&self, p: Float) -> Option<Vec3> {
+        match self.frame {
+            SweepFrame::Frenet => self.path.frenet_frame(p).map(|frame| frame.normal),
+            SweepFrame::FixedBinormal(direction) => {
+                let normal = direction.cross(self.path.tangent(p));
+                if normal.length_squared().near2(0.0) {
+                    None
+                } else {
+                    Some(normal)
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Frames at the parameters, an undefined normal is replaced by the one before it
+    /// so that the section does not turn abruptly
+    fn carried_frames(&self, parameters: &[Float]) -> Vec<Frame> {
+        let normals = parameters
+            .iter()
+            .map(|&p| self.defined_normal(p))
+            .collect::<Vec<_>>();
+        let mut last = normals
+            .iter()
+            .flatten()
+            .next()
+            .copied()
+            .unwrap_or(self.start.normal);
+        parameters
+            .iter()
+            .zip(normals)
+            .map(|(&p, normal)| {
+                let frame = normal_frame(
+                    self.path.get_point(p),
+                    self.path.tangent(p),
+                    normal.unwrap_or(last),
+                );
+                last = frame.normal;
+                frame
+            })
+            .collect()
+    }
+
+    /// Linear map turning and scaling the section at the path parameter
+    fn section_map(&self, p: Float) -> Mat4 {
+        let twist = Mat4::from_rotation_z(self.twist.value(p));
+        let scale = Mat4::from_scale(Vec3::splat(self.scale.value(p)));
+        rotation(&self.frame_at(p)) * twist * rotation(&self.start).transpose() * scale
+    }
+
+    /// Affine map moving the section from the start to the path parameter
+    pub fn transform_at(&self, p: Float) -> Mat4 {
+        Mat4::from_translation(self.path.get_point(p) + self.offset())
+            * self.section_map(p)
+            * Mat4::from_translation(-self.start.origin)
+    }
+
+    /// Offset of the section from the path point after it is moved from the start
+    fn offset(&self) -> Vec3 {
+        match self.frame {
+            SweepFrame::Translation => self.start.origin,
+            _ => Vec3::ZERO,
+        }
+    }
+
+    /// Parameters of the nearest point, starting from the nearest path point
+    pub fn project(&self, point: Point3) -> Point2 {
+        let p = utils::clamp_in_range(self.path.project(point), self.path_range);
+        let local = self.transform_at(p).inverse().transform_point3(point);
+        let s = self.section.project(local);
+        let ranges = (self.path_range, (Float::MIN, Float::MAX));
+        refine_nearest_parameters(self, point, Point2::new(p, s), ranges, 16)
+    }
+}
+
+/// Frame with the tangent and the normal made perpendicular to it
+fn normal_frame(origin: Point3, tangent: Vec3, normal: Vec3) -> Frame {
+    let normal = (normal - tangent * normal.dot(tangent)).normalize();
+    Frame {
+        origin,
+        tangent,
+        normal,
+        binormal: tangent.cross(normal),
+    }
+}
+
+/// Rotation taking x, y, z axes to the normal, binormal and tangent
+fn rotation(frame: &Frame) -> Mat4 {
+    Mat4::from_cols(
+        frame.normal.extend(0.0),
+        frame.binormal.extend(0.0),
+        frame.tangent.extend(0.0),
+        Point4::W,
+    )
+}
+
+impl<P: Curve + ToNurbs, S: Curve + ToNurbs> SweepSurface<P, S> {
+    /// Rational B-spline with rows following the path and columns the section,
+    /// `None` unless the section is translated with constant twist and scale
+    pub fn to_nurbs(&self) -> Option<BSplineSurface<Point4>> {
+        match (self.frame, &self.twist, &self.scale) {
+            (
+                SweepFrame::Translation | SweepFrame::AnchoredTranslation,
+                Law::Constant(_),
+                Law::Constant(_),
+            ) => {}
+            _ => return None,
+        }
+        let path = self.path.to_nurbs();
+        let section = self.section.to_nurbs();
+        let map = self.section_map(self.path_range.0);
+        let (origin, offset) = (self.start.origin, self.offset());
+        // weights multiply, the points add
+        let points = path
+            .control_points
+            .iter()
+            .flat_map(|p| {
+                section.control_points.iter().map(move |s| {
+                    let offset = offset + map.transform_vector3(s.truncate() / s.w - origin);
+                    (p.truncate() / p.w + offset).extend(1.0) * (p.w * s.w)
+                })
+            })
+            .collect();
+        Some(BSplineSurface {
+            control_points: Grid::from_vec(points, section.control_points.len()),
+            knots: (path.knots, section.knots),
+            degree: (path.degree, section.degree),
+        })
+    }
 }
 
 impl<P: Curve, S: Curve> Surface for SweepSurface<P, S> {
     fn get_point(&self, p: Float, s: Float) -> Point3 {
-        self.path.get_point(p) + self.section.get_point(s)
+        self.transform_at(p)
+            .transform_point3(self.section.get_point(s))
+    }
+
+    fn periods(&self) -> (Option<Float>, Option<Float>) {
+        (None, self.section.period())
+    }
+
+    fn nearest_parameters(&self, point: Point3) -> Option<Point2> {
+        Some(self.project(point))
+    }
+
+    fn trim_polygons(&self, bounds: &[Vec<Point3>]) -> TriangleMesh {
+        let params = project_polygons(bounds, |point| self.project(point));
+        self.trim_parameter_polygons(bounds, &params)
     }
+
+    fn trim_parameter_polygons(
+        &self,
+        bounds: &[Vec<Point3>],
+        params: &[Vec<Point2>],
+    ) -> TriangleMesh {
+        trim_in_parameter_space(self, bounds, params)
+    }
+}
+
+#[test]
+fn test_sweep_frames() {
+    use crate::curve::{Circle, Line};
+
+    // pipe around a quarter of a circle is a part of a torus
+    let pipe = |frame| {
+        let path = Circle {
+            center: Point3::ZERO,
+            radius: 5.0,
+            axis: Vec3::Z,
+            ref_dir: Vec3::X,
+        };
+        let section = Circle {
+            center: Point3::new(5.0, 0.0, 0.0),
+            radius: 1.0,
+            axis: Vec3::Y,
+            ref_dir: Vec3::X,
+        };
+        SweepSurface::new(path, section, (0.0, crate::consts::FRAC_PI_2), frame)
+    };
+    let on_torus = |point: Point3| {
+        let radial = point.truncate().length() - 5.0;
+        (radial.hypot(point.z) - 1.0).abs() < 1e-9
+    };
+    let parameters = utils::uniform_divide((0.0, crate::consts::FRAC_PI_2), 10);
+    for frame in [
+        SweepFrame::Frenet,
+        SweepFrame::RotationMinimizing,
+        SweepFrame::FixedBinormal(Vec3::Z),
+    ] {
+        let sweep = pipe(frame);
+        for &p in &parameters {
+            for &s in &[0.0, 1.0, 2.5, 4.0] {
+                assert!(on_torus(sweep.get_point(p, s)));
+            }
+        }
+        let point = sweep.get_point(0.7, 2.0);
+        let param = sweep.project(point);
+        assert!(sweep.get_point(param.x, param.y).distance(point) < 1e-7);
+        // normals of the outer side point away from the path
+        let normal = sweep.get_normals(&[Point2::new(0.7, 0.0)])[0];
+        let outward = sweep.get_point(0.7, 0.0).truncate().extend(0.0).normalize();
+        assert!(normal.dot(outward).abs() > 0.99);
+    }
+    assert!(!on_torus(pipe(SweepFrame::Translation).get_point(1.0, 0.0)));
+
+    let sweep = pipe(SweepFrame::RotationMinimizing);
+    // the boundary runs backwards around the start and forwards around the end
+    let bounds = [(0.0, -1.0), (crate::consts::FRAC_PI_2, 1.0)]
+        .iter()
+        .map(|&(p, sense)| {
+            (0..16)
+                .map(|i| sweep.get_point(p, sense * i as Float / 16.0 * crate::consts::TAU))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let mesh = sweep.trim_polygons(&bounds);
+    assert!(!mesh.triangles.is_empty());
+    assert!(mesh
+        .vertices
+        .iter()
+        .all(|&v| (v.truncate().length() - 5.0).hypot(v.z) < 1.0 + 1e-9));
+
+    // a segment twisted by a quarter turn and shrunk along a straight path
+    let mut sweep = SweepSurface::new(
+        Line {
+            origin: Point3::ZERO,
+            direction: Vec3::Z,
+        },
+        Line {
+            origin: Point3::ZERO,
+            direction: Vec3::X,
+        },
+        (0.0, 2.0),
+        SweepFrame::RotationMinimizing,
+    );
+    sweep.twist = Law::Linear(0.0, crate::consts::FRAC_PI_4);
+    sweep.scale = Law::Table(vec![(0.0, 1.0), (2.0, 0.5)]);
+    let start = sweep.get_point(0.0, 1.0);
+    let end = sweep.get_point(2.0, 1.0);
+    assert!(start.distance(Point3::X) < 1e-12);
+    assert!(((end - Point3::new(0.0, 0.0, 2.0)).length() - 0.5).abs() < 1e-12);
+    assert!((end - Point3::new(0.0, 0.0, 2.0)).dot(Vec3::X).abs() < 1e-12);
+
+    // translated sections are added to the path point or moved from the start of the path
+    let translated = |frame| {
+        SweepSurface::new(
+            Line {
+                origin: Point3::new(1.0, 2.0, 3.0),
+                direction: Vec3::Z,
+            },
+            Line {
+                origin: Point3::new(0.0, 1.0, 0.0),
+                direction: Vec3::X,
+            },
+            (1.0, 2.0),
+            frame,
+        )
+    };
+    let sweep = translated(SweepFrame::Translation);
+    assert_eq!(sweep.path_range(), (1.0, 2.0));
+    assert_eq!(sweep.path().origin, Point3::new(1.0, 2.0, 3.0));
+    assert_eq!(sweep.section().direction, Vec3::X);
+    for &(p, s) in &[(1.0, 0.0), (1.5, 2.0), (2.0, -1.0)] {
+        let point = sweep.path().get_point(p) + sweep.section().get_point(s);
+        assert!(sweep.get_point(p, s).distance(point) < 1e-12);
+        let param = sweep.project(point);
+        assert!(param.distance(Point2::new(p, s)) < 1e-9);
+    }
+    let anchored = translated(SweepFrame::AnchoredTranslation);
+    let start = anchored.path().get_point(1.0);
+    for &(p, s) in &[(1.0, 0.0), (1.5, 2.0), (2.0, -1.0)] {
+        let point = anchored.section().get_point(s) - start + anchored.path().get_point(p);
+        assert!(anchored.get_point(p, s).distance(point) < 1e-12);
+    }
+    // translated sections are scaled about their point at the start of the path
+    let mut sweep = translated(SweepFrame::Translation);
+    sweep.scale = Law::Constant(2.0);
+    for &(p, s) in &[(1.0, 0.0), (1.5, 2.0), (2.0, -1.0)] {
+        let section = start + (sweep.section().get_point(s) - start) * 2.0;
+        let point = sweep.path().get_point(p) + section;
+        assert!(sweep.get_point(p, s).distance(point) < 1e-12);
+    }
+}
+
+#[test]
+fn test_sweep_straight_spans() {
+    use crate::curve::{BSplineCurve, Line};
+    use crate::KnotVector;
+
+    // curved, straight and curved again, all bending towards +x
+    let path = BSplineCurve {
+        control_points: vec![
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(0.0, 0.0, 1.0),
+            Point3::new(0.0, 0.0, 2.0),
+            Point3::new(0.0, 0.0, 3.0),
+            Point3::new(1.0, 0.0, 4.0),
+        ],
+        knots: KnotVector::uniform_knot(2, 3),
+        degree: 2,
+    };
+    let section = Line {
+        origin: Point3::new(1.0, 0.0, 0.0),
+        direction: Vec3::Y,
+    };
+    let sweep = SweepSurface::new(path, section, (0.0, 1.0), SweepFrame::Frenet);
+    // the normal at the end of the first curved span is kept over the straight one
+    let normals = [0.3, 0.4, 0.5, 0.6, 0.7]
+        .iter()
+        .map(|&p| sweep.frame_at(p).normal)
+        .collect::<Vec<_>>();
+    assert!(normals.iter().all(|normal| normal.dot(Vec3::X) > 0.98));
+
+    // the binormal direction is along the straight span
+    let sweep = SweepSurface::new(
+        sweep.path().clone(),
+        Line {
+            origin: Point3::new(1.0, 0.0, 0.0),
+            direction: Vec3::Y,
+        },
+        (0.0, 1.0),
+        SweepFrame::FixedBinormal(Vec3::Z),
+    );
+    let normals = [0.3, 0.4, 0.5, 0.6]
+        .iter()
+        .map(|&p| sweep.frame_at(p).normal)
+        .collect::<Vec<_>>();
+    assert!(normals.windows(2).all(|pair| pair[0].dot(pair[1]) > 0.99));
 }
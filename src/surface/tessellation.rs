@@ -0,0 +1,413 @@
+use super::Surface;
+use crate::mesh::{create_quads, QuadMesh};
+use crate::utils::{distance_to_line_segment, range_at, SurfaceTolerance, TOLERANCE2};
+use crate::{Float, Grid, Point2, Point3, TriangleMesh, Vec3};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Cells of the initial grid along each parameter
+const INITIAL_DIVISION: u64 = 4;
+
+/// Limit of cell bisections along each parameter
+const MAX_DEPTH: u32 = 10;
+
+/// Lattice steps along each parameter, cell corners have integer coordinates
+const LATTICE_SIZE: u64 = INITIAL_DIVISION << MAX_DEPTH;
+
+/// Relative parameters in a cell where the surface is compared with its two triangles
+const SAMPLES: [(Float, Float); 5] = [
+    (0.5, 0.5),
+    (0.25, 0.25),
+    (0.75, 0.25),
+    (0.75, 0.75),
+    (0.25, 0.75),
+];
+
+/// Tessellate the surface over the parameter ranges.
+///
+/// Cells over the parameters are bisected along `u`, `v` or both until their triangles
+/// satisfy the tolerance, so surfaces curved one way get long thin cells. A cell next to
+/// finer cells is fanned from its center through their corners, so the mesh has no cracks.
+/// Triangles turn from `u` to `v`.
+pub fn tessellate<S: Surface + ?Sized>(
+    surface: &S,
+    parameter_range: ((Float, Float), (Float, Float)),
+    tolerance: &SurfaceTolerance,
+) -> TriangleMesh {
+    let mut tessellator = Tessellator {
+        surface,
+        parameter_range,
+        tolerance,
+        indices: HashMap::new(),
+        u_lines: BTreeMap::new(),
+        v_lines: BTreeMap::new(),
+        leaves: Vec::new(),
+        vertices: Vec::new(),
+        normals: Vec::new(),
+    };
+    let size = LATTICE_SIZE / INITIAL_DIVISION;
+    for i in 0..INITIAL_DIVISION {
+        for j in 0..INITIAL_DIVISION {
+            tessellator.refine((i * size, j * size), (size, size));
+        }
+    }
+    tessellator.into_mesh()
+}
+
+struct Tessellator<'a, S: ?Sized> {
+    surface: &'a S,
+    parameter_range: ((Float, Float), (Float, Float)),
+    tolerance: &'a SurfaceTolerance,
+    /// Vertex indices of the lattice coordinates
+    indices: HashMap<(u64, u64), u32>,
+    /// `v` coordinates of the vertices on each line of constant `u`
+    u_lines: BTreeMap<u64, BTreeSet<u64>>,
+    /// `u` coordinates of the vertices on each line of constant `v`
+    v_lines: BTreeMap<u64, BTreeSet<u64>>,
+    /// Lower corners and sizes of the cells which are not divided
+    leaves: Vec<((u64, u64), (u64, u64))>,
+    vertices: Vec<Point3>,
+    normals: Vec<Vec3>,
+}
+
+impl<'a, S: Surface + ?Sized> Tessellator<'a, S> {
+    fn parameter(&self, i: Float, j: Float) -> Point2 {
+        let (u_range, v_range) = self.parameter_range;
+        let size = LATTICE_SIZE as Float;
+        Point2::new(range_at(u_range, i / size), range_at(v_range, j / size))
+    }
+
+    fn add_vertex(&mut self, param: Point2) -> u32 {
+        self.vertices.push(self.surface.get_point(param.x, param.y));
+        self.normals.extend(self.surface.get_normals(&[param]));
+        self.vertices.len() as u32 - 1
+    }
+
+    /// Index of the vertex at the lattice coordinates
+    fn vertex(&mut self, (i, j): (u64, u64)) -> u32 {
+        if let Some(&index) = self.indices.get(&(i, j)) {
+            return index;
+        }
+        let index = self.add_vertex(self.parameter(i as Float, j as Float));
+        self.indices.insert((i, j), index);
+        self.u_lines.entry(i).or_default().insert(j);
+        self.v_lines.entry(j).or_default().insert(i);
+        index
+    }
+
+    fn refine(&mut self, (i, j): (u64, u64), (di, dj): (u64, u64)) {
+        match self.bisections((i, j), (di, dj)) {
+            (true, true) => {
+                let (hi, hj) = (di / 2, dj / 2);
+                for &(oi, oj) in &[(0, 0), (hi, 0), (0, hj), (hi, hj)] {
+                    self.refine((i + oi, j + oj), (hi, hj));
+                }
+            }
+            (true, false) => {
+                self.refine((i, j), (di / 2, dj));
+                self.refine((i + di / 2, j), (di / 2, dj));
+            }
+            (false, true) => {
+                self.refine((i, j), (di, dj / 2));
+                self.refine((i, j + dj / 2), (di, dj / 2));
+            }
+            (false, false) => self.leaves.push(((i, j), (di, dj))),
+        }
+    }
+
+    /// Whether the cell is bisected along `u` and `v`
+    fn bisections(&mut self, (i, j): (u64, u64), (di, dj): (u64, u64)) -> (bool, bool) {
+        let corners = [(i, j), (i + di, j), (i, j + dj), (i + di, j + dj)]
+            .map(|corner| self.vertex(corner) as usize);
+        let [p00, p10, p01, p11] = corners.map(|index| self.vertices[index]);
+        let [n00, n10, n01, n11] = corners.map(|index| self.normals[index]);
+        let param = |a: Float, b: Float| {
+            self.parameter(i as Float + a * di as Float, j as Float + b * dj as Float)
+        };
+        let point = |a: Float, b: Float| {
+            let param = param(a, b);
+            self.surface.get_point(param.x, param.y)
+        };
+        let tolerance = self.tolerance;
+        let lengths = (
+            p00.distance(p10).max(p01.distance(p11)),
+            p00.distance(p01).max(p10.distance(p11)),
+        );
+        let mut bisect = (
+            lengths.0 > tolerance.max_length,
+            lengths.1 > tolerance.max_length,
+        );
+
+        // middle points of the lines across the cell against their chords
+        let center = point(0.5, 0.5);
+        let (p0m, p1m) = (point(0.0, 0.5), point(1.0, 0.5));
+        let (pm0, pm1) = (point(0.5, 0.0), point(0.5, 1.0));
+        let deviates = |middle: Point3, a: Point3, b: Point3| {
+            middle.distance((a + b) / 2.0) > tolerance.chord_height
+        };
+        bisect.0 |=
+            deviates(pm0, p00, p10) || deviates(center, p0m, p1m) || deviates(pm1, p01, p11);
+        bisect.1 |=
+            deviates(p0m, p00, p01) || deviates(center, pm0, pm1) || deviates(p1m, p10, p11);
+
+        // singular points have no normal
+        let turns = |a: Vec3, b: Vec3| {
+            let valid = |n: Vec3| n.is_finite() && n != Vec3::ZERO;
+            valid(a) && valid(b) && a.angle_between(b) > tolerance.max_angle
+        };
+        bisect.0 |= turns(n00, n10) || turns(n01, n11);
+        bisect.1 |= turns(n00, n01) || turns(n10, n11);
+
+        // a twisted cell can be far from its triangles while its lines are straight
+        if !bisect.0 && !bisect.1 {
+            let far = SAMPLES.iter().any(|&(a, b)| {
+                let linear = if a + b <= 1.0 {
+                    p00 + (p10 - p00) * a + (p01 - p00) * b
+                } else {
+                    p11 + (p01 - p11) * (1.0 - a) + (p10 - p11) * (1.0 - b)
+                };
+                point(a, b).distance(linear) > tolerance.chord_height
+            });
+            bisect = (far, far);
+        }
+        (
+            bisect.0 && di > 1 && lengths.0 / 2.0 >= tolerance.min_length,
+            bisect.1 && dj > 1 && lengths.1 / 2.0 >= tolerance.min_length,
+        )
+    }
+
+    /// Vertex indices around the cell, counter-clockwise in parameters
+    fn boundary(&self, (i, j): (u64, u64), (di, dj): (u64, u64)) -> Vec<u32> {
+        let (i1, j1) = (i + di, j + dj);
+        let bottom = self.v_lines[&j].range(i..i1).map(|&k| (k, j));
+        let right = self.u_lines[&i1].range(j..j1).map(|&k| (i1, k));
+        let top = self.v_lines[&j1].range(i + 1..=i1).rev().map(|&k| (k, j1));
+        let left = self.u_lines[&i].range(j + 1..=j1).rev().map(|&k| (i, k));
+        bottom
+            .chain(right)
+            .chain(top)
+            .chain(left)
+            .map(|key| self.indices[&key])
+            .collect()
+    }
+
+    fn into_mesh(mut self) -> TriangleMesh {
+        let mut triangles = Vec::new();
+        for ((i, j), (di, dj)) in std::mem::take(&mut self.leaves) {
+            let boundary = self.boundary((i, j), (di, dj));
+            if let [a, b, c, d] = boundary[..] {
+                triangles.extend_from_slice(&[a, b, d, d, b, c]);
+            } else {
+                let (ci, cj) = (
+                    i as Float + di as Float / 2.0,
+                    j as Float + dj as Float / 2.0,
+                );
+                let center = self.add_vertex(self.parameter(ci, cj));
+                for (k, &a) in boundary.iter().enumerate() {
+                    let b = boundary[(k + 1) % boundary.len()];
+                    triangles.extend_from_slice(&[center, a, b]);
+                }
+            }
+        }
+        // drop the triangles collapsed at poles
+        let vertices = &self.vertices;
+        let triangles = triangles
+            .chunks(3)
+            .filter(|t| {
+                let [a, b, c] = [0, 1, 2].map(|k| vertices[t[k] as usize]);
+                a.distance_squared(b) > TOLERANCE2
+                    && b.distance_squared(c) > TOLERANCE2
+                    && c.distance_squared(a) > TOLERANCE2
+            })
+            .flatten()
+            .copied()
+            .collect::<Vec<_>>();
+        let mut normals = self.normals;
+        borrow_missing_normals(&mut normals, triangles.chunks(3));
+        TriangleMesh {
+            vertices: self.vertices,
+            normals,
+            triangles,
+        }
+    }
+}
+
+/// Singular points have no normal, borrow one from a face around
+pub(crate) fn borrow_missing_normals<'a>(
+    normals: &mut [Vec3],
+    faces: impl Iterator<Item = &'a [u32]>,
+) {
+    let missing = |normal: Vec3| !normal.is_finite() || normal == Vec3::ZERO;
+    for face in faces {
+        let found = face
+            .iter()
+            .map(|&k| normals[k as usize])
+            .find(|&n| !missing(n));
+        if let Some(normal) = found {
+            for &k in face {
+                if missing(normals[k as usize]) {
+                    normals[k as usize] = normal;
+                }
+            }
+        }
+    }
+}
+
+/// Quadrangulate the surface over the parameter ranges by lines of constant `u` and `v`.
+///
+/// Each parameter range is bisected where a line across the surface needs it, and the
+/// lines run through the whole range, so the quads form a structured grid. Quads turn from
+/// `u` to `v` like the triangles of `tessellate`, quads collapsed at poles become triangles.
+pub fn quadrangulate<S: Surface + ?Sized>(
+    surface: &S,
+    parameter_range: ((Float, Float), (Float, Float)),
+    tolerance: &SurfaceTolerance,
+) -> QuadMesh {
+    let (u_range, v_range) = parameter_range;
+    let size = LATTICE_SIZE as Float;
+    let parameter = |i: u64, j: u64| {
+        Point2::new(
+            range_at(u_range, i as Float / size),
+            range_at(v_range, j as Float / size),
+        )
+    };
+    let sample = |param: Point2| {
+        let normal = surface.get_normals(&[param]).pop().unwrap_or(Vec3::ZERO);
+        (surface.get_point(param.x, param.y), normal)
+    };
+    let mut us = BTreeSet::new();
+    let mut vs = BTreeSet::new();
+    for k in 0..=INITIAL_DIVISION {
+        let across = k * (LATTICE_SIZE / INITIAL_DIVISION);
+        let along_u = |i: u64| sample(parameter(i, across));
+        let along_v = |j: u64| sample(parameter(across, j));
+        divide_lattice_line(&along_u, tolerance, &mut us);
+        divide_lattice_line(&along_v, tolerance, &mut vs);
+    }
+    let params = us
+        .iter()
+        .flat_map(|&i| vs.iter().map(move |&j| (i, j)))
+        .map(|(i, j)| parameter(i, j))
+        .collect::<Vec<_>>();
+    let vertices = params
+        .iter()
+        .map(|p| surface.get_point(p.x, p.y))
+        .collect::<Vec<_>>();
+    let mut normals = surface.get_normals(&params);
+    let grid = Grid::from_vec(vertices, vs.len());
+
+    // drop the corners collapsed at poles
+    let mut quads = Vec::new();
+    let mut triangles = Vec::new();
+    for quad in create_quads(&grid).chunks(4) {
+        let point = |k: usize| grid[quad[k] as usize / vs.len()][quad[k] as usize % vs.len()];
+        let corners = (0..4)
+            .filter(|&k| point(k).distance_squared(point((k + 1) % 4)) > TOLERANCE2)
+            .map(|k| quad[k])
+            .collect::<Vec<_>>();
+        match corners.len() {
+            4 => quads.extend(corners),
+            3 => triangles.extend(corners),
+            _ => {}
+        }
+    }
+    borrow_missing_normals(&mut normals, quads.chunks(4).chain(triangles.chunks(3)));
+    QuadMesh {
+        vertices: grid.into_vec(),
+        normals,
+        quads,
+        triangles,
+    }
+}
+
+/// Add the lattice coordinates dividing a line on the surface into spans within the tolerance
+fn divide_lattice_line(
+    sample: &dyn Fn(u64) -> (Point3, Vec3),
+    tolerance: &SurfaceTolerance,
+    lattice: &mut BTreeSet<u64>,
+) {
+    let size = LATTICE_SIZE / INITIAL_DIVISION;
+    lattice.insert(0);
+    for k in 0..INITIAL_DIVISION {
+        divide_lattice_span(sample, (k * size, size), tolerance, lattice);
+    }
+}
+
+fn divide_lattice_span(
+    sample: &dyn Fn(u64) -> (Point3, Vec3),
+    (i, di): (u64, u64),
+    tolerance: &SurfaceTolerance,
+    lattice: &mut BTreeSet<u64>,
+) {
+    lattice.insert(i + di);
+    let ((p0, n0), (p1, n1)) = (sample(i), sample(i + di));
+    let length = p0.distance(p1);
+    let valid = |n: Vec3| n.is_finite() && n != Vec3::ZERO;
+    // quarter points as well, the middle point alone can lie on the chord of an S-shaped span
+    let bisect = length > tolerance.max_length
+        || (valid(n0) && valid(n1) && n0.angle_between(n1) > tolerance.max_angle)
+        || [di / 4, di / 2, di - di / 4].iter().any(|&offset| {
+            distance_to_line_segment(p0, p1, sample(i + offset).0) > tolerance.chord_height
+        });
+    if bisect && di > 1 && length / 2.0 >= tolerance.min_length {
+        divide_lattice_span(sample, (i, di / 2), tolerance, lattice);
+        divide_lattice_span(sample, (i + di / 2, di / 2), tolerance, lattice);
+    }
+}
+
+#[test]
+fn test_adaptive_tessellation() {
+    use crate::surface::{Cylinder, Plane};
+    use std::collections::HashSet;
+
+    let tolerance = SurfaceTolerance::with_chord_height(1e-3);
+    let plane = Plane {
+        origin: Point3::ZERO,
+        normal: Vec3::Z,
+        u_axis: Vec3::X,
+        v_axis: Vec3::Y,
+    };
+    let flat = tessellate(&plane, ((0.0, 10.0), (0.0, 5.0)), &tolerance);
+    assert_eq!(flat.triangle_count(), 32);
+    let limited = SurfaceTolerance {
+        max_length: 1.0,
+        ..tolerance
+    };
+    let fine = tessellate(&plane, ((0.0, 10.0), (0.0, 5.0)), &limited);
+    for triangle in fine.triangles.chunks(3) {
+        let [a, b, c] = [0, 1, 2].map(|k| fine.vertices[triangle[k] as usize]);
+        assert!(a.distance(b).max(b.distance(c)).max(c.distance(a)) <= 1.5);
+    }
+
+    // only the angle is bisected, cells are fanned to the corners of their finer neighbors without cracks
+    let cylinder = Cylinder {
+        origin: Point3::ZERO,
+        axis: Vec3::Z,
+        ref_dir: Vec3::X,
+        radius: 2.0,
+    };
+    let range = ((0.0, 4.0), (0.0, crate::consts::PI));
+    let mesh = tessellate(&cylinder, range, &tolerance);
+    let edges = mesh
+        .triangles
+        .chunks(3)
+        .flat_map(|t| (0..3).map(move |k| (t[k], t[(k + 1) % 3])))
+        .collect::<HashSet<_>>();
+    for &(a, b) in &edges {
+        if !edges.contains(&(b, a)) {
+            // an edge without its twin lies on the border of the domain
+            let (pa, pb) = (mesh.vertices[a as usize], mesh.vertices[b as usize]);
+            let on_border =
+                |p: Point3| p.z.abs() < 1e-9 || (p.z - 4.0).abs() < 1e-9 || p.y.abs() < 1e-9;
+            assert!(on_border(pa) && on_border(pb));
+        }
+    }
+    for triangle in mesh.triangles.chunks(3) {
+        let [a, b, c] = [0, 1, 2].map(|k| mesh.vertices[triangle[k] as usize]);
+        let center = (a + b + c) / 3.0;
+        assert!(2.0 - center.truncate().length() < 1e-3 + 1e-9);
+    }
+    for (vertex, normal) in mesh.vertices.iter().zip(&mesh.normals) {
+        assert!(normal.dot(vertex.truncate().extend(0.0) / 2.0) > 1.0 - 1e-9);
+    }
+    assert!(mesh.triangle_count() <= 4 * 64 * 2);
+}
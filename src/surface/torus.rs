@@ -0,0 +1,162 @@
+use crate::consts::TAU;
+use crate::surface::{project_polygons, trim_in_parameter_space, Surface, SurfaceDerivatives};
+use crate::{Float, Point2, Point3, Quat, TriangleMesh, Vec3};
+
+#[derive(Debug)]
+pub struct Torus {
+    pub center: Point3,
+    pub axis: Vec3,
+    /// Normalized direction for angle start
+    pub ref_dir: Vec3,
+    /// Distance from the center to the center of the tube
+    pub major_radius: Float,
+    /// Radius of the tube
+    pub minor_radius: Float,
+}
+
+impl Torus {
+    /// Get (angle around the tube, angle around the axis) of the point, both in [0, 2π)
+    pub fn project(&self, point: Point3) -> Point2 {
+        let positive = |angle: Float| if angle >= 0.0 { angle } else { angle + TAU };
+        let vector = point - self.center;
+        let x = vector.dot(self.ref_dir);
+        let y = vector.dot(self.axis.cross(self.ref_dir));
+        let z = vector.dot(self.axis);
+        let angle = y.atan2(x);
+        let tube_angle = z.atan2((x * x + y * y).sqrt() - self.major_radius);
+        Point2::new(positive(tube_angle), positive(angle))
+    }
+}
+
+impl Surface for Torus {
+    fn get_point(&self, tube_angle: Float, angle: Float) -> Point3 {
+        let radial = Quat::from_axis_angle(self.axis, angle) * self.ref_dir;
+        let (sin, cos) = tube_angle.sin_cos();
+        self.center
+            + radial * (self.major_radius + self.minor_radius * cos)
+            + self.axis * (self.minor_radius * sin)
+    }
+
+    fn derivatives(&self, tube_angle: Float, angle: Float) -> SurfaceDerivatives {
+        let radial = Quat::from_axis_angle(self.axis, angle) * self.ref_dir;
+        let tangent = self.axis.cross(radial);
+        let (sin, cos) = tube_angle.sin_cos();
+        let distance = self.major_radius + self.minor_radius * cos;
+        SurfaceDerivatives {
+            point: self.get_point(tube_angle, angle),
+            du: (self.axis * cos - radial * sin) * self.minor_radius,
+            dv: tangent * distance,
+            duu: -(radial * cos + self.axis * sin) * self.minor_radius,
+            duv: tangent * (-sin * self.minor_radius),
+            dvv: -radial * distance,
+        }
+    }
+
+    /// Normals point away from the center circle of the tube
+    fn get_normals(&self, params: &[Point2]) -> Vec<Vec3> {
+        params
+            .iter()
+            .map(|p| {
+                let radial = Quat::from_axis_angle(self.axis, p.y) * self.ref_dir;
+                let (sin, cos) = p.x.sin_cos();
+                radial * cos + self.axis * sin
+            })
+            .collect()
+    }
+
+    fn periods(&self) -> (Option<Float>, Option<Float>) {
+        (Some(TAU), Some(TAU))
+    }
+
+    fn nearest_parameters(&self, point: Point3) -> Option<Point2> {
+        Some(self.project(point))
+    }
+
+    fn trim_polygons(&self, bounds: &[Vec<Point3>]) -> TriangleMesh {
+        let params = project_polygons(bounds, |point| self.project(point));
+        self.trim_parameter_polygons(bounds, &params)
+    }
+
+    fn trim_parameter_polygons(
+        &self,
+        bounds: &[Vec<Point3>],
+        params: &[Vec<Point2>],
+    ) -> TriangleMesh {
+        trim_in_parameter_space(self, bounds, params)
+    }
+}
+
+#[test]
+fn test_torus_projection() {
+    let torus = Torus {
+        center: Point3::new(0.0, 0.0, 1.0),
+        axis: Vec3::Z,
+        ref_dir: Vec3::X,
+        major_radius: 3.0,
+        minor_radius: 1.0,
+    };
+    let point = torus.get_point(TAU / 4.0, TAU / 2.0);
+    assert!(
+        point.distance(Point3::new(-3.0, 0.0, 2.0)) < 1e-9,
+        "{:?}",
+        point
+    );
+    let param = torus.project(point);
+    assert!(param.distance(Point2::new(TAU / 4.0, TAU / 2.0)) < 1e-9);
+}
+
+#[test]
+fn test_torus_whole_domain() {
+    let torus = Torus {
+        center: Point3::ZERO,
+        axis: Vec3::Z,
+        ref_dir: Vec3::X,
+        major_radius: 3.0,
+        minor_radius: 1.0,
+    };
+    let circle = |from: Point2, to: Point2| {
+        (0..32)
+            .map(|i| {
+                let p = from.lerp(to, i as Float / 32.0);
+                torus.get_point(p.x, p.y)
+            })
+            .collect::<Vec<_>>()
+    };
+    // area of the triangles seen from the outside of the tube
+    let area = |mesh: &TriangleMesh| {
+        let mut area = 0.0;
+        for triangle in mesh.triangles.chunks(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| mesh.vertices[triangle[i] as usize]);
+            let param = torus.project((a + b + c) / 3.0);
+            area += (b - a).cross(c - a).dot(torus.get_normals(&[param])[0]) / 2.0;
+        }
+        area
+    };
+    let torus_area = TAU * TAU * torus.major_radius * torus.minor_radius;
+
+    // without loops the face is the whole torus
+    let mesh = torus.trim_polygons(&[]);
+    assert!((area(&mesh) / torus_area - 1.0).abs() < 0.02);
+
+    // loops around both periods bound no region, a small loop is a hole
+    let corners = [(1.0, 1.0), (1.0, 2.0), (2.0, 2.0), (2.0, 1.0)].map(|(u, v)| Point2::new(u, v));
+    let hole = (0..4)
+        .flat_map(|i| {
+            let (from, to) = (corners[i], corners[(i + 1) % 4]);
+            (0..8).map(move |k| from.lerp(to, k as Float / 8.0))
+        })
+        .map(|p| torus.get_point(p.x, p.y))
+        .collect::<Vec<_>>();
+    let mesh = torus.trim_polygons(&[
+        circle(Point2::new(0.0, 0.0), Point2::new(TAU, 0.0)),
+        circle(Point2::new(0.0, 0.0), Point2::new(0.0, TAU)),
+        hole,
+    ]);
+    let hole_area = torus.minor_radius * (torus.major_radius + 2.0_f64.sin() - 1.0_f64.sin());
+    assert!(((area(&mesh) + hole_area) / torus_area - 1.0).abs() < 0.01);
+    for triangle in mesh.triangles.chunks(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| mesh.vertices[triangle[i] as usize]);
+        let param = torus.project((a + b + c) / 3.0);
+        assert!(!(1.05..1.95).contains(&param.x) || !(1.05..1.95).contains(&param.y));
+    }
+}
@@ -0,0 +1,388 @@
+use crate::curve::{Curve, CurveSegment};
+use crate::surface::join_edges;
+use crate::{Float, Point2, Point3};
+
+/// Boundary polygons and the parameters of their vertices on the surface
+pub type ParameterPolygons = (Vec<Vec<Point3>>, Vec<Vec<Point2>>);
+
+/// A point shared by the edges meeting at it.
+#[derive(Debug)]
+pub struct Vertex {
+    pub point: Point3,
+}
+
+/// A curve segment bounded by two vertices, shared by the faces on both sides.
+#[derive(Debug)]
+pub struct Edge {
+    /// Index of start vertex
+    pub start: usize,
+    /// Index of end vertex
+    pub end: usize,
+    /// The curve runs from start vertex to end vertex
+    pub curve: CurveSegment<Box<dyn Curve>>,
+    /// Uses of the edge by face loops, a manifold edge has two co-edges.
+    pub coedges: Vec<usize>,
+}
+
+/// A use of an edge by a loop.
+#[derive(Debug)]
+pub struct CoEdge {
+    pub edge: usize,
+    pub edge_loop: usize,
+    /// Whether the loop traverses the edge from start to end
+    pub same_sense: bool,
+    /// Curve in the parameter space of the face surface with points `(u, v, 0)`,
+    /// it shares the parameters of the edge curve.
+    pub pcurve: Option<Box<dyn Curve>>,
+}
+
+/// A closed chain of co-edges bounding a face.
+#[derive(Debug)]
+pub struct Loop {
+    pub face: usize,
+    pub coedges: Vec<usize>,
+    /// Whether the loop keeps its orientation in the face
+    pub same_sense: bool,
+}
+
+/// A face bounded by loops, its index is the same as the face in the model.
+#[derive(Debug)]
+pub struct Face {
+    pub loops: Vec<usize>,
+    /// Whether the face normal agrees with the normal of the surface in the source file
+    pub same_sense: bool,
+}
+
+/// A set of faces connected by edges.
+#[derive(Debug)]
+pub struct Shell {
+    pub faces: Vec<usize>,
+}
+
+/// A solid bounded by an outer shell and void shells.
+#[derive(Debug)]
+pub struct Solid {
+    pub outer: usize,
+    pub voids: Vec<usize>,
+}
+
+/// Boundary representation connecting faces through shared edges and vertices.
+#[derive(Debug, Default)]
+pub struct Topology {
+    pub vertices: Vec<Vertex>,
+    pub edges: Vec<Edge>,
+    pub coedges: Vec<CoEdge>,
+    pub loops: Vec<Loop>,
+    pub faces: Vec<Face>,
+    pub shells: Vec<Shell>,
+    pub solids: Vec<Solid>,
+}
+
+impl Topology {
+    pub fn new() -> Topology {
+        Topology::default()
+    }
+
+    pub fn add_vertex(&mut self, point: Point3) -> usize {
+        self.vertices.push(Vertex { point });
+        self.vertices.len() - 1
+    }
+
+    pub fn add_edge(
+        &mut self,
+        start: usize,
+        end: usize,
+        curve: CurveSegment<Box<dyn Curve>>,
+    ) -> usize {
+        self.edges.push(Edge {
+            start,
+            end,
+            curve,
+            coedges: Vec::new(),
+        });
+        self.edges.len() - 1
+    }
+
+    pub fn add_face(&mut self) -> usize {
+        self.faces.push(Face {
+            loops: Vec::new(),
+            same_sense: true,
+        });
+        self.faces.len() - 1
+    }
+
+    /// Add a loop to the face, each edge is given with whether it is traversed from start to end
+    pub fn add_loop(&mut self, face: usize, edges: &[(usize, bool)], same_sense: bool) -> usize {
+        let edge_loop = self.loops.len();
+        let mut coedges = Vec::with_capacity(edges.len());
+        for &(edge, sense) in edges {
+            let coedge = self.coedges.len();
+            self.coedges.push(CoEdge {
+                edge,
+                edge_loop,
+                same_sense: sense,
+                pcurve: None,
+            });
+            self.edges[edge].coedges.push(coedge);
+            coedges.push(coedge);
+        }
+        self.loops.push(Loop {
+            face,
+            coedges,
+            same_sense,
+        });
+        self.faces[face].loops.push(edge_loop);
+        edge_loop
+    }
+
+    pub fn add_shell(&mut self, faces: Vec<usize>) -> usize {
+        self.shells.push(Shell { faces });
+        self.shells.len() - 1
+    }
+
+    pub fn add_solid(&mut self, outer: usize, voids: Vec<usize>) -> usize {
+        self.solids.push(Solid { outer, voids });
+        self.solids.len() - 1
+    }
+
+    /// Face using the co-edge
+    pub fn coedge_face(&self, coedge: usize) -> usize {
+        self.loops[self.coedges[coedge].edge_loop].face
+    }
+
+    /// Edges bounding the face
+    pub fn face_edges(&self, face: usize) -> Vec<usize> {
+        self.faces[face]
+            .loops
+            .iter()
+            .flat_map(|&edge_loop| &self.loops[edge_loop].coedges)
+            .map(|&coedge| self.coedges[coedge].edge)
+            .collect()
+    }
+
+    /// Faces sharing at least one edge with the face
+    pub fn face_neighbours(&self, face: usize) -> Vec<usize> {
+        let mut neighbours = self
+            .face_edges(face)
+            .into_iter()
+            .flat_map(|edge| &self.edges[edge].coedges)
+            .map(|&coedge| self.coedge_face(coedge))
+            .filter(|&neighbour| neighbour != face)
+            .collect::<Vec<_>>();
+        neighbours.sort_unstable();
+        neighbours.dedup();
+        neighbours
+    }
+
+    /// Edges used by only one face loop
+    pub fn free_edges(&self) -> Vec<usize> {
+        self.edges
+            .iter()
+            .enumerate()
+            .filter(|(_, edge)| edge.coedges.len() == 1)
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Seam edges of the face, each used by two of its co-edges running in opposite
+    /// directions along both sides of a closed surface's parameter domain
+    pub fn seam_edges(&self, face: usize) -> Vec<(usize, [usize; 2])> {
+        let mut uses = std::collections::BTreeMap::<usize, Vec<usize>>::new();
+        for &edge_loop in &self.faces[face].loops {
+            for &coedge in &self.loops[edge_loop].coedges {
+                uses.entry(self.coedges[coedge].edge)
+                    .or_default()
+                    .push(coedge);
+            }
+        }
+        uses.into_iter()
+            .filter(|(_, coedges)| {
+                coedges.len() == 2 && {
+                    let sense = |coedge: usize| {
+                        let coedge = &self.coedges[coedge];
+                        coedge.same_sense == self.loops[coedge.edge_loop].same_sense
+                    };
+                    sense(coedges[0]) != sense(coedges[1])
+                }
+            })
+            .map(|(edge, coedges)| (edge, [coedges[0], coedges[1]]))
+            .collect()
+    }
+
+    /// Parameters discretizing every edge curve within its tolerance
+    pub fn discretize_edge_parameters(&self) -> Vec<Vec<Float>> {
+        self.edges
+            .iter()
+            .map(|edge| edge.curve.get_parameters())
+            .collect()
+    }
+
+    /// Discretize every edge once, the end points are the exact vertex points
+    /// so that edges meeting at a vertex share it.
+    pub fn discretize_edges(&self) -> Vec<Vec<Point3>> {
+        self.discretize_edge_parameters()
+            .iter()
+            .zip(&self.edges)
+            .map(|(parameters, edge)| {
+                let mut points = parameters
+                    .iter()
+                    .map(|&u| edge.curve.curve.get_point(u))
+                    .collect::<Vec<_>>();
+                if let Some(first) = points.first_mut() {
+                    *first = self.vertices[edge.start].point;
+                }
+                if let Some(last) = points.last_mut() {
+                    *last = self.vertices[edge.end].point;
+                }
+                points
+            })
+            .collect()
+    }
+
+    /// Boundary polygons of the face assembled from discretized edges
+    pub fn face_polygons(&self, face: usize, edge_points: &[Vec<Point3>]) -> Vec<Vec<Point3>> {
+        self.faces[face]
+            .loops
+            .iter()
+            .map(|&edge_loop| {
+                self.loop_polygon(edge_loop, |coedge| {
+                    Some(edge_points[coedge.edge].iter().map(|&p| (p, ())).collect())
+                })
+                .unwrap()
+                .into_iter()
+                .map(|(point, _)| point)
+                .collect()
+            })
+            .collect()
+    }
+
+    /// Boundary polygons of the face with parameters of their vertices given by
+    /// the co-edge pcurves, returns `None` when some co-edge has no pcurve.
+    pub fn face_parameter_polygons(
+        &self,
+        face: usize,
+        edge_parameters: &[Vec<Float>],
+        edge_points: &[Vec<Point3>],
+    ) -> Option<ParameterPolygons> {
+        let polygons = self.faces[face]
+            .loops
+            .iter()
+            .map(|&edge_loop| {
+                self.loop_polygon(edge_loop, |coedge| {
+                    let pcurve = coedge.pcurve.as_ref()?;
+                    let points = edge_points[coedge.edge].iter();
+                    let params = edge_parameters[coedge.edge]
+                        .iter()
+                        .map(|&u| pcurve.get_point(u).truncate());
+                    Some(points.copied().zip(params).collect())
+                })
+                .map(|polygon| polygon.into_iter().unzip())
+            })
+            .collect::<Option<Vec<(Vec<_>, Vec<_>)>>>()?;
+        Some(polygons.into_iter().unzip())
+    }
+
+    /// Polygon of a loop from points of its co-edges in the sense of the edges
+    fn loop_polygon<T>(
+        &self,
+        edge_loop: usize,
+        coedge_points: impl Fn(&CoEdge) -> Option<Vec<(Point3, T)>>,
+    ) -> Option<Vec<(Point3, T)>> {
+        let edge_loop = &self.loops[edge_loop];
+        let mut points = Vec::new();
+        for &coedge in &edge_loop.coedges {
+            let coedge = &self.coedges[coedge];
+            let mut edge_points = coedge_points(coedge)?;
+            if !coedge.same_sense {
+                edge_points.reverse();
+            }
+            points.push(edge_points);
+        }
+        let mut polygon = join_edges(points.into_iter(), |p| p.0);
+        if !edge_loop.same_sense {
+            polygon.reverse();
+        }
+        Some(polygon)
+    }
+
+    /// A shell is closed when every edge of its faces is used twice in opposite directions
+    pub fn is_shell_closed(&self, shell: usize) -> bool {
+        let faces = &self.shells[shell].faces;
+        let mut uses = std::collections::HashMap::<usize, Vec<bool>>::new();
+        for &face in faces {
+            for &edge_loop in &self.faces[face].loops {
+                let edge_loop = &self.loops[edge_loop];
+                for &coedge in &edge_loop.coedges {
+                    let coedge = &self.coedges[coedge];
+                    uses.entry(coedge.edge)
+                        .or_default()
+                        .push(coedge.same_sense == edge_loop.same_sense);
+                }
+            }
+        }
+        !uses.is_empty()
+            && uses
+                .values()
+                .all(|senses| senses.len() == 2 && senses[0] != senses[1])
+    }
+}
+
+#[test]
+fn test_tetrahedron_topology() {
+    use crate::curve::Line;
+
+    let points = [
+        Point3::new(0.0, 0.0, 0.0),
+        Point3::new(1.0, 0.0, 0.0),
+        Point3::new(0.0, 1.0, 0.0),
+        Point3::new(0.0, 0.0, 1.0),
+    ];
+    let mut topology = Topology::new();
+    for &point in &points {
+        topology.add_vertex(point);
+    }
+    let mut edge = |start: usize, end: usize| {
+        let line = Line {
+            origin: points[start],
+            direction: points[end] - points[start],
+        };
+        let curve = CurveSegment {
+            curve: Box::new(line) as Box<dyn Curve>,
+            parameter_range: (0.0, 1.0),
+            tolerance: 0.01,
+            parameter_division: 1,
+        };
+        topology.add_edge(start, end, curve)
+    };
+    let edges = [
+        edge(0, 1),
+        edge(1, 2),
+        edge(2, 0),
+        edge(0, 3),
+        edge(1, 3),
+        edge(2, 3),
+    ];
+    let loops = [
+        vec![(edges[0], false), (edges[2], false), (edges[1], false)],
+        vec![(edges[0], true), (edges[4], true), (edges[3], false)],
+        vec![(edges[1], true), (edges[5], true), (edges[4], false)],
+        vec![(edges[2], true), (edges[3], true), (edges[5], false)],
+    ];
+    let mut faces = Vec::new();
+    for coedges in &loops[..3] {
+        let face = topology.add_face();
+        topology.add_loop(face, coedges, true);
+        faces.push(face);
+    }
+    let shell = topology.add_shell(faces.clone());
+    assert!(!topology.is_shell_closed(shell));
+    assert_eq!(topology.free_edges(), vec![edges[2], edges[3], edges[5]]);
+    assert_eq!(topology.face_neighbours(faces[0]), vec![faces[1], faces[2]]);
+
+    let face = topology.add_face();
+    topology.add_loop(face, &loops[3], true);
+    topology.shells[shell].faces.push(face);
+    assert!(topology.is_shell_closed(shell));
+    assert!(topology.free_edges().is_empty());
+    assert_eq!(topology.face_neighbours(face), vec![0, 1, 2]);
+}
@@ -65,24 +65,142 @@ pub fn uniform_divide(range: (Float, Float), division: usize) -> Vec<Float> {
     parameters
 }
 
+/// Tolerances controlling how finely a curve is divided into line segments
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CurveTolerance {
+    /// Maximum distance between a line segment and the curve
+    pub chord_height: Float,
+    /// Maximum length of a line segment
+    pub max_length: Float,
+    /// Maximum angle between adjacent line segments in radians
+    pub max_angle: Float,
+}
+
+impl Default for CurveTolerance {
+    fn default() -> Self {
+        CurveTolerance {
+            chord_height: 0.01,
+            max_length: Float::INFINITY,
+            max_angle: crate::consts::PI / 12.0,
+        }
+    }
+}
+
+impl CurveTolerance {
+    /// Default tolerance with the given chord height
+    pub fn with_chord_height(chord_height: Float) -> Self {
+        CurveTolerance {
+            chord_height,
+            ..Default::default()
+        }
+    }
+}
+
+/// Tolerances controlling how finely a surface is divided into triangles
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SurfaceTolerance {
+    /// Maximum distance between a triangle and the surface
+    pub chord_height: Float,
+    /// Maximum angle between the surface normals over a triangle in radians
+    pub max_angle: Float,
+    /// Maximum length of a triangle edge
+    pub max_length: Float,
+    /// Triangles are not divided further once their edges are shorter
+    pub min_length: Float,
+}
+
+impl Default for SurfaceTolerance {
+    fn default() -> Self {
+        SurfaceTolerance {
+            chord_height: 0.01,
+            max_angle: crate::consts::PI / 12.0,
+            max_length: Float::INFINITY,
+            min_length: 0.0,
+        }
+    }
+}
+
+impl SurfaceTolerance {
+    /// Default tolerance with the given chord height
+    pub fn with_chord_height(chord_height: Float) -> Self {
+        SurfaceTolerance {
+            chord_height,
+            ..Default::default()
+        }
+    }
+}
+
+/// Limit of range bisections, a range is divided into at most 2^16 spans
+const MAX_DIVISION_DEPTH: usize = 16;
+
 /// Creates the curve division
-pub fn parameter_division(curve: &dyn Curve, range: (Float, Float), tol: Float) -> Vec<Float> {
-    let p = 0.5 + (0.2 * rand::random::<Float>() - 0.1);
-    let t = range_at(range, p);
-    let pt0 = curve.get_point(range.0);
-    let pt1 = curve.get_point(range.1);
-    let mid = pt0 + (pt1 - pt0) * p;
-    if curve.get_point(t).distance(mid) < tol {
-        vec![range.0, range.1]
+///
+/// The range is bisected until every span satisfies the tolerance, the result is deterministic.
+/// # Examples
+/// ```
+/// use geom3d::curve::Line;
+/// use geom3d::utils::*;
+/// use geom3d::{Point3, Vec3};
+/// let line = Line { origin: Point3::ZERO, direction: Vec3::X };
+/// let tolerance = CurveTolerance { max_length: 0.25, ..Default::default() };
+/// assert_eq!(
+///     parameter_division(&line, (0.0, 1.0), &tolerance),
+///     vec![0.0, 0.25, 0.5, 0.75, 1.0]
+/// );
+/// ```
+pub fn parameter_division(
+    curve: &dyn Curve,
+    range: (Float, Float),
+    tolerance: &CurveTolerance,
+) -> Vec<Float> {
+    let mut parameters = vec![range.0];
+    let ends = (curve.get_point(range.0), curve.get_point(range.1));
+    divide_range(curve, range, ends, tolerance, 0, &mut parameters);
+    parameters
+}
+
+fn divide_range(
+    curve: &dyn Curve,
+    (t0, t1): (Float, Float),
+    (p0, p1): (Point3, Point3),
+    tolerance: &CurveTolerance,
+    depth: usize,
+    parameters: &mut Vec<Float>,
+) {
+    let tm = (t0 + t1) / 2.0;
+    let pm = curve.get_point(tm);
+    if depth < MAX_DIVISION_DEPTH && exceeds_tolerance(curve, (t0, t1), (p0, pm, p1), tolerance) {
+        divide_range(curve, (t0, tm), (p0, pm), tolerance, depth + 1, parameters);
+        divide_range(curve, (tm, t1), (pm, p1), tolerance, depth + 1, parameters);
     } else {
-        let mid = (range.0 + range.1) / 2.0;
-        let mut res = parameter_division(curve, (range.0, mid), tol);
-        let _ = res.pop();
-        res.extend(parameter_division(curve, (mid, range.1), tol));
-        res
+        parameters.push(t1);
     }
 }
 
+fn exceeds_tolerance(
+    curve: &dyn Curve,
+    range: (Float, Float),
+    (p0, pm, p1): (Point3, Point3, Point3),
+    tolerance: &CurveTolerance,
+) -> bool {
+    if p0.distance(p1) > tolerance.max_length {
+        return true;
+    }
+    let (v0, v1) = (pm - p0, p1 - pm);
+    if v0.length_squared() > TOLERANCE2
+        && v1.length_squared() > TOLERANCE2
+        && v0.angle_between(v1) > tolerance.max_angle
+    {
+        return true;
+    }
+    // check quarter points as well, the middle point alone can lie on the chord of an S-shaped span
+    [0.25, 0.75]
+        .iter()
+        .map(|&ratio| curve.get_point(range_at(range, ratio)))
+        .chain(std::iter::once(pm))
+        .any(|point| distance_to_line_segment(p0, p1, point) > tolerance.chord_height)
+}
+
 pub fn range_at((start, end): (Float, Float), ratio: Float) -> Float {
     start + (end - start) * ratio
 }
@@ -129,7 +247,7 @@ pub fn distance_to_line_segment(a: Point3, b: Point3, p: Point3) -> Float {
         let bp = p - b;
         return bp.length();
     }
-    return ap.cross(ab).length() / ab.length();
+    ap.cross(ab).length() / ab.length()
 }
 
 use crate::curve::Curve;
@@ -184,9 +302,41 @@ pub fn get_min_max_by_key<T, F: Fn(&T) -> Float>(items: &Vec<T>, key: F) -> (Flo
     (min_value, max_value)
 }
 
+mod delaunay;
+mod linear;
 mod meshgen;
 mod point;
 mod polygon;
+mod predicates;
+pub use delaunay::*;
+pub use linear::*;
 pub use meshgen::*;
 pub use point::*;
 pub use polygon::*;
+pub use predicates::*;
+
+#[test]
+fn test_circle_division() {
+    use crate::curve::Circle;
+    let circle = Circle {
+        center: Point3::ZERO,
+        radius: 10.0,
+        axis: crate::Vec3::Z,
+        ref_dir: crate::Vec3::X,
+    };
+    let range = (0.0, crate::consts::TAU);
+    let tolerance = CurveTolerance {
+        chord_height: 0.01,
+        max_length: 1.0,
+        max_angle: 0.1,
+    };
+    let parameters = parameter_division(&circle, range, &tolerance);
+    assert_eq!(parameters, parameter_division(&circle, range, &tolerance));
+    assert_eq!(parameters.len(), 129);
+    for pair in parameters.windows(2) {
+        let (p0, p1) = (circle.get_point(pair[0]), circle.get_point(pair[1]));
+        let middle = circle.get_point((pair[0] + pair[1]) / 2.0);
+        assert!(p0.distance(p1) <= tolerance.max_length);
+        assert!(distance_to_line_segment(p0, p1, middle) <= tolerance.chord_height);
+    }
+}
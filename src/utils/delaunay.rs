@@ -0,0 +1,891 @@
+use super::{incircle, orient2d};
+use crate::{Float, Point2, Vec3};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+const NONE: usize = usize::MAX;
+
+/// Targets of Delaunay refinement
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeshQuality {
+    /// Triangles with a smaller angle in radians are refined, refinement ends for angles
+    /// up to about 20 degrees, small angles between input segments are kept
+    pub min_angle: Float,
+    /// Triangles with a longer edge are refined
+    pub max_length: Float,
+    /// Whether segments may be split by new points, otherwise the input boundary is kept
+    pub split_segments: bool,
+    /// Limit of points added by refinement
+    pub max_points: usize,
+}
+
+impl Default for MeshQuality {
+    fn default() -> Self {
+        MeshQuality {
+            min_angle: crate::consts::PI / 9.0,
+            max_length: Float::INFINITY,
+            split_segments: true,
+            max_points: 100_000,
+        }
+    }
+}
+
+/// Symmetric positive definite tensor measuring lengths in the plane,
+/// the squared length of `v` is `xx * v.x² + 2 * xy * v.x * v.y + yy * v.y²`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Metric {
+    pub xx: Float,
+    pub xy: Float,
+    pub yy: Float,
+}
+
+impl Metric {
+    /// Euclidean lengths
+    pub const IDENTITY: Metric = Metric {
+        xx: 1.0,
+        xy: 0.0,
+        yy: 1.0,
+    };
+
+    /// First fundamental form of a surface with the partial derivatives,
+    /// lengths in parameter space become lengths on the surface
+    pub fn from_derivatives(du: Vec3, dv: Vec3) -> Self {
+        Metric {
+            xx: du.dot(du),
+            xy: du.dot(dv),
+            yy: dv.dot(dv),
+        }
+    }
+
+    /// Mean of the metrics, the identity when there are none
+    pub fn mean(metrics: &[Metric]) -> Self {
+        if metrics.is_empty() {
+            return Metric::IDENTITY;
+        }
+        let n = metrics.len() as Float;
+        let sum = metrics.iter().fold([0.0; 3], |sum, m| {
+            [sum[0] + m.xx, sum[1] + m.xy, sum[2] + m.yy]
+        });
+        Metric {
+            xx: sum[0] / n,
+            xy: sum[1] / n,
+            yy: sum[2] / n,
+        }
+    }
+
+    pub fn length(&self, v: Point2) -> Float {
+        self.to_euclidean(v).length()
+    }
+
+    /// Map a vector to coordinates where the metric is Euclidean,
+    /// degenerate metrics leave vectors unchanged
+    pub fn to_euclidean(&self, v: Point2) -> Point2 {
+        match self.factor() {
+            Some((a, b, c)) => Point2::new(a * v.x + b * v.y, c * v.y),
+            None => v,
+        }
+    }
+
+    /// Inverse of `to_euclidean`
+    pub fn from_euclidean(&self, v: Point2) -> Point2 {
+        match self.factor() {
+            Some((a, b, c)) => {
+                let y = v.y / c;
+                Point2::new((v.x - b * y) / a, y)
+            }
+            None => v,
+        }
+    }
+
+    /// Upper triangular Cholesky factor `[[a, b], [0, c]]`
+    fn factor(&self) -> Option<(Float, Float, Float)> {
+        let det = self.xx * self.yy - self.xy * self.xy;
+        if !(self.xx > 0.0 && det > Float::EPSILON * self.xx * self.yy) {
+            return None;
+        }
+        let a = self.xx.sqrt();
+        Some((a, self.xy / a, (det / self.xx).sqrt()))
+    }
+}
+
+/// Constrained Delaunay triangulation of a domain bounded by polygons.
+///
+/// Triangles are counter-clockwise, the Delaunay property holds between points which see
+/// each other without crossing a segment. Predicates are exact, so nearly collinear
+/// and touching loops are triangulated consistently.
+/// # Examples
+/// ```
+/// use geom3d::{utils::*, Point2};
+/// // a square with a square hole
+/// let points = [(0.0, 0.0), (4.0, 0.0), (4.0, 4.0), (0.0, 4.0), (1.0, 1.0), (1.0, 3.0), (3.0, 3.0), (3.0, 1.0)]
+///     .iter()
+///     .map(|&(x, y)| Point2::new(x, y))
+///     .collect::<Vec<_>>();
+/// let mesh = ConstrainedDelaunay::new(&points, &[0, 4, 8]);
+/// assert_eq!(mesh.triangles().len(), 8 * 3);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ConstrainedDelaunay {
+    /// Input points, three points of the enclosing triangle and added points
+    points: Vec<Point2>,
+    input_count: usize,
+    /// Counter-clockwise vertices, edge `k` runs from vertex `k` to the next one
+    triangles: Vec<[usize; 3]>,
+    /// Triangles across the edges, `NONE` outside the domain
+    neighbors: Vec<[usize; 3]>,
+    /// Segments which are kept as edges, keyed by sorted vertex pairs
+    segments: HashSet<(usize, usize)>,
+    /// Segments of the bounding polygons, crossing one enters or leaves the domain
+    boundary: HashSet<(usize, usize)>,
+    /// Vertex replacing each input point, points repeated in the input share one vertex
+    aliases: Vec<usize>,
+    /// A triangle around each vertex
+    vertex_triangles: Vec<usize>,
+}
+
+enum Location {
+    /// The point is inside or on the triangle
+    Inside(usize),
+    /// The way to the point crosses the segment at the edge of the triangle
+    Blocked(usize, usize),
+}
+
+fn key(a: usize, b: usize) -> (usize, usize) {
+    (a.min(b), a.max(b))
+}
+
+impl ConstrainedDelaunay {
+    /// Triangulate the domain bounded by polygons, `polygons` holds the start of each polygon
+    /// in the points and the end of the last one. The domain is covered an odd number of times
+    /// by the polygons, so holes are found whatever the polygon orientations are.
+    pub fn new(points: &[Point2], polygons: &[usize]) -> Self {
+        let input_count = points.len();
+        let (min, max) = points.iter().fold(
+            (Point2::splat(Float::MAX), Point2::splat(Float::MIN)),
+            |(min, max), p| (min.min(*p), max.max(*p)),
+        );
+        let (center, size) = if points.is_empty() {
+            (Point2::ZERO, 1.0)
+        } else {
+            ((min + max) / 2.0, (max - min).max_element().max(1.0))
+        };
+        let mut points = points.to_vec();
+        points.extend_from_slice(&[
+            center + Point2::new(-20.0, -10.0) * size,
+            center + Point2::new(20.0, -10.0) * size,
+            center + Point2::new(0.0, 20.0) * size,
+        ]);
+        let enclosing = [input_count, input_count + 1, input_count + 2];
+        let mut mesh = ConstrainedDelaunay {
+            points,
+            input_count,
+            triangles: vec![enclosing],
+            neighbors: vec![[NONE; 3]],
+            segments: HashSet::new(),
+            boundary: HashSet::new(),
+            aliases: (0..input_count).collect(),
+            vertex_triangles: vec![0; input_count + 3],
+        };
+        let mut hint = 0;
+        for index in 0..input_count {
+            let point = mesh.points[index];
+            if let Location::Inside(triangle) = mesh.locate(point, hint, false) {
+                match mesh.triangles[triangle]
+                    .iter()
+                    .find(|&&v| mesh.points[v] == point)
+                {
+                    Some(&vertex) => mesh.aliases[index] = vertex,
+                    None => {
+                        mesh.insert_vertex(index, triangle, None);
+                    }
+                }
+                hint = mesh.vertex_triangles[mesh.aliases[index]];
+            }
+        }
+        for pair in polygons.windows(2) {
+            let (start, end) = (pair[0], pair[1]);
+            for i in start..end {
+                let next = if i + 1 == end { start } else { i + 1 };
+                let (a, b) = (mesh.aliases[i], mesh.aliases[next]);
+                for (a, b) in mesh.insert_segment(a, b) {
+                    // a segment covered twice cancels out
+                    if !mesh.boundary.insert(key(a, b)) {
+                        mesh.boundary.remove(&key(a, b));
+                    }
+                }
+            }
+        }
+        mesh.remove_exterior();
+        mesh
+    }
+
+    /// Add a segment inside the domain which is kept as an edge
+    pub fn add_segment(&mut self, a: Point2, b: Point2) {
+        if let (Some(a), Some(b)) = (self.add_point(a), self.add_point(b)) {
+            self.insert_segment(a, b);
+        }
+    }
+
+    /// Add a point inside the domain and return its index, `None` outside the domain
+    pub fn add_point(&mut self, point: Point2) -> Option<usize> {
+        if self.triangles.is_empty() {
+            return None;
+        }
+        // the walk stops at holes, points on the boundary are inside
+        let triangle = match self.locate(point, 0, false) {
+            Location::Inside(triangle) => triangle,
+            Location::Blocked(..) => self.find_triangle(point)?,
+        };
+        if let Some(&vertex) = self.triangles[triangle]
+            .iter()
+            .find(|&&v| self.points[v] == point)
+        {
+            return Some(vertex);
+        }
+        // a point on a segment splits it
+        let split = (0..3)
+            .map(|k| {
+                let (a, b) = self.edge(triangle, k);
+                key(a, b)
+            })
+            .find(|&(a, b)| {
+                self.segments.contains(&key(a, b))
+                    && orient2d(self.points[a], self.points[b], point) == 0.0
+            });
+        self.points.push(point);
+        let vertex = self.points.len() - 1;
+        self.vertex_triangles.push(triangle);
+        self.insert_vertex(vertex, triangle, split);
+        Some(vertex)
+    }
+
+    /// Points of the triangulation, the input points come first
+    pub fn points(&self) -> Vec<Point2> {
+        let mut points = self.points[..self.input_count].to_vec();
+        points.extend_from_slice(&self.points[self.input_count + 3..]);
+        points
+    }
+
+    /// Indices of the triangle vertices into the points
+    pub fn triangles(&self) -> Vec<u32> {
+        let index = |v: usize| {
+            if v < self.input_count {
+                v as u32
+            } else {
+                (v - 3) as u32
+            }
+        };
+        self.triangles.iter().flatten().map(|&v| index(v)).collect()
+    }
+
+    /// Insert points until the triangles meet the quality, first at the middle of segments
+    /// seen at an obtuse angle, then at the circumcenters of bad triangles.
+    pub fn refine(&mut self, quality: &MeshQuality) {
+        self.refine_in_metric(quality, |_| Metric::IDENTITY);
+    }
+
+    /// Refine with angles and lengths measured in the metric at each triangle, so the triangles
+    /// are well shaped in the metric, e.g. on a surface meshed in its parameter space.
+    /// New points are connected to the points around them by the Delaunay criterion in the
+    /// metric at the refined triangle, which suits metrics varying slowly over a triangle.
+    pub fn refine_in_metric(&mut self, quality: &MeshQuality, metric: impl Fn(Point2) -> Metric) {
+        let limit = self.points.len() + quality.max_points;
+        let min_sine = quality.min_angle.sin();
+        let mut encroached = self
+            .segments
+            .iter()
+            .copied()
+            .filter(|&(a, b)| self.is_encroached(a, b))
+            .collect::<VecDeque<_>>();
+        let mut bad = (0..self.triangles.len()).collect::<VecDeque<_>>();
+        let mut rejected = HashSet::new();
+        while self.points.len() < limit {
+            if let Some((a, b)) = encroached.pop_front() {
+                if quality.split_segments && self.segments.contains(&(a, b)) {
+                    let triangles = self.split_segment(a, b);
+                    self.check_triangles(&triangles, &mut encroached, &mut bad);
+                }
+                continue;
+            }
+            let triangle = match bad.pop_front() {
+                Some(triangle) => triangle,
+                None => break,
+            };
+            if triangle >= self.triangles.len() || rejected.contains(&self.triangles[triangle]) {
+                continue;
+            }
+            let vertices = self.triangles[triangle];
+            let [a, b, c] = vertices.map(|v| self.points[v]);
+            let metric = metric((a + b + c) / 3.0);
+            let [a, b, c] = [a, b, c].map(|p| metric.to_euclidean(p));
+            if !is_bad([a, b, c], min_sine, quality.max_length) {
+                continue;
+            }
+            let center = metric.from_euclidean(circumcenter([a, b, c]));
+            // segments hiding the circumcenter or encroached by it are split instead
+            let (blocking, cavity, edges) = match self.locate(center, triangle, true) {
+                Location::Inside(located) => {
+                    let (cavity, edges) = self.cavity(center, located, None, &metric);
+                    let blocking = edges
+                        .iter()
+                        .filter(|&&(a, b, _)| self.segments.contains(&key(a, b)))
+                        .filter(|&&(a, b, _)| {
+                            (self.points[a] - center).dot(self.points[b] - center) <= 0.0
+                        })
+                        .map(|&(a, b, _)| key(a, b))
+                        .collect::<Vec<_>>();
+                    (blocking, cavity, edges)
+                }
+                Location::Blocked(located, edge) => {
+                    let (a, b) = self.edge(located, edge);
+                    let blocking = Some(key(a, b))
+                        .filter(|segment| self.segments.contains(segment))
+                        .into_iter()
+                        .collect();
+                    (blocking, Vec::new(), Vec::new())
+                }
+            };
+            let collapsed = edges
+                .iter()
+                .any(|&(a, b, _)| orient2d(self.points[a], self.points[b], center) <= 0.0);
+            if !blocking.is_empty() && quality.split_segments {
+                encroached.extend(blocking);
+                bad.push_back(triangle);
+                continue;
+            }
+            if cavity.is_empty() || !blocking.is_empty() || collapsed {
+                rejected.insert(vertices);
+                continue;
+            }
+            self.points.push(center);
+            let vertex = self.points.len() - 1;
+            self.vertex_triangles.push(cavity[0]);
+            let triangles = self.fill_cavity(vertex, &cavity, &edges);
+            self.check_triangles(&triangles, &mut encroached, &mut bad);
+        }
+    }
+
+    /// Queue the segments of new triangles encroached by their apexes and the triangles
+    fn check_triangles(
+        &self,
+        triangles: &[usize],
+        encroached: &mut VecDeque<(usize, usize)>,
+        bad: &mut VecDeque<usize>,
+    ) {
+        for &triangle in triangles {
+            for k in 0..3 {
+                let (a, b) = self.edge(triangle, k);
+                if self.segments.contains(&key(a, b)) && self.is_encroached(a, b) {
+                    encroached.push_back(key(a, b));
+                }
+            }
+            bad.push_back(triangle);
+        }
+    }
+
+    fn edge(&self, triangle: usize, k: usize) -> (usize, usize) {
+        let vertices = self.triangles[triangle];
+        (vertices[k], vertices[(k + 1) % 3])
+    }
+
+    /// Whether the apex of a triangle at the segment lies in its diametral circle
+    fn is_encroached(&self, a: usize, b: usize) -> bool {
+        let (pa, pb) = (self.points[a], self.points[b]);
+        self.edge_triangles(a, b).into_iter().any(|(triangle, k)| {
+            let apex = self.points[self.triangles[triangle][(k + 2) % 3]];
+            (pa - apex).dot(pb - apex) < 0.0
+        })
+    }
+
+    /// Triangles with the edge in either direction and the index of the edge
+    fn edge_triangles(&self, a: usize, b: usize) -> Vec<(usize, usize)> {
+        let mut found = Vec::new();
+        for &(from, to) in &[(a, b), (b, a)] {
+            if let Some(k) = self.find_edge(from, to) {
+                found.push(k);
+            }
+        }
+        found
+    }
+
+    /// Triangle with the directed edge and the index of the edge
+    fn find_edge(&self, from: usize, to: usize) -> Option<(usize, usize)> {
+        let start = self.vertex_triangles[from];
+        if start >= self.triangles.len() || !self.triangles[start].contains(&from) {
+            return None;
+        }
+        // turn around the vertex one way, then the other way when the boundary is met
+        for &forward in &[true, false] {
+            let mut triangle = start;
+            loop {
+                let k = self.triangles[triangle].iter().position(|&v| v == from)?;
+                if self.triangles[triangle][(k + 1) % 3] == to {
+                    return Some((triangle, k));
+                }
+                triangle = if forward {
+                    self.neighbors[triangle][k]
+                } else {
+                    self.neighbors[triangle][(k + 2) % 3]
+                };
+                if triangle == start {
+                    return None;
+                }
+                if triangle == NONE {
+                    break;
+                }
+            }
+        }
+        None
+    }
+
+    /// Triangle containing the point by checking all of them
+    fn find_triangle(&self, point: Point2) -> Option<usize> {
+        (0..self.triangles.len()).find(|&triangle| {
+            (0..3).all(|k| {
+                let (a, b) = self.edge(triangle, k);
+                orient2d(self.points[a], self.points[b], point) >= 0.0
+            })
+        })
+    }
+
+    /// Walk from the triangle towards the point, optionally stopping at segments
+    fn locate(&self, point: Point2, start: usize, stop_at_segments: bool) -> Location {
+        let mut triangle = start;
+        for step in 0..self.triangles.len() * 3 + 3 {
+            let crossed = (0..3).map(|k| (k + step) % 3).find(|&k| {
+                let (a, b) = self.edge(triangle, k);
+                orient2d(self.points[a], self.points[b], point) < 0.0
+            });
+            let k = match crossed {
+                Some(k) => k,
+                None => return Location::Inside(triangle),
+            };
+            let (a, b) = self.edge(triangle, k);
+            let next = self.neighbors[triangle][k];
+            if next == NONE || (stop_at_segments && self.segments.contains(&key(a, b))) {
+                return Location::Blocked(triangle, k);
+            }
+            triangle = next;
+        }
+        // walking can circle in a constrained triangulation, search all triangles
+        Location::Inside(self.find_triangle(point).unwrap_or(start))
+    }
+
+    /// Triangles whose circumcircles in the metric contain the point and which the point sees
+    /// from the start, with the edges around them and the triangles across
+    #[allow(clippy::type_complexity)]
+    fn cavity(
+        &self,
+        point: Point2,
+        start: usize,
+        split: Option<(usize, usize)>,
+        metric: &Metric,
+    ) -> (Vec<usize>, Vec<(usize, usize, usize)>) {
+        let point_in_metric = metric.to_euclidean(point);
+        let mut cavity = vec![start];
+        let mut inside = HashSet::new();
+        inside.insert(start);
+        let mut index = 0;
+        while index < cavity.len() {
+            let triangle = cavity[index];
+            index += 1;
+            for k in 0..3 {
+                let next = self.neighbors[triangle][k];
+                if next == NONE || inside.contains(&next) {
+                    continue;
+                }
+                let (a, b) = self.edge(triangle, k);
+                let is_split = split == Some(key(a, b));
+                if self.segments.contains(&key(a, b)) && !is_split {
+                    continue;
+                }
+                let [p, q, r] = self.triangles[next].map(|v| metric.to_euclidean(self.points[v]));
+                if is_split || incircle(p, q, r, point_in_metric) > 0.0 {
+                    inside.insert(next);
+                    cavity.push(next);
+                }
+            }
+        }
+        let mut edges = Vec::new();
+        for &triangle in &cavity {
+            for k in 0..3 {
+                let next = self.neighbors[triangle][k];
+                if next == NONE || !inside.contains(&next) {
+                    let (a, b) = self.edge(triangle, k);
+                    edges.push((a, b, next));
+                }
+            }
+        }
+        (cavity, edges)
+    }
+
+    fn insert_vertex(&mut self, vertex: usize, triangle: usize, split: Option<(usize, usize)>) {
+        let point = self.points[vertex];
+        let (cavity, edges) = self.cavity(point, triangle, split, &Metric::IDENTITY);
+        self.fill_cavity(vertex, &cavity, &edges);
+        if let Some((a, b)) = split {
+            self.segments.remove(&(a, b));
+            self.segments.insert(key(a, vertex));
+            self.segments.insert(key(vertex, b));
+            if self.boundary.remove(&(a, b)) {
+                self.boundary.insert(key(a, vertex));
+                self.boundary.insert(key(vertex, b));
+            }
+        }
+    }
+
+    /// Replace the cavity by triangles joining its edges to the vertex and return them,
+    /// edges through the vertex are left open
+    fn fill_cavity(
+        &mut self,
+        vertex: usize,
+        cavity: &[usize],
+        edges: &[(usize, usize, usize)],
+    ) -> Vec<usize> {
+        let point = self.points[vertex];
+        let edges = edges
+            .iter()
+            .filter(|&&(a, b, _)| orient2d(self.points[a], self.points[b], point) != 0.0)
+            .collect::<Vec<_>>();
+        let mut slots = cavity.to_vec();
+        let mut created = Vec::with_capacity(edges.len());
+        let mut starts = HashMap::new();
+        for &&(a, b, outer) in &edges {
+            let triangle = match slots.pop() {
+                Some(slot) => slot,
+                None => {
+                    self.triangles.push([NONE; 3]);
+                    self.neighbors.push([NONE; 3]);
+                    self.triangles.len() - 1
+                }
+            };
+            self.triangles[triangle] = [a, b, vertex];
+            self.neighbors[triangle] = [outer, NONE, NONE];
+            if outer != NONE {
+                let k = (0..3).find(|&k| self.edge(outer, k) == (b, a)).unwrap();
+                self.neighbors[outer][k] = triangle;
+            }
+            for &v in &[a, b, vertex] {
+                self.vertex_triangles[v] = triangle;
+            }
+            starts.insert(a, triangle);
+            created.push(triangle);
+        }
+        for &triangle in &created {
+            let b = self.triangles[triangle][1];
+            if let Some(&next) = starts.get(&b) {
+                self.neighbors[triangle][1] = next;
+                self.neighbors[next][2] = triangle;
+            }
+        }
+        // the cavity has at least as many triangles as edges unless it is split
+        slots.sort_unstable();
+        for slot in slots.into_iter().rev() {
+            let last = self.triangles.len() - 1;
+            self.remove_triangle(slot);
+            for triangle in created.iter_mut().filter(|t| **t == last) {
+                *triangle = slot;
+            }
+        }
+        created
+    }
+
+    /// Move the last triangle into the slot
+    fn remove_triangle(&mut self, slot: usize) {
+        let last = self.triangles.len() - 1;
+        self.triangles.swap_remove(slot);
+        self.neighbors.swap_remove(slot);
+        if slot == last {
+            return;
+        }
+        for k in 0..3 {
+            let next = self.neighbors[slot][k];
+            if next != NONE {
+                for n in self.neighbors[next].iter_mut() {
+                    if *n == last {
+                        *n = slot;
+                    }
+                }
+            }
+            self.vertex_triangles[self.triangles[slot][k]] = slot;
+        }
+    }
+
+    fn split_segment(&mut self, a: usize, b: usize) -> Vec<usize> {
+        let middle = (self.points[a] + self.points[b]) / 2.0;
+        let (triangle, _) = self.edge_triangles(a, b)[0];
+        self.points.push(middle);
+        let vertex = self.points.len() - 1;
+        self.vertex_triangles.push(triangle);
+        let (cavity, edges) = self.cavity(middle, triangle, Some((a, b)), &Metric::IDENTITY);
+        let created = self.fill_cavity(vertex, &cavity, &edges);
+        self.segments.remove(&(a, b));
+        self.segments.insert(key(a, vertex));
+        self.segments.insert(key(vertex, b));
+        if self.boundary.remove(&(a, b)) {
+            self.boundary.insert(key(a, vertex));
+            self.boundary.insert(key(vertex, b));
+        }
+        created
+    }
+
+    /// Make the segment edges of the triangulation and return its pieces between the vertices on it
+    fn insert_segment(&mut self, a: usize, b: usize) -> Vec<(usize, usize)> {
+        if a == b {
+            return Vec::new();
+        }
+        if self.find_edge(a, b).is_some() || self.find_edge(b, a).is_some() {
+            self.segments.insert(key(a, b));
+            return vec![(a, b)];
+        }
+        let (pa, pb) = (self.points[a], self.points[b]);
+        // the triangle at `a` whose opposite edge crosses the segment
+        let mut start = None;
+        for (triangle, vertices) in self.triangles.iter().enumerate() {
+            let k = match vertices.iter().position(|&v| v == a) {
+                Some(k) => k,
+                None => continue,
+            };
+            let (v1, v2) = (vertices[(k + 1) % 3], vertices[(k + 2) % 3]);
+            for &v in &[v1, v2] {
+                let p = self.points[v];
+                if orient2d(pa, pb, p) == 0.0 && (p - pa).dot(pb - pa) > 0.0 {
+                    // the segment passes through the vertex
+                    let mut pieces = self.insert_segment(a, v);
+                    pieces.extend(self.insert_segment(v, b));
+                    return pieces;
+                }
+            }
+            if orient2d(pa, self.points[v1], pb) > 0.0 && orient2d(pa, self.points[v2], pb) < 0.0 {
+                start = Some((triangle, v1, v2));
+            }
+        }
+        let (mut triangle, mut right, mut left) = match start {
+            Some(start) => start,
+            None => return Vec::new(),
+        };
+        let mut crossed = vec![triangle];
+        let (mut left_chain, mut right_chain) = (vec![left], vec![right]);
+        loop {
+            let k = (0..3)
+                .find(|&k| self.edge(triangle, k) == (right, left))
+                .unwrap();
+            triangle = self.neighbors[triangle][k];
+            if triangle == NONE {
+                return Vec::new();
+            }
+            crossed.push(triangle);
+            let k = (0..3)
+                .find(|&k| self.edge(triangle, k) == (left, right))
+                .unwrap();
+            let apex = self.triangles[triangle][(k + 2) % 3];
+            if apex == b {
+                break;
+            }
+            let side = orient2d(pa, pb, self.points[apex]);
+            if side == 0.0 {
+                let mut pieces = self.insert_segment(a, apex);
+                pieces.extend(self.insert_segment(apex, b));
+                return pieces;
+            } else if side > 0.0 {
+                left_chain.push(apex);
+                left = apex;
+            } else {
+                right_chain.push(apex);
+                right = apex;
+            }
+        }
+        right_chain.reverse();
+        let mut triangles = Vec::with_capacity(crossed.len());
+        self.triangulate_pseudo_polygon(a, b, &left_chain, &mut triangles);
+        self.triangulate_pseudo_polygon(b, a, &right_chain, &mut triangles);
+        for (&slot, vertices) in crossed.iter().zip(triangles) {
+            self.triangles[slot] = vertices;
+        }
+        self.segments.insert(key(a, b));
+        self.connect();
+        vec![(a, b)]
+    }
+
+    /// Delaunay triangles between the edge and the chain of vertices on its left
+    fn triangulate_pseudo_polygon(
+        &self,
+        a: usize,
+        b: usize,
+        chain: &[usize],
+        triangles: &mut Vec<[usize; 3]>,
+    ) {
+        if chain.is_empty() {
+            return;
+        }
+        let (pa, pb) = (self.points[a], self.points[b]);
+        let mut index = 0;
+        for i in 1..chain.len() {
+            if incircle(pa, pb, self.points[chain[index]], self.points[chain[i]]) > 0.0 {
+                index = i;
+            }
+        }
+        let c = chain[index];
+        triangles.push([a, b, c]);
+        self.triangulate_pseudo_polygon(a, c, &chain[..index], triangles);
+        self.triangulate_pseudo_polygon(c, b, &chain[index + 1..], triangles);
+    }
+
+    /// Find the neighbors and a triangle around each vertex again
+    fn connect(&mut self) {
+        let mut edges = HashMap::with_capacity(self.triangles.len() * 3);
+        for triangle in 0..self.triangles.len() {
+            for k in 0..3 {
+                edges.insert(self.edge(triangle, k), triangle);
+            }
+        }
+        for triangle in 0..self.triangles.len() {
+            for k in 0..3 {
+                let (a, b) = self.edge(triangle, k);
+                self.neighbors[triangle][k] = edges.get(&(b, a)).copied().unwrap_or(NONE);
+                self.vertex_triangles[a] = triangle;
+            }
+        }
+    }
+
+    /// Keep the triangles inside an odd number of polygons
+    fn remove_exterior(&mut self) {
+        let mut parity = vec![None; self.triangles.len()];
+        let outer = (0..self.triangles.len())
+            .find(|&t| self.triangles[t].iter().any(|&v| v >= self.input_count))
+            .unwrap();
+        parity[outer] = Some(false);
+        let mut stack = vec![outer];
+        while let Some(triangle) = stack.pop() {
+            let inside = parity[triangle].unwrap();
+            for k in 0..3 {
+                let next = self.neighbors[triangle][k];
+                if next == NONE || parity[next].is_some() {
+                    continue;
+                }
+                let (a, b) = self.edge(triangle, k);
+                parity[next] = Some(inside != self.boundary.contains(&key(a, b)));
+                stack.push(next);
+            }
+        }
+        let (triangles, neighbors) = self
+            .triangles
+            .iter()
+            .zip(&parity)
+            .filter(|(_, &inside)| inside == Some(true))
+            .map(|(&vertices, _)| (vertices, [NONE; 3]))
+            .unzip();
+        self.triangles = triangles;
+        self.neighbors = neighbors;
+        self.connect();
+        // input segments outside the domain are not kept
+        let triangles = &self.triangles;
+        let mut kept = HashSet::new();
+        for vertices in triangles {
+            for k in 0..3 {
+                kept.insert(key(vertices[k], vertices[(k + 1) % 3]));
+            }
+        }
+        self.segments.retain(|segment| kept.contains(segment));
+        self.boundary.retain(|segment| kept.contains(segment));
+    }
+}
+
+fn is_bad([a, b, c]: [Point2; 3], min_sine: Float, max_length: Float) -> bool {
+    let lengths = [a.distance(b), b.distance(c), c.distance(a)];
+    let shortest = lengths.iter().fold(Float::MAX, |min, &l| min.min(l));
+    let longest = lengths.iter().fold(0.0, |max: Float, &l| max.max(l));
+    // the smallest angle is opposite to the shortest edge, its sine is shortest / 2R
+    let area = orient2d(a, b, c) / 2.0;
+    let radius = lengths[0] * lengths[1] * lengths[2] / (4.0 * area);
+    longest > max_length || shortest / (2.0 * radius) < min_sine
+}
+
+fn circumcenter([a, b, c]: [Point2; 3]) -> Point2 {
+    let (ab, ac) = (b - a, c - a);
+    let d = 2.0 * ab.perp_dot(ac);
+    let (ab2, ac2) = (ab.length_squared(), ac.length_squared());
+    a + Point2::new(ac.y * ab2 - ab.y * ac2, ab.x * ac2 - ac.x * ab2) / d
+}
+
+/// Triangulate the domain bounded by polygons and refine it when the quality is given,
+/// the input points come first in the points
+pub fn triangulate_domain(
+    points: &[Point2],
+    polygons: &[usize],
+    quality: Option<&MeshQuality>,
+) -> (Vec<Point2>, Vec<u32>) {
+    let mut mesh = ConstrainedDelaunay::new(points, polygons);
+    if let Some(quality) = quality {
+        mesh.refine(quality);
+    }
+    (mesh.points(), mesh.triangles())
+}
+
+#[test]
+fn test_constrained_delaunay() {
+    let square = |center: Point2, size: Float| {
+        [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+            .iter()
+            .map(move |&(x, y)| center + Point2::new(x, y) * size)
+            .collect::<Vec<_>>()
+    };
+    // a square with two holes sharing an edge, points along the bottom are nearly collinear
+    let mut points = vec![Point2::new(0.0, 0.0)];
+    points.extend((1..20).map(|i| Point2::new(i as Float * 0.5, 1e-15 * (i % 3) as Float)));
+    points.extend_from_slice(&[
+        Point2::new(10.0, 0.0),
+        Point2::new(10.0, 10.0),
+        Point2::new(0.0, 10.0),
+    ]);
+    let outer = points.len();
+    points.extend(square(Point2::new(3.0, 5.0), 1.0));
+    points.extend(square(Point2::new(5.0, 5.0), 1.0).iter().rev());
+    let polygons = [0, outer, outer + 4, outer + 8];
+    let areas = |points: &[Point2], triangles: &[u32]| {
+        triangles
+            .chunks(3)
+            .map(|t| {
+                orient2d(
+                    points[t[0] as usize],
+                    points[t[1] as usize],
+                    points[t[2] as usize],
+                )
+            })
+            .map(|area| area / 2.0)
+            .collect::<Vec<_>>()
+    };
+    let mut mesh = ConstrainedDelaunay::new(&points, &polygons);
+    let area = areas(&mesh.points(), &mesh.triangles());
+    assert!(area.iter().all(|&a| a > 0.0));
+    assert!((area.iter().sum::<Float>() - 92.0).abs() < 1e-9);
+
+    // a segment and a point inside are kept, refinement leaves no small angles
+    let (start, end) = (Point2::new(7.0, 2.0), Point2::new(9.0, 8.0));
+    mesh.add_segment(start, end);
+    let point = mesh.add_point(Point2::new(2.0, 8.0)).unwrap();
+    assert_eq!(mesh.add_point(Point2::new(4.0, 5.0)), None);
+    let quality = MeshQuality {
+        max_length: 2.0,
+        ..Default::default()
+    };
+    mesh.refine(&quality);
+    let (points, triangles) = (mesh.points(), mesh.triangles());
+    let area = areas(&points, &triangles);
+    assert!(area.iter().all(|&a| a > 0.0));
+    assert!((area.iter().sum::<Float>() - 92.0).abs() < 1e-9);
+    assert!(triangles.contains(&(point as u32 - 3)));
+    let mut on_segment = 0.0;
+    for triangle in triangles.chunks(3) {
+        let [a, b, c] = [0, 1, 2].map(|k| points[triangle[k] as usize]);
+        for &(p, q, r) in &[(a, b, c), (b, c, a), (c, a, b)] {
+            assert!(p.distance(q) <= quality.max_length + 1e-9);
+            let angle = (q - p).angle_between(r - p);
+            assert!(angle > quality.min_angle - 1e-6, "{} at {}", angle, p);
+            if orient2d(start, end, p) == 0.0 && orient2d(start, end, q) == 0.0 {
+                on_segment += p.distance(q);
+            }
+        }
+    }
+    // edges along the segment are seen from both sides
+    assert!((on_segment - 2.0 * start.distance(end)).abs() < 1e-9);
+}
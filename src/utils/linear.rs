@@ -0,0 +1,123 @@
+use crate::{Float, Grid, Vec3};
+use std::ops::{Add, Mul, Sub};
+
+/// Solve `matrix · x = rhs` by Gaussian elimination with partial pivoting,
+/// the right-hand sides may be points. Returns `None` when the matrix is singular.
+/// # Examples
+/// ```
+/// use geom3d::{utils::*, Grid, Point2};
+/// let matrix = Grid::from_vec(vec![2.0, 1.0, 1.0, 3.0], 2);
+/// let rhs = vec![Point2::new(3.0, 1.0), Point2::new(4.0, -7.0)];
+/// let x = solve_linear_system(matrix, rhs).unwrap();
+/// assert!(x[0].abs_diff_eq(Point2::new(1.0, 2.0), 1e-12));
+/// assert!(x[1].abs_diff_eq(Point2::new(1.0, -3.0), 1e-12));
+/// ```
+pub fn solve_linear_system<P>(mut matrix: Grid<Float>, mut rhs: Vec<P>) -> Option<Vec<P>>
+where
+    P: Add<Output = P> + Sub<Output = P> + Mul<Float, Output = P> + Copy,
+{
+    let n = rhs.len();
+    assert_eq!(matrix.size(), (n, n));
+    let scale = matrix.iter().fold(0.0, |max: Float, a| max.max(a.abs()));
+    for k in 0..n {
+        let pivot = (k..n)
+            .max_by(|&i, &j| matrix[i][k].abs().total_cmp(&matrix[j][k].abs()))
+            .unwrap();
+        if matrix[pivot][k].abs() <= Float::EPSILON * scale * n as Float {
+            return None;
+        }
+        if pivot != k {
+            for j in k..n {
+                let temp = matrix[k][j];
+                matrix[k][j] = matrix[pivot][j];
+                matrix[pivot][j] = temp;
+            }
+            rhs.swap(k, pivot);
+        }
+        for i in k + 1..n {
+            let factor = matrix[i][k] / matrix[k][k];
+            if factor == 0.0 {
+                continue;
+            }
+            for j in k..n {
+                matrix[i][j] -= factor * matrix[k][j];
+            }
+            rhs[i] = rhs[i] - rhs[k] * factor;
+        }
+    }
+    for k in (0..n).rev() {
+        let mut value = rhs[k];
+        for j in k + 1..n {
+            value = value - rhs[j] * matrix[k][j];
+        }
+        rhs[k] = value * (1.0 / matrix[k][k]);
+    }
+    Some(rhs)
+}
+
+/// Least squares solution of the overdetermined system `matrix · x = rhs`
+/// through the normal equations, `None` when the columns are dependent.
+pub fn solve_least_squares<P>(matrix: &Grid<Float>, rhs: &[P]) -> Option<Vec<P>>
+where
+    P: Add<Output = P> + Sub<Output = P> + Mul<Float, Output = P> + Copy + Default,
+{
+    let (rows, cols) = matrix.size();
+    assert_eq!(rows, rhs.len());
+    let mut normal = Grid::init(cols, cols, 0.0);
+    let mut projected = vec![P::default(); cols];
+    for k in 0..rows {
+        for i in 0..cols {
+            let a = matrix[k][i];
+            if a == 0.0 {
+                continue;
+            }
+            projected[i] = projected[i] + rhs[k] * a;
+            for j in 0..cols {
+                normal[i][j] += a * matrix[k][j];
+            }
+        }
+    }
+    solve_linear_system(normal, projected)
+}
+
+/// Eigenvalues in ascending order with unit eigenvectors of a symmetric 3×3 matrix
+/// given by rows, computed by Jacobi rotations
+pub fn symmetric_eigen3(mut matrix: [[Float; 3]; 3]) -> [(Float, Vec3); 3] {
+    let mut vectors = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+    let scale = matrix
+        .iter()
+        .flatten()
+        .fold(0.0, |max: Float, a| max.max(a.abs()));
+    for _ in 0..64 {
+        let off = matrix[0][1].abs() + matrix[0][2].abs() + matrix[1][2].abs();
+        if off <= Float::EPSILON * scale {
+            break;
+        }
+        for (p, q) in [(0, 1), (0, 2), (1, 2)] {
+            if matrix[p][q] == 0.0 {
+                continue;
+            }
+            // rotate in the (p, q) plane to zero the element
+            let theta = (matrix[q][q] - matrix[p][p]) / (2.0 * matrix[p][q]);
+            let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+            let c = 1.0 / (t * t + 1.0).sqrt();
+            let s = t * c;
+            for row in matrix.iter_mut().chain(vectors.iter_mut()) {
+                let (a, b) = (row[p], row[q]);
+                row[p] = c * a - s * b;
+                row[q] = s * a + c * b;
+            }
+            let (row_p, row_q) = (matrix[p], matrix[q]);
+            for (k, (&a, &b)) in row_p.iter().zip(&row_q).enumerate() {
+                matrix[p][k] = c * a - s * b;
+                matrix[q][k] = s * a + c * b;
+            }
+        }
+    }
+    let mut pairs = [0, 1, 2].map(|k| {
+        let vector = Vec3::new(vectors[0][k], vectors[1][k], vectors[2][k]);
+        (matrix[k][k], vector)
+    });
+    pairs.sort_by(|a, b| a.0.total_cmp(&b.0));
+    pairs
+}
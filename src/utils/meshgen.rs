@@ -63,7 +63,7 @@ fn find_intersection_with_front(
     end: usize,
     selected_node: Option<usize>,
     front: &VecDeque<(usize, usize)>,
-    vertices: &Vec<Point2>,
+    vertices: &[Point2],
 ) -> Option<usize> {
     let (a, b) = (vertices[start], vertices[end]);
     let new_point = match selected_node {
@@ -95,9 +95,7 @@ fn find_intersection_with_front(
                     {
                         let fc = shape_factor(a, b, c);
                         let fd = shape_factor(a, b, d);
-                        return if e.1 == start {
-                            Some((e.0, fc))
-                        } else if fc > fd {
+                        return if e.1 == start || fc > fd {
                             Some((e.0, fc))
                         } else {
                             Some((e.1, fd))
@@ -105,13 +103,13 @@ fn find_intersection_with_front(
                     }
                 }
             }
-            return None;
+            None
         })
         .collect::<Vec<_>>();
 
     nearby_nodes.sort_by(|a, b| match a.0.cmp(&b.0) {
         std::cmp::Ordering::Equal => b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Less),
-        order @ _ => order,
+        order => order,
     });
     nearby_nodes.dedup_by_key(|(node, _)| *node);
     if nearby_nodes.len() > 1 {
@@ -148,7 +146,7 @@ fn is_segments_intersect(a: Point2, b: Point2, c: Point2, d: Point2) -> bool {
     if ac.perp_dot(bc) * bd.perp_dot(ad) < 0.0 {
         return false;
     }
-    return true;
+    true
 }
 
 fn shape_factor(a: Point2, b: Point2, c: Point2) -> Float {
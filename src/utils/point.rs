@@ -18,7 +18,7 @@ impl Point for Point2 {
             let bp = *self - b;
             return bp.length();
         }
-        return ap.perp_dot(ab).abs() / ab.length();
+        ap.perp_dot(ab).abs() / ab.length()
     }
 
     fn is_inside_triangle(&self, a: Point2, b: Point2, c: Point2) -> bool {
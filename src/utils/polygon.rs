@@ -174,13 +174,14 @@ fn is_ear(
     next: usize,
 ) -> bool {
     for &other in concave_points {
-        if other != prev && other != next {
-            if is_inside_triangle(points[prev], points[curr], points[next], points[other]) {
-                return false;
-            }
+        if other != prev
+            && other != next
+            && is_inside_triangle(points[prev], points[curr], points[next], points[other])
+        {
+            return false;
         }
     }
-    return true;
+    true
 }
 
 pub fn merge_polygons(points: &[Point2], polygons: &[usize]) -> VecDeque<usize> {
@@ -190,8 +191,7 @@ pub fn merge_polygons(points: &[Point2], polygons: &[usize]) -> VecDeque<usize>
         if let [start, end] = pair {
             let mut x_max = Float::MIN;
             let mut i_max = 0;
-            for index in *start..*end {
-                let point = points[index];
+            for (index, point) in points.iter().enumerate().take(*end).skip(*start) {
                 if point.x > x_max {
                     x_max = point.x;
                     i_max = index;
@@ -0,0 +1,134 @@
+use crate::{Float, Point2};
+
+const EPSILON: Float = Float::EPSILON / 2.0;
+const ORIENT_BOUND: Float = (3.0 + 16.0 * EPSILON) * EPSILON;
+const INCIRCLE_BOUND: Float = (10.0 + 96.0 * EPSILON) * EPSILON;
+
+/// Twice the signed area of the triangle, positive when `a`, `b`, `c` turn counter-clockwise.
+/// The sign is exact, the floating point value is refined by exact arithmetic when rounding
+/// could change it.
+pub fn orient2d(a: Point2, b: Point2, c: Point2) -> Float {
+    let left = (a.x - c.x) * (b.y - c.y);
+    let right = (a.y - c.y) * (b.x - c.x);
+    let det = left - right;
+    if det.abs() > ORIENT_BOUND * (left.abs() + right.abs()) {
+        return det;
+    }
+    let (acx, bcx) = (difference(a.x, c.x), difference(b.x, c.x));
+    let (acy, bcy) = (difference(a.y, c.y), difference(b.y, c.y));
+    let det = sum(&product(&acx, &bcy), &negate(&product(&acy, &bcx)));
+    estimate(&det)
+}
+
+/// Positive when `d` lies inside the circle through the counter-clockwise `a`, `b`, `c`,
+/// negative outside and zero on it. The sign is exact.
+pub fn incircle(a: Point2, b: Point2, c: Point2, d: Point2) -> Float {
+    let (adx, ady) = (a.x - d.x, a.y - d.y);
+    let (bdx, bdy) = (b.x - d.x, b.y - d.y);
+    let (cdx, cdy) = (c.x - d.x, c.y - d.y);
+    let (bc, cb) = (bdx * cdy, cdx * bdy);
+    let (ca, ac) = (cdx * ady, adx * cdy);
+    let (ab, ba) = (adx * bdy, bdx * ady);
+    let alift = adx * adx + ady * ady;
+    let blift = bdx * bdx + bdy * bdy;
+    let clift = cdx * cdx + cdy * cdy;
+    let det = alift * (bc - cb) + blift * (ca - ac) + clift * (ab - ba);
+    let permanent = (bc.abs() + cb.abs()) * alift
+        + (ca.abs() + ac.abs()) * blift
+        + (ab.abs() + ba.abs()) * clift;
+    if det.abs() > INCIRCLE_BOUND * permanent {
+        return det;
+    }
+    let (adx, ady) = (difference(a.x, d.x), difference(a.y, d.y));
+    let (bdx, bdy) = (difference(b.x, d.x), difference(b.y, d.y));
+    let (cdx, cdy) = (difference(c.x, d.x), difference(c.y, d.y));
+    let lift = |x: &[Float], y: &[Float]| sum(&product(x, x), &product(y, y));
+    let cross = |x0: &[Float], y1: &[Float], x1: &[Float], y0: &[Float]| {
+        sum(&product(x0, y1), &negate(&product(x1, y0)))
+    };
+    let det = sum(
+        &sum(
+            &product(&lift(&adx, &ady), &cross(&bdx, &cdy, &cdx, &bdy)),
+            &product(&lift(&bdx, &bdy), &cross(&cdx, &ady, &adx, &cdy)),
+        ),
+        &product(&lift(&cdx, &cdy), &cross(&adx, &bdy, &bdx, &ady)),
+    );
+    estimate(&det)
+}
+
+// Exact arithmetic on expansions, sums of non-overlapping floats in increasing magnitude
+
+fn two_sum(a: Float, b: Float) -> (Float, Float) {
+    let x = a + b;
+    let bv = x - a;
+    let av = x - bv;
+    (x, (a - av) + (b - bv))
+}
+
+fn difference(a: Float, b: Float) -> Vec<Float> {
+    let (x, y) = two_sum(a, -b);
+    vec![y, x]
+}
+
+/// Add a float to the expansion, zero components are dropped
+fn grow(expansion: &[Float], b: Float) -> Vec<Float> {
+    let mut result = Vec::with_capacity(expansion.len() + 1);
+    let mut q = b;
+    for &e in expansion {
+        let (sum, error) = two_sum(q, e);
+        if error != 0.0 {
+            result.push(error);
+        }
+        q = sum;
+    }
+    result.push(q);
+    result
+}
+
+fn sum(e: &[Float], f: &[Float]) -> Vec<Float> {
+    f.iter().fold(e.to_vec(), |result, &b| grow(&result, b))
+}
+
+fn negate(e: &[Float]) -> Vec<Float> {
+    e.iter().map(|x| -x).collect()
+}
+
+fn product(e: &[Float], f: &[Float]) -> Vec<Float> {
+    let mut result = vec![0.0];
+    for &a in e {
+        for &b in f {
+            let x = a * b;
+            result = grow(&grow(&result, a.mul_add(b, -x)), x);
+        }
+    }
+    result
+}
+
+/// The largest component carries the sign of the expansion
+fn estimate(e: &[Float]) -> Float {
+    e.iter().sum()
+}
+
+#[test]
+fn test_exact_predicates() {
+    // points on a line through awkward coordinates are exactly collinear
+    let a = Point2::new(0.1, 0.1);
+    let b = Point2::new(0.3, 0.3);
+    for i in 0..64 {
+        let t = 0.5 + i as Float * Float::EPSILON;
+        let c = Point2::new(t, t);
+        assert_eq!(orient2d(a, b, c), 0.0);
+    }
+    let c = Point2::new(0.5, 0.5 + Float::EPSILON);
+    assert!(orient2d(a, b, c) > 0.0);
+    assert!(orient2d(b, a, c) < 0.0);
+
+    // cocircular points on the unit circle
+    let a = Point2::new(1.0, 0.0);
+    let b = Point2::new(0.0, 1.0);
+    let c = Point2::new(-1.0, 0.0);
+    assert_eq!(incircle(a, b, c, Point2::new(0.0, -1.0)), 0.0);
+    assert!(incircle(a, b, c, Point2::new(0.0, -1.0 + Float::EPSILON)) > 0.0);
+    assert!(incircle(a, b, c, Point2::new(0.0, -1.0 - Float::EPSILON)) < 0.0);
+    assert!(incircle(a, b, c, Point2::new(0.2, 0.3)) > 0.0);
+}